@@ -0,0 +1,743 @@
+use crate::config::{AccessPattern, DurationMode, ProgressMode, StopCondition, WritePattern};
+use crate::influx::check_url;
+use crate::measurements::DEFAULT_PERCENTILES;
+use crate::operations::{ReadOperation, Table};
+use crate::primary_simulator::{Phase, WriteMix};
+use crate::read_selector::WeightedReadSelector;
+use anyhow::{anyhow, bail, Result};
+use rand::{thread_rng, Rng};
+use std::path::PathBuf;
+use std::time::Duration;
+use strum::IntoEnumIterator;
+use url::Url;
+
+/// The settings of a benchmark run, for running a benchmark from Rust code
+/// with `run_with_config` instead of from the command line. Start from
+/// `BenchmarkConfig::for_duration` or `BenchmarkConfig::for_operations` and
+/// change the settings that should differ from the command line defaults:
+///
+/// ```no_compile
+/// let config = BenchmarkConfig::for_duration(Duration::from_secs(60))
+///     .transactions_per_second(100)
+///     .concurrency(8)
+///     .seed(42);
+/// let measurements = run_with_config(config, MyBenchmark::new()?)?;
+/// ```
+///
+/// The command line builds the same configuration from its arguments.
+#[derive(Clone)]
+pub struct BenchmarkConfig {
+    pub(crate) stop: StopCondition,
+    pub(crate) duration_mode: DurationMode,
+    pub(crate) warmup: Duration,
+    /// The read clients start evenly staggered over this long.
+    pub(crate) ramp: Duration,
+    pub(crate) transactions_per_second: u32,
+    pub(crate) write_pattern: WritePattern,
+    pub(crate) concurrency: u32,
+    pub(crate) target_read_tps: Option<u32>,
+    pub(crate) correct_coordinated_omission: bool,
+    pub(crate) read_operations: Vec<ReadOperation>,
+    /// Restricted to `read_operations` when the benchmark runs.
+    pub(crate) read_weights: Option<WeightedReadSelector>,
+    pub(crate) think_time: Duration,
+    pub(crate) think_time_jitter: Duration,
+    pub(crate) id_refresh_interval: Duration,
+    pub(crate) access_pattern: AccessPattern,
+    /// The percentage of the sampled values in the hot set of
+    /// `AccessPattern::Hotset`.
+    pub(crate) hotset_pct: u32,
+    pub(crate) reconnect_every: Option<u32>,
+    pub(crate) query_timeout: Option<Duration>,
+    pub(crate) max_retries: u32,
+    pub(crate) latency_includes_retries: bool,
+    /// Whether the first failed read operation or write transaction aborts
+    /// the benchmark, rather than being counted as an error.
+    pub(crate) fail_on_error: bool,
+    pub(crate) conflict_retries: u32,
+    pub(crate) connect_retries: u32,
+    /// The delay before the first connection retry.
+    pub(crate) connect_backoff: Duration,
+    pub(crate) write_mix: WriteMix,
+    pub(crate) phases: Vec<Phase>,
+    pub(crate) max_db_size: Option<u64>,
+    pub(crate) batch_size: u32,
+    pub(crate) hierarchy_pct: u32,
+    pub(crate) delete_leaf_bias: u32,
+    pub(crate) write_tables: Vec<Table>,
+    pub(crate) seed: u64,
+    /// Whether the primary database simulator is left out.
+    pub(crate) no_writes: bool,
+    pub(crate) measure_lag: bool,
+    /// Check read-your-writes consistency every this many read operations.
+    pub(crate) read_your_writes: Option<u32>,
+    pub(crate) track_growth: bool,
+    pub(crate) report_interval: Option<Duration>,
+    pub(crate) samples_out: Option<PathBuf>,
+    pub(crate) timeseries_out: Option<PathBuf>,
+    pub(crate) influx_url: Option<Url>,
+    /// How often metrics are pushed to `influx_url`. When `None`, they are
+    /// pushed once, when the benchmark finishes.
+    pub(crate) influx_interval: Option<Duration>,
+    /// Defaults to the time the benchmark started.
+    pub(crate) run_id: Option<String>,
+    /// Whether a second set of read clients runs against the comparison
+    /// read replica.
+    pub(crate) compare: bool,
+    /// Whether the tables and views are validated before the short
+    /// benchmark of `--smoke`.
+    pub(crate) smoke: bool,
+    pub(crate) apply_schema: bool,
+    pub(crate) prime_connections: bool,
+    #[cfg(feature = "prometheus")]
+    pub(crate) prometheus_port: Option<u16>,
+    pub(crate) progress: ProgressMode,
+    /// The percentiles of the read latencies reported besides the median.
+    pub(crate) percentiles: Vec<f64>,
+}
+
+impl BenchmarkConfig {
+    /// A benchmark whose read clients run for `duration`.
+    pub fn for_duration(duration: Duration) -> Self {
+        Self::new(StopCondition::Duration(duration))
+    }
+
+    /// A benchmark whose read clients run `operations` read operations
+    /// between them.
+    pub fn for_operations(operations: u64) -> Self {
+        Self::new(StopCondition::Operations(operations))
+    }
+
+    fn new(stop: StopCondition) -> Self {
+        Self {
+            stop,
+            duration_mode: DurationMode::Experiment,
+            warmup: Duration::ZERO,
+            ramp: Duration::ZERO,
+            transactions_per_second: 10,
+            write_pattern: WritePattern::Uniform,
+            concurrency: 1,
+            target_read_tps: None,
+            correct_coordinated_omission: false,
+            read_operations: ReadOperation::iter().collect(),
+            read_weights: None,
+            think_time: Duration::ZERO,
+            think_time_jitter: Duration::ZERO,
+            id_refresh_interval: Duration::from_secs(10),
+            access_pattern: AccessPattern::Uniform,
+            hotset_pct: 10,
+            reconnect_every: None,
+            query_timeout: None,
+            max_retries: 0,
+            latency_includes_retries: false,
+            fail_on_error: false,
+            conflict_retries: 0,
+            connect_retries: 0,
+            connect_backoff: Duration::from_millis(500),
+            write_mix: WriteMix {
+                insert: 45,
+                update: 45,
+                delete: 10,
+            },
+            phases: Vec::new(),
+            max_db_size: None,
+            batch_size: 1,
+            hierarchy_pct: 0,
+            delete_leaf_bias: 0,
+            write_tables: Table::iter().collect(),
+            seed: thread_rng().gen(),
+            no_writes: false,
+            measure_lag: false,
+            read_your_writes: None,
+            track_growth: false,
+            report_interval: None,
+            samples_out: None,
+            timeseries_out: None,
+            influx_url: None,
+            influx_interval: None,
+            run_id: None,
+            compare: false,
+            smoke: false,
+            apply_schema: false,
+            prime_connections: false,
+            #[cfg(feature = "prometheus")]
+            prometheus_port: None,
+            progress: ProgressMode::PerClient,
+            percentiles: DEFAULT_PERCENTILES.to_vec(),
+        }
+    }
+
+    /// How the duration is measured. Ignored when running a number of
+    /// operations.
+    pub fn duration_mode(mut self, duration_mode: DurationMode) -> Self {
+        self.duration_mode = duration_mode;
+        self
+    }
+
+    /// How long reads run before measurements are recorded.
+    pub fn warmup(mut self, warmup: Duration) -> Self {
+        self.warmup = warmup;
+        self
+    }
+
+    /// Staggers the start of the read clients evenly over `ramp`. Clients
+    /// that start later run for less time, so that all of them stop at the
+    /// duration, which `ramp` must be shorter than.
+    pub fn ramp(mut self, ramp: Duration) -> Self {
+        self.ramp = ramp;
+        self
+    }
+
+    /// The number of transactions per second executed against the primary
+    /// database.
+    pub fn transactions_per_second(mut self, transactions_per_second: u32) -> Self {
+        self.transactions_per_second = transactions_per_second;
+        self
+    }
+
+    /// How primary database transactions are spaced out.
+    pub fn write_pattern(mut self, write_pattern: WritePattern) -> Self {
+        self.write_pattern = write_pattern;
+        self
+    }
+
+    /// The number of concurrent read clients.
+    pub fn concurrency(mut self, concurrency: u32) -> Self {
+        self.concurrency = concurrency;
+        self
+    }
+
+    /// Paces the read clients to this combined number of read operations per
+    /// second. The duration is then measured in wall-clock time.
+    pub fn target_read_tps(mut self, target_read_tps: u32) -> Self {
+        self.target_read_tps = Some(target_read_tps);
+        self.duration_mode = DurationMode::Wallclock;
+        self
+    }
+
+    /// Corrects read latencies for coordinated omission. Requires
+    /// `target_read_tps`.
+    pub fn correct_coordinated_omission(mut self, correct: bool) -> Self {
+        self.correct_coordinated_omission = correct;
+        self
+    }
+
+    /// The read operations to run. Defaults to every operation.
+    pub fn read_operations(mut self, operations: impl IntoIterator<Item = ReadOperation>) -> Self {
+        self.read_operations = operations.into_iter().collect();
+        self.read_operations.sort();
+        self.read_operations.dedup();
+        self
+    }
+
+    /// Runs the read operations in proportion to `weights` instead of in
+    /// turn. Operations left out of `read_operations` are skipped.
+    pub fn read_weights(mut self, weights: WeightedReadSelector) -> Self {
+        self.read_weights = Some(weights);
+        self
+    }
+
+    /// How long each read client pauses between read operations, plus a
+    /// random amount of time up to `jitter`.
+    pub fn think_time(mut self, think_time: Duration, jitter: Duration) -> Self {
+        self.think_time = think_time;
+        self.think_time_jitter = jitter;
+        self
+    }
+
+    /// How often the ids that read operations look up are resampled from the
+    /// primary database.
+    pub fn id_refresh_interval(mut self, interval: Duration) -> Self {
+        self.id_refresh_interval = interval;
+        self
+    }
+
+    /// How read operations pick the values they look up among those
+    /// sampled from the primary database.
+    pub fn access_pattern(mut self, pattern: AccessPattern) -> Self {
+        self.access_pattern = pattern;
+        self
+    }
+
+    /// The percentage of the sampled values that receive most of the reads
+    /// with `AccessPattern::Hotset`.
+    pub fn hotset_pct(mut self, hotset_pct: u32) -> Self {
+        self.hotset_pct = hotset_pct;
+        self
+    }
+
+    /// Reconnects to the read replica before every `every` read operations.
+    pub fn reconnect_every(mut self, every: u32) -> Self {
+        self.reconnect_every = Some(every);
+        self
+    }
+
+    /// Cancels read operations that run longer than `timeout`.
+    pub fn query_timeout(mut self, timeout: Duration) -> Self {
+        self.query_timeout = Some(timeout);
+        self
+    }
+
+    /// The number of times a failed operation is retried before it is
+    /// counted as an error.
+    pub fn max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Whether the latency of a read operation that succeeded after being
+    /// retried includes the time spent on the attempts that failed.
+    pub fn latency_includes_retries(mut self, includes_retries: bool) -> Self {
+        self.latency_includes_retries = includes_retries;
+        self
+    }
+
+    /// Whether the first read operation or write transaction that fails
+    /// after `max_retries` retries aborts the benchmark. The error is then
+    /// returned with the measurements collected so far, which
+    /// `BenchmarkAborted` holds. By default failures are counted as errors.
+    pub fn fail_on_error(mut self, fail_on_error: bool) -> Self {
+        self.fail_on_error = fail_on_error;
+        self
+    }
+
+    /// The number of times a write transaction that the primary database
+    /// rejected with a serialization failure or a deadlock is executed
+    /// again before it is counted as a failed attempt.
+    pub fn conflict_retries(mut self, retries: u32) -> Self {
+        self.conflict_retries = retries;
+        self
+    }
+
+    /// The number of times connecting to a database is retried, waiting
+    /// `backoff` before the first retry and twice as long before each
+    /// further one.
+    pub fn connect_retries(mut self, retries: u32, backoff: Duration) -> Self {
+        self.connect_retries = retries;
+        self.connect_backoff = backoff;
+        self
+    }
+
+    /// The percentages of primary database operations that are inserts,
+    /// updates, and deletes, which must sum to 100.
+    pub fn write_mix(mut self, insert: u32, update: u32, delete: u32) -> Self {
+        self.write_mix = WriteMix {
+            insert,
+            update,
+            delete,
+        };
+        self
+    }
+
+    /// Phases run one after another from the start of the benchmark, in
+    /// which the primary database simulator follows each phase's write mix
+    /// instead of `write_mix`.
+    pub fn phases(mut self, phases: impl IntoIterator<Item = Phase>) -> Self {
+        self.phases = phases.into_iter().collect();
+        self
+    }
+
+    /// Replaces inserts with deletes while the primary database is at least
+    /// `bytes` large.
+    pub fn max_db_size(mut self, bytes: u64) -> Self {
+        self.max_db_size = Some(bytes);
+        self
+    }
+
+    /// The number of write operations executed in each primary database
+    /// transaction.
+    pub fn batch_size(mut self, batch_size: u32) -> Self {
+        self.batch_size = batch_size;
+        self
+    }
+
+    /// The percentage of inserted accounts, trades, and orders that reference
+    /// an existing row of the same table as their parent.
+    pub fn hierarchy_pct(mut self, hierarchy_pct: u32) -> Self {
+        self.hierarchy_pct = hierarchy_pct;
+        self
+    }
+
+    /// The percentage of deletes that target a table no other table
+    /// references.
+    pub fn delete_leaf_bias(mut self, delete_leaf_bias: u32) -> Self {
+        self.delete_leaf_bias = delete_leaf_bias;
+        self
+    }
+
+    /// The tables the primary database simulator writes to.
+    pub fn write_tables(mut self, tables: impl IntoIterator<Item = Table>) -> Self {
+        self.write_tables = tables.into_iter().collect();
+        self.write_tables.sort();
+        self.write_tables.dedup();
+        self
+    }
+
+    /// The seed of every random number generator. Defaults to a random seed.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Leaves out the primary database simulator, running only the read
+    /// clients.
+    pub fn no_writes(mut self, no_writes: bool) -> Self {
+        self.no_writes = no_writes;
+        self
+    }
+
+    /// Measures replication lag between the primary database and the read
+    /// replica.
+    pub fn measure_lag(mut self, measure_lag: bool) -> Self {
+        self.measure_lag = measure_lag;
+        self
+    }
+
+    /// Checks read-your-writes consistency every `every` read operations.
+    pub fn read_your_writes(mut self, every: u32) -> Self {
+        self.read_your_writes = Some(every);
+        self
+    }
+
+    /// Reports how much the primary database grew during the run.
+    pub fn track_growth(mut self, track_growth: bool) -> Self {
+        self.track_growth = track_growth;
+        self
+    }
+
+    /// Logs interim read measurements every `interval`.
+    pub fn report_interval(mut self, interval: Duration) -> Self {
+        self.report_interval = Some(interval);
+        self
+    }
+
+    /// Writes every read latency sample to a CSV file at `path`.
+    pub fn samples_out(mut self, path: impl Into<PathBuf>) -> Self {
+        self.samples_out = Some(path.into());
+        self
+    }
+
+    /// Writes read latencies per second to a CSV file at `path`.
+    pub fn timeseries_out(mut self, path: impl Into<PathBuf>) -> Self {
+        self.timeseries_out = Some(path.into());
+        self
+    }
+
+    /// Pushes read metrics to InfluxDB at `url`, which is either
+    /// `udp://HOST:PORT` or an `http://` write URL.
+    pub fn influx_url(mut self, url: Url) -> Self {
+        self.influx_url = Some(url);
+        self
+    }
+
+    /// Pushes the metrics to InfluxDB every `interval`, rather than once
+    /// when the benchmark finishes.
+    pub fn influx_interval(mut self, interval: Duration) -> Self {
+        self.influx_interval = Some(interval);
+        self
+    }
+
+    /// The `run_id` tag of the metrics pushed to InfluxDB.
+    pub fn run_id(mut self, run_id: impl Into<String>) -> Self {
+        self.run_id = Some(run_id.into());
+        self
+    }
+
+    /// Runs a second set of read clients against the benchmark's
+    /// `compare_read_replica`.
+    pub fn compare(mut self, compare: bool) -> Self {
+        self.compare = compare;
+        self
+    }
+
+    /// Creates the views on the read replica before the run.
+    pub fn apply_schema(mut self, apply_schema: bool) -> Self {
+        self.apply_schema = apply_schema;
+        self
+    }
+
+    /// Opens and pings every connection before the run.
+    pub fn prime_connections(mut self, prime_connections: bool) -> Self {
+        self.prime_connections = prime_connections;
+        self
+    }
+
+    /// Serves live metrics in the Prometheus text format on `port`.
+    #[cfg(feature = "prometheus")]
+    pub fn prometheus_port(mut self, port: u16) -> Self {
+        self.prometheus_port = Some(port);
+        self
+    }
+
+    /// How read client progress is displayed while the benchmark runs.
+    pub fn progress(mut self, progress: ProgressMode) -> Self {
+        self.progress = progress;
+        self
+    }
+
+    /// The percentiles of the read latencies to report besides the median,
+    /// in the order given. Fractional percentiles, like 99.9, are supported.
+    pub fn percentiles(mut self, percentiles: impl IntoIterator<Item = f64>) -> Self {
+        self.percentiles = percentiles.into_iter().collect();
+        self
+    }
+
+    /// Checks that the settings are valid and consistent with each other.
+    /// The command line runs the same checks once it has parsed its
+    /// arguments.
+    pub(crate) fn validate(&self) -> Result<()> {
+        match self.stop {
+            StopCondition::Duration(duration) if duration.is_zero() => {
+                bail!("the duration must be greater than zero")
+            }
+            StopCondition::Duration(duration) if !self.ramp.is_zero() && self.ramp >= duration => {
+                bail!("the ramp must be shorter than the duration")
+            }
+            // The phases are timed in real time, so comparing them with a
+            // duration that leaves out think time and pacing is meaningless.
+            StopCondition::Duration(_)
+                if !self.phases.is_empty() && self.duration_mode == DurationMode::Experiment =>
+            {
+                bail!(
+                    "phases are timed in wall-clock time and require the wall-clock duration mode"
+                )
+            }
+            StopCondition::Duration(duration) => {
+                let phases: Duration = self.phases.iter().map(|phase| phase.duration).sum();
+                if phases > duration {
+                    bail!(
+                        "the phases add up to {}, longer than the duration of {}",
+                        humantime::format_duration(phases),
+                        humantime::format_duration(duration)
+                    );
+                }
+            }
+            StopCondition::Operations(0) => bail!("the number of operations must be at least 1"),
+            _ => {}
+        }
+        if self.concurrency == 0 {
+            bail!("the concurrency must be at least 1");
+        }
+        if self.transactions_per_second == 0 {
+            bail!("the transactions per second must be at least 1");
+        }
+        if self.batch_size == 0 {
+            bail!("the batch size must be at least 1");
+        }
+        let WriteMix {
+            insert,
+            update,
+            delete,
+        } = self.write_mix;
+        if insert + update + delete != 100 {
+            bail!(
+                "the write mix must sum to 100, not {}",
+                insert + update + delete
+            );
+        }
+        if self.hierarchy_pct > 100 || self.delete_leaf_bias > 100 || self.hotset_pct > 100 {
+            bail!("percentages must be at most 100");
+        }
+        if self.hotset_pct == 0 {
+            bail!("the hot set must be at least 1 percent");
+        }
+        if self.read_operations.is_empty() {
+            bail!("there must be at least one read operation to run");
+        }
+        if let Some(weights) = &self.read_weights {
+            weights
+                .only(&self.read_operations)
+                .map_err(|e| anyhow!("invalid read weights: {e}"))?;
+        }
+        if self.write_tables.is_empty() {
+            bail!("there must be at least one table to write to");
+        }
+        // Every kind of operation in the write mix needs a table to write to.
+        let updates = update > 0 || self.phases.iter().any(|phase| phase.write_mix.update > 0);
+        if updates && !self.write_tables.iter().any(|table| table.is_updatable()) {
+            bail!(
+                "the write tables only contain securities, which are never updated; \
+                 add another table or leave updates out of the write mix and phases"
+            );
+        }
+        if self.delete_leaf_bias > 0 && !self.write_tables.iter().any(|table| table.is_leaf()) {
+            bail!(
+                "the delete leaf bias requires trades, orders, or market_data in the write tables"
+            );
+        }
+        if self.target_read_tps == Some(0) {
+            bail!("the target read tps must be at least 1");
+        }
+        if self.target_read_tps.is_some() && self.duration_mode == DurationMode::Experiment {
+            bail!("pacing the read clients requires the wall-clock duration mode");
+        }
+        if self.correct_coordinated_omission && self.target_read_tps.is_none() {
+            bail!("correcting for coordinated omission requires a target read tps");
+        }
+        if self.reconnect_every == Some(0) || self.read_your_writes == Some(0) {
+            bail!("read operation counts must be at least 1");
+        }
+        if self.no_writes {
+            if self.read_your_writes.is_some() {
+                bail!("read-your-writes checks require writes");
+            }
+            if self.measure_lag {
+                bail!("measuring replication lag requires writes");
+            }
+            if !self.phases.is_empty() {
+                bail!("phases require writes");
+            }
+            if self.max_db_size.is_some() {
+                bail!("a maximum database size requires writes");
+            }
+        }
+        if let Some(percentile) = self
+            .percentiles
+            .iter()
+            .find(|percentile| !(0.0..=100.0).contains(*percentile))
+        {
+            bail!("percentile {percentile} is not between 0 and 100");
+        }
+        if self.id_refresh_interval.is_zero() {
+            bail!("the id refresh interval must be greater than zero");
+        }
+        if self.query_timeout.is_some_and(|timeout| timeout.is_zero()) {
+            bail!("the query timeout must be greater than zero");
+        }
+        if self
+            .report_interval
+            .is_some_and(|interval| interval.is_zero())
+        {
+            bail!("the report interval must be greater than zero");
+        }
+        if let Some(url) = &self.influx_url {
+            check_url(url).map_err(|e| anyhow!("invalid InfluxDB URL: {e}"))?;
+        } else if self.influx_interval.is_some() || self.run_id.is_some() {
+            bail!("an InfluxDB interval or run id requires an InfluxDB URL");
+        }
+        if self
+            .influx_interval
+            .is_some_and(|interval| interval.is_zero())
+        {
+            bail!("the InfluxDB interval must be greater than zero");
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defaults_match_the_command_line() {
+        let config = BenchmarkConfig::for_operations(10);
+        assert!(config.validate().is_ok());
+        assert_eq!(config.stop, StopCondition::Operations(10));
+        assert_eq!(config.transactions_per_second, 10);
+        assert_eq!(config.concurrency, 1);
+        assert_eq!(config.write_tables.len(), Table::iter().count());
+    }
+
+    #[test]
+    fn invalid_settings_are_errors() {
+        let config = BenchmarkConfig::for_operations(10).write_mix(50, 50, 50);
+        assert_eq!(
+            config.validate().unwrap_err().to_string(),
+            "the write mix must sum to 100, not 150"
+        );
+        let config = BenchmarkConfig::for_operations(10).concurrency(0);
+        assert!(config.validate().is_err());
+        let config =
+            BenchmarkConfig::for_duration(Duration::from_secs(10)).ramp(Duration::from_secs(10));
+        assert_eq!(
+            config.validate().unwrap_err().to_string(),
+            "the ramp must be shorter than the duration"
+        );
+        let config = BenchmarkConfig::for_operations(10)
+            .no_writes(true)
+            .read_your_writes(5);
+        assert!(config.validate().is_err());
+        let config = BenchmarkConfig::for_operations(10).run_id("nightly");
+        assert_eq!(
+            config.validate().unwrap_err().to_string(),
+            "an InfluxDB interval or run id requires an InfluxDB URL"
+        );
+        let url = Url::parse("https://localhost:8086/write?db=bench").unwrap();
+        let config = BenchmarkConfig::for_operations(10).influx_url(url);
+        assert_eq!(
+            config.validate().unwrap_err().to_string(),
+            "invalid InfluxDB URL: unsupported scheme 'https' in \
+             'https://localhost:8086/write?db=bench', expected udp or http"
+        );
+    }
+
+    #[test]
+    fn cross_field_settings_are_checked() {
+        let inserts = Phase {
+            write_mix: WriteMix::INSERTS,
+            duration: Duration::from_secs(30),
+        };
+        let updates = Phase {
+            write_mix: WriteMix::UPDATES,
+            ..inserts
+        };
+        let config = BenchmarkConfig::for_duration(Duration::from_secs(45))
+            .duration_mode(DurationMode::Wallclock)
+            .phases([inserts; 2]);
+        assert_eq!(
+            config.validate().unwrap_err().to_string(),
+            "the phases add up to 1m, longer than the duration of 45s"
+        );
+        let config = BenchmarkConfig::for_operations(10)
+            .write_mix(100, 0, 0)
+            .write_tables([Table::Securities])
+            .phases([updates]);
+        assert!(config.validate().is_err());
+        let config = config.phases([inserts]);
+        assert!(config.validate().is_ok());
+        let config = BenchmarkConfig::for_duration(Duration::from_secs(45)).phases([inserts]);
+        assert_eq!(
+            config.validate().unwrap_err().to_string(),
+            "phases are timed in wall-clock time and require the wall-clock duration mode"
+        );
+
+        let config = BenchmarkConfig::for_operations(10).no_writes(true);
+        assert!(config.validate().is_ok());
+        assert!(config.clone().measure_lag(true).validate().is_err());
+        assert!(config.clone().phases([inserts]).validate().is_err());
+        assert!(config.max_db_size(1 << 20).validate().is_err());
+
+        let config = BenchmarkConfig::for_operations(10).percentiles([99.0, 100.5]);
+        assert_eq!(
+            config.validate().unwrap_err().to_string(),
+            "percentile 100.5 is not between 0 and 100"
+        );
+        assert!(BenchmarkConfig::for_operations(10)
+            .percentiles([f64::NAN])
+            .validate()
+            .is_err());
+    }
+
+    #[test]
+    fn read_weights_must_cover_a_read_operation() {
+        let weights = WeightedReadSelector::new(&[(ReadOperation::TopPerformers, 0)]).unwrap();
+        let config = BenchmarkConfig::for_operations(10).read_weights(weights);
+        assert!(config.validate().is_ok());
+        let config = config.read_operations([ReadOperation::TopPerformers]);
+        assert_eq!(
+            config.validate().unwrap_err().to_string(),
+            "invalid read weights: at least one of the read operations must have a non-zero weight"
+        );
+    }
+
+    #[test]
+    fn pacing_measures_wall_clock_time() {
+        let config = BenchmarkConfig::for_duration(Duration::from_secs(1)).target_read_tps(100);
+        assert_eq!(config.duration_mode, DurationMode::Wallclock);
+        assert!(config.validate().is_ok());
+
+        let config = config.duration_mode(DurationMode::Experiment);
+        assert!(config.validate().is_err());
+    }
+}
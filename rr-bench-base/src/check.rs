@@ -0,0 +1,166 @@
+use crate::freshness::NO_PRICE;
+use crate::operations::ReadOperation;
+use crate::{PrimaryDatabase, ReadReplica};
+use anyhow::{bail, Result};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use strum::IntoEnumIterator;
+
+/// Runs every random-id query against the primary database and every read
+/// operation against the read replica once, reporting each one that fails.
+/// Returns an error if any check failed.
+pub fn check<P: PrimaryDatabase, R: ReadReplica>(primary: &mut P, reader: &mut R) -> Result<()> {
+    let mut rng = StdRng::from_entropy();
+    let mut failures = 0;
+    let mut report = |name: &str, result: Result<()>| match result {
+        Ok(()) => eprintln!("ok      {name}"),
+        Err(e) => {
+            failures += 1;
+            eprintln!("FAILED  {name}: {e:#}");
+        }
+    };
+
+    report(
+        "get_random_customer_id",
+        primary.get_random_customer_id(&mut rng).map(|_| ()),
+    );
+    report(
+        "get_random_account_id",
+        primary.get_random_account_id(&mut rng).map(|_| ()),
+    );
+    report(
+        "get_random_security_id",
+        primary.get_random_security_id(&mut rng).map(|_| ()),
+    );
+    report(
+        "get_random_trade_id",
+        primary.get_random_trade_id(&mut rng).map(|_| ()),
+    );
+    report(
+        "get_random_order_id",
+        primary.get_random_order_id(&mut rng).map(|_| ()),
+    );
+    report(
+        "get_random_market_data_id",
+        primary.get_random_market_data_id(&mut rng).map(|_| ()),
+    );
+    report(
+        "get_random_ticker",
+        primary.get_random_ticker(&mut rng).map(|_| ()),
+    );
+    report(
+        "get_random_sector",
+        primary.get_random_sector(&mut rng).map(|_| ()),
+    );
+
+    for operation in ReadOperation::iter() {
+        report(
+            operation.as_ref(),
+            read(primary, reader, &mut rng, operation),
+        );
+    }
+
+    if failures > 0 {
+        bail!("{failures} checks failed");
+    }
+
+    Ok(())
+}
+
+fn read<P: PrimaryDatabase, R: ReadReplica>(
+    primary: &mut P,
+    reader: &mut R,
+    rng: &mut StdRng,
+    operation: ReadOperation,
+) -> Result<()> {
+    let rows = match operation {
+        ReadOperation::CustomerPortfolio => {
+            reader.customer_portfolio(primary.get_random_customer_id(rng)?)
+        }
+        ReadOperation::TopPerformers => reader.top_performers(),
+        ReadOperation::MarketOverview => reader.market_overview(&primary.get_random_sector(rng)?),
+        ReadOperation::RecentLargeTrades => {
+            reader.recent_large_trades(primary.get_random_account_id(rng)?)
+        }
+        ReadOperation::CustomerOrderBook => {
+            reader.customer_order_book(primary.get_random_customer_id(rng)?)
+        }
+        ReadOperation::SectorPerformance => {
+            reader.sector_performance(primary.get_random_sector(rng)?)
+        }
+        ReadOperation::AccountActivitySummary => {
+            reader.account_activity_summary(primary.get_random_account_id(rng)?)
+        }
+        ReadOperation::DailyMarketMovements => {
+            reader.daily_market_movements(primary.get_random_security_id(rng)?)
+        }
+        ReadOperation::HighValueCustomers => reader.high_value_customers(),
+        ReadOperation::PendingOrdersSummary => {
+            reader.pending_orders_summary(&primary.get_random_ticker(rng)?)
+        }
+        ReadOperation::TradeVolumeByHour => reader.trade_volume_by_hour(),
+        ReadOperation::TopSecuritiesBySector => {
+            reader.top_securities_by_sector(primary.get_random_sector(rng)?)
+        }
+        ReadOperation::RecentTradesByAccount => {
+            reader.recent_trades_by_account(primary.get_random_account_id(rng)?)
+        }
+        ReadOperation::OrderFulfillmentRates => {
+            reader.order_fulfillment_rates(primary.get_random_customer_id(rng)?)
+        }
+        ReadOperation::SectorOrderActivity => {
+            reader.sector_order_activity(primary.get_random_sector(rng)?)
+        }
+        ReadOperation::CascadingOrderCancellationAlert => {
+            reader.cascading_order_cancellation_alert()
+        }
+        ReadOperation::RowFreshness => {
+            return reader
+                .check_row_freshness(primary.get_random_security_id(rng)?, NO_PRICE)
+                .map(|_| ());
+        }
+    };
+    rows.map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{FakeDatabase, FakeReplica};
+    use rand::SeedableRng;
+    use std::collections::BTreeSet;
+
+    /// The read methods of `ReadReplica` that every benchmark implements.
+    const READ_METHODS: &[&str] = &[
+        "customer_portfolio",
+        "top_performers",
+        "market_overview",
+        "recent_large_trades",
+        "customer_order_book",
+        "sector_performance",
+        "account_activity_summary",
+        "daily_market_movements",
+        "high_value_customers",
+        "pending_orders_summary",
+        "trade_volume_by_hour",
+        "top_securities_by_sector",
+        "recent_trades_by_account",
+        "order_fulfillment_rates",
+        "sector_order_activity",
+        "cascading_order_cancellation_alert",
+        "check_row_freshness",
+    ];
+
+    #[test]
+    fn every_read_method_has_one_read_operation() {
+        assert_eq!(ReadOperation::iter().count(), READ_METHODS.len());
+
+        let mut reader = FakeReplica::default();
+        let mut rng = StdRng::seed_from_u64(0);
+        for operation in ReadOperation::iter() {
+            read(&mut FakeDatabase, &mut reader, &mut rng, operation).unwrap();
+        }
+        let called: BTreeSet<_> = reader.calls.iter().copied().collect();
+        assert_eq!(called, READ_METHODS.iter().copied().collect());
+    }
+}
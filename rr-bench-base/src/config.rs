@@ -1,5 +1,20 @@
-use clap::{value_parser, Arg, ArgMatches, Command};
+use crate::benchmark_config::BenchmarkConfig;
+use crate::influx::parse_influx_url;
+use crate::operations::{ReadOperation, Table};
+use crate::primary_simulator::{parse_phases, parse_table, Phase, WriteMix};
+use crate::read_selector::{parse_read_operation, WeightedReadSelector};
+use crate::sla::Sla;
+use clap::error::ErrorKind;
+use clap::parser::ValueSource;
+use clap::{value_parser, Arg, ArgAction, ArgGroup, ArgMatches, Command, ValueEnum};
+use rand::{thread_rng, Rng};
+use std::path::PathBuf;
 use std::time::Duration;
+use strum::IntoEnumIterator;
+use url::Url;
+
+/// The duration of the benchmark run with `--smoke`.
+const SMOKE_DURATION: Duration = Duration::from_secs(5);
 
 pub struct Args {
     command: Command,
@@ -12,10 +27,44 @@ impl Args {
                 Arg::new("duration")
                     .short('d')
                     .long("duration")
-                    .help("The duration of the benchmark (e.g., 10s, 5m, 1h)")
+                    .help("The duration of the benchmark (e.g., 10s, 5m, 1h). Required unless --operations or --smoke is given")
                     .value_name("DURATION")
-                    .required(true)
                     .value_parser(parse_duration)
+                    .required_unless_present_any(["operations", "smoke"]),
+            )
+            .arg(
+                Arg::new("operations")
+                    .long("operations")
+                    .help("The total number of read operations to run, split evenly across clients. An alternative to --duration")
+                    .value_name("COUNT")
+                    .value_parser(value_parser!(u64).range(1..)),
+            )
+            .group(
+                ArgGroup::new("stop_condition").args(["duration", "operations"]),
+            )
+            .arg(
+                Arg::new("duration_mode")
+                    .long("duration-mode")
+                    .help("How the duration is measured: 'experiment' counts only the time read clients spend in read operations, 'wallclock' counts real time")
+                    .value_name("MODE")
+                    .default_value("experiment")
+                    .value_parser(value_parser!(DurationMode)),
+            )
+            .arg(
+                Arg::new("warmup")
+                    .long("warmup")
+                    .help("The amount of time to run reads before measurements are recorded (e.g., 10s, 1m)")
+                    .value_name("DURATION")
+                    .default_value("0s")
+                    .value_parser(parse_duration),
+            )
+            .arg(
+                Arg::new("ramp")
+                    .long("ramp")
+                    .help("Stagger the start of the read clients evenly over this long (e.g., 30s), so that concurrency builds up gradually. Clients that start later run for less time, so that all of them stop at --duration")
+                    .value_name("DURATION")
+                    .default_value("0s")
+                    .value_parser(parse_duration),
             )
             .arg(
                 Arg::new("transactions_per_second")
@@ -25,7 +74,15 @@ impl Args {
                     )
                     .value_name("TPS")
                     .default_value("10")
-                    .value_parser(value_parser!(u32)),
+                    .value_parser(value_parser!(u32).range(1..)),
+            )
+            .arg(
+                Arg::new("write_pattern")
+                    .long("write-pattern")
+                    .help("How primary database transactions are spaced out: 'uniform' at a fixed interval, 'poisson' at random exponentially distributed intervals, 'burst' in alternating busy and idle one second windows. All three average --transactions-per-second")
+                    .value_name("PATTERN")
+                    .default_value("uniform")
+                    .value_parser(value_parser!(WritePattern)),
             )
             .arg(
                 Arg::new("concurrency")
@@ -34,36 +91,963 @@ impl Args {
                     .help("The number of concurrent clients to open against the read replica")
                     .value_name("CONCURRENCY")
                     .default_value("1")
+                    .value_parser(value_parser!(u32).range(1..)),
+            )
+            .arg(
+                Arg::new("target_read_tps")
+                    .long("target-read-tps")
+                    .help("Pace the read clients to this combined number of read operations per second. The duration is then measured in wall-clock time")
+                    .value_name("TPS")
+                    .value_parser(value_parser!(u32).range(1..)),
+            )
+            .arg(
+                Arg::new("correct_coordinated_omission")
+                    .long("correct-coordinated-omission")
+                    .help("Correct read latencies for coordinated omission by recording the reads a stalled client would have issued at its --target-read-tps rate")
+                    .requires("target_read_tps")
+                    .action(ArgAction::SetTrue),
+            )
+            .arg(
+                Arg::new("read_weights")
+                    .long("read-weights")
+                    .help("Relative weights of read operations (e.g., customer_portfolio=5,top_performers=1). Unlisted operations have a weight of 1. Defaults to running every operation once per cycle, in a random order")
+                    .value_name("WEIGHTS")
+                    .value_parser(|s: &str| s.parse::<WeightedReadSelector>()),
+            )
+            .arg(
+                Arg::new("read_ops")
+                    .long("read-ops")
+                    .help("The read operations to run (e.g., top_performers,market_overview), named after their views. Defaults to every operation")
+                    .value_name("OPERATIONS")
+                    .value_delimiter(',')
+                    .value_parser(parse_read_operation),
+            )
+            .arg(
+                Arg::new("think_time")
+                    .long("think-time")
+                    .help("The amount of time each client pauses between read operations (e.g., 10ms, 1s). Not included in measurements")
+                    .value_name("DURATION")
+                    .default_value("0s")
+                    .value_parser(parse_duration),
+            )
+            .arg(
+                Arg::new("think_time_jitter")
+                    .long("think-time-jitter")
+                    .help("The maximum random amount of time added to each --think-time pause (e.g., 5ms)")
+                    .value_name("DURATION")
+                    .default_value("0s")
+                    .value_parser(parse_duration),
+            )
+            .arg(
+                Arg::new("id_refresh_interval")
+                    .long("id-refresh-interval")
+                    .help("How often the ids that read operations look up are resampled from the primary database (e.g., 10s, 1m). Rows inserted since the last sample are not read")
+                    .value_name("DURATION")
+                    .default_value("10s")
+                    .value_parser(parse_duration),
+            )
+            .arg(
+                Arg::new("access_pattern")
+                    .long("access-pattern")
+                    .help("How read operations pick the ids, tickers, and sectors they look up among those sampled from the primary database: 'uniform' picks every one equally often, 'zipf' picks the n-th one in proportion to 1/n, 'hotset' sends 90% of the picks to --hotset-pct of them")
+                    .value_name("PATTERN")
+                    .default_value("uniform")
+                    .value_parser(value_parser!(AccessPattern)),
+            )
+            .arg(
+                Arg::new("hotset_pct")
+                    .long("hotset-pct")
+                    .help("The percentage of the sampled ids, tickers, and sectors that receive 90% of the picks with --access-pattern hotset")
+                    .value_name("PERCENT")
+                    .default_value("10")
+                    .value_parser(value_parser!(u32).range(1..=100)),
+            )
+            .arg(
+                Arg::new("reconnect_per_op")
+                    .long("reconnect-per-op")
+                    .help("Reconnect to the read replica before every read operation, or every N read operations, as a serverless client would. The time spent reconnecting is reported separately")
+                    .value_name("N")
+                    .num_args(0..=1)
+                    .default_missing_value("1")
+                    .value_parser(value_parser!(u32).range(1..)),
+            )
+            .arg(
+                Arg::new("query_timeout")
+                    .long("query-timeout")
+                    .help("Cancel read operations that run longer than this (e.g., 500ms, 5s). Timed out operations are reported separately from errors")
+                    .value_name("DURATION")
+                    .value_parser(parse_duration),
+            )
+            .arg(
+                Arg::new("consume")
+                    .long("consume")
+                    .help("How read operations consume the result of each query: 'all-rows' receives every row, 'first-row' fetches only the first row through a cursor, 'count' has the database count the rows and receives only the count")
+                    .value_name("MODE")
+                    .default_value("all-rows")
+                    .value_parser(value_parser!(Consume)),
+            )
+            .arg(
+                Arg::new("session_setting")
+                    .long("session-setting")
+                    .help("A setting applied with SET to every connection the benchmark opens, right after it connects (e.g., work_mem=64MB). May be given more than once")
+                    .value_name("KEY=VALUE")
+                    .action(ArgAction::Append)
+                    .value_parser(parse_session_setting),
+            )
+            .arg(
+                Arg::new("max_retries")
+                    .long("max-retries")
+                    .help("The number of times a failed operation is retried, with exponential backoff, before it is counted as an error")
+                    .value_name("RETRIES")
+                    .default_value("0")
                     .value_parser(value_parser!(u32)),
             )
+            .arg(
+                Arg::new("latency_includes_retries")
+                    .long("latency-includes-retries")
+                    .help("Whether the latency recorded for a read operation that succeeded after --max-retries retries includes the time spent on the attempts that failed (yes), or is only that of the attempt that succeeded (no). Backoff and reconnects are never included")
+                    .value_name("Y/N")
+                    .default_value("no")
+                    .value_parser(["yes", "no"]),
+            )
+            .arg(
+                Arg::new("fail_on_error")
+                    .long("fail-on-error")
+                    .help("Abort the benchmark on the first read operation or write transaction that still fails after --max-retries retries, print the measurements collected so far, and exit with a nonzero status. By default failures are counted as errors and the benchmark continues")
+                    .action(ArgAction::SetTrue),
+            )
+            .arg(
+                Arg::new("conflict_retries")
+                    .long("conflict-retries")
+                    .help("The number of times a write transaction that fails with a serialization failure or a deadlock is executed again, with a short exponential backoff, before it is handed to --max-retries. Only backends that can recognize these failures retry them, currently PostgreSQL")
+                    .value_name("RETRIES")
+                    .default_value("0")
+                    .value_parser(value_parser!(u32)),
+            )
+            .arg(
+                Arg::new("connect_retries")
+                    .long("connect-retries")
+                    .help("The number of times connecting to the primary database or a read replica is retried, with exponential backoff, before the benchmark fails, e.g. to ride out a rolling restart or failover. Retries stop once they would wait more than 2 minutes in total")
+                    .value_name("RETRIES")
+                    .default_value("0")
+                    .value_parser(value_parser!(u32)),
+            )
+            .arg(
+                Arg::new("connect_backoff")
+                    .long("connect-backoff")
+                    .help("The delay before the first connection retry (e.g., 500ms, 2s), doubled before each further retry")
+                    .value_name("DURATION")
+                    .default_value("500ms")
+                    .value_parser(parse_duration),
+            )
+            .arg(
+                Arg::new("insert_pct")
+                    .long("insert-pct")
+                    .help("The percentage of primary database operations that are inserts")
+                    .value_name("PERCENT")
+                    .default_value("45")
+                    .value_parser(value_parser!(u32).range(0..=100)),
+            )
+            .arg(
+                Arg::new("update_pct")
+                    .long("update-pct")
+                    .help("The percentage of primary database operations that are updates")
+                    .value_name("PERCENT")
+                    .default_value("45")
+                    .value_parser(value_parser!(u32).range(0..=100)),
+            )
+            .arg(
+                Arg::new("delete_pct")
+                    .long("delete-pct")
+                    .help("The percentage of primary database operations that are deletes")
+                    .value_name("PERCENT")
+                    .default_value("10")
+                    .value_parser(value_parser!(u32).range(0..=100)),
+            )
+            .arg(
+                Arg::new("phases")
+                    .long("phases")
+                    .help("Phases in which the primary database simulator only inserts, updates, or deletes, run one after another from the start of the benchmark (e.g., insert:2m,delete:1m). Once they are over, --insert-pct, --update-pct, and --delete-pct apply again. Phases are timed in real time, so with --duration they require --duration-mode wallclock")
+                    .value_name("PHASES")
+                    .value_parser(parse_phases)
+                    .conflicts_with("no_writes"),
+            )
+            .arg(
+                Arg::new("max_db_size")
+                    .long("max-db-size")
+                    .help("Replace inserts with deletes while the primary database is at least this large (e.g., 500mb, 2gib), checked every 5 seconds. Requires a backend that reports its size, as for --track-growth")
+                    .value_name("SIZE")
+                    .value_parser(parse_size)
+                    .conflicts_with("no_writes"),
+            )
+            .arg(
+                Arg::new("write_tables")
+                    .long("write-tables")
+                    .help("The tables the primary database simulator writes to (e.g., trades,market_data). Defaults to every table")
+                    .value_name("TABLES")
+                    .value_delimiter(',')
+                    .value_parser(parse_table),
+            )
+            .arg(
+                Arg::new("delete_leaf_bias")
+                    .long("delete-leaf-bias")
+                    .help("The percentage of deletes that target trades, orders, or market_data, which no other table references, instead of a random table. Other deletes rely on ON DELETE CASCADE")
+                    .value_name("PERCENT")
+                    .default_value("0")
+                    .value_parser(value_parser!(u32).range(0..=100)),
+            )
+            .arg(
+                Arg::new("batch_size")
+                    .long("batch-size")
+                    .help("The number of write operations executed in each primary database transaction")
+                    .value_name("SIZE")
+                    .default_value("1")
+                    .value_parser(value_parser!(u32).range(1..)),
+            )
+            .arg(
+                Arg::new("hierarchy_pct")
+                    .long("hierarchy-pct")
+                    .help("The percentage of inserted accounts, trades, and orders that reference an existing row of the same table as their parent")
+                    .value_name("PERCENT")
+                    .default_value("0")
+                    .value_parser(value_parser!(u32).range(0..=100)),
+            )
+            .arg(
+                Arg::new("seed")
+                    .long("seed")
+                    .help("The seed used to generate the workload. Defaults to a random seed")
+                    .value_name("SEED")
+                    .value_parser(value_parser!(u64)),
+            )
+            .arg(
+                Arg::new("no_writes")
+                    .long("no-writes")
+                    .help("Run the read clients only, without the primary database simulator, so the data stays unchanged. The ids that read operations look up are sampled once and not refreshed")
+                    .action(ArgAction::SetTrue)
+                    .conflicts_with("measure_lag"),
+            )
+            .arg(
+                Arg::new("measure_lag")
+                    .long("measure-lag")
+                    .help("Measure replication lag between the primary database and the read replica")
+                    .action(ArgAction::SetTrue),
+            )
+            .arg(
+                Arg::new("read_your_writes")
+                    .long("read-your-writes")
+                    .help("After every N read operations, each read client inserts a customer into the primary database and reads it back from the read replica, polling until it is visible. Reports how often the first read found it, and how long it took to become visible")
+                    .value_name("N")
+                    .value_parser(value_parser!(u32).range(1..))
+                    .conflicts_with("no_writes"),
+            )
+            .arg(
+                Arg::new("track_growth")
+                    .long("track-growth")
+                    .help("Periodically sample the size of the primary database and report how much it grew during the run")
+                    .action(ArgAction::SetTrue),
+            )
+            .arg(
+                Arg::new("report_interval")
+                    .long("report-interval")
+                    .help("Periodically print a summary of recent reads to stderr (e.g., 1s, 10s)")
+                    .value_name("DURATION")
+                    .value_parser(parse_duration),
+            )
+            .arg(
+                Arg::new("samples_out")
+                    .long("samples-out")
+                    .help("Write every measured read latency to a CSV file")
+                    .value_name("PATH")
+                    .value_parser(value_parser!(PathBuf)),
+            )
+            .arg(
+                Arg::new("timeseries_out")
+                    .long("timeseries-out")
+                    .help("Write the number of reads, their TPS, and their 95th percentile latency for every second of the run to a CSV file")
+                    .value_name("PATH")
+                    .value_parser(value_parser!(PathBuf)),
+            )
+            .arg(
+                Arg::new("influx_url")
+                    .long("influx-url")
+                    .help("Push the number of reads, their TPS, and their latency percentiles per read operation, and the number of errors, in the InfluxDB line protocol to this endpoint, either udp://HOST:PORT or an HTTP write URL (e.g., http://localhost:8086/write?db=bench)")
+                    .value_name("URL")
+                    .value_parser(parse_influx_url),
+            )
+            .arg(
+                Arg::new("influx_interval")
+                    .long("influx-interval")
+                    .help("Push the metrics of the reads completed in every interval of this length to --influx-url while the benchmark runs (e.g., 10s). By default they are pushed once, for the whole run, when the benchmark finishes")
+                    .value_name("DURATION")
+                    .requires("influx_url")
+                    .value_parser(parse_duration),
+            )
+            .arg(
+                Arg::new("run_id")
+                    .long("run-id")
+                    .help("The run_id tag of every point pushed to --influx-url, which tells runs apart. Defaults to the time the benchmark started, in seconds since the Unix epoch")
+                    .value_name("ID")
+                    .requires("influx_url"),
+            )
+            .arg(
+                Arg::new("max_p99")
+                    .long("max-p99")
+                    .help("Exit with code 3 if the 99th percentile read latency exceeds this value (e.g., 200ms)")
+                    .value_name("DURATION")
+                    .value_parser(parse_duration),
+            )
+            .arg(
+                Arg::new("min_tps")
+                    .long("min-tps")
+                    .help("Exit with code 3 if fewer read operations per second than this were completed")
+                    .value_name("TPS")
+                    .value_parser(value_parser!(f64)),
+            )
+            .args(prometheus_args())
+            .arg(
+                Arg::new("view_prefix")
+                    .long("view-prefix")
+                    .help("A prefix added to the name of every view queried on the read replica, such as a schema name (e.g., bench.). Defaults to no prefix")
+                    .value_name("PREFIX"),
+            )
+            .arg(
+                Arg::new("compare_reader_url")
+                    .long("compare-reader-url")
+                    .help("Run the same read workload against a second read replica at the same time, and report the differences between the two. The ids that read operations look up are sampled once and not refreshed, so both read replicas are queried with the same ids")
+                    .value_name("URL"),
+            )
+            .arg(
+                Arg::new("check")
+                    .long("check")
+                    .help("Verify that every table and view used by the benchmark exists, then exit without running it")
+                    .action(ArgAction::SetTrue),
+            )
+            .arg(
+                Arg::new("smoke")
+                    .long("smoke")
+                    .help("Verify the setup end to end: run the checks of --check, then a 5 second benchmark with 1 client at 10 transactions per second, and fail if any operation fails")
+                    .conflicts_with_all(["stop_condition", "concurrency", "transactions_per_second", "check"])
+                    .action(ArgAction::SetTrue),
+            )
+            .arg(
+                Arg::new("apply_schema")
+                    .long("apply-schema")
+                    .help("Create the views queried by the read clients on the read replica before running the benchmark. The views must not already exist")
+                    .conflicts_with("view_prefix")
+                    .action(ArgAction::SetTrue),
+            )
+            .arg(
+                Arg::new("progress")
+                    .long("progress")
+                    .help("How read client progress is displayed")
+                    .value_name("MODE")
+                    .default_value("per-client")
+                    .value_parser(value_parser!(ProgressMode)),
+            )
+            .arg(
+                Arg::new("output")
+                    .long("output")
+                    .visible_alias("format")
+                    .help("The format used to report the benchmark results")
+                    .value_name("FORMAT")
+                    .default_value("text")
+                    .value_parser(value_parser!(OutputFormat)),
+            )
+            .arg(
+                Arg::new("log_level")
+                    .long("log-level")
+                    .help("The least severe log messages printed to stderr")
+                    .value_name("LEVEL")
+                    .default_value("info")
+                    .value_parser(value_parser!(LogLevel)),
+            )
+            .arg(
+                Arg::new("latency_unit")
+                    .long("latency-unit")
+                    .help("The unit every latency in the text report and live reports is printed in. JSON output is always in milliseconds")
+                    .value_name("UNIT")
+                    .default_value("ms")
+                    .value_parser(value_parser!(LatencyUnit)),
+            )
+            .arg(
+                Arg::new("prime_connections")
+                    .long("prime-connections")
+                    .help("Open and ping every pooled primary database connection and every read replica connection before the read clients start, so that the measured run does not pay for establishing them")
+                    .action(ArgAction::SetTrue),
+            )
+            .arg(
+                Arg::new("histogram")
+                    .long("histogram")
+                    .help("Add a histogram of read latencies, in log-spaced buckets, to the text report")
+                    .action(ArgAction::SetTrue),
+            )
+            .arg(
+                Arg::new("percentiles")
+                    .long("percentiles")
+                    .help("The percentiles of the read latencies to report besides the median, in the order given (e.g., 50,90,99,99.9)")
+                    .value_name("PERCENTILES")
+                    .value_delimiter(',')
+                    .default_value("90,95,99,99.9")
+                    .value_parser(parse_percentile),
+            )
             .args(args);
 
         Self { command }
     }
 
-    pub fn parse(self) -> Cli {
-        let matches = self.command.get_matches();
-        let duration = *matches.get_one::<Duration>("duration").unwrap();
+    pub fn parse(mut self) -> Cli {
+        let matches = self.command.get_matches_mut();
+        self.cli(matches)
+    }
+
+    /// Parses `args`, which do not include the program name, instead of
+    /// the command line.
+    #[cfg(test)]
+    pub fn parse_from(mut self, args: &[&str]) -> Cli {
+        let matches = self
+            .command
+            .try_get_matches_from_mut(["rr-bench"].iter().chain(args))
+            .unwrap();
+        self.cli(matches)
+    }
+
+    fn cli(&mut self, matches: ArgMatches) -> Cli {
+        let smoke = matches.get_flag("smoke");
+        let stop = match matches.get_one::<u64>("operations") {
+            Some(&operations) => StopCondition::Operations(operations),
+            None if smoke => StopCondition::Duration(SMOKE_DURATION),
+            None => StopCondition::Duration(*matches.get_one::<Duration>("duration").unwrap()),
+        };
+        let mut duration_mode = *matches.get_one::<DurationMode>("duration_mode").unwrap();
+        let warmup = *matches.get_one::<Duration>("warmup").unwrap();
+        let ramp = *matches.get_one::<Duration>("ramp").unwrap();
+        let mut read_operations: Vec<ReadOperation> = match matches.get_many("read_ops") {
+            Some(operations) => operations.copied().collect(),
+            None => ReadOperation::iter().collect(),
+        };
+        read_operations.sort();
+        read_operations.dedup();
+        let read_weights = matches
+            .get_one::<WeightedReadSelector>("read_weights")
+            .cloned();
+        let think_time = *matches.get_one::<Duration>("think_time").unwrap();
+        let think_time_jitter = *matches.get_one::<Duration>("think_time_jitter").unwrap();
+        let id_refresh_interval = *matches.get_one::<Duration>("id_refresh_interval").unwrap();
+        let access_pattern = *matches.get_one::<AccessPattern>("access_pattern").unwrap();
+        let hotset_pct = *matches.get_one::<u32>("hotset_pct").unwrap();
+        let reconnect_every = matches.get_one::<u32>("reconnect_per_op").copied();
+        let query_timeout = matches.get_one::<Duration>("query_timeout").copied();
+        let max_retries = *matches.get_one::<u32>("max_retries").unwrap();
+        let latency_includes_retries = matches
+            .get_one::<String>("latency_includes_retries")
+            .unwrap()
+            == "yes";
+        let fail_on_error = matches.get_flag("fail_on_error");
+        let conflict_retries = *matches.get_one::<u32>("conflict_retries").unwrap();
+        let connect_retries = *matches.get_one::<u32>("connect_retries").unwrap();
+        let connect_backoff = *matches.get_one::<Duration>("connect_backoff").unwrap();
         let transactions_per_second = *matches.get_one::<u32>("transactions_per_second").unwrap();
+        let write_pattern = *matches.get_one::<WritePattern>("write_pattern").unwrap();
         let concurrency = *matches.get_one::<u32>("concurrency").unwrap();
+        let target_read_tps = matches.get_one::<u32>("target_read_tps").copied();
+        let correct_coordinated_omission = matches.get_flag("correct_coordinated_omission");
+        let batch_size = *matches.get_one::<u32>("batch_size").unwrap();
+        let hierarchy_pct = *matches.get_one::<u32>("hierarchy_pct").unwrap();
+        let delete_leaf_bias = *matches.get_one::<u32>("delete_leaf_bias").unwrap();
+        let mut write_tables: Vec<Table> = match matches.get_many::<Table>("write_tables") {
+            Some(tables) => tables.copied().collect(),
+            None => Table::iter().collect(),
+        };
+        write_tables.sort();
+        write_tables.dedup();
+        let seed = matches
+            .get_one::<u64>("seed")
+            .copied()
+            .unwrap_or_else(|| thread_rng().gen());
+        let no_writes = matches.get_flag("no_writes");
+        let measure_lag = matches.get_flag("measure_lag");
+        let read_your_writes = matches.get_one::<u32>("read_your_writes").copied();
+        let track_growth = matches.get_flag("track_growth");
+        let report_interval = matches.get_one::<Duration>("report_interval").copied();
+        let samples_out = matches.get_one::<PathBuf>("samples_out").cloned();
+        let timeseries_out = matches.get_one::<PathBuf>("timeseries_out").cloned();
+        let influx_url = matches.get_one::<Url>("influx_url").cloned();
+        let influx_interval = matches.get_one::<Duration>("influx_interval").copied();
+        let run_id = matches.get_one::<String>("run_id").cloned();
+        let sla = Sla {
+            max_p99: matches.get_one::<Duration>("max_p99").copied(),
+            min_tps: matches.get_one::<f64>("min_tps").copied(),
+        };
+        let compare = matches.contains_id("compare_reader_url");
+        let check = matches.get_flag("check");
+        let apply_schema = matches.get_flag("apply_schema");
+        let prime_connections = matches.get_flag("prime_connections");
+        #[cfg(feature = "prometheus")]
+        let prometheus_port = matches.get_one::<u16>("prometheus_port").copied();
+        let progress = *matches.get_one::<ProgressMode>("progress").unwrap();
+        let output = *matches.get_one::<OutputFormat>("output").unwrap();
+        let histogram = matches.get_flag("histogram");
+        let percentiles = matches
+            .get_many::<f64>("percentiles")
+            .unwrap()
+            .copied()
+            .collect();
+        let latency_unit = *matches.get_one::<LatencyUnit>("latency_unit").unwrap();
+        let log_level = *matches.get_one::<LogLevel>("log_level").unwrap();
 
-        Cli {
-            duration,
+        let phases = matches
+            .get_one::<Vec<Phase>>("phases")
+            .cloned()
+            .unwrap_or_default();
+        let max_db_size = matches.get_one::<u64>("max_db_size").copied();
+        let write_mix = WriteMix {
+            insert: *matches.get_one::<u32>("insert_pct").unwrap(),
+            update: *matches.get_one::<u32>("update_pct").unwrap(),
+            delete: *matches.get_one::<u32>("delete_pct").unwrap(),
+        };
+
+        // Pacing is defined in wall-clock terms, so paced clients measure
+        // their duration in wall-clock time unless told otherwise, which
+        // `validate` rejects.
+        if target_read_tps.is_some()
+            && matches.value_source("duration_mode") != Some(ValueSource::CommandLine)
+        {
+            duration_mode = DurationMode::Wallclock;
+        }
+
+        let config = BenchmarkConfig {
+            stop,
+            duration_mode,
+            warmup,
+            ramp,
             transactions_per_second,
+            write_pattern,
             concurrency,
+            target_read_tps,
+            correct_coordinated_omission,
+            read_operations,
+            read_weights,
+            think_time,
+            think_time_jitter,
+            id_refresh_interval,
+            access_pattern,
+            hotset_pct,
+            reconnect_every,
+            query_timeout,
+            max_retries,
+            latency_includes_retries,
+            fail_on_error,
+            conflict_retries,
+            connect_retries,
+            connect_backoff,
+            write_mix,
+            phases,
+            max_db_size,
+            batch_size,
+            hierarchy_pct,
+            delete_leaf_bias,
+            write_tables,
+            seed,
+            no_writes,
+            measure_lag,
+            read_your_writes,
+            track_growth,
+            report_interval,
+            samples_out,
+            timeseries_out,
+            influx_url,
+            influx_interval,
+            run_id,
+            compare,
+            smoke,
+            apply_schema,
+            prime_connections,
+            #[cfg(feature = "prometheus")]
+            prometheus_port,
+            progress,
+            percentiles,
+        };
+        // The cross-field checks are shared with `run_with_config`.
+        if let Err(e) = config.validate() {
+            self.command
+                .error(ErrorKind::ArgumentConflict, e.to_string())
+                .exit();
+        }
+
+        Cli {
+            config,
+            sla,
+            check,
+            output,
+            histogram,
+            latency_unit,
+            log_level,
             matches,
         }
     }
 }
 
+#[cfg(feature = "prometheus")]
+fn prometheus_args() -> Vec<Arg> {
+    vec![Arg::new("prometheus_port")
+        .long("prometheus-port")
+        .help("Serve live metrics in the Prometheus text format on this port")
+        .value_name("PORT")
+        .value_parser(value_parser!(u16))]
+}
+
+#[cfg(not(feature = "prometheus"))]
+fn prometheus_args() -> Vec<Arg> {
+    vec![]
+}
+
 fn parse_duration(s: &str) -> Result<Duration, String> {
     humantime::parse_duration(s)
         .map_err(|_| format!("Invalid duration {}. Use formats like '10s', '5m', '1h'", s))
 }
 
+/// Parses a percentile between 0 and 100, which may be fractional, like 99.9.
+fn parse_percentile(s: &str) -> Result<f64, String> {
+    let percentile: f64 = s
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid percentile '{s}', expected a number"))?;
+    if !(0.0..=100.0).contains(&percentile) {
+        return Err(format!("percentile {s} is not between 0 and 100"));
+    }
+    Ok(percentile)
+}
+
+/// Parses a human-readable size, like `500mb` or `1.5gb`, into bytes.
+pub fn parse_size(s: &str) -> Result<u64, String> {
+    let s = s.trim().to_ascii_lowercase();
+    let split = s.find(|c: char| c.is_ascii_alphabetic()).unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let number: f64 = number
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid size '{s}', expected a number followed by a unit"))?;
+    let multiplier: u64 = match unit {
+        "" | "b" => 1,
+        "kb" => 1000,
+        "mb" => 1000 * 1000,
+        "gb" => 1000 * 1000 * 1000,
+        "tb" => 1000 * 1000 * 1000 * 1000,
+        "kib" => 1024,
+        "mib" => 1024 * 1024,
+        "gib" => 1024 * 1024 * 1024,
+        "tib" => 1024 * 1024 * 1024 * 1024,
+        _ => {
+            return Err(format!(
+                "unknown unit '{unit}', expected b, kb, mb, gb, tb, kib, mib, gib or tib"
+            ))
+        }
+    };
+
+    let bytes = number * multiplier as f64;
+    if bytes < 1.0 {
+        return Err(format!("invalid size '{s}', expected at least one byte"));
+    }
+    if bytes > u64::MAX as f64 {
+        return Err(format!("invalid size '{s}', too large"));
+    }
+    Ok(bytes as u64)
+}
+
+/// Parses a `--session-setting` of the form `key=value`. The key is used
+/// unquoted in a SET statement, so it may only contain letters, digits,
+/// underscores, and dots, and must not start with a digit.
+fn parse_session_setting(s: &str) -> Result<(String, String), String> {
+    let (key, value) = s
+        .split_once('=')
+        .ok_or_else(|| format!("invalid session setting '{s}', expected KEY=VALUE"))?;
+    let (key, value) = (key.trim(), value.trim());
+    let valid_key = key
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.');
+    if !valid_key {
+        return Err(format!(
+            "invalid session setting name '{key}', expected letters, digits, '_' or '.'"
+        ));
+    }
+    if value.is_empty() {
+        return Err(format!("missing value for session setting '{key}'"));
+    }
+    Ok((key.to_string(), value.to_string()))
+}
+
+/// How read client progress is displayed while the benchmark runs.
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum ProgressMode {
+    /// One progress bar per read client
+    PerClient,
+    /// A single progress bar that follows the least-progressed read client
+    Summary,
+    /// No progress bars, e.g. for CI logs
+    None,
+}
+
+/// The format used to print the final `Measurements`.
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum OutputFormat {
+    /// Human readable summary
+    Text,
+    /// A single JSON object, suitable for downstream tooling
+    Json,
+    /// A single line of key=value pairs, suitable for grepping CI logs
+    Oneline,
+}
+
+/// The unit latencies are printed in by the text report.
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum LatencyUnit {
+    /// Microseconds, with 3 decimals
+    Us,
+    /// Milliseconds, with 6 decimals
+    Ms,
+    /// Seconds, with 9 decimals
+    S,
+}
+
+/// The least severe log messages that are printed.
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum LogLevel {
+    /// Nothing
+    Off,
+    /// Errors that stop the benchmark or fail an operation
+    Error,
+    /// Also retried operations and interruptions
+    Warn,
+    /// Also the seed and the benchmark duration
+    Info,
+    /// Also connection setup, client startup, and shutdown
+    Debug,
+    /// Everything
+    Trace,
+}
+
+/// When the read clients stop running the benchmark.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StopCondition {
+    /// Run for a fixed amount of time, measured according to the `DurationMode`.
+    Duration(Duration),
+    /// Run a fixed number of read operations.
+    Operations(u64),
+}
+
+/// How a read client measures the benchmark duration.
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum DurationMode {
+    /// The summed time of read operations, excluding think time and pacing
+    Experiment,
+    /// Real time since the client started
+    Wallclock,
+}
+
+/// How read operations pick the values they look up from the sampled ones.
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum AccessPattern {
+    /// Every sampled value equally often
+    Uniform,
+    /// The n-th sampled value in proportion to 1/n
+    Zipf,
+    /// Most often a hot set of --hotset-pct of the sampled values
+    Hotset,
+}
+
+/// How the primary database simulator spaces out its transactions.
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum WritePattern {
+    /// A fixed interval between transactions
+    Uniform,
+    /// Exponentially distributed intervals, as in a Poisson process
+    Poisson,
+    /// Alternating windows of transactions at twice the rate and no transactions
+    Burst,
+}
+
+/// How a read operation consumes the result of its query. Read by the
+/// backends, which refuse the modes they cannot implement.
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum Consume {
+    /// Receive every row, measuring the time to the last row
+    AllRows,
+    /// Fetch only the first row, measuring the time to the first row
+    FirstRow,
+    /// Count the rows in the database and receive only the count
+    Count,
+}
+
+/// The parsed command line: the configuration of the benchmark run, and
+/// the settings of how its results are checked and printed.
 pub struct Cli {
-    pub duration: Duration,
-    pub transactions_per_second: u32,
-    pub concurrency: u32,
+    pub config: BenchmarkConfig,
+    pub sla: Sla,
+    pub check: bool,
+    pub output: OutputFormat,
+    pub histogram: bool,
+    pub latency_unit: LatencyUnit,
+    pub log_level: LogLevel,
     pub matches: ArgMatches,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn try_parse(args: &[&str]) -> Result<ArgMatches, clap::Error> {
+        Args::new(Vec::<Arg>::new())
+            .command
+            .try_get_matches_from(["rr-bench"].iter().chain(args))
+    }
+
+    #[test]
+    fn zero_concurrency_is_rejected() {
+        let error = try_parse(&["--operations", "10", "--concurrency", "0"]).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::ValueValidation);
+        assert!(try_parse(&["--operations", "10", "--concurrency", "1"]).is_ok());
+    }
+
+    #[test]
+    fn zero_transactions_per_second_is_rejected() {
+        let error =
+            try_parse(&["--operations", "10", "--transactions-per-second", "0"]).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::ValueValidation);
+        assert!(error.to_string().contains("--transactions-per-second"));
+        assert!(try_parse(&["--operations", "10", "--transactions-per-second", "1"]).is_ok());
+    }
+
+    #[test]
+    fn read_your_writes_needs_writes() {
+        let cli = Args::new(Vec::<Arg>::new()).parse_from(&[
+            "--operations",
+            "10",
+            "--read-your-writes",
+            "5",
+        ]);
+        assert_eq!(cli.config.read_your_writes, Some(5));
+
+        let error = try_parse(&["--operations", "10", "--read-your-writes", "0"]).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::ValueValidation);
+        let error = try_parse(&[
+            "--operations",
+            "10",
+            "--read-your-writes",
+            "5",
+            "--no-writes",
+        ])
+        .unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::ArgumentConflict);
+    }
+
+    #[test]
+    fn percentiles_are_fractional_and_at_most_100() {
+        let cli = Args::new(Vec::<Arg>::new()).parse_from(&["--operations", "10"]);
+        assert_eq!(cli.config.percentiles, [90.0, 95.0, 99.0, 99.9]);
+        let cli = Args::new(Vec::<Arg>::new()).parse_from(&[
+            "--operations",
+            "10",
+            "--percentiles",
+            "50,99.99",
+        ]);
+        assert_eq!(cli.config.percentiles, [50.0, 99.99]);
+
+        for percentiles in ["101", "-1", "p99", "NaN"] {
+            let percentiles = format!("--percentiles={percentiles}");
+            let error = try_parse(&["--operations", "10", &percentiles]).unwrap_err();
+            assert_eq!(error.kind(), ErrorKind::ValueValidation);
+        }
+    }
+
+    #[test]
+    fn read_ops_are_validated_at_parse_time() {
+        let cli = Args::new(Vec::<Arg>::new()).parse_from(&["--operations", "10"]);
+        assert_eq!(
+            cli.config.read_operations.len(),
+            ReadOperation::iter().count()
+        );
+        let cli = Args::new(Vec::<Arg>::new()).parse_from(&[
+            "--operations",
+            "10",
+            "--read-ops",
+            "top_performers,market_overview,top_performers",
+        ]);
+        assert_eq!(
+            cli.config.read_operations,
+            [ReadOperation::TopPerformers, ReadOperation::MarketOverview]
+        );
+
+        let error =
+            try_parse(&["--operations", "10", "--read-ops", "top_performers,bogus"]).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::ValueValidation);
+        assert!(error.to_string().contains("unknown read operation 'bogus'"));
+    }
+
+    #[test]
+    fn smoke_replaces_the_stop_condition() {
+        let cli = Args::new(Vec::<Arg>::new()).parse_from(&["--smoke"]);
+        assert_eq!(cli.config.stop, StopCondition::Duration(SMOKE_DURATION));
+        assert_eq!(cli.config.concurrency, 1);
+        assert_eq!(cli.config.transactions_per_second, 10);
+
+        let error = try_parse(&["--smoke", "--duration", "1m"]).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::ArgumentConflict);
+        let error = try_parse(&[]).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::MissingRequiredArgument);
+    }
+
+    #[test]
+    fn session_settings_are_key_value_pairs() {
+        let matches = try_parse(&[
+            "--operations",
+            "10",
+            "--session-setting",
+            "enable_seqscan=off",
+            "--session-setting",
+            "work_mem = 64MB",
+        ])
+        .unwrap();
+        let settings: Vec<_> = matches
+            .get_many::<(String, String)>("session_setting")
+            .unwrap()
+            .cloned()
+            .collect();
+        assert_eq!(
+            settings,
+            [
+                ("enable_seqscan".to_string(), "off".to_string()),
+                ("work_mem".to_string(), "64MB".to_string()),
+            ]
+        );
+
+        for invalid in ["work_mem", "=64MB", "work_mem=", "1key=on", "key;DROP=1"] {
+            let error =
+                try_parse(&["--operations", "10", "--session-setting", invalid]).unwrap_err();
+            assert_eq!(error.kind(), ErrorKind::ValueValidation, "{invalid}");
+        }
+    }
+
+    #[test]
+    fn consume_defaults_to_all_rows() {
+        let consume = |args: &[&str]| {
+            *try_parse(args)
+                .unwrap()
+                .get_one::<Consume>("consume")
+                .unwrap()
+        };
+        assert_eq!(consume(&["--operations", "10"]), Consume::AllRows);
+        assert_eq!(
+            consume(&["--operations", "10", "--consume", "first-row"]),
+            Consume::FirstRow
+        );
+        assert_eq!(
+            consume(&["--operations", "10", "--consume", "count"]),
+            Consume::Count
+        );
+        assert!(try_parse(&["--operations", "10", "--consume", "none"]).is_err());
+    }
+}
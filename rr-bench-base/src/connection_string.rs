@@ -0,0 +1,274 @@
+use crate::credentials::Credentials;
+use anyhow::{anyhow, bail, Result};
+use clap::ArgMatches;
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use url::Url;
+
+/// The environment variable read when `--writer-url` is not given.
+pub const WRITER_URL_ENV: &str = "RR_BENCH_WRITER_URL";
+
+/// The environment variable read when `--reader-url` is not given.
+pub const READER_URL_ENV: &str = "RR_BENCH_READER_URL";
+
+/// `ConnectionString` validates a database URL passed on the command line, such as
+/// `postgres://user@host:5432/bench?sslmode=require&pool_size=20`, so that backends
+/// can report malformed input instead of panicking on it.
+///
+/// The host may instead be given as a `host` parameter, as in
+/// `postgres://user@/bench?host=/var/run/postgresql` for a Unix domain socket.
+///
+/// Parameters that are interpreted by the benchmark itself, like `pool_size`, are
+/// removed from the URL handed to the database driver. All other parameters, like
+/// `sslmode`, are passed through to the driver.
+#[derive(Clone, Debug)]
+pub struct ConnectionString {
+    url: Url,
+    pool_size: Option<u32>,
+}
+
+impl ConnectionString {
+    /// Parses `s`, which must be a URL with one of the given `schemes` and a host,
+    /// either in the URL itself or as a `host` parameter.
+    pub fn parse(s: &str, schemes: &[&str]) -> Result<Self> {
+        let mut url = Url::parse(s).map_err(|e| {
+            anyhow!(
+                "{e}, expected a URL like {}://user@host:port/database",
+                schemes.first().copied().unwrap_or("scheme")
+            )
+        })?;
+
+        if !schemes.contains(&url.scheme()) {
+            bail!(
+                "unsupported scheme '{}', expected {}",
+                url.scheme(),
+                schemes.join(" or ")
+            );
+        }
+
+        if url.host_str().is_none_or(str::is_empty)
+            && !url.query_pairs().any(|(key, _)| key == "host")
+        {
+            bail!("missing host");
+        }
+
+        let mut pool_size = None;
+        let mut params = vec![];
+        for (key, value) in url.query_pairs() {
+            match key.as_ref() {
+                "pool_size" => match value.parse::<u32>() {
+                    Ok(size) if size > 0 => pool_size = Some(size),
+                    _ => bail!("invalid pool_size '{value}', expected a positive integer"),
+                },
+                _ => params.push((key.to_string(), value.to_string())),
+            }
+        }
+
+        if params.is_empty() {
+            url.set_query(None);
+        } else {
+            url.query_pairs_mut().clear().extend_pairs(params);
+        }
+
+        Ok(Self { url, pool_size })
+    }
+
+    /// Replaces the user and password in the URL with those in `credentials`.
+    /// A user or password missing from `credentials` is left unchanged.
+    pub fn set_credentials(&mut self, credentials: &Credentials) {
+        // A URL without a host has nowhere to put a user and password, so
+        // they are passed as parameters instead.
+        if self.url.host_str().is_none_or(str::is_empty) {
+            if let Some(user) = credentials.user() {
+                self.set_param("user", user);
+            }
+            if let Some(password) = credentials.password() {
+                self.set_param("password", password);
+            }
+            return;
+        }
+        // `Url` leaves `%` unescaped in the user and password, so they are
+        // escaped here. Setting them only fails for URLs without a host,
+        // which are handled above.
+        if let Some(user) = credentials.user() {
+            self.url
+                .set_username(&utf8_percent_encode(user, NON_ALPHANUMERIC).to_string())
+                .expect("connection URL has a host");
+        }
+        if let Some(password) = credentials.password() {
+            let password = utf8_percent_encode(password, NON_ALPHANUMERIC).to_string();
+            self.url
+                .set_password(Some(&password))
+                .expect("connection URL has a host");
+        }
+    }
+
+    /// Replaces the parameter `key`, or adds it if it is not present.
+    fn set_param(&mut self, key: &str, value: &str) {
+        let params: Vec<(String, String)> = self
+            .url
+            .query_pairs()
+            .filter(|(k, _)| k != key)
+            .map(|(k, v)| (k.into_owned(), v.into_owned()))
+            .collect();
+        self.url
+            .query_pairs_mut()
+            .clear()
+            .extend_pairs(params)
+            .append_pair(key, value);
+    }
+
+    /// The URL to hand to the database driver.
+    pub fn url(&self) -> &str {
+        self.url.as_str()
+    }
+
+    /// The `pool_size` parameter, if present.
+    pub fn pool_size(&self) -> Option<u32> {
+        self.pool_size
+    }
+}
+
+/// Returns the URL given with the backend's `writer` argument, `--writer-url`,
+/// or else the value of `RR_BENCH_WRITER_URL`, along with where it came from
+/// for error messages.
+pub fn writer_url(args: &ArgMatches) -> Result<(String, &'static str)> {
+    let (mut urls, source) = urls(args, "writer", "--writer-url", WRITER_URL_ENV)?;
+    Ok((urls.remove(0), source))
+}
+
+/// Returns the URL given with the backend's `reader` argument, `--reader-url`,
+/// or else the value of `RR_BENCH_READER_URL`, along with where it came from
+/// for error messages.
+pub fn reader_url(args: &ArgMatches) -> Result<(String, &'static str)> {
+    let (mut urls, source) = reader_urls(args)?;
+    Ok((urls.remove(0), source))
+}
+
+/// Returns the URLs given with the backend's `reader` argument, `--reader-url`,
+/// which some backends accept more than once, or else the single value of
+/// `RR_BENCH_READER_URL`, along with where they came from for error messages.
+pub fn reader_urls(args: &ArgMatches) -> Result<(Vec<String>, &'static str)> {
+    urls(args, "reader", "--reader-url", READER_URL_ENV)
+}
+
+/// Command line arguments take precedence over the environment, and an
+/// empty variable counts as unset.
+fn urls(
+    args: &ArgMatches,
+    id: &str,
+    flag: &'static str,
+    var: &'static str,
+) -> Result<(Vec<String>, &'static str)> {
+    if let Some(urls) = args.get_many::<String>(id) {
+        return Ok((urls.cloned().collect(), flag));
+    }
+    match std::env::var(var) {
+        Ok(url) if !url.is_empty() => Ok((vec![url], var)),
+        Ok(_) | Err(std::env::VarError::NotPresent) => {
+            bail!("no URL given with {flag}, and {var} is not set")
+        }
+        Err(e) => bail!("invalid {var}: {e}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCHEMES: &[&str] = &["postgres", "postgresql"];
+
+    fn error(s: &str) -> String {
+        ConnectionString::parse(s, SCHEMES).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn malformed_urls_are_errors() {
+        assert_eq!(
+            error("/var/run/postgresql"),
+            "relative URL without a base, expected a URL like postgres://user@host:port/database"
+        );
+        assert_eq!(
+            error("mysql://root@localhost/bench"),
+            "unsupported scheme 'mysql', expected postgres or postgresql"
+        );
+        assert_eq!(error("postgres:///bench"), "missing host");
+        assert_eq!(
+            error("postgres://localhost/bench?pool_size=0"),
+            "invalid pool_size '0', expected a positive integer"
+        );
+        assert_eq!(
+            error("postgres://localhost/bench?pool_size=many"),
+            "invalid pool_size 'many', expected a positive integer"
+        );
+    }
+
+    #[test]
+    fn pool_size_is_removed_and_other_params_are_kept() {
+        let url = ConnectionString::parse(
+            "postgres://localhost/bench?sslmode=require&pool_size=20",
+            SCHEMES,
+        )
+        .unwrap();
+        assert_eq!(url.pool_size(), Some(20));
+        assert_eq!(url.url(), "postgres://localhost/bench?sslmode=require");
+    }
+
+    #[test]
+    fn host_param_replaces_the_url_host() {
+        let url =
+            ConnectionString::parse("postgres:///bench?host=/var/run/postgresql", SCHEMES).unwrap();
+        assert_eq!(url.pool_size(), None);
+        assert_eq!(
+            url.url(),
+            "postgres:///bench?host=%2Fvar%2Frun%2Fpostgresql"
+        );
+    }
+
+    fn args(given: &[&str]) -> ArgMatches {
+        clap::Command::new("rr-bench")
+            .arg(
+                clap::Arg::new("reader")
+                    .long("reader-url")
+                    .action(clap::ArgAction::Append),
+            )
+            .get_matches_from(["rr-bench"].iter().chain(given))
+    }
+
+    #[test]
+    fn urls_fall_back_to_the_environment() {
+        // Each test uses a variable of its own, since tests run concurrently.
+        const VAR: &str = "RR_BENCH_TEST_FALLBACK_URL";
+        let error = urls(&args(&[]), "reader", "--reader-url", VAR).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "no URL given with --reader-url, and RR_BENCH_TEST_FALLBACK_URL is not set"
+        );
+
+        std::env::set_var(VAR, "postgres://env/bench");
+        assert_eq!(
+            urls(&args(&[]), "reader", "--reader-url", VAR).unwrap(),
+            (vec!["postgres://env/bench".to_string()], VAR)
+        );
+        // The command line takes precedence.
+        let given = args(&[
+            "--reader-url",
+            "postgres://a/bench",
+            "--reader-url",
+            "postgres://b/bench",
+        ]);
+        assert_eq!(
+            urls(&given, "reader", "--reader-url", VAR).unwrap(),
+            (
+                vec![
+                    "postgres://a/bench".to_string(),
+                    "postgres://b/bench".to_string()
+                ],
+                "--reader-url"
+            )
+        );
+
+        std::env::set_var(VAR, "");
+        assert!(urls(&args(&[]), "reader", "--reader-url", VAR).is_err());
+        std::env::remove_var(VAR);
+    }
+}
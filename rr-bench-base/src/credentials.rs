@@ -0,0 +1,82 @@
+use anyhow::{bail, Context, Result};
+use std::fs;
+use std::path::Path;
+
+/// `Credentials` holds a user and password read from a file, so they do not
+/// have to appear in connection URLs, where they leak into process listings
+/// and shell history.
+///
+/// The file uses `.env` syntax, one `key=value` pair per line:
+///
+/// ```text
+/// # Comments and blank lines are ignored.
+/// user=bench
+/// password="s3cret"
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Credentials {
+    user: Option<String>,
+    password: Option<String>,
+}
+
+impl Credentials {
+    /// Reads and parses the credentials file at `path`.
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("failed to read credentials file {}", path.display()))?;
+        Self::parse(&contents)
+            .with_context(|| format!("invalid credentials file {}", path.display()))
+    }
+
+    fn parse(contents: &str) -> Result<Self> {
+        let mut credentials = Self::default();
+        for (number, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let Some((key, value)) = line.split_once('=') else {
+                bail!("line {}: expected KEY=VALUE", number + 1);
+            };
+            let value = unquote(value.trim()).to_string();
+            match key.trim() {
+                "user" => credentials.user = Some(value),
+                "password" => credentials.password = Some(value),
+                key => bail!(
+                    "line {}: unknown key '{key}', expected user or password",
+                    number + 1
+                ),
+            }
+        }
+
+        if credentials.user.is_none() && credentials.password.is_none() {
+            bail!("expected a user or password");
+        }
+
+        Ok(credentials)
+    }
+
+    /// The user, if present.
+    pub fn user(&self) -> Option<&str> {
+        self.user.as_deref()
+    }
+
+    /// The password, if present.
+    pub fn password(&self) -> Option<&str> {
+        self.password.as_deref()
+    }
+}
+
+/// Strips a matching pair of single or double quotes around `value`.
+fn unquote(value: &str) -> &str {
+    for quote in ['"', '\''] {
+        if let Some(unquoted) = value
+            .strip_prefix(quote)
+            .and_then(|value| value.strip_suffix(quote))
+        {
+            return unquoted;
+        }
+    }
+    value
+}
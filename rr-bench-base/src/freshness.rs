@@ -0,0 +1,54 @@
+use crate::WriteOperation;
+use std::sync::{Arc, Mutex};
+
+/// The expected price of a freshness check that has no write to look for.
+/// No market data price is negative, so the check reads the same rows as
+/// any other but never finds the price.
+pub const NO_PRICE: f64 = -1.0;
+
+/// A market data price inserted into the primary database.
+#[derive(Clone, Copy, Debug)]
+pub struct PriceWrite {
+    pub security_id: i32,
+    pub price: f64,
+}
+
+impl PriceWrite {
+    /// The price inserted by `op`, if it inserts market data.
+    pub fn from_operation(op: &WriteOperation) -> Option<Self> {
+        match op {
+            WriteOperation::InsertMarketData {
+                security_id, price, ..
+            } => Some(Self {
+                security_id: *security_id,
+                price: *price,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// `FreshnessChannel` carries the market data price most recently committed
+/// by the primary database simulator to the read clients, which check
+/// whether the read replica reflects it yet.
+///
+/// Like a watch channel, only the latest write is kept, so a client always
+/// checks the row that has had the least time to replicate. Clones share
+/// the same channel.
+#[derive(Clone, Default)]
+pub struct FreshnessChannel {
+    latest: Arc<Mutex<Option<PriceWrite>>>,
+}
+
+impl FreshnessChannel {
+    /// Publishes a write once it has been committed.
+    pub fn send(&self, write: PriceWrite) {
+        *self.latest.lock().unwrap() = Some(write);
+    }
+
+    /// The latest committed write, or `None` if no market data
+    /// has been inserted since the benchmark started.
+    pub fn latest(&self) -> Option<PriceWrite> {
+        *self.latest.lock().unwrap()
+    }
+}
@@ -0,0 +1,71 @@
+use crate::task_handle::TaskCompletion;
+use crate::PrimaryDatabase;
+use anyhow::{Context, Result};
+use log::debug;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+/// How often the size of the primary database is sampled.
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How often the monitor checks whether the benchmark has completed.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The size of the primary database, in bytes, when the benchmark started
+/// and when it completed, and the largest size sampled in between.
+#[derive(Clone, Copy, Debug)]
+pub struct Growth {
+    pub start: u64,
+    pub end: u64,
+    pub peak: u64,
+}
+
+/// `GrowthMonitor` periodically samples the size of the primary database,
+/// which the write workload grows, because it inserts more rows than it
+/// deletes, until every read client has finished.
+pub struct GrowthMonitor<P: PrimaryDatabase> {
+    primary: P,
+    completion_tracker: TaskCompletion,
+}
+
+impl<P: PrimaryDatabase> GrowthMonitor<P> {
+    pub fn new(primary: P, completion_tracker: TaskCompletion) -> Self {
+        Self {
+            primary,
+            completion_tracker,
+        }
+    }
+
+    pub fn run(&mut self) -> Result<Growth> {
+        let start = self.sample()?;
+        let mut growth = Growth {
+            start,
+            end: start,
+            peak: start,
+        };
+        let mut sampled = Instant::now();
+        while !self.completion_tracker.is_done() {
+            let remaining = SAMPLE_INTERVAL.saturating_sub(sampled.elapsed());
+            if !remaining.is_zero() {
+                sleep(POLL_INTERVAL.min(remaining));
+                continue;
+            }
+            let size = self.sample()?;
+            growth.peak = growth.peak.max(size);
+            sampled = Instant::now();
+        }
+
+        growth.end = self.sample()?;
+        growth.peak = growth.peak.max(growth.end);
+        Ok(growth)
+    }
+
+    fn sample(&mut self) -> Result<u64> {
+        let size = self
+            .primary
+            .database_size()
+            .context("failed to query the size of the primary database")?;
+        debug!("primary database size: {size} bytes");
+        Ok(size)
+    }
+}
@@ -0,0 +1,161 @@
+use crate::config::AccessPattern;
+use crate::key_distribution::KeyDistribution;
+use crate::operations::IdKind;
+use crate::task_handle::TaskCompletion;
+use crate::PrimaryDatabase;
+use anyhow::{Context, Result};
+use log::{debug, warn};
+use rand::rngs::StdRng;
+use rand::Rng;
+use std::sync::{Arc, RwLock};
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+/// The number of values sampled for each kind of id.
+const SAMPLE_SIZE: usize = 256;
+
+/// How often the refresher checks whether the benchmark has completed.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// `IdCache` holds a random sample of the ids, tickers, and sectors that read
+/// operations are parameterized with, drawn from the primary database. Read
+/// clients share a single cache through an `Arc`, so they never query the
+/// primary database while the benchmark runs.
+///
+/// The sample is replaced every refresh interval by `IdRefresher`. Until then,
+/// rows inserted into the primary database are never read, and ids of deleted
+/// rows are still read and match nothing.
+pub struct IdCache {
+    ids: RwLock<Ids>,
+    /// Which of the sampled values read operations look up.
+    distribution: KeyDistribution,
+}
+
+struct Ids {
+    customers: Vec<i32>,
+    accounts: Vec<i32>,
+    securities: Vec<i32>,
+    tickers: Vec<String>,
+    sectors: Vec<String>,
+}
+
+impl Ids {
+    fn sample<P: PrimaryDatabase>(primary: &mut P, rng: &mut StdRng) -> Result<Self> {
+        Ok(Self {
+            customers: primary
+                .get_random_ids(IdKind::Customer, rng, SAMPLE_SIZE)
+                .context("failed to sample customer ids")?,
+            accounts: primary
+                .get_random_ids(IdKind::Account, rng, SAMPLE_SIZE)
+                .context("failed to sample account ids")?,
+            securities: primary
+                .get_random_ids(IdKind::Security, rng, SAMPLE_SIZE)
+                .context("failed to sample security ids")?,
+            tickers: sample(|| primary.get_random_ticker(rng))
+                .context("failed to sample tickers")?,
+            sectors: sample(|| primary.get_random_sector(rng))
+                .context("failed to sample sectors")?,
+        })
+    }
+}
+
+fn sample<T>(mut f: impl FnMut() -> Result<T>) -> Result<Vec<T>> {
+    (0..SAMPLE_SIZE).map(|_| f()).collect()
+}
+
+impl IdCache {
+    /// Samples the initial ids from the primary database.
+    pub fn load<P: PrimaryDatabase>(primary: &mut P, rng: &mut StdRng) -> Result<Self> {
+        Ok(Self {
+            ids: RwLock::new(Ids::sample(primary, rng)?),
+            distribution: KeyDistribution::Uniform,
+        })
+    }
+
+    /// Picks the values that read operations look up following `pattern`,
+    /// rather than uniformly, with a hot set of `hotset_pct` percent of each
+    /// sample for `AccessPattern::Hotset`.
+    pub fn with_access_pattern(mut self, pattern: AccessPattern, hotset_pct: u32) -> Self {
+        self.distribution = KeyDistribution::new(pattern, hotset_pct, SAMPLE_SIZE);
+        self
+    }
+
+    /// Replaces the sample with a new one from the primary database. The
+    /// old sample stays in use while the new one is drawn.
+    fn refresh<P: PrimaryDatabase>(&self, primary: &mut P, rng: &mut StdRng) -> Result<()> {
+        let ids = Ids::sample(primary, rng)?;
+        *self.ids.write().unwrap() = ids;
+        Ok(())
+    }
+
+    pub fn customer_id(&self, rng: &mut impl Rng) -> i32 {
+        *self.pick(&self.ids.read().unwrap().customers, rng)
+    }
+
+    pub fn account_id(&self, rng: &mut impl Rng) -> i32 {
+        *self.pick(&self.ids.read().unwrap().accounts, rng)
+    }
+
+    pub fn security_id(&self, rng: &mut impl Rng) -> i32 {
+        *self.pick(&self.ids.read().unwrap().securities, rng)
+    }
+
+    pub fn ticker(&self, rng: &mut impl Rng) -> String {
+        self.pick(&self.ids.read().unwrap().tickers, rng).clone()
+    }
+
+    pub fn sector(&self, rng: &mut impl Rng) -> String {
+        self.pick(&self.ids.read().unwrap().sectors, rng).clone()
+    }
+
+    /// Picks a value of `sample` following the access pattern.
+    fn pick<'a, T>(&self, sample: &'a [T], rng: &mut impl Rng) -> &'a T {
+        &sample[self.distribution.index(sample.len(), rng)]
+    }
+}
+
+/// `IdRefresher` periodically replaces the sample in an `IdCache` until every
+/// read client has finished.
+pub struct IdRefresher<P: PrimaryDatabase> {
+    primary: P,
+    cache: Arc<IdCache>,
+    rng: StdRng,
+    interval: Duration,
+    completion_tracker: TaskCompletion,
+}
+
+impl<P: PrimaryDatabase> IdRefresher<P> {
+    pub fn new(
+        primary: P,
+        cache: Arc<IdCache>,
+        rng: StdRng,
+        interval: Duration,
+        completion_tracker: TaskCompletion,
+    ) -> Self {
+        Self {
+            primary,
+            cache,
+            rng,
+            interval,
+            completion_tracker,
+        }
+    }
+
+    /// Refreshes the cache every interval. A failed refresh is logged and
+    /// the previous sample is kept, since it is still usable.
+    pub fn run(&mut self) {
+        let mut refreshed = Instant::now();
+        while !self.completion_tracker.is_done() {
+            let remaining = self.interval.saturating_sub(refreshed.elapsed());
+            if !remaining.is_zero() {
+                sleep(POLL_INTERVAL.min(remaining));
+                continue;
+            }
+            match self.cache.refresh(&mut self.primary, &mut self.rng) {
+                Ok(()) => debug!("refreshed the id cache"),
+                Err(e) => warn!("keeping the previous id cache: {e:#}"),
+            }
+            refreshed = Instant::now();
+        }
+    }
+}
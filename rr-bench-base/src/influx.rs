@@ -0,0 +1,353 @@
+use crate::measurements::Latencies;
+use crate::operations::ReadOperation;
+use anyhow::{bail, Context, Result};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpStream, ToSocketAddrs, UdpSocket};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use url::Url;
+
+/// How long connecting to, writing to, and reading from an HTTP endpoint may
+/// take before the push fails.
+const HTTP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Parses `--influx-url`.
+pub(crate) fn parse_influx_url(s: &str) -> Result<Url, String> {
+    let url = Url::parse(s).map_err(|e| format!("invalid URL '{s}': {e}"))?;
+    check_url(&url)?;
+    Ok(url)
+}
+
+/// Checks that `url` is an endpoint `InfluxSink` can push to: `udp://HOST:PORT`,
+/// or an `http://` URL that InfluxDB accepts writes on.
+pub(crate) fn check_url(url: &Url) -> Result<(), String> {
+    if url.host_str().is_none() {
+        return Err(format!("'{url}' has no host"));
+    }
+    match url.scheme() {
+        "udp" if url.port().is_none() => Err(format!("'{url}' has no port")),
+        "udp" | "http" => Ok(()),
+        scheme => Err(format!(
+            "unsupported scheme '{scheme}' in '{url}', expected udp or http"
+        )),
+    }
+}
+
+/// Where the metrics are pushed to.
+enum Transport {
+    /// Every push is sent as a single datagram.
+    Udp(UdpSocket),
+    /// Every push is sent as the body of a POST request, on a new connection.
+    Http {
+        /// The host and port to connect to, and to send as the `Host` header.
+        host: String,
+        /// The path and query string of the request, e.g. `/write?db=bench`.
+        target: String,
+    },
+}
+
+/// `InfluxSink` accumulates the latencies of read operations and pushes their
+/// summary to `--influx-url` in the InfluxDB line protocol, with one
+/// `rr_bench_reads` point per operation and one `rr_bench` point for the
+/// whole benchmark, each tagged with the run id.
+///
+/// Every push covers the reads completed since the previous one, so pushing
+/// only once, when the benchmark finishes, summarizes the whole run.
+pub struct InfluxSink {
+    transport: Transport,
+    run_id: String,
+    reads: BTreeMap<ReadOperation, Latencies>,
+    /// When the reads that have not been pushed yet started accumulating.
+    since: Instant,
+}
+
+impl InfluxSink {
+    /// Creates a sink for `url`, which `check_url` accepts. Only UDP sockets
+    /// are connected right away; an HTTP endpoint is first contacted when the
+    /// metrics are pushed.
+    pub fn connect(url: &Url, run_id: String) -> Result<Self> {
+        check_url(url).map_err(anyhow::Error::msg)?;
+        let host = url.host_str().unwrap_or_default();
+        let port = url.port_or_known_default().unwrap_or_default();
+        let transport = match url.scheme() {
+            "udp" => {
+                let address = (host, port)
+                    .to_socket_addrs()
+                    .with_context(|| format!("failed to resolve {url}"))?
+                    .next()
+                    .with_context(|| format!("failed to resolve {url}"))?;
+                let local = if address.is_ipv4() {
+                    "0.0.0.0:0"
+                } else {
+                    "[::]:0"
+                };
+                let socket = UdpSocket::bind(local).context("failed to bind a UDP socket")?;
+                socket
+                    .connect(address)
+                    .with_context(|| format!("failed to connect to {url}"))?;
+                Transport::Udp(socket)
+            }
+            _ => {
+                let mut target = url.path().to_string();
+                if let Some(query) = url.query() {
+                    target.push('?');
+                    target.push_str(query);
+                }
+                Transport::Http {
+                    host: format!("{host}:{port}"),
+                    target,
+                }
+            }
+        };
+        Ok(Self {
+            transport,
+            run_id,
+            reads: BTreeMap::new(),
+            since: Instant::now(),
+        })
+    }
+
+    /// Records a read operation measured by a read client.
+    pub fn observe(&mut self, operation: ReadOperation, latency: Duration) {
+        self.reads.entry(operation).or_default().push(latency);
+    }
+
+    /// The time since the metrics were last pushed.
+    pub fn elapsed(&self) -> Duration {
+        self.since.elapsed()
+    }
+
+    /// Pushes the reads completed since the previous push, and `errors`, the
+    /// number of operations that failed since the benchmark started.
+    pub fn push(&mut self, errors: usize) -> Result<()> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let lines = self.lines(errors, timestamp);
+        match &self.transport {
+            Transport::Udp(socket) => {
+                socket
+                    .send(lines.as_bytes())
+                    .context("failed to send metrics to InfluxDB")?;
+            }
+            Transport::Http { host, target } => post(host, target, &lines)
+                .with_context(|| format!("failed to send metrics to InfluxDB at {host}"))?,
+        }
+        Ok(())
+    }
+
+    /// Formats the reads completed since the previous push as points at
+    /// `timestamp`, since the Unix epoch, and starts accumulating anew.
+    fn lines(&mut self, errors: usize, timestamp: Duration) -> String {
+        let elapsed = self.since.elapsed().as_secs_f64();
+        let tps = |count: usize| {
+            if elapsed > 0.0 {
+                count as f64 / elapsed
+            } else {
+                0.0
+            }
+        };
+        let timestamp = timestamp.as_nanos();
+        let run_id = escape_tag(&self.run_id);
+
+        let mut out = String::new();
+        let mut total = 0;
+        for (operation, latencies) in &self.reads {
+            let count = latencies.len();
+            total += count;
+            write!(
+                out,
+                "rr_bench_reads,run_id={run_id},operation={} count={count}i,tps={}",
+                operation.as_ref(),
+                tps(count),
+            )
+            .unwrap();
+            let fields = [
+                ("p50_us", latencies.median()),
+                ("p95_us", latencies.percentile_95()),
+                ("p99_us", latencies.percentile_99()),
+                ("max_us", latencies.max()),
+            ];
+            for (name, latency) in fields {
+                if let Some(latency) = latency {
+                    let us = Duration::from(latency).as_secs_f64() * 1_000_000.0;
+                    write!(out, ",{name}={us}").unwrap();
+                }
+            }
+            writeln!(out, " {timestamp}").unwrap();
+        }
+        writeln!(
+            out,
+            "rr_bench,run_id={run_id} reads={total}i,tps={},errors={errors}i {timestamp}",
+            tps(total),
+        )
+        .unwrap();
+
+        self.reads.clear();
+        self.since = Instant::now();
+        out
+    }
+}
+
+/// Escapes the characters that delimit tags in the line protocol.
+fn escape_tag(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, ',' | '=' | ' ' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// POSTs `body` to `target` on `host`, and fails unless the response has a
+/// 2xx status.
+fn post(host: &str, target: &str, body: &str) -> Result<()> {
+    let address = host
+        .to_socket_addrs()?
+        .next()
+        .context("the host did not resolve to any address")?;
+    let mut stream = TcpStream::connect_timeout(&address, HTTP_TIMEOUT)?;
+    stream.set_read_timeout(Some(HTTP_TIMEOUT))?;
+    stream.set_write_timeout(Some(HTTP_TIMEOUT))?;
+    let request = format!(
+        "POST {target} HTTP/1.1\r\n\
+         Host: {host}\r\n\
+         Content-Type: text/plain; charset=utf-8\r\n\
+         Content-Length: {}\r\n\
+         Connection: close\r\n\
+         \r\n\
+         {body}",
+        body.len()
+    );
+    stream.write_all(request.as_bytes())?;
+
+    let mut status = String::new();
+    BufReader::new(stream).read_line(&mut status)?;
+    let status = status.trim_end();
+    let success = status
+        .split(' ')
+        .nth(1)
+        .is_some_and(|code| code.starts_with('2'));
+    if !success {
+        bail!("unexpected response: {status}");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use std::net::TcpListener;
+    use std::thread;
+
+    #[test]
+    fn only_udp_and_http_urls_are_accepted() {
+        assert!(parse_influx_url("udp://localhost:8089").is_ok());
+        assert!(parse_influx_url("http://localhost:8086/write?db=bench").is_ok());
+        assert_eq!(
+            parse_influx_url("udp://localhost").unwrap_err(),
+            "'udp://localhost' has no port"
+        );
+        assert_eq!(
+            parse_influx_url("https://localhost/write").unwrap_err(),
+            "unsupported scheme 'https' in 'https://localhost/write', expected udp or http"
+        );
+    }
+
+    #[test]
+    fn one_point_per_operation_and_one_for_the_run() {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let url = Url::parse(&format!("udp://{}", socket.local_addr().unwrap())).unwrap();
+        let mut sink = InfluxSink::connect(&url, "nightly run".to_string()).unwrap();
+        let ms = Duration::from_millis;
+        sink.observe(ReadOperation::TopPerformers, ms(2));
+        sink.observe(ReadOperation::TopPerformers, ms(4));
+        sink.observe(ReadOperation::CustomerPortfolio, ms(1));
+
+        let lines = sink.lines(3, Duration::from_secs(10));
+        let lines: Vec<&str> = lines.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with(
+            "rr_bench_reads,run_id=nightly\\ run,operation=customer_portfolio count=1i,tps="
+        ));
+        assert!(lines[1]
+            .starts_with("rr_bench_reads,run_id=nightly\\ run,operation=top_performers count=2i,"));
+        assert!(lines[1].contains(",p50_us=2"));
+        assert!(lines[1].contains(",max_us=4"));
+        assert!(lines[2].starts_with("rr_bench,run_id=nightly\\ run reads=3i,tps="));
+        assert!(lines[2].ends_with(",errors=3i 10000000000"));
+
+        // A push only covers the reads since the previous one.
+        assert_eq!(sink.lines(3, Duration::from_secs(11)).lines().count(), 1);
+    }
+
+    #[test]
+    fn pushes_over_udp() {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        socket
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let url = Url::parse(&format!("udp://{}", socket.local_addr().unwrap())).unwrap();
+        let mut sink = InfluxSink::connect(&url, "1".to_string()).unwrap();
+        sink.observe(ReadOperation::TopPerformers, Duration::from_millis(1));
+        sink.push(0).unwrap();
+
+        let mut buffer = [0; 1024];
+        let len = socket.recv(&mut buffer).unwrap();
+        let datagram = std::str::from_utf8(&buffer[..len]).unwrap();
+        assert_eq!(datagram.lines().count(), 2);
+    }
+
+    #[test]
+    fn pushes_over_http() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let responses = ["HTTP/1.1 204 No Content", "HTTP/1.1 400 Bad Request"];
+            let mut requests = vec![];
+            for response in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request = String::new();
+                let mut length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if let Some(value) = line.strip_prefix("Content-Length: ") {
+                        length = value.trim().parse().unwrap();
+                    }
+                    request.push_str(&line);
+                    if line == "\r\n" {
+                        break;
+                    }
+                }
+                let mut body = vec![0; length];
+                reader.read_exact(&mut body).unwrap();
+                request.push_str(std::str::from_utf8(&body).unwrap());
+                requests.push(request);
+                write!(stream, "{response}\r\nContent-Length: 0\r\n\r\n").unwrap();
+            }
+            requests
+        });
+
+        let url = Url::parse(&format!("http://{address}/write?db=bench")).unwrap();
+        let mut sink = InfluxSink::connect(&url, "1".to_string()).unwrap();
+        sink.push(0).unwrap();
+        let error = sink.push(0).unwrap_err();
+        assert_eq!(
+            format!("{error:#}"),
+            format!(
+                "failed to send metrics to InfluxDB at {address}: \
+                 unexpected response: HTTP/1.1 400 Bad Request"
+            )
+        );
+
+        let requests = server.join().unwrap();
+        assert!(requests[0].starts_with("POST /write?db=bench HTTP/1.1\r\n"));
+        assert!(requests[0].contains("\r\n\r\nrr_bench,run_id=1 reads=0i,tps="));
+    }
+}
@@ -0,0 +1,128 @@
+use crate::config::AccessPattern;
+use rand::Rng;
+
+/// The exponent of the Zipfian distribution: the entry at rank `k`, starting
+/// from 1, is picked with a probability proportional to `1 / k^ZIPF_EXPONENT`.
+const ZIPF_EXPONENT: f64 = 1.0;
+
+/// The share of the picks that go to the hot set of `AccessPattern::Hotset`.
+const HOT_SET_SHARE: f64 = 0.9;
+
+/// `KeyDistribution` picks which entry of a cached sample of ids a read
+/// operation looks up.
+///
+/// The sample is drawn at random from the primary database, so the entries
+/// that the skewed distributions favor, those at the lowest indexes, hold a
+/// random subset of the ids. They change whenever the sample is refreshed.
+#[derive(Clone, Debug)]
+pub enum KeyDistribution {
+    /// Every entry is equally likely.
+    Uniform,
+    /// The entry at index `i` is picked in proportion to
+    /// `1 / (i + 1)^ZIPF_EXPONENT`. Holds the cumulative weights of the
+    /// entries of the longest sample.
+    Zipf { cumulative: Vec<f64> },
+    /// `HOT_SET_SHARE` of the picks go to the first `pct` percent of the
+    /// entries, at least one, and the rest to the other entries, uniformly
+    /// within each.
+    Hotset { pct: u32 },
+}
+
+impl KeyDistribution {
+    /// The distribution of `pattern` over samples of at most `max_len`
+    /// entries, whose hot set, if any, is `hotset_pct` percent of them.
+    pub fn new(pattern: AccessPattern, hotset_pct: u32, max_len: usize) -> Self {
+        match pattern {
+            AccessPattern::Uniform => Self::Uniform,
+            AccessPattern::Zipf => Self::Zipf {
+                cumulative: (1..=max_len)
+                    .scan(0.0, |sum, rank| {
+                        *sum += 1.0 / (rank as f64).powf(ZIPF_EXPONENT);
+                        Some(*sum)
+                    })
+                    .collect(),
+            },
+            AccessPattern::Hotset => Self::Hotset { pct: hotset_pct },
+        }
+    }
+
+    /// Picks the index of an entry in a sample of `len` entries. With
+    /// `Zipf`, entries past `max_len` are never picked.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `len` is zero.
+    pub fn index(&self, len: usize, rng: &mut impl Rng) -> usize {
+        assert!(len > 0, "cannot pick an entry of an empty sample");
+        match self {
+            Self::Uniform => rng.gen_range(0..len),
+            Self::Zipf { cumulative } => {
+                let cumulative = &cumulative[..len.min(cumulative.len())];
+                let target = rng.gen_range(0.0..cumulative[cumulative.len() - 1]);
+                cumulative.partition_point(|&weight| weight <= target)
+            }
+            Self::Hotset { pct } => {
+                let hot = (len * *pct as usize).div_ceil(100).clamp(1, len);
+                if hot == len || rng.gen_bool(HOT_SET_SHARE) {
+                    rng.gen_range(0..hot)
+                } else {
+                    rng.gen_range(hot..len)
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    /// Counts how often each of `len` entries is picked in 100,000 picks.
+    fn histogram(distribution: &KeyDistribution, len: usize) -> Vec<u32> {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut counts = vec![0; len];
+        for _ in 0..100_000 {
+            counts[distribution.index(len, &mut rng)] += 1;
+        }
+        counts
+    }
+
+    #[test]
+    fn uniform_picks_every_entry_equally() {
+        let counts = histogram(&KeyDistribution::new(AccessPattern::Uniform, 10, 100), 100);
+        assert!(counts.iter().all(|&count| (800..1200).contains(&count)));
+    }
+
+    #[test]
+    fn zipf_picks_entries_in_inverse_proportion_to_their_rank() {
+        let distribution = KeyDistribution::new(AccessPattern::Zipf, 10, 256);
+        let counts = histogram(&distribution, 100);
+        // The first entry is picked about twice as often as the second, and
+        // ten times as often as the tenth.
+        let ratio = |i: usize| counts[0] as f64 / counts[i] as f64;
+        assert!((1.8..2.2).contains(&ratio(1)));
+        assert!((8.5..11.5).contains(&ratio(9)));
+        // With H(100) ~ 5.19, the first entry gets about 19% of the picks.
+        assert!((18_000..20_500).contains(&counts[0]));
+    }
+
+    #[test]
+    fn hotset_sends_most_picks_to_the_first_entries() {
+        let distribution = KeyDistribution::new(AccessPattern::Hotset, 10, 256);
+        let counts = histogram(&distribution, 200);
+        let hot: u32 = counts[..20].iter().sum();
+        assert!((89_000..91_000).contains(&hot));
+        assert!(counts[20..].iter().all(|&count| count > 0));
+
+        // A hot set covering every entry is uniform.
+        let distribution = KeyDistribution::new(AccessPattern::Hotset, 100, 256);
+        assert!(histogram(&distribution, 10)
+            .iter()
+            .all(|&count| count > 9000));
+        // Even the smallest sample has a hot entry.
+        let distribution = KeyDistribution::new(AccessPattern::Hotset, 1, 256);
+        assert!(histogram(&distribution, 3)[0] > 89_000);
+    }
+}
@@ -0,0 +1,71 @@
+use crate::measurements::Latencies;
+use crate::task_handle::TaskCompletion;
+use crate::{PrimaryDatabase, ReadReplica};
+use anyhow::{Context, Result};
+use std::thread::sleep;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// How often a new token is written to the primary database.
+const PROBE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How long to wait between polls of the read replica.
+const POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+/// `LagMonitor` measures replication lag by writing a monotonically increasing
+/// token to the primary database and polling the read replica until it becomes
+/// visible. Tokens are derived from the wall clock, so they keep increasing
+/// across runs against the same database.
+pub struct LagMonitor<P: PrimaryDatabase, R: ReadReplica> {
+    primary: P,
+    reader: R,
+    completion_tracker: TaskCompletion,
+}
+
+impl<P: PrimaryDatabase, R: ReadReplica> LagMonitor<P, R> {
+    pub fn new(primary: P, reader: R, completion_tracker: TaskCompletion) -> Self {
+        Self {
+            primary,
+            reader,
+            completion_tracker,
+        }
+    }
+
+    pub fn run(&mut self) -> Result<Latencies> {
+        let mut lag = Latencies::default();
+        while !self.completion_tracker.is_done() {
+            let token = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .context("system clock is before the unix epoch")?
+                .as_micros() as i64;
+
+            self.primary
+                .insert_lag_token(token)
+                .context("failed to insert replication lag token")?;
+            let start = Instant::now();
+
+            if let Some(elapsed) = self.wait_for(token, start)? {
+                lag.push(elapsed);
+            }
+            sleep(PROBE_INTERVAL.saturating_sub(start.elapsed()));
+        }
+
+        Ok(lag)
+    }
+
+    /// Polls the read replica until `token` is visible, returning the time since
+    /// `start`, or `None` if the benchmark completed first.
+    fn wait_for(&mut self, token: i64, start: Instant) -> Result<Option<Duration>> {
+        while !self.completion_tracker.is_done() {
+            let observed = self
+                .reader
+                .max_observed_token()
+                .context("failed to query replication lag token")?;
+            if observed.is_some_and(|observed| observed >= token) {
+                return Ok(Some(start.elapsed()));
+            }
+            sleep(POLL_INTERVAL);
+        }
+
+        Ok(None)
+    }
+}
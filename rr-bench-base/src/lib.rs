@@ -1,29 +1,85 @@
 #![allow(clippy::needless_doctest_main)]
 
-use crate::config::{Args, Cli};
-use crate::measurements::Measurements;
-use crate::operations::WriteOperation;
-use crate::primary_simulator::PrimarySimulator;
-use crate::read_simulator::ReaderSimulator;
-use crate::task_handle::new_task_handles;
-use anyhow::{Context, Result};
+use crate::config::{Args, OutputFormat, StopCondition};
+use crate::freshness::FreshnessChannel;
+use crate::growth_monitor::GrowthMonitor;
+use crate::id_cache::{IdCache, IdRefresher};
+use crate::influx::InfluxSink;
+use crate::lag_monitor::LagMonitor;
+use crate::measurements::Window;
+use crate::operations::{IdKind, WriteOperation};
+use crate::primary_simulator::{PrimaryConfig, PrimarySimulator};
+use crate::read_simulator::{
+    ProgressBars, ReaderConfig, ReaderSimulator, Sample, SummaryProgressBar,
+};
+use crate::read_your_writes::{ReadYourWritesCheck, ReadYourWritesResults};
+use crate::retry::{connect_with_retries, ErrorCounter};
+use crate::sla::SLA_VIOLATION_EXIT_CODE;
+use crate::task_handle::{new_task_handles, TaskCompletion, TaskHandle};
+use crate::timeseries::TimeSeries;
+use anyhow::{anyhow, bail, Context, Result};
 use clap::{Arg, ArgMatches};
-use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use console::Term;
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
+use log::{debug, error, info, warn};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use serde::Serialize;
+use std::fmt;
+use std::fs::File;
 use std::process::exit;
 use std::sync::mpsc;
 use std::sync::mpsc::RecvTimeoutError;
+use std::sync::Arc;
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
+pub use benchmark_config::BenchmarkConfig;
 pub use clap;
+pub use config::{parse_size, AccessPattern, Consume, DurationMode, ProgressMode, WritePattern};
+pub use connection_string::{
+    reader_url, reader_urls, writer_url, ConnectionString, READER_URL_ENV, WRITER_URL_ENV,
+};
+pub use credentials::Credentials;
+pub use log;
+pub use measurements::Measurements;
+pub use metrics::Metrics;
+pub use primary_simulator::{Phase, WriteMix};
+pub use rand;
+pub use random_id_cache::RandomIdCache;
+pub use read_selector::WeightedReadSelector;
+pub use retry::connect_with_retries_from;
+pub use url;
 
+mod benchmark_config;
+mod check;
 mod config;
+mod connection_string;
+mod credentials;
+mod freshness;
+mod growth_monitor;
+mod id_cache;
+mod influx;
+mod key_distribution;
+mod lag_monitor;
+mod logging;
 mod measurements;
+mod metrics;
 pub mod operations;
 mod pretty_duration;
 mod primary_simulator;
+#[cfg(feature = "prometheus")]
+mod prometheus;
+mod random_id_cache;
+mod read_selector;
 mod read_simulator;
+mod read_your_writes;
+mod retry;
+mod sla;
 mod task_handle;
+#[cfg(test)]
+mod testing;
+mod timeseries;
 
 /// The `Benchmark` trait defines the interface for setting up a database benchmarking environment.
 /// Implementors of this trait are responsible for providing access to both the primary database
@@ -35,74 +91,216 @@ pub trait Benchmark<'a>: Send {
 
     /// Provides access to the primary database. This
     /// method may be called multiple times but may utilize
-    /// connection pooling.
-    fn primary_database(&'a self) -> Result<Self::Writer>;
+    /// connection pooling, in which case the time spent
+    /// acquiring connections should be recorded in `metrics`.
+    fn primary_database(&'a self, metrics: &Metrics) -> Result<Self::Writer>;
 
     /// Provides access to the read replica. This
     /// method may be called multiple times and should
     /// return a handle with a new connection each time.
     fn read_replica(&self) -> Result<Self::Reader>;
+
+    /// Provides access to the read replica given with `--compare-reader-url`,
+    /// which runs the same read workload for a side-by-side comparison. Like
+    /// `read_replica`, it should return a handle with a new connection each time.
+    fn compare_read_replica(&self) -> Result<Self::Reader> {
+        bail!("--compare-reader-url is not supported by this benchmark")
+    }
+
+    /// Verifies that every table and view used by the benchmark exists
+    /// by running each random-id query and each read operation once.
+    /// Invoked instead of the benchmark when run with `--check`.
+    fn validate(&'a self) -> Result<()> {
+        let mut primary = self
+            .primary_database(&Metrics::default())
+            .context("failed to build primary database client")?;
+        let mut reader = self
+            .read_replica()
+            .context("failed to build read replica client")?;
+        check::check(&mut primary, &mut reader)
+    }
+
+    /// Creates the views queried by the read operations on the read replica.
+    /// Invoked before the benchmark, or before `validate`, when run with
+    /// `--apply-schema`.
+    fn apply_schema(&self) -> Result<()> {
+        bail!("--apply-schema is not supported by this benchmark")
+    }
+
+    /// Puts the databases into a known state, e.g. by truncating volatile
+    /// tables or loading fixtures. Invoked once before the benchmark, after
+    /// `apply_schema`, and before any client connects. Does nothing by default.
+    fn setup(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Opens every connection of the pool that `primary_database` draws from
+    /// and runs a trivial query on each, so that the benchmark does not pay
+    /// for establishing them. Invoked before the benchmark when run with
+    /// `--prime-connections`. Returns the number of connections primed, which
+    /// is zero by default, for benchmarks without a pool.
+    fn prime_pool(&self) -> Result<u32> {
+        Ok(0)
+    }
 }
 
 /// The `PrimaryDatabase` trait defines the interface for interacting with the primary database
 /// in a benchmarking environment. This trait includes methods for retrieving random IDs from
 /// various tables and executing operations such as inserts, updates, or deletes.
+///
+/// The random rows are chosen with `rng`, the caller's seeded generator, so that
+/// runs with the same `--seed` pick the same rows from the same data.
 pub trait PrimaryDatabase: Send {
-    fn get_random_customer_id(&mut self) -> Result<i32>;
+    fn get_random_customer_id(&mut self, rng: &mut StdRng) -> Result<i32>;
 
-    fn get_random_account_id(&mut self) -> Result<i32>;
+    fn get_random_account_id(&mut self, rng: &mut StdRng) -> Result<i32>;
 
-    fn get_random_security_id(&mut self) -> Result<i32>;
+    fn get_random_security_id(&mut self, rng: &mut StdRng) -> Result<i32>;
 
-    fn get_random_trade_id(&mut self) -> Result<i32>;
+    fn get_random_trade_id(&mut self, rng: &mut StdRng) -> Result<i32>;
 
-    fn get_random_order_id(&mut self) -> Result<i32>;
+    fn get_random_order_id(&mut self, rng: &mut StdRng) -> Result<i32>;
 
-    fn get_random_market_data_id(&mut self) -> Result<i32>;
+    fn get_random_market_data_id(&mut self, rng: &mut StdRng) -> Result<i32>;
 
-    fn get_random_ticker(&mut self) -> Result<String>;
+    fn get_random_ticker(&mut self, rng: &mut StdRng) -> Result<String>;
 
-    fn get_random_sector(&mut self) -> Result<String>;
+    fn get_random_sector(&mut self, rng: &mut StdRng) -> Result<String>;
+
+    /// Returns `n` random ids of `kind`, drawn from `rng`, which may repeat.
+    /// Callers that need many ids, like the read clients' id sample, use it
+    /// in place of the single id methods. By default it calls them `n` times;
+    /// backends should fetch the ids in as few round trips as they can.
+    fn get_random_ids(&mut self, kind: IdKind, rng: &mut StdRng, n: usize) -> Result<Vec<i32>> {
+        (0..n)
+            .map(|_| match kind {
+                IdKind::Customer => self.get_random_customer_id(rng),
+                IdKind::Account => self.get_random_account_id(rng),
+                IdKind::Security => self.get_random_security_id(rng),
+                IdKind::Trade => self.get_random_trade_id(rng),
+                IdKind::Order => self.get_random_order_id(rng),
+                IdKind::MarketData => self.get_random_market_data_id(rng),
+            })
+            .collect()
+    }
 
     fn execute_command(&self, op: WriteOperation) -> Result<()>;
+
+    /// Executes a batch of write operations, used when the benchmark is
+    /// run with `--batch-size`. Databases should execute the batch in a
+    /// single transaction; by default the operations are executed one by one.
+    fn execute_batch(&self, ops: Vec<WriteOperation>) -> Result<()> {
+        ops.into_iter().try_for_each(|op| self.execute_command(op))
+    }
+
+    /// Returns whether `error`, returned by `execute_command` or
+    /// `execute_batch`, means that the transaction lost a serialization
+    /// failure or a deadlock to a concurrent one and can be executed again
+    /// as is. Such transactions are retried with `--conflict-retries`.
+    fn is_conflict(&self, _error: &anyhow::Error) -> bool {
+        false
+    }
+
+    /// Writes a replication lag token to the primary database. Tokens
+    /// are monotonically increasing and are only required when the
+    /// benchmark is run with `--measure-lag`.
+    fn insert_lag_token(&self, _token: i64) -> Result<()> {
+        bail!("replication lag measurement is not supported by this benchmark")
+    }
+
+    /// Inserts a customer and returns the id it was assigned. Only required
+    /// when the benchmark is run with `--read-your-writes`.
+    fn insert_customer(&self, _name: &str, _address: &str) -> Result<i32> {
+        bail!("--read-your-writes is not supported by this benchmark")
+    }
+
+    /// Returns the size of the primary database in bytes. Only required
+    /// when the benchmark is run with `--track-growth`.
+    fn database_size(&mut self) -> Result<u64> {
+        bail!("--track-growth is not supported by this benchmark")
+    }
 }
 
 /// The `ReadReplica` trait defines the interface for interacting with a read replica
 /// in a benchmarking environment. This trait includes methods for executing various
 /// read operations that are typical in OLTP systems, such as fetching customer portfolios
 /// or querying market data.
+///
+/// Each read operation returns the number of rows it read, which is reported
+/// alongside its latency. How the rows are consumed is up to `--consume`, so
+/// with `first-row` an operation reads at most one row, and with `count` it
+/// returns the number of rows the server counted.
 pub trait ReadReplica: Send {
-    fn customer_portfolio(&mut self, customer_id: i32) -> Result<()>;
+    fn customer_portfolio(&mut self, customer_id: i32) -> Result<u64>;
+
+    fn top_performers(&mut self) -> Result<u64>;
+
+    fn market_overview(&mut self, sector: &str) -> Result<u64>;
+
+    fn recent_large_trades(&mut self, account_id: i32) -> Result<u64>;
+
+    fn customer_order_book(&mut self, customer_id: i32) -> Result<u64>;
+
+    fn sector_performance(&mut self, sector: String) -> Result<u64>;
 
-    fn top_performers(&mut self) -> Result<()>;
+    fn account_activity_summary(&mut self, account_id: i32) -> Result<u64>;
 
-    fn market_overview(&mut self, sector: &str) -> Result<()>;
+    fn daily_market_movements(&mut self, security_id: i32) -> Result<u64>;
 
-    fn recent_large_trades(&mut self, account_id: i32) -> Result<()>;
+    fn high_value_customers(&mut self) -> Result<u64>;
 
-    fn customer_order_book(&mut self, customer_id: i32) -> Result<()>;
+    fn pending_orders_summary(&mut self, ticker: &str) -> Result<u64>;
 
-    fn sector_performance(&mut self, sector: String) -> Result<()>;
+    fn trade_volume_by_hour(&mut self) -> Result<u64>;
 
-    fn account_activity_summary(&mut self, account_id: i32) -> Result<()>;
+    fn top_securities_by_sector(&mut self, sector: String) -> Result<u64>;
 
-    fn daily_market_movements(&mut self, security_id: i32) -> Result<()>;
+    fn recent_trades_by_account(&mut self, account_id: i32) -> Result<u64>;
 
-    fn high_value_customers(&mut self) -> Result<()>;
+    fn order_fulfillment_rates(&mut self, customer_id: i32) -> Result<u64>;
 
-    fn pending_orders_summary(&mut self, ticker: &str) -> Result<()>;
+    fn sector_order_activity(&mut self, sector: String) -> Result<u64>;
 
-    fn trade_volume_by_hour(&mut self) -> Result<()>;
+    fn cascading_order_cancellation_alert(&mut self) -> Result<u64>;
 
-    fn top_securities_by_sector(&mut self, sector: String) -> Result<()>;
+    /// Reads the market data of `security_id` and returns whether it is
+    /// stale, that is, whether the row with `expected_price` that was just
+    /// inserted into the primary database is not yet visible. Reads the
+    /// `market_data` table rather than a view.
+    fn check_row_freshness(&mut self, security_id: i32, expected_price: f64) -> Result<bool>;
 
-    fn recent_trades_by_account(&mut self, account_id: i32) -> Result<()>;
+    /// Reads the customer with `customer_id` from the `customers` table,
+    /// rather than a view, and returns its name, or `None` if the customer
+    /// is not visible. Only required when the benchmark is run with
+    /// `--read-your-writes`.
+    fn fetch_customer(&mut self, _customer_id: i32) -> Result<Option<String>> {
+        bail!("--read-your-writes is not supported by this benchmark")
+    }
+
+    /// Closes the connection to the read replica and opens a new one. Only
+    /// required when the benchmark is run with `--reconnect-per-op`.
+    fn reconnect(&mut self) -> Result<()> {
+        bail!("--reconnect-per-op is not supported by this benchmark")
+    }
 
-    fn order_fulfillment_rates(&mut self, customer_id: i32) -> Result<()>;
+    /// Runs a trivial query, like `SELECT 1`, on the read replica connection.
+    /// Only required when the benchmark is run with `--prime-connections`.
+    fn ping(&mut self) -> Result<()> {
+        bail!("--prime-connections is not supported by this benchmark")
+    }
 
-    fn sector_order_activity(&mut self, sector: String) -> Result<()>;
+    /// Returns whether `error`, returned by a read operation, means that the
+    /// operation was cancelled for running longer than `--query-timeout`.
+    /// Timed out operations are reported separately from errors.
+    fn is_timeout(&self, _error: &anyhow::Error) -> bool {
+        false
+    }
 
-    fn cascading_order_cancellation_alert(&mut self) -> Result<()>;
+    /// Returns the largest replication lag token visible on the read replica,
+    /// or `None` if no tokens have been replicated yet.
+    fn max_observed_token(&mut self) -> Result<Option<i64>> {
+        bail!("replication lag measurement is not supported by this benchmark")
+    }
 }
 
 /// The `benchmark` function runs a benchmarking test using the provided closures to set up
@@ -114,11 +312,11 @@ pub trait ReadReplica: Send {
 /// # Arguments
 ///
 /// * `args` - A closure that returns an iterator of arguments, each implementing `Into<Arg>`.
-///            These arguments are for configuring the command-line interface using `clap`.
-///            Implementors can use this to add their own command-line arguments for benchmark-specific
-///            configurations.
+///   These arguments are for configuring the command-line interface using `clap`.
+///   Implementors can use this to add their own command-line arguments for benchmark-specific
+///   configurations.
 /// * `f` - A closure that takes an `ArgMatches` (parsed command-line arguments) and returns a
-///         `Result` containing an instance of a type that implements the `Benchmark` trait.
+///   `Result` containing an instance of a type that implements the `Benchmark` trait.
 ///
 /// # Example
 ///
@@ -153,91 +351,995 @@ where
 {
     let args = Args::new(args());
     let cli = args.parse();
+    logging::init(cli.log_level);
+    pretty_duration::set_unit(cli.latency_unit);
+    let output = cli.output;
+    let histogram = cli.histogram;
+    let sla = cli.sla.clone();
+    let smoke = cli.config.smoke;
+
+    if cli.check {
+        let apply_schema = cli.config.apply_schema;
+        let result = f(cli.matches).and_then(|benchmark| {
+            if apply_schema {
+                benchmark
+                    .apply_schema()
+                    .context("failed to apply the schema")?;
+            }
+            benchmark.validate()
+        });
+        match result {
+            Ok(()) => {
+                eprintln!("all checks passed");
+                exit(0)
+            }
+            Err(e) => {
+                error!("{e:?}");
+                exit(1)
+            }
+        }
+    }
+
+    let config = cli.config;
+    let (handle, tracker) = new_task_handles();
+    let result = install_signal_handler(&tracker)
+        .and_then(|()| f(cli.matches))
+        .and_then(|benchmark| inner(config, benchmark, handle, tracker));
+    let print = |measurements: &Measurements| match output {
+        OutputFormat::Text => {
+            println!("{}", measurements);
+            if histogram {
+                // Fall back to a typical terminal width when stdout is redirected.
+                let width = Term::stdout().size_checked().map_or(80, |(_, cols)| cols);
+                println!("{}", measurements.histogram(width as usize));
+            }
+        }
+        OutputFormat::Json => println!("{}", measurements.to_json()),
+        OutputFormat::Oneline => println!("{}", measurements.oneline()),
+    };
+    match result {
+        Ok(measurements) => {
+            print(&measurements);
+
+            let violations = sla.violations(&measurements);
+            if !violations.is_empty() {
+                for violation in violations {
+                    error!("SLA violated: {violation}");
+                }
+                exit(SLA_VIOLATION_EXIT_CODE)
+            }
 
-    match inner(cli, f) {
-        Ok(measurements) => println!("{}", measurements),
+            if smoke {
+                let failed = measurements.failed_operations();
+                if failed > 0 {
+                    error!("smoke test failed: {failed} operations failed");
+                    exit(1)
+                }
+                if measurements.is_empty() {
+                    error!("smoke test failed: no read operations completed");
+                    exit(1)
+                }
+                eprintln!("smoke test passed");
+            }
+        }
         Err(e) => {
-            eprintln!("{:?}", e);
+            // Thresholds are not checked against a benchmark that stopped
+            // early.
+            if let Some(aborted) = e.downcast_ref::<BenchmarkAborted>() {
+                print(&aborted.measurements);
+            }
+            error!("{e:?}");
             exit(1)
         }
     }
 }
 
-fn inner<B: for<'a> Benchmark<'a>, F>(cli: Cli, f: F) -> Result<Measurements>
-where
-    F: Fn(ArgMatches) -> Result<B>,
-{
-    let benchmark: B = f(cli.matches)?;
+/// Runs `benchmark` with `config` and returns its measurements, without
+/// parsing the command line. Unlike `benchmark`, it neither initializes
+/// logging nor installs a signal handler, and leaves reporting the
+/// measurements and checking them against thresholds to the caller.
+pub fn run_with_config<B: for<'a> Benchmark<'a>>(
+    config: BenchmarkConfig,
+    benchmark: B,
+) -> Result<Measurements> {
+    config
+        .validate()
+        .context("invalid benchmark configuration")?;
     let (handle, tracker) = new_task_handles();
+    inner(config, benchmark, handle, tracker)
+}
+
+/// The context of the error returned when a read client, the primary
+/// database simulator, or a monitor failed, which stopped the benchmark
+/// early. Holds the measurements collected until then, and can be recovered
+/// from the error with `downcast_ref`.
+pub struct BenchmarkAborted {
+    pub measurements: Measurements,
+}
+
+impl fmt::Display for BenchmarkAborted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "the benchmark was aborted")
+    }
+}
+
+impl fmt::Debug for BenchmarkAborted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BenchmarkAborted").finish_non_exhaustive()
+    }
+}
+
+/// A row in the `--samples-out` CSV file.
+#[derive(Serialize)]
+struct SampleRecord {
+    operation: &'static str,
+    latency_us: f64,
+    /// Seconds since the read clients were started.
+    offset_secs: f64,
+}
+
+/// Adds a read operation measured by a read client to `measurements`.
+fn record_sample(measurements: &mut Measurements, sample: &Sample) {
+    measurements.push(sample.operation, sample.latency);
+    if let Some(rows) = sample.rows {
+        measurements.push_rows(sample.operation, rows);
+    }
+    if let Some(stale) = sample.stale {
+        measurements.push_freshness_check(stale);
+    }
+    if let Some(reconnect) = sample.reconnect {
+        measurements.push_reconnect(reconnect);
+    }
+}
+
+/// Installs the handler that stops the benchmark on the first Ctrl-C, SIGTERM,
+/// or SIGHUP, so that the measurements collected so far can still be reported
+/// and the samples file is flushed once the read clients disconnect. A second
+/// signal exits immediately.
+fn install_signal_handler(tracker: &TaskCompletion) -> Result<()> {
+    let interrupt = tracker.clone();
+    ctrlc::set_handler(move || {
+        if interrupt.is_cancelled() {
+            exit(130);
+        }
+        warn!("interrupted, stopping benchmark and reporting partial results");
+        interrupt.cancel();
+    })
+    .context("failed to install signal handler")
+}
+
+fn inner<B: for<'a> Benchmark<'a>>(
+    config: BenchmarkConfig,
+    benchmark: B,
+    handle: TaskHandle,
+    tracker: TaskCompletion,
+) -> Result<Measurements> {
+    if config.apply_schema {
+        debug!("creating the views on the read replica");
+        benchmark
+            .apply_schema()
+            .context("failed to apply the schema")?;
+    }
+    benchmark
+        .setup()
+        .context("failed to set up the benchmark")?;
+    if config.smoke {
+        debug!("checking the tables and views before the smoke test");
+        benchmark.validate().context("smoke test checks failed")?;
+    }
+
+    info!("Using seed {}", config.seed);
+
+    if config.prime_connections {
+        let start = Instant::now();
+        let primed = benchmark
+            .prime_pool()
+            .context("failed to prime the primary database connections")?;
+        if primed == 0 {
+            debug!("the benchmark has no pooled primary database connections to prime");
+        } else {
+            info!(
+                "Primed {primed} primary database connections in {:?}",
+                start.elapsed()
+            );
+        }
+    }
+
+    let lag_tracker = tracker.clone();
+    let id_tracker = tracker.clone();
+    let errors = ErrorCounter::default();
+    let compare_errors = ErrorCounter::default();
+    let metrics = Metrics::default();
+    let freshness = FreshnessChannel::default();
+    let read_your_writes = ReadYourWritesResults::default();
+    let interrupt_tracker = tracker.clone();
+    // Every connection is retried with --connect-retries, so that a
+    // database that is briefly unavailable does not abort the benchmark.
+    let primary_database = || {
+        connect_with_retries(
+            "primary database",
+            config.connect_retries,
+            config.connect_backoff,
+            || benchmark.primary_database(&metrics),
+        )
+        .context("failed to build primary database client")
+    };
+    let read_replica = || {
+        connect_with_retries(
+            "read replica",
+            config.connect_retries,
+            config.connect_backoff,
+            || benchmark.read_replica(),
+        )
+        .context("failed to build read replica client")
+    };
+    let compare_read_replica = || {
+        connect_with_retries(
+            "comparison read replica",
+            config.connect_retries,
+            config.connect_backoff,
+            || benchmark.compare_read_replica(),
+        )
+        .context("failed to build comparison read replica client")
+    };
+
+    // The weights are only restricted to the read operations here, since
+    // `BenchmarkConfig` lets them be set in either order.
+    let read_weights = config
+        .read_weights
+        .as_ref()
+        .map(|weights| weights.only(&config.read_operations))
+        .transpose()
+        .map_err(|e| anyhow!(e))?;
 
     thread::scope(|s| {
-        let primary = benchmark
-            .primary_database()
-            .context("failed to build primary database client")?;
+        // Read clients draw their ids from a shared sample instead of
+        // querying the primary database before every read operation.
+        info!("Sampling ids from the primary database");
+        let mut id_primary = primary_database()?;
+        // Seeded apart from the primary database simulator and the read
+        // clients, which are seeded with `config.seed` and the values after it.
+        let mut id_rng = StdRng::seed_from_u64(config.seed.wrapping_sub(1));
+        let ids = Arc::new(
+            IdCache::load(&mut id_primary, &mut id_rng)
+                .context("failed to sample ids for the read clients")?
+                .with_access_pattern(config.access_pattern, config.hotset_pct),
+        );
+        // Comparison clients only read the same ids as their counterparts,
+        // which have the same seed, while the sample stays the same. Without
+        // writes, the sample never goes stale.
+        if config.compare || config.no_writes {
+            debug!("not refreshing the id cache");
+        } else {
+            let refresher_ids = ids.clone();
+            s.spawn(move || {
+                debug!("starting id cache refresher");
+                IdRefresher::new(
+                    id_primary,
+                    refresher_ids,
+                    id_rng,
+                    config.id_refresh_interval,
+                    id_tracker,
+                )
+                .run();
+                debug!("shutting down id cache refresher");
+            });
+        }
 
-        s.spawn(move || {
-            eprintln!("starting primary database simulator");
-            let mut simulator =
-                PrimarySimulator::new(primary, cli.transactions_per_second, 42, tracker);
-            if let Err(e) = simulator.run() {
-                eprintln!("{:?}", e);
-                exit(1)
-            }
-            eprintln!("shutting down primary database simulator");
-        });
+        // Without writes, the read clients stop on their own and nothing
+        // waits for the primary database simulator.
+        let primary_simulator = if config.no_writes {
+            debug!("not running the primary database simulator");
+            None
+        } else {
+            debug!("connecting to the primary database");
+            let primary = primary_database()?;
+
+            let primary_config = PrimaryConfig {
+                tps: config.transactions_per_second,
+                write_pattern: config.write_pattern,
+                write_mix: config.write_mix,
+                phases: config.phases.clone(),
+                max_db_size: config.max_db_size,
+                seed: config.seed,
+                max_retries: config.max_retries,
+                conflict_retries: config.conflict_retries,
+                fail_on_error: config.fail_on_error,
+                batch_size: config.batch_size,
+                hierarchy_pct: config.hierarchy_pct,
+                delete_leaf_bias: config.delete_leaf_bias,
+                write_tables: config.write_tables.clone(),
+            };
+            // A simulator that fails stops every other one, and its
+            // error is returned once they have all shut down.
+            let primary_errors = errors.clone();
+            let primary_failure = tracker.clone();
+            let primary_freshness = freshness.clone();
+            Some(s.spawn(move || {
+                debug!("starting primary database simulator");
+                let start = Instant::now();
+                let mut simulator = PrimarySimulator::new(
+                    primary,
+                    primary_config,
+                    primary_errors,
+                    primary_freshness,
+                    tracker,
+                );
+                let writes = simulator.run().inspect_err(|_| {
+                    primary_failure.cancel();
+                })?;
+                debug!("shutting down primary database simulator");
+                Ok::<_, anyhow::Error>((writes, start.elapsed()))
+            }))
+        };
+
+        let lag_monitor = if config.measure_lag {
+            debug!("connecting the replication lag monitor");
+            let primary = primary_database()?;
+            let reader = read_replica()?;
+            let lag_failure = lag_tracker.clone();
+            Some(s.spawn(move || {
+                debug!("starting replication lag monitor");
+                let mut monitor = LagMonitor::new(primary, reader, lag_tracker);
+                let lag = monitor.run().inspect_err(|_| {
+                    lag_failure.cancel();
+                })?;
+                debug!("shutting down replication lag monitor");
+                Ok::<_, anyhow::Error>(lag)
+            }))
+        } else {
+            None
+        };
+
+        let growth_monitor = if config.track_growth {
+            debug!("connecting the database growth monitor");
+            let primary = primary_database()?;
+            let growth_tracker = interrupt_tracker.clone();
+            let growth_failure = interrupt_tracker.clone();
+            Some(s.spawn(move || {
+                debug!("starting database growth monitor");
+                let mut monitor = GrowthMonitor::new(primary, growth_tracker);
+                let growth = monitor.run().inspect_err(|_| {
+                    growth_failure.cancel();
+                })?;
+                debug!("shutting down database growth monitor");
+                Ok::<_, anyhow::Error>(growth)
+            }))
+        } else {
+            None
+        };
 
         let (tx, rx) = mpsc::channel();
 
-        println!(
-            "Starting benchmark for {}",
-            humantime::format_duration(cli.duration)
-        );
+        match config.stop {
+            StopCondition::Duration(duration) => info!(
+                "Starting benchmark for {}",
+                humantime::format_duration(duration)
+            ),
+            StopCondition::Operations(operations) => {
+                info!("Starting benchmark for {operations} read operations")
+            }
+        }
 
         let m = MultiProgress::new();
         let style = ProgressStyle::default_bar()
             .template("{msg} {wide_bar} {pos}/{len} [{elapsed_precise}] ETA: {eta_precise}")
             .unwrap()
             .progress_chars("#>-");
+        if config.progress == ProgressMode::None {
+            m.set_draw_target(ProgressDrawTarget::hidden());
+        }
+        logging::set_progress(&m);
+        // Clears any bar left unfinished when the benchmark fails.
+        let mut bars = ProgressBars::new(m.clone());
 
-        println!("Spawning {} clients", cli.concurrency);
-        for i in 0..cli.concurrency {
-            let secondary = benchmark
-                .primary_database()
-                .context("failed to build primary database client")?;
+        let mut summary = (config.progress == ProgressMode::Summary).then(|| {
+            let pb = bars.add(ProgressBar::new(match config.stop {
+                StopCondition::Duration(duration) => duration.as_secs(),
+                StopCondition::Operations(operations) => operations,
+            }));
+            pb.set_style(style.clone());
+            pb.set_message(format!("{} clients", config.concurrency));
+            SummaryProgressBar::new(pb)
+        });
 
-            let reader = benchmark
-                .read_replica()
-                .context("failed to build read replica client")?;
+        // With --compare-reader-url, a second set of clients runs the same
+        // operations, with the same seeds, against the other read replica.
+        let comparisons: &[bool] = if config.compare {
+            &[false, true]
+        } else {
+            &[false]
+        };
+        // Every client connects before any of them starts, so that the first
+        // clients do not run while the others are still connecting.
+        let mut readers = Vec::with_capacity(config.concurrency as usize * comparisons.len());
+        for &comparison in comparisons {
+            for i in 0..config.concurrency {
+                let name = if comparison {
+                    format!("comparison client {i}")
+                } else {
+                    format!("client {i}")
+                };
+                debug!("connecting read {name}");
+                let mut reader = if comparison {
+                    compare_read_replica()?
+                } else {
+                    read_replica()?
+                };
+                // Fail fast, rather than on every read operation, when
+                // the benchmark does not support reconnecting.
+                if config.reconnect_every.is_some() {
+                    reader
+                        .reconnect()
+                        .context("failed to reconnect to the read replica")?;
+                }
+                // Each client checks read-your-writes consistency over
+                // a primary database connection of its own.
+                let check = match config.read_your_writes {
+                    Some(every) if !comparison => {
+                        let primary = primary_database()?;
+                        Some(ReadYourWritesCheck::new(
+                            Box::new(primary),
+                            every,
+                            format!("{}-{i}", config.seed),
+                            read_your_writes.clone(),
+                        ))
+                    }
+                    _ => None,
+                };
+                readers.push((comparison, i, name, reader, check));
+            }
+        }
+        if config.prime_connections {
+            let start = Instant::now();
+            for (_, _, name, reader, _) in &mut readers {
+                reader
+                    .ping()
+                    .with_context(|| format!("failed to prime the connection of read {name}"))?;
+            }
+            info!(
+                "Primed {} read replica connections in {:?}",
+                readers.len(),
+                start.elapsed()
+            );
+        }
 
+        let start = Instant::now();
+        debug!("Spawning {} clients", readers.len());
+        let mut clients = Vec::with_capacity(readers.len());
+        for (comparison, i, name, reader, check) in readers {
             let tx = tx.clone();
             let handle = handle.clone();
-            let duration = cli.duration;
+            // With --ramp, client i starts i / concurrency of the way into
+            // the ramp, alongside its comparison client, and runs for that
+            // much less time, so that every client stops at --duration.
+            let start_delay = config.ramp * i / config.concurrency;
+            // Operations are split as evenly as possible,
+            // with the remainder going to the first clients.
+            let stop = match config.stop {
+                StopCondition::Operations(operations) => {
+                    let concurrency = config.concurrency as u64;
+                    let remainder = (i as u64) < operations % concurrency;
+                    StopCondition::Operations(operations / concurrency + remainder as u64)
+                }
+                StopCondition::Duration(duration) => {
+                    StopCondition::Duration(duration - start_delay)
+                }
+            };
+            let reader_config = ReaderConfig {
+                stop,
+                duration_mode: config.duration_mode,
+                start_delay,
+                warmup: config.warmup,
+                seed: config.seed.wrapping_add(i as u64 + 1),
+                read_operations: config.read_operations.clone(),
+                read_weights: read_weights.clone(),
+                think_time: config.think_time,
+                think_time_jitter: config.think_time_jitter,
+                reconnect_every: config.reconnect_every,
+                max_retries: config.max_retries,
+                latency_includes_retries: config.latency_includes_retries,
+                fail_on_error: config.fail_on_error,
+                target_tps: config
+                    .target_read_tps
+                    .map(|tps| tps as f64 / config.concurrency as f64),
+                comparison,
+            };
+            let errors = if comparison {
+                compare_errors.clone()
+            } else {
+                errors.clone()
+            };
+            let freshness = freshness.clone();
+            let ids = ids.clone();
 
-            let pb = m.add(ProgressBar::new(duration.as_secs()));
+            let len = match stop {
+                StopCondition::Duration(duration) => duration.as_secs(),
+                StopCondition::Operations(operations) => operations,
+            };
+            let pb = match &mut summary {
+                Some(summary) => summary.add_client(len),
+                None => bars.add(ProgressBar::new(len)),
+            };
             pb.set_style(style.clone());
-            pb.set_message(format!("client {i}"));
+            pb.set_message(name.clone());
 
-            s.spawn(move || {
-                let mut simulator =
-                    ReaderSimulator::new(reader, secondary, duration, tx, pb, handle);
-                if let Err(e) = simulator.run() {
-                    eprintln!("{:?}", e);
-                    exit(1)
-                };
-            });
+            let failure = interrupt_tracker.clone();
+            clients.push((
+                comparison,
+                s.spawn(move || {
+                    debug!("starting read {name}");
+                    let mut simulator = ReaderSimulator::new(
+                        reader,
+                        ids,
+                        reader_config,
+                        tx,
+                        freshness,
+                        pb,
+                        errors,
+                        handle,
+                    );
+                    if let Some(check) = check {
+                        simulator = simulator.with_read_your_writes(check);
+                    }
+                    let measured = simulator.run().inspect_err(|_| {
+                        failure.cancel();
+                    })?;
+                    debug!("read {name} finished");
+                    Ok::<_, anyhow::Error>(measured)
+                }),
+            ));
         }
 
         drop(tx);
         drop(handle);
 
-        let mut measurements = Measurements::new(cli.duration);
+        let new_measurements = || {
+            let mut measurements = Measurements::new(match config.stop {
+                StopCondition::Duration(duration) => duration,
+                // Replaced by the measured time once every client has finished.
+                StopCondition::Operations(_) => Duration::ZERO,
+            });
+            measurements.set_percentiles(config.percentiles.clone());
+            if let Some(target_tps) = config.target_read_tps {
+                measurements.set_target_tps(target_tps);
+                if config.correct_coordinated_omission {
+                    // Each client issues its share of the target rate.
+                    let interval = config.concurrency as f64 / target_tps as f64;
+                    measurements.set_expected_interval(Duration::from_secs_f64(interval));
+                }
+            }
+            measurements
+        };
+        let mut measurements = new_measurements();
+        // Samples from the comparison clients are only recorded here, and not
+        // in the samples file, the live reports, or the Prometheus metrics.
+        let mut comparison = config.compare.then(new_measurements);
+        #[cfg(feature = "prometheus")]
+        let exporter = config
+            .prometheus_port
+            .map(prometheus::PrometheusExporter::start)
+            .transpose()?;
+        let mut window = Window::new();
+        let mut samples = match &config.samples_out {
+            Some(path) => Some(
+                csv::Writer::from_path(path)
+                    .with_context(|| format!("failed to create {}", path.display()))?,
+            ),
+            None => None,
+        };
+        let mut timeseries = match &config.timeseries_out {
+            Some(path) => {
+                let file = File::create(path)
+                    .with_context(|| format!("failed to create {}", path.display()))?;
+                Some(TimeSeries::new(file))
+            }
+            None => None,
+        };
+        let mut influx = match &config.influx_url {
+            Some(url) => {
+                let run_id = config.run_id.clone().unwrap_or_else(|| {
+                    let now = SystemTime::now().duration_since(UNIX_EPOCH);
+                    now.unwrap_or_default().as_secs().to_string()
+                });
+                info!("pushing metrics to {url} with run id {run_id}");
+                Some(InfluxSink::connect(url, run_id)?)
+            }
+            None => None,
+        };
+        let timeout = [config.report_interval, config.influx_interval]
+            .into_iter()
+            .flatten()
+            .fold(Duration::from_secs(1), Duration::min);
         loop {
-            match rx.recv_timeout(Duration::from_secs(1)) {
-                Ok(duration) => measurements.push(duration),
-                Err(RecvTimeoutError::Disconnected) => break,
+            match rx.recv_timeout(timeout) {
+                Ok(sample) if sample.comparison => {
+                    if let Some(comparison) = &mut comparison {
+                        record_sample(comparison, &sample);
+                    }
+                }
+                Ok(sample) => {
+                    if let Some(writer) = &mut samples {
+                        writer
+                            .serialize(SampleRecord {
+                                operation: sample.operation.into(),
+                                latency_us: sample.latency.as_secs_f64() * 1_000_000.0,
+                                offset_secs: sample
+                                    .timestamp
+                                    .saturating_duration_since(start)
+                                    .as_secs_f64(),
+                            })
+                            .context("failed to write latency sample")?;
+                    }
+                    #[cfg(feature = "prometheus")]
+                    if let Some(exporter) = &exporter {
+                        exporter.observe(sample.operation, sample.latency);
+                    }
+                    if let Some(timeseries) = &mut timeseries {
+                        timeseries.push(
+                            sample.timestamp.saturating_duration_since(start),
+                            sample.latency,
+                        )?;
+                    }
+                    if let Some(influx) = &mut influx {
+                        influx.observe(sample.operation, sample.latency);
+                    }
+                    record_sample(&mut measurements, &sample);
+                    window.push(sample.latency);
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    if let Some(writer) = &mut samples {
+                        writer.flush().context("failed to flush latency samples")?;
+                    }
+                    if let Some(timeseries) = timeseries.take() {
+                        timeseries.finish(start.elapsed())?;
+                    }
+                    if let Some(influx) = &mut influx {
+                        if let Err(e) = influx.push(errors.count()) {
+                            warn!("{e:#}");
+                        }
+                    }
+                    if let Some(summary) = &summary {
+                        summary.finish();
+                    }
+                    break;
+                }
                 _ => {}
             }
+
+            if let Some(summary) = &mut summary {
+                summary.update();
+            }
+
+            if let Some(timeseries) = &mut timeseries {
+                timeseries.advance(start.elapsed())?;
+            }
+
+            #[cfg(feature = "prometheus")]
+            if let Some(exporter) = &exporter {
+                exporter.set_errors(errors.count());
+            }
+
+            // A metrics backend that is briefly unreachable must not stop
+            // the benchmark, so failed pushes are only logged.
+            if let (Some(influx), Some(interval)) = (&mut influx, config.influx_interval) {
+                if influx.elapsed() >= interval {
+                    if let Err(e) = influx.push(errors.count()) {
+                        warn!("{e:#}");
+                    }
+                }
+            }
+
+            if let Some(interval) = config.report_interval {
+                if window.elapsed() >= interval {
+                    let line = format!(
+                        "[{}] {}",
+                        humantime::format_duration(Duration::from_secs(start.elapsed().as_secs())),
+                        window.take()
+                    );
+                    // Progress bars are hidden when stderr is not a terminal,
+                    // and so is anything printed through them.
+                    if m.is_hidden() {
+                        eprintln!("{line}");
+                    } else {
+                        m.println(line).context("failed to print live metrics")?;
+                    }
+                }
+            }
+        }
+
+        // The first simulator or monitor that failed, whose error is returned
+        // with the measurements collected until every other one stopped.
+        let mut failure = None;
+
+        // The time measured by the slowest client of each set.
+        let mut measured = Duration::ZERO;
+        let mut compare_measured = Duration::ZERO;
+        for (comparison, client) in clients {
+            let client_measured = match client
+                .join()
+                .expect("read client panicked")
+                .context("read client failed")
+            {
+                Ok(client_measured) => client_measured,
+                Err(e) => {
+                    failure.get_or_insert(e);
+                    continue;
+                }
+            };
+            let slowest = if comparison {
+                &mut compare_measured
+            } else {
+                &mut measured
+            };
+            *slowest = (*slowest).max(client_measured);
+        }
+        let set_total_duration =
+            |measurements: &mut Measurements, measured: Duration| match config.stop {
+                StopCondition::Duration(duration) if interrupt_tracker.is_cancelled() => {
+                    measurements.set_total_duration(measured.min(duration));
+                }
+                StopCondition::Duration(_) => {}
+                StopCondition::Operations(_) => measurements.set_total_duration(measured),
+            };
+        set_total_duration(&mut measurements, measured);
+
+        if let Some(primary_simulator) = primary_simulator {
+            match primary_simulator
+                .join()
+                .map_err(|_| anyhow!("primary database simulator panicked"))?
+                .context("primary database simulator failed")
+            {
+                Ok((writes, elapsed)) => {
+                    measurements.set_writes(writes, elapsed, config.transactions_per_second)
+                }
+                Err(e) => {
+                    failure.get_or_insert(e);
+                }
+            }
+        }
+        measurements.set_errors(errors.count());
+        if config.query_timeout.is_some() {
+            measurements.set_timeouts(errors.timeouts());
+        }
+        if config.conflict_retries > 0 {
+            measurements.set_conflict_retries(errors.conflict_retries());
+        }
+        measurements.set_pool_wait(metrics.take_pool_wait());
+        if config.read_your_writes.is_some() {
+            measurements.set_read_your_writes(read_your_writes.take());
+        }
+
+        if let Some(mut comparison) = comparison {
+            set_total_duration(&mut comparison, compare_measured);
+            comparison.set_errors(compare_errors.count());
+            if config.query_timeout.is_some() {
+                comparison.set_timeouts(compare_errors.timeouts());
+            }
+            measurements.set_comparison(comparison);
+        }
+
+        if let Some(lag_monitor) = lag_monitor {
+            match lag_monitor
+                .join()
+                .map_err(|_| anyhow!("replication lag monitor panicked"))?
+                .context("replication lag monitor failed")
+            {
+                Ok(lag) => measurements.set_replication_lag(lag),
+                Err(e) => {
+                    failure.get_or_insert(e);
+                }
+            }
+        }
+
+        if let Some(growth_monitor) = growth_monitor {
+            match growth_monitor
+                .join()
+                .map_err(|_| anyhow!("database growth monitor panicked"))?
+                .context("database growth monitor failed")
+            {
+                Ok(growth) => measurements.set_growth(growth),
+                Err(e) => {
+                    failure.get_or_insert(e);
+                }
+            }
+        }
+
+        if let Some(e) = failure {
+            return Err(e.context(BenchmarkAborted { measurements }));
         }
         Ok(measurements)
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{FakeDatabase, FakeReplica};
+    use std::fs;
+
+    #[derive(Default)]
+    struct FakeBenchmark {
+        fail_setup: bool,
+        /// The number of read operations that fail on each read replica.
+        read_failures: usize,
+    }
+
+    impl<'a> Benchmark<'a> for FakeBenchmark {
+        type Writer = FakeDatabase;
+
+        type Reader = FakeReplica;
+
+        fn primary_database(&'a self, _metrics: &Metrics) -> Result<FakeDatabase> {
+            Ok(FakeDatabase)
+        }
+
+        fn read_replica(&self) -> Result<FakeReplica> {
+            Ok(FakeReplica {
+                failures: self.read_failures,
+                ..FakeReplica::default()
+            })
+        }
+
+        fn setup(&self) -> Result<()> {
+            if self.fail_setup {
+                bail!("fixtures are missing");
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn cancelled_run_writes_the_samples_file() {
+        let path =
+            std::env::temp_dir().join(format!("rr-bench-{}-samples.csv", std::process::id()));
+        let cli = Args::new(Vec::<Arg>::new()).parse_from(&[
+            "--duration",
+            "60s",
+            "--think-time",
+            "1ms",
+            "--progress",
+            "none",
+            "--samples-out",
+            path.to_str().unwrap(),
+        ]);
+        let (handle, tracker) = new_task_handles();
+        let interrupt = tracker.clone();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(500));
+            interrupt.cancel();
+        });
+
+        let start = Instant::now();
+        let measurements = inner(cli.config, FakeBenchmark::default(), handle, tracker).unwrap();
+        assert!(start.elapsed() < Duration::from_secs(30));
+
+        let mut samples = csv::Reader::from_path(&path).unwrap();
+        assert_eq!(
+            samples.headers().unwrap(),
+            vec!["operation", "latency_us", "offset_secs"]
+        );
+        let rows = samples.records().collect::<Result<Vec<_>, _>>().unwrap();
+        fs::remove_file(&path).unwrap();
+        assert!(!rows.is_empty());
+        assert_eq!(rows.len(), measurements.total_transactions());
+    }
+
+    #[test]
+    fn no_writes_runs_only_the_read_clients() {
+        let cli = Args::new(Vec::<Arg>::new()).parse_from(&[
+            "--operations",
+            "100",
+            "--concurrency",
+            "2",
+            "--progress",
+            "none",
+            "--no-writes",
+        ]);
+        let (handle, tracker) = new_task_handles();
+        let measurements = inner(cli.config, FakeBenchmark::default(), handle, tracker).unwrap();
+        assert_eq!(measurements.total_transactions(), 100);
+
+        let report: serde_json::Value = serde_json::from_str(&measurements.to_json()).unwrap();
+        assert_eq!(report["writes"]["total_transactions"], 0);
+        assert_eq!(report["writes"]["tps"], 0.0);
+    }
+
+    #[test]
+    fn run_with_config_bypasses_the_command_line() {
+        let config = BenchmarkConfig::for_operations(50)
+            .concurrency(2)
+            .transactions_per_second(100)
+            .seed(7)
+            .progress(ProgressMode::None);
+        let measurements = run_with_config(config, FakeBenchmark::default()).unwrap();
+        assert_eq!(measurements.total_transactions(), 50);
+
+        let config = BenchmarkConfig::for_operations(50).write_mix(100, 100, 0);
+        let Err(error) = run_with_config(config, FakeBenchmark::default()) else {
+            panic!("the benchmark ran with an invalid write mix");
+        };
+        assert_eq!(
+            format!("{error:#}"),
+            "invalid benchmark configuration: the write mix must sum to 100, not 200"
+        );
+    }
+
+    #[test]
+    fn fail_on_error_returns_the_measurements_so_far() {
+        let benchmark = || FakeBenchmark {
+            read_failures: 1,
+            ..FakeBenchmark::default()
+        };
+        let config = || {
+            BenchmarkConfig::for_operations(50)
+                .no_writes(true)
+                .progress(ProgressMode::None)
+        };
+        let measurements = run_with_config(config(), benchmark()).unwrap();
+        assert_eq!(measurements.total_transactions(), 49);
+
+        let Err(error) = run_with_config(config().fail_on_error(true), benchmark()) else {
+            panic!("the benchmark continued after a failed read operation");
+        };
+        assert!(format!("{error:#}").starts_with("the benchmark was aborted: read client failed: "));
+        let aborted = error.downcast_ref::<BenchmarkAborted>().unwrap();
+        assert_eq!(aborted.measurements.total_transactions(), 0);
+    }
+
+    #[test]
+    fn failed_monitor_returns_the_measurements_so_far() {
+        let config = BenchmarkConfig::for_operations(50)
+            .no_writes(true)
+            .track_growth(true)
+            .progress(ProgressMode::None);
+        let Err(error) = run_with_config(config, FakeBenchmark::default()) else {
+            panic!("the benchmark ignored the failed growth monitor");
+        };
+        assert_eq!(
+            format!("{error:#}"),
+            "the benchmark was aborted: database growth monitor failed: \
+             failed to query the size of the primary database: \
+             --track-growth is not supported by this benchmark"
+        );
+        assert!(error.downcast_ref::<BenchmarkAborted>().is_some());
+    }
+
+    #[test]
+    fn failed_setup_stops_the_benchmark() {
+        let cli =
+            Args::new(Vec::<Arg>::new()).parse_from(&["--operations", "10", "--progress", "none"]);
+        let (handle, tracker) = new_task_handles();
+        let benchmark = FakeBenchmark {
+            fail_setup: true,
+            ..FakeBenchmark::default()
+        };
+        let Err(error) = inner(cli.config, benchmark, handle, tracker) else {
+            panic!("the benchmark ran despite the failed setup");
+        };
+        assert_eq!(
+            format!("{error:#}"),
+            "failed to set up the benchmark: fixtures are missing"
+        );
+    }
+
+    #[test]
+    fn priming_fails_without_ping() {
+        let cli = Args::new(Vec::<Arg>::new()).parse_from(&[
+            "--operations",
+            "10",
+            "--progress",
+            "none",
+            "--prime-connections",
+        ]);
+        let (handle, tracker) = new_task_handles();
+        let Err(error) = inner(cli.config, FakeBenchmark::default(), handle, tracker) else {
+            panic!("the benchmark ran without priming its connections");
+        };
+        assert_eq!(
+            format!("{error:#}"),
+            "failed to prime the connection of read client 0: \
+             --prime-connections is not supported by this benchmark"
+        );
+    }
+}
@@ -0,0 +1,61 @@
+use crate::config::LogLevel;
+use indicatif::MultiProgress;
+use log::{LevelFilter, Log, Metadata, Record};
+use std::sync::OnceLock;
+
+static LOGGER: StderrLogger = StderrLogger {
+    progress: OnceLock::new(),
+};
+
+/// `StderrLogger` writes log records to stderr, one per line. Once the
+/// progress bars are drawn, records are printed through them so they do
+/// not tear the bars apart.
+struct StderrLogger {
+    progress: OnceLock<MultiProgress>,
+}
+
+impl Log for StderrLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let line = format!("{:<5} {}", record.level(), record.args());
+        match self.progress.get() {
+            Some(progress) => progress.suspend(|| eprintln!("{line}")),
+            None => eprintln!("{line}"),
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// Installs the logger, discarding records less severe than `level`.
+pub fn init(level: LogLevel) {
+    // Only fails if a logger is already installed, which
+    // leaves that logger in place.
+    let _ = log::set_logger(&LOGGER);
+    log::set_max_level(level.into());
+}
+
+/// Prints subsequent log records through `progress`.
+pub fn set_progress(progress: &MultiProgress) {
+    let _ = LOGGER.progress.set(progress.clone());
+}
+
+impl From<LogLevel> for LevelFilter {
+    fn from(level: LogLevel) -> Self {
+        match level {
+            LogLevel::Off => LevelFilter::Off,
+            LogLevel::Error => LevelFilter::Error,
+            LogLevel::Warn => LevelFilter::Warn,
+            LogLevel::Info => LevelFilter::Info,
+            LogLevel::Debug => LevelFilter::Debug,
+            LogLevel::Trace => LevelFilter::Trace,
+        }
+    }
+}
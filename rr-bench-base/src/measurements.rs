@@ -1,103 +1,1496 @@
+use crate::growth_monitor::Growth;
+use crate::operations::ReadOperation;
 use crate::pretty_duration::PrettyDuration;
+use crate::read_your_writes::{ReadYourWrites, VISIBILITY_TIMEOUT};
+use hdrhistogram::Histogram;
+use indicatif::HumanBytes;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+
+/// The percentiles of the read latencies reported by default, besides the
+/// median.
+pub const DEFAULT_PERCENTILES: [f64; 4] = [90.0, 95.0, 99.0, 99.9];
 
 pub struct Measurements {
-    durations: Vec<Duration>,
+    latencies: Latencies,
+    operations: BTreeMap<ReadOperation, Latencies>,
+    rows: Rows,
+    operation_rows: BTreeMap<ReadOperation, Rows>,
+    replication_lag: Latencies,
+    growth: Option<Growth>,
+    writes: Latencies,
+    write_duration: Duration,
+    write_target_tps: u32,
+    /// Set when the benchmark was run with `--conflict-retries`.
+    conflict_retries: Option<usize>,
+    pool_wait: Latencies,
+    reconnects: Latencies,
+    freshness_checks: usize,
+    stale_reads: usize,
+    read_your_writes: Option<ReadYourWrites>,
+    transactions: usize,
+    errors: usize,
+    timeouts: Option<usize>,
+    target_tps: Option<u32>,
+    expected_interval: Option<Duration>,
+    /// The percentiles of the read latencies that are reported, besides
+    /// the median.
+    percentiles: Vec<f64>,
     total_duration: Duration,
+    /// The reads made against `--compare-reader-url`.
+    comparison: Option<Box<Measurements>>,
 }
 
 impl Measurements {
     pub fn new(total_duration: Duration) -> Self {
         Self {
-            durations: Vec::new(),
+            latencies: Latencies::default(),
+            operations: BTreeMap::new(),
+            rows: Rows::default(),
+            operation_rows: BTreeMap::new(),
+            replication_lag: Latencies::default(),
+            growth: None,
+            writes: Latencies::default(),
+            write_duration: Duration::ZERO,
+            write_target_tps: 0,
+            conflict_retries: None,
+            pool_wait: Latencies::default(),
+            reconnects: Latencies::default(),
+            freshness_checks: 0,
+            stale_reads: 0,
+            read_your_writes: None,
+            transactions: 0,
+            errors: 0,
+            timeouts: None,
+            target_tps: None,
+            expected_interval: None,
+            percentiles: DEFAULT_PERCENTILES.to_vec(),
             total_duration,
+            comparison: None,
         }
     }
 
-    pub fn push(&mut self, value: Duration) {
-        self.durations.push(value)
+    pub fn push(&mut self, operation: ReadOperation, value: Duration) {
+        self.transactions += 1;
+        match self.expected_interval {
+            Some(interval) => self.latencies.push_corrected(value, interval),
+            None => self.latencies.push(value),
+        }
+        self.operations.entry(operation).or_default().push(value);
+    }
+
+    /// Records the number of rows read by a read operation.
+    pub fn push_rows(&mut self, operation: ReadOperation, rows: u64) {
+        self.rows.push(rows);
+        self.operation_rows.entry(operation).or_default().push(rows);
+    }
+
+    /// Records how long a read client took to reconnect to the read replica.
+    pub fn push_reconnect(&mut self, value: Duration) {
+        self.reconnects.push(value);
+    }
+
+    /// Records the outcome of a row freshness check, which is
+    /// `stale` when the read replica had not yet caught up.
+    pub fn push_freshness_check(&mut self, stale: bool) {
+        self.freshness_checks += 1;
+        self.stale_reads += stale as usize;
+    }
+
+    /// The fraction of row freshness checks that found the read replica
+    /// stale, or `None` if no checks were made.
+    pub fn stale_read_rate(&self) -> Option<f64> {
+        (self.freshness_checks > 0).then(|| self.stale_reads as f64 / self.freshness_checks as f64)
+    }
+
+    /// Sets the outcomes of the checks made with `--read-your-writes`.
+    pub fn set_read_your_writes(&mut self, read_your_writes: ReadYourWrites) {
+        self.read_your_writes = Some(read_your_writes);
+    }
+
+    /// Overrides the duration used to compute throughput, e.g. when
+    /// the benchmark was interrupted before it ran to completion.
+    pub fn set_total_duration(&mut self, total_duration: Duration) {
+        self.total_duration = total_duration;
+    }
+
+    pub fn set_replication_lag(&mut self, lag: Latencies) {
+        self.replication_lag = lag;
+    }
+
+    /// Sets the size of the primary database sampled with `--track-growth`.
+    pub fn set_growth(&mut self, growth: Growth) {
+        self.growth = Some(growth);
+    }
+
+    /// Sets the latencies of the write transactions executed against the
+    /// primary database, the wall-clock time they were executed over, and
+    /// the rate requested with `--transactions-per-second`, which lookups
+    /// and slow commits can keep the primary database simulator from reaching.
+    pub fn set_writes(&mut self, writes: Latencies, duration: Duration, target_tps: u32) {
+        self.writes = writes;
+        self.write_duration = duration;
+        self.write_target_tps = target_tps;
+    }
+
+    /// Sets the number of times a write transaction was executed again after
+    /// a serialization failure or a deadlock. Conflict retries are only
+    /// reported once this is set.
+    pub fn set_conflict_retries(&mut self, conflict_retries: usize) {
+        self.conflict_retries = Some(conflict_retries);
+    }
+
+    /// Sets the time spent acquiring pooled primary database connections,
+    /// as recorded by the backend.
+    pub fn set_pool_wait(&mut self, pool_wait: Latencies) {
+        self.pool_wait = pool_wait;
+    }
+
+    /// The number of write transactions per second executed against the primary database.
+    pub fn write_tps(&self) -> f64 {
+        if self.writes.is_empty() {
+            return 0.0;
+        }
+        self.writes.len() as f64 / self.write_duration.as_secs_f64()
+    }
+
+    /// Sets the read rate the clients were paced to, so it
+    /// can be reported alongside the achieved rate.
+    pub fn set_target_tps(&mut self, target_tps: u32) {
+        self.target_tps = Some(target_tps);
+    }
+
+    /// Corrects the overall latency distribution for coordinated omission,
+    /// given the interval at which each client was expected to issue reads.
+    /// Must be set before any samples are pushed. Per-operation statistics
+    /// are left uncorrected.
+    pub fn set_expected_interval(&mut self, interval: Duration) {
+        self.expected_interval = Some(interval);
+    }
+
+    /// Reports `percentiles` of the read latencies instead of
+    /// `DEFAULT_PERCENTILES`.
+    pub fn set_percentiles(&mut self, percentiles: Vec<f64>) {
+        self.percentiles = percentiles;
+    }
+
+    /// Sets the reads made by the clients running the same workload against
+    /// `--compare-reader-url`, which are reported next to these.
+    pub fn set_comparison(&mut self, comparison: Measurements) {
+        self.comparison = Some(Box::new(comparison));
+    }
+
+    /// Sets the number of operations that failed after exhausting their retries.
+    pub fn set_errors(&mut self, errors: usize) {
+        self.errors = errors;
+    }
+
+    /// Sets the number of read operations that were cancelled by
+    /// `--query-timeout`. Timeouts are only reported once this is set.
+    pub fn set_timeouts(&mut self, timeouts: usize) {
+        self.timeouts = Some(timeouts);
+    }
+
+    /// The number of operations that failed or timed out.
+    pub fn failed_operations(&self) -> usize {
+        self.errors + self.timeouts.unwrap_or(0)
+    }
+
+    /// The fraction of attempted operations that failed.
+    pub fn error_rate(&self) -> f64 {
+        let attempted = self.attempted();
+        if attempted == 0 {
+            return 0.0;
+        }
+        self.errors as f64 / attempted as f64
+    }
+
+    /// The fraction of attempted operations that timed out, or `None` when
+    /// the benchmark was run without `--query-timeout`.
+    pub fn timeout_rate(&self) -> Option<f64> {
+        let timeouts = self.timeouts?;
+        let attempted = self.attempted();
+        if attempted == 0 {
+            return Some(0.0);
+        }
+        Some(timeouts as f64 / attempted as f64)
+    }
+
+    fn attempted(&self) -> usize {
+        self.total_transactions() + self.errors + self.timeouts.unwrap_or(0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.latencies.is_empty()
+    }
+
+    /// The latencies of every read operation.
+    pub fn latencies(&self) -> &Latencies {
+        &self.latencies
     }
 
     pub fn total_transactions(&self) -> usize {
-        self.durations.len()
+        self.transactions
     }
 
     pub fn tps(&self) -> f64 {
         self.total_transactions() as f64 / self.total_duration.as_secs_f64()
     }
 
-    pub fn max(&self) -> PrettyDuration {
-        (*self.durations.iter().max().unwrap()).into()
+    /// Renders the distribution of read latencies as an ASCII histogram no
+    /// wider than `width` columns, with `HISTOGRAM_BUCKETS` log-spaced
+    /// buckets between the minimum and maximum latency.
+    pub fn histogram(&self, width: usize) -> String {
+        let buckets = self.latencies.log_buckets(HISTOGRAM_BUCKETS);
+        let Some(largest) = buckets.iter().map(|bucket| bucket.count).max() else {
+            return "No samples collected\n".to_string();
+        };
+
+        // Room for the bucket edges and the count, e.g.
+        // "   0.016000 ms -    0.025000 ms     1234 ".
+        let bar_width = width.saturating_sub(41).max(10);
+        let mut out = String::from("Read Latency Histogram\n");
+        for bucket in buckets {
+            // Every non-empty bucket gets at least one mark, so rare outliers stay visible.
+            let bar = (bucket.count as f64 / largest as f64 * bar_width as f64).ceil() as usize;
+            let line = format!(
+                "{:>14} - {:>14} {:>8} {}",
+                PrettyDuration::from(bucket.low),
+                PrettyDuration::from(bucket.high),
+                bucket.count,
+                "#".repeat(bar),
+            );
+            out.push_str(line.trim_end());
+            out.push('\n');
+        }
+        out
+    }
+
+    /// Summarizes the reads on a single line of space-separated `key=value`
+    /// pairs, e.g. `transactions=5000 tps=512.30 p50=1.200000ms ...
+    /// errors=0`, with a `pN` pair for the median and each of the
+    /// percentiles. Latencies are `n/a` when no samples were collected.
+    pub fn oneline(&self) -> String {
+        let latency = |latency: Option<PrettyDuration>| {
+            latency.map_or_else(|| "n/a".to_string(), |latency| latency.compact())
+        };
+        let mut pairs = vec![
+            format!("transactions={}", self.total_transactions()),
+            format!("tps={:.2}", self.tps()),
+            format!("p50={}", latency(self.latencies.median())),
+        ];
+        // The median is always reported, so it is not repeated when it is
+        // also one of the percentiles.
+        for &percentile in self.percentiles.iter().filter(|&&p| p != 50.0) {
+            pairs.push(format!(
+                "p{percentile}={}",
+                latency(self.latencies.percentile(percentile))
+            ));
+        }
+        pairs.push(format!("errors={}", self.errors));
+        if let Some(timeouts) = self.timeouts {
+            pairs.push(format!("timeouts={timeouts}"));
+        }
+        pairs.join(" ")
+    }
+
+    /// Serializes the full result set as a JSON object. Latencies
+    /// are reported in milliseconds and are `null` when no samples
+    /// were collected.
+    pub fn to_json(&self) -> String {
+        let report = Report {
+            samples: self.latencies.len(),
+            total_duration_secs: self.total_duration.as_secs_f64(),
+            total_transactions: self.total_transactions(),
+            tps: self.tps(),
+            target_tps: self.target_tps,
+            expected_interval_ms: self
+                .expected_interval
+                .map(|interval| millis(interval.into())),
+            errors: self.errors,
+            error_rate: self.error_rate(),
+            timeouts: self.timeouts,
+            timeout_rate: self.timeout_rate(),
+            latency_ms: self.latencies.report(),
+            rows_read: self.rows.report(),
+            percentiles: self
+                .percentiles
+                .iter()
+                .map(|&percentile| PercentileReport {
+                    percentile,
+                    latency_ms: self.latencies.percentile(percentile).map(millis),
+                })
+                .collect(),
+            operations: self
+                .operations
+                .iter()
+                .map(|(operation, latencies)| {
+                    let report = OperationReport {
+                        samples: latencies.len(),
+                        latency_ms: latencies.report(),
+                        rows_read: self.operation_rows.get(operation).and_then(Rows::report),
+                    };
+                    (operation.as_ref().to_string(), report)
+                })
+                .collect(),
+            writes: WriteReport {
+                total_transactions: self.writes.len(),
+                tps: self.write_tps(),
+                target_tps: self.write_target_tps,
+                conflict_retries: self.conflict_retries,
+                latency_ms: self.writes.report(),
+            },
+            pool_wait: (!self.pool_wait.is_empty()).then(|| PoolWaitReport {
+                acquisitions: self.pool_wait.len(),
+                latency_ms: self.pool_wait.report(),
+            }),
+            reconnects: (!self.reconnects.is_empty()).then(|| ReconnectReport {
+                reconnects: self.reconnects.len(),
+                latency_ms: self.reconnects.report(),
+            }),
+            replication_lag_ms: (!self.replication_lag.is_empty())
+                .then(|| self.replication_lag.report()),
+            growth: self.growth.map(|growth| GrowthReport {
+                start_bytes: growth.start,
+                end_bytes: growth.end,
+                peak_bytes: growth.peak,
+                growth_pct: growth_pct(growth),
+            }),
+            freshness: self.stale_read_rate().map(|rate| FreshnessReport {
+                checks: self.freshness_checks,
+                stale_reads: self.stale_reads,
+                stale_read_rate: rate,
+            }),
+            read_your_writes: self
+                .read_your_writes
+                .as_ref()
+                .map(|checks| ReadYourWritesReport {
+                    checks: checks.checks,
+                    visible_on_first_read: checks.visible,
+                    success_rate: checks.success_rate(),
+                    not_visible: checks.not_visible,
+                    visibility_delay_ms: (!checks.delays.is_empty())
+                        .then(|| checks.delays.report()),
+                }),
+            comparison: self
+                .comparison
+                .as_deref()
+                .map(|comparison| self.comparison_report(comparison)),
+        };
+
+        serde_json::to_string_pretty(&report).expect("failed to serialize measurements")
+    }
+}
+
+impl Measurements {
+    fn comparison_report(&self, comparison: &Measurements) -> ComparisonReport {
+        let latency_ms = comparison.latencies.report();
+        ComparisonReport {
+            samples: comparison.latencies.len(),
+            total_transactions: comparison.total_transactions(),
+            tps: comparison.tps(),
+            errors: comparison.errors,
+            error_rate: comparison.error_rate(),
+            timeouts: comparison.timeouts,
+            timeout_rate: comparison.timeout_rate(),
+            tps_difference_pct: percent_difference(Some(self.tps()), Some(comparison.tps())),
+            latency_difference_pct: self.latencies.report().percent_difference(&latency_ms),
+            regression: self.latencies.is_regression(&comparison.latencies),
+            latency_ms,
+            operations: comparison
+                .operations
+                .iter()
+                .map(|(operation, latencies)| {
+                    let latency_ms = latencies.report();
+                    let base = self.operations.get(operation);
+                    let report = OperationComparisonReport {
+                        samples: latencies.len(),
+                        latency_difference_pct: base
+                            .map(|base| base.report().percent_difference(&latency_ms)),
+                        regression: base.is_some_and(|base| base.is_regression(latencies)),
+                        latency_ms,
+                    };
+                    (operation.as_ref().to_string(), report)
+                })
+                .collect(),
+        }
+    }
+}
+
+/// A statistic computed over a set of latencies.
+type Stat = fn(&Latencies) -> Option<PrettyDuration>;
+
+/// Writes a row of the comparison table. Missing values are shown as "n/a".
+fn fmt_latency_row(
+    f: &mut fmt::Formatter<'_>,
+    label: &str,
+    base: Option<PrettyDuration>,
+    other: Option<PrettyDuration>,
+    regression: bool,
+) -> fmt::Result {
+    let format = |value: &Option<PrettyDuration>| {
+        value
+            .as_ref()
+            .map_or_else(|| "n/a".to_string(), ToString::to_string)
+    };
+    fmt_comparison_row(
+        f,
+        label,
+        format(&base),
+        format(&other),
+        percent_difference(base.map(millis), other.map(millis)),
+        regression,
+    )
+}
+
+fn fmt_comparison_row(
+    f: &mut fmt::Formatter<'_>,
+    label: &str,
+    base: String,
+    other: String,
+    difference: Option<f64>,
+    regression: bool,
+) -> fmt::Result {
+    let difference = difference.map_or_else(
+        || "n/a".to_string(),
+        |difference| format!("{difference:+.2}%"),
+    );
+    let flag = if regression { "  regression" } else { "" };
+    writeln!(
+        f,
+        "{:<36} {:>16} {:>16} {:>12}{}",
+        label, base, other, difference, flag
+    )
+}
+
+/// The percent difference from `base` to `other`, which is positive when
+/// `other` is larger, or `None` when either is missing or `base` is zero.
+fn percent_difference(base: Option<f64>, other: Option<f64>) -> Option<f64> {
+    match (base, other) {
+        (Some(base), Some(other)) if base != 0.0 => Some((other - base) / base * 100.0),
+        _ => None,
+    }
+}
+
+/// Accumulates the reads completed since the last live report,
+/// alongside the cumulative `Measurements`.
+pub struct Window {
+    latencies: Latencies,
+    start: Instant,
+}
+
+impl Window {
+    pub fn new() -> Self {
+        Self {
+            latencies: Latencies::default(),
+            start: Instant::now(),
+        }
+    }
+
+    pub fn push(&mut self, value: Duration) {
+        self.latencies.push(value);
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    /// Summarizes the reads in the current window and starts a new one.
+    pub fn take(&mut self) -> String {
+        let elapsed = self.elapsed().as_secs_f64();
+        let summary = format!(
+            "{} reads, {:.2} TPS, 95th percentile {}",
+            self.latencies.len(),
+            self.latencies.len() as f64 / elapsed,
+            self.latencies
+                .percentile_95()
+                .map_or_else(|| "n/a".to_string(), |p95| p95.to_string()),
+        );
+
+        *self = Self::new();
+        summary
+    }
+}
+
+/// The rows read by a set of read operations.
+#[derive(Default)]
+struct Rows {
+    operations: usize,
+    total: u64,
+}
+
+impl Rows {
+    fn push(&mut self, rows: u64) {
+        self.operations += 1;
+        self.total += rows;
+    }
+
+    /// The average number of rows read per operation, or `None` if no
+    /// operations reported their rows.
+    fn average(&self) -> Option<f64> {
+        (self.operations > 0).then(|| self.total as f64 / self.operations as f64)
+    }
+
+    fn report(&self) -> Option<RowsReport> {
+        self.average().map(|average| RowsReport {
+            total: self.total,
+            average_per_operation: average,
+        })
+    }
+}
+
+/// The number of buckets in the `--histogram` output.
+const HISTOGRAM_BUCKETS: usize = 20;
+
+/// A range of latencies and the number of samples that fell within it.
+struct Bucket {
+    low: Duration,
+    high: Duration,
+    count: u64,
+}
+
+/// The fewest samples each side of a comparison needs before a difference
+/// in average latency is tested for significance.
+const MIN_SIGNIFICANCE_SAMPLES: usize = 30;
+
+/// The two-sided critical value of the normal distribution at the 5% level.
+const CRITICAL_VALUE: f64 = 1.96;
+
+/// The number of significant decimal digits preserved for
+/// each recorded latency.
+const SIGNIFICANT_FIGURES: u8 = 3;
+
+/// A set of latency samples and the statistics computed over them.
+///
+/// Samples are recorded in nanoseconds into an HDR histogram, so memory
+/// usage stays constant regardless of how long the benchmark runs.
+pub struct Latencies {
+    histogram: Histogram<u64>,
+}
+
+impl Default for Latencies {
+    fn default() -> Self {
+        Self {
+            histogram: Histogram::new(SIGNIFICANT_FIGURES)
+                .expect("failed to create latency histogram"),
+        }
+    }
+}
+
+impl Latencies {
+    pub fn push(&mut self, value: Duration) {
+        let nanos = u64::try_from(value.as_nanos()).unwrap_or(u64::MAX);
+        if self.histogram.record(nanos).is_err() {
+            self.histogram.saturating_record(nanos);
+        }
+    }
+
+    /// Records `value` along with the samples that were never taken because
+    /// an operation took longer than `expected_interval`, as if operations had
+    /// kept being issued on schedule. Corrects for coordinated omission, see
+    /// `Histogram::record_correct`.
+    pub fn push_corrected(&mut self, value: Duration, expected_interval: Duration) {
+        let nanos = u64::try_from(value.as_nanos()).unwrap_or(u64::MAX);
+        let interval = u64::try_from(expected_interval.as_nanos()).unwrap_or(u64::MAX);
+        if self.histogram.record_correct(nanos, interval).is_err() {
+            self.histogram.saturating_record(nanos);
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.histogram.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.histogram.len() as usize
+    }
+
+    pub fn max(&self) -> Option<PrettyDuration> {
+        self.stat(|h| h.max() as f64)
+    }
+
+    pub fn min(&self) -> Option<PrettyDuration> {
+        self.stat(|h| h.min() as f64)
+    }
+
+    pub fn average(&self) -> Option<PrettyDuration> {
+        self.stat(Histogram::mean)
+    }
+
+    pub fn median(&self) -> Option<PrettyDuration> {
+        self.stat(|h| h.value_at_quantile(0.5) as f64)
+    }
+
+    /// The population standard deviation. Both it and the mean it is taken
+    /// around are computed in `f64` nanoseconds, so sub-millisecond latencies
+    /// keep their precision up to `SIGNIFICANT_FIGURES`.
+    pub fn standard_deviation(&self) -> Option<PrettyDuration> {
+        self.stat(Histogram::stdev)
+    }
+
+    pub fn percentile_95(&self) -> Option<PrettyDuration> {
+        self.percentile(95.0)
     }
 
-    pub fn min(&self) -> PrettyDuration {
-        (*self.durations.iter().min().unwrap()).into()
+    pub fn percentile_99(&self) -> Option<PrettyDuration> {
+        self.percentile(99.0)
     }
 
-    pub fn average(&self) -> PrettyDuration {
-        let total_duration: Duration = self.durations.iter().cloned().sum();
-        (total_duration / self.total_transactions() as u32).into()
+    /// The nearest-rank percentile, without interpolation: the smallest sample
+    /// that at least `percentile`% of the samples are less than or equal to,
+    /// accurate to `SIGNIFICANT_FIGURES`. Fractional percentiles, like 99.9,
+    /// are supported. The 0th percentile is the minimum, and the 100th, like
+    /// any larger percentile, is the maximum.
+    pub fn percentile(&self, percentile: f64) -> Option<PrettyDuration> {
+        if percentile <= 0.0 {
+            return self.stat(|h| h.value_at_quantile(0.0) as f64);
+        }
+        // `value_at_quantile` takes the ceiling of the quantile times the
+        // number of samples, so the rounding error in e.g. 99.9 / 100 can
+        // push it one rank too far. Compute the rank here, tolerating that
+        // error, and ask for the quantile half a rank below it instead.
+        self.stat(|h| {
+            let samples = h.len() as f64;
+            let count = percentile.min(100.0) / 100.0 * samples;
+            let rank = if (count - count.round()).abs() < samples * 1e-12 {
+                count.round()
+            } else {
+                count.ceil()
+            };
+            h.value_at_quantile((rank.max(1.0) - 0.5) / samples) as f64
+        })
     }
 
-    pub fn median(&self) -> PrettyDuration {
-        let mut sorted = self.durations.clone();
-        sorted.sort();
+    /// Splits the range between the minimum and maximum sample into `buckets`
+    /// buckets whose edges grow geometrically, and counts the samples in each.
+    /// Returns no buckets when there are no samples.
+    fn log_buckets(&self, buckets: usize) -> Vec<Bucket> {
+        if self.is_empty() {
+            return vec![];
+        }
 
-        let mid = sorted.len() / 2;
-        let median = if sorted.len() % 2 == 0 {
-            (sorted[mid - 1] + sorted[mid]) / 2
+        let (min, max) = (self.histogram.min(), self.histogram.max());
+        // `min` and `max` are the edges of the same histogram bucket
+        // when every sample has (nearly) the same value.
+        let buckets = if self.histogram.equivalent(min, max) {
+            1
         } else {
-            sorted[mid]
+            buckets
         };
+        let (min, max) = (min.max(1) as f64, max.max(1) as f64);
+        let ratio = (max / min).powf(1.0 / buckets as f64);
+        let edge = |i: usize| Duration::from_nanos((min * ratio.powi(i as i32)).round() as u64);
 
-        median.into()
-    }
+        let mut counts = vec![0; buckets];
+        for value in self.histogram.iter_recorded() {
+            let nanos = value.value_iterated_to().max(1) as f64;
+            // The cast saturates, and turns the NaN computed
+            // when there is a single bucket into 0.
+            let i = ((nanos / min).ln() / ratio.ln()).floor() as usize;
+            counts[i.min(buckets - 1)] += value.count_at_value();
+        }
 
-    pub fn standard_deviation(&self) -> PrettyDuration {
-        let avg_secs: Duration = self.average().into();
-        let avg_secs = avg_secs.as_secs_f64();
-        let variance: f64 = self
-            .durations
-            .iter()
-            .map(|&duration| {
-                let diff_secs = duration.as_secs_f64() - avg_secs;
-                diff_secs.powi(2)
+        counts
+            .into_iter()
+            .enumerate()
+            .map(|(i, count)| Bucket {
+                low: edge(i),
+                high: edge(i + 1),
+                count,
             })
-            .sum::<f64>()
-            / self.total_transactions() as f64;
+            .collect()
+    }
+
+    /// Whether the average latency of `other` is higher than that of `self`,
+    /// and the difference is statistically significant according to Welch's
+    /// t-test at the 5% level. With at least `MIN_SIGNIFICANCE_SAMPLES`
+    /// samples on each side, the t distribution is close enough to the
+    /// normal distribution to compare against its critical value.
+    fn is_regression(&self, other: &Latencies) -> bool {
+        if self.len() < MIN_SIGNIFICANCE_SAMPLES || other.len() < MIN_SIGNIFICANCE_SAMPLES {
+            return false;
+        }
+
+        let (a, b) = (&self.histogram, &other.histogram);
+        let standard_error =
+            (a.stdev().powi(2) / a.len() as f64 + b.stdev().powi(2) / b.len() as f64).sqrt();
+        if standard_error == 0.0 {
+            return b.mean() > a.mean();
+        }
+        (b.mean() - a.mean()) / standard_error > CRITICAL_VALUE
+    }
 
-        let stddev_secs = variance.sqrt();
-        Duration::from_secs_f64(stddev_secs).into()
+    /// Computes a statistic, in nanoseconds, over the recorded samples.
+    fn stat(&self, f: impl Fn(&Histogram<u64>) -> f64) -> Option<PrettyDuration> {
+        if self.is_empty() {
+            return None;
+        }
+
+        Some(Duration::from_nanos(f(&self.histogram).round() as u64).into())
     }
 
-    pub fn percentile_95(&self) -> PrettyDuration {
-        self.percentile(95)
+    fn report(&self) -> LatencyReport {
+        LatencyReport {
+            min: self.min().map(millis),
+            max: self.max().map(millis),
+            average: self.average().map(millis),
+            median: self.median().map(millis),
+            p95: self.percentile_95().map(millis),
+            p99: self.percentile_99().map(millis),
+            stddev: self.standard_deviation().map(millis),
+        }
     }
+}
+
+/// The change in size of the primary database as a percentage of its size
+/// at the start, or `None` if it started out empty.
+fn growth_pct(growth: Growth) -> Option<f64> {
+    (growth.start > 0)
+        .then(|| (growth.end as f64 - growth.start as f64) / growth.start as f64 * 100.0)
+}
 
-    pub fn percentile_99(&self) -> PrettyDuration {
-        self.percentile(95)
+/// Formats `percentile` as an ordinal number, e.g. 1st, 99th, or 99.9th.
+fn ordinal(percentile: f64) -> String {
+    if percentile.fract() != 0.0 {
+        return format!("{percentile}th");
     }
+    let suffix = match (percentile as u64 % 10, percentile as u64 % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("{percentile}{suffix}")
+}
+
+fn millis(value: PrettyDuration) -> f64 {
+    Duration::from(value).as_secs_f64() * 1000.0
+}
+
+#[derive(Serialize)]
+struct Report {
+    samples: usize,
+    total_duration_secs: f64,
+    total_transactions: usize,
+    tps: f64,
+    target_tps: Option<u32>,
+    /// Set when latencies are corrected for coordinated omission.
+    expected_interval_ms: Option<f64>,
+    errors: usize,
+    error_rate: f64,
+    /// Set when the benchmark was run with `--query-timeout`.
+    timeouts: Option<usize>,
+    timeout_rate: Option<f64>,
+    latency_ms: LatencyReport,
+    /// Set when any read operations reported the rows they read.
+    rows_read: Option<RowsReport>,
+    /// The `--percentiles` of the read latencies, in the order given.
+    percentiles: Vec<PercentileReport>,
+    operations: BTreeMap<String, OperationReport>,
+    writes: WriteReport,
+    /// Set when the backend records connection pool wait times.
+    pool_wait: Option<PoolWaitReport>,
+    /// Set when read clients reconnected with `--reconnect-per-op`.
+    reconnects: Option<ReconnectReport>,
+    replication_lag_ms: Option<LatencyReport>,
+    /// Set when the benchmark was run with `--track-growth`.
+    growth: Option<GrowthReport>,
+    /// Set when any row freshness checks were made.
+    freshness: Option<FreshnessReport>,
+    /// Set when the benchmark was run with `--read-your-writes`.
+    read_your_writes: Option<ReadYourWritesReport>,
+    /// Set when the benchmark was run with `--compare-reader-url`.
+    comparison: Option<ComparisonReport>,
+}
+
+/// The reads made against `--compare-reader-url`. Differences are percentages
+/// relative to the read replica, and are positive when the comparison is higher.
+#[derive(Serialize)]
+struct ComparisonReport {
+    samples: usize,
+    total_transactions: usize,
+    tps: f64,
+    errors: usize,
+    error_rate: f64,
+    timeouts: Option<usize>,
+    timeout_rate: Option<f64>,
+    latency_ms: LatencyReport,
+    operations: BTreeMap<String, OperationComparisonReport>,
+    tps_difference_pct: Option<f64>,
+    latency_difference_pct: LatencyReport,
+    /// Whether the average latency is significantly higher.
+    regression: bool,
+}
+
+#[derive(Serialize)]
+struct OperationComparisonReport {
+    samples: usize,
+    latency_ms: LatencyReport,
+    /// `None` when the read replica never ran the operation.
+    latency_difference_pct: Option<LatencyReport>,
+    regression: bool,
+}
+
+#[derive(Serialize)]
+struct FreshnessReport {
+    checks: usize,
+    stale_reads: usize,
+    stale_read_rate: f64,
+}
+
+#[derive(Serialize)]
+struct ReadYourWritesReport {
+    checks: usize,
+    visible_on_first_read: usize,
+    /// `None` when no checks were made.
+    success_rate: Option<f64>,
+    /// Checks that did not find their write within the visibility timeout.
+    not_visible: usize,
+    /// The time from each commit to the read that found it.
+    visibility_delay_ms: Option<LatencyReport>,
+}
+
+#[derive(Serialize)]
+struct GrowthReport {
+    start_bytes: u64,
+    end_bytes: u64,
+    peak_bytes: u64,
+    /// `None` when the primary database started out empty.
+    growth_pct: Option<f64>,
+}
+
+#[derive(Serialize)]
+struct WriteReport {
+    total_transactions: usize,
+    tps: f64,
+    target_tps: u32,
+    /// Set when the benchmark was run with `--conflict-retries`.
+    conflict_retries: Option<usize>,
+    latency_ms: LatencyReport,
+}
+
+#[derive(Serialize)]
+struct PoolWaitReport {
+    acquisitions: usize,
+    latency_ms: LatencyReport,
+}
+
+#[derive(Serialize)]
+struct ReconnectReport {
+    reconnects: usize,
+    latency_ms: LatencyReport,
+}
+
+#[derive(Serialize)]
+struct OperationReport {
+    samples: usize,
+    latency_ms: LatencyReport,
+    /// `None` for `row_freshness`, which does not report rows.
+    rows_read: Option<RowsReport>,
+}
+
+#[derive(Serialize)]
+struct RowsReport {
+    total: u64,
+    average_per_operation: f64,
+}
+
+#[derive(Serialize)]
+struct PercentileReport {
+    percentile: f64,
+    latency_ms: Option<f64>,
+}
 
-    pub fn percentile(&self, percentile: usize) -> PrettyDuration {
-        let mut sorted = self.durations.clone();
-        sorted.sort();
+#[derive(Serialize)]
+struct LatencyReport {
+    min: Option<f64>,
+    max: Option<f64>,
+    average: Option<f64>,
+    median: Option<f64>,
+    p95: Option<f64>,
+    p99: Option<f64>,
+    stddev: Option<f64>,
+}
 
-        let idx = ((percentile as f64 / 100.0) * sorted.len() as f64).ceil() as usize - 1;
-        sorted[idx.min(sorted.len() - 1)].into()
+impl LatencyReport {
+    /// The percent difference of each statistic from `self` to `other`.
+    fn percent_difference(&self, other: &LatencyReport) -> LatencyReport {
+        LatencyReport {
+            min: percent_difference(self.min, other.min),
+            max: percent_difference(self.max, other.max),
+            average: percent_difference(self.average, other.average),
+            median: percent_difference(self.median, other.median),
+            p95: percent_difference(self.p95, other.p95),
+            p99: percent_difference(self.p99, other.p99),
+            stddev: percent_difference(self.stddev, other.stddev),
+        }
     }
 }
 
 impl fmt::Display for Measurements {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Errors: {} ({:.2}%)",
+            self.errors,
+            self.error_rate() * 100.0
+        )?;
+        if let (Some(timeouts), Some(rate)) = (self.timeouts, self.timeout_rate()) {
+            writeln!(f, "Timeouts: {} ({:.2}%)", timeouts, rate * 100.0)?;
+        }
+
+        writeln!(f)?;
+        writeln!(f, "Reads (read replica)")?;
         writeln!(f, "Total Transactions: {}", self.total_transactions())?;
+        if self.is_empty() {
+            writeln!(f, "No samples collected")?;
+        } else {
+            self.fmt_reads(f)?;
+        }
+
+        writeln!(f)?;
+        writeln!(f, "Writes (primary database)")?;
+        writeln!(f, "Total Transactions: {}", self.writes.len())?;
+        writeln!(
+            f,
+            "Target Transactions per Second: {}",
+            self.write_target_tps
+        )?;
+        if let Some(conflict_retries) = self.conflict_retries {
+            writeln!(f, "Conflict Retries: {conflict_retries}")?;
+        }
+        if self.writes.is_empty() {
+            writeln!(f, "No samples collected")?;
+        } else {
+            let writes = &self.writes;
+            writeln!(f, "Transactions per Second (TPS): {:.2}", self.write_tps())?;
+            writeln!(f, "Max Latency: {}", writes.max().unwrap())?;
+            writeln!(f, "Average Latency: {}", writes.average().unwrap())?;
+            writeln!(f, "Median Latency: {}", writes.median().unwrap())?;
+            writeln!(
+                f,
+                "95th Percentile Latency: {}",
+                writes.percentile_95().unwrap()
+            )?;
+            writeln!(
+                f,
+                "99th Percentile Latency: {}",
+                writes.percentile_99().unwrap()
+            )?;
+        }
+
+        if !self.pool_wait.is_empty() {
+            let wait = &self.pool_wait;
+            writeln!(f)?;
+            writeln!(f, "Pool Wait (primary database connections)")?;
+            writeln!(f, "Acquisitions: {}", wait.len())?;
+            writeln!(f, "Max Wait: {}", wait.max().unwrap())?;
+            writeln!(f, "Average Wait: {}", wait.average().unwrap())?;
+            writeln!(f, "95th Percentile Wait: {}", wait.percentile_95().unwrap())?;
+            writeln!(f, "99th Percentile Wait: {}", wait.percentile_99().unwrap())?;
+        }
+
+        if !self.reconnects.is_empty() {
+            let reconnects = &self.reconnects;
+            writeln!(f)?;
+            writeln!(f, "Reconnects (read replica)")?;
+            writeln!(f, "Reconnects: {}", reconnects.len())?;
+            writeln!(f, "Max Latency: {}", reconnects.max().unwrap())?;
+            writeln!(f, "Average Latency: {}", reconnects.average().unwrap())?;
+            writeln!(f, "Median Latency: {}", reconnects.median().unwrap())?;
+            writeln!(
+                f,
+                "99th Percentile Latency: {}",
+                reconnects.percentile_99().unwrap()
+            )?;
+        }
+
+        if !self.replication_lag.is_empty() {
+            let lag = &self.replication_lag;
+            writeln!(f)?;
+            writeln!(f, "Replication Lag Samples: {}", lag.len())?;
+            writeln!(f, "Max Replication Lag: {}", lag.max().unwrap())?;
+            writeln!(f, "Average Replication Lag: {}", lag.average().unwrap())?;
+            writeln!(
+                f,
+                "95th Percentile Replication Lag: {}",
+                lag.percentile_95().unwrap()
+            )?;
+        }
+
+        if let Some(growth) = self.growth {
+            writeln!(f)?;
+            writeln!(f, "Database Growth (primary database)")?;
+            writeln!(f, "Start Size: {}", HumanBytes(growth.start))?;
+            writeln!(f, "End Size: {}", HumanBytes(growth.end))?;
+            writeln!(f, "Peak Size: {}", HumanBytes(growth.peak))?;
+            let (sign, change) = if growth.end >= growth.start {
+                ('+', growth.end - growth.start)
+            } else {
+                ('-', growth.start - growth.end)
+            };
+            match growth_pct(growth) {
+                Some(pct) => writeln!(f, "Growth: {sign}{} ({pct:+.2}%)", HumanBytes(change))?,
+                None => writeln!(f, "Growth: {sign}{}", HumanBytes(change))?,
+            }
+        }
+
+        if let Some(rate) = self.stale_read_rate() {
+            writeln!(f)?;
+            writeln!(f, "Row Freshness (read replica)")?;
+            writeln!(f, "Freshness Checks: {}", self.freshness_checks)?;
+            writeln!(
+                f,
+                "Stale Reads: {} ({:.2}%)",
+                self.stale_reads,
+                rate * 100.0
+            )?;
+        }
+
+        if let Some(checks) = &self.read_your_writes {
+            writeln!(f)?;
+            writeln!(f, "Read Your Writes (read replica)")?;
+            writeln!(f, "Checks: {}", checks.checks)?;
+            if let Some(rate) = checks.success_rate() {
+                writeln!(
+                    f,
+                    "Visible on First Read: {} ({:.2}%)",
+                    checks.visible,
+                    rate * 100.0
+                )?;
+                writeln!(
+                    f,
+                    "Not Visible Within {}: {}",
+                    humantime::format_duration(VISIBILITY_TIMEOUT),
+                    checks.not_visible
+                )?;
+            }
+            if !checks.delays.is_empty() {
+                let delays = &checks.delays;
+                writeln!(f, "Max Delay Until Visible: {}", delays.max().unwrap())?;
+                writeln!(
+                    f,
+                    "Average Delay Until Visible: {}",
+                    delays.average().unwrap()
+                )?;
+                writeln!(
+                    f,
+                    "95th Percentile Delay Until Visible: {}",
+                    delays.percentile_95().unwrap()
+                )?;
+            }
+        }
+
+        if let Some(comparison) = &self.comparison {
+            writeln!(f)?;
+            self.fmt_comparison(f, comparison)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Measurements {
+    /// Formats the reads made against `--compare-reader-url` next to those
+    /// made against the read replica, with the percent difference of each.
+    fn fmt_comparison(&self, f: &mut fmt::Formatter<'_>, comparison: &Measurements) -> fmt::Result {
+        writeln!(f, "Comparison (--compare-reader-url)")?;
+        writeln!(
+            f,
+            "Errors: {} ({:.2}%)",
+            comparison.errors,
+            comparison.error_rate() * 100.0
+        )?;
+        if let (Some(timeouts), Some(rate)) = (comparison.timeouts, comparison.timeout_rate()) {
+            writeln!(f, "Timeouts: {} ({:.2}%)", timeouts, rate * 100.0)?;
+        }
+        writeln!(f, "Total Transactions: {}", comparison.total_transactions())?;
+        if self.is_empty() || comparison.is_empty() {
+            writeln!(f, "No samples collected")?;
+            return Ok(());
+        }
+
+        writeln!(f)?;
+        writeln!(
+            f,
+            "{:<36} {:>16} {:>16} {:>12}",
+            "Metric", "Read Replica", "Comparison", "Difference"
+        )?;
+        fmt_comparison_row(
+            f,
+            "Transactions per Second (TPS)",
+            format!("{:.2}", self.tps()),
+            format!("{:.2}", comparison.tps()),
+            percent_difference(Some(self.tps()), Some(comparison.tps())),
+            false,
+        )?;
+        let stats: [(&str, Stat); 5] = [
+            ("Average Latency", Latencies::average),
+            ("Median Latency", Latencies::median),
+            ("95th Percentile Latency", Latencies::percentile_95),
+            ("99th Percentile Latency", Latencies::percentile_99),
+            ("Max Latency", Latencies::max),
+        ];
+        for (label, stat) in stats {
+            let regression =
+                label == "Average Latency" && self.latencies.is_regression(&comparison.latencies);
+            fmt_latency_row(
+                f,
+                label,
+                stat(&self.latencies),
+                stat(&comparison.latencies),
+                regression,
+            )?;
+        }
+
+        writeln!(f)?;
+        writeln!(
+            f,
+            "{:<36} {:>16} {:>16} {:>12}",
+            "Average Latency by Operation", "Read Replica", "Comparison", "Difference"
+        )?;
+        let operations: BTreeSet<_> = self
+            .operations
+            .keys()
+            .chain(comparison.operations.keys())
+            .collect();
+        for operation in operations {
+            let base = self.operations.get(operation);
+            let other = comparison.operations.get(operation);
+            let regression = base
+                .zip(other)
+                .is_some_and(|(base, other)| base.is_regression(other));
+            fmt_latency_row(
+                f,
+                operation.as_ref(),
+                base.and_then(Latencies::average),
+                other.and_then(Latencies::average),
+                regression,
+            )?;
+        }
+
+        writeln!(f)?;
+        writeln!(
+            f,
+            "A regression is a significantly higher average latency (Welch's t-test, p < 0.05)"
+        )
+    }
+
+    /// Formats the read statistics, which requires at least one sample.
+    fn fmt_reads(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let latencies = &self.latencies;
         writeln!(f, "Transactions per Second (TPS): {:.2}", self.tps())?;
-        writeln!(f, "Max Latency: {}", self.max())?;
-        writeln!(f, "Min Latency: {}", self.min())?;
-        writeln!(f, "Average Latency: {}", self.average())?;
-        writeln!(f, "Median Latency: {}", self.median())?;
-        writeln!(f, "95th Percentile Latency: {}", self.percentile_95())?;
-        writeln!(f, "99th Percentile Latency: {}", self.percentile_99())?;
-        writeln!(f, "Standard Deviation: {}", self.standard_deviation())
+        if let Some(target_tps) = self.target_tps {
+            writeln!(f, "Target Transactions per Second: {}", target_tps)?;
+        }
+        if let Some(interval) = self.expected_interval {
+            writeln!(
+                f,
+                "Latencies corrected for coordinated omission (expected interval: {})",
+                PrettyDuration::from(interval)
+            )?;
+        }
+        writeln!(f, "Max Latency: {}", latencies.max().unwrap())?;
+        writeln!(f, "Min Latency: {}", latencies.min().unwrap())?;
+        writeln!(f, "Average Latency: {}", latencies.average().unwrap())?;
+        writeln!(f, "Median Latency: {}", latencies.median().unwrap())?;
+        for &percentile in &self.percentiles {
+            writeln!(
+                f,
+                "{} Percentile Latency: {}",
+                ordinal(percentile),
+                latencies.percentile(percentile).unwrap()
+            )?;
+        }
+        writeln!(
+            f,
+            "Standard Deviation: {}",
+            latencies.standard_deviation().unwrap()
+        )?;
+        if let Some(average) = self.rows.average() {
+            writeln!(f, "Rows Read: {}", self.rows.total)?;
+            writeln!(f, "Average Rows per Operation: {average:.2}")?;
+        }
+
+        writeln!(f)?;
+        writeln!(
+            f,
+            "{:<36} {:>8} {:>16} {:>16} {:>16} {:>10}",
+            "Operation", "Count", "Min", "Average", "95th Percentile", "Avg Rows"
+        )?;
+        for (operation, latencies) in &self.operations {
+            let rows = self
+                .operation_rows
+                .get(operation)
+                .and_then(Rows::average)
+                .map_or_else(|| "n/a".to_string(), |average| format!("{average:.2}"));
+            writeln!(
+                f,
+                "{:<36} {:>8} {:>16} {:>16} {:>16} {:>10}",
+                operation.as_ref(),
+                latencies.len(),
+                latencies.min().unwrap().to_string(),
+                latencies.average().unwrap().to_string(),
+                latencies.percentile_95().unwrap().to_string(),
+                rows,
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn latencies(millis: &[u64]) -> Latencies {
+        let mut latencies = Latencies::default();
+        for &ms in millis {
+            latencies.push(Duration::from_millis(ms));
+        }
+        latencies
+    }
+
+    /// Asserts that `actual` is within the precision of the histogram
+    /// of `expected_ms` milliseconds.
+    fn assert_close(actual: Option<PrettyDuration>, expected_ms: f64) {
+        let actual = millis(actual.expect("no samples"));
+        let tolerance = expected_ms * 10f64.powi(-i32::from(SIGNIFICANT_FIGURES));
+        assert!(
+            (actual - expected_ms).abs() <= tolerance,
+            "expected {expected_ms} ms, got {actual} ms"
+        );
+    }
+
+    #[test]
+    fn percentile_99_differs_from_percentile_95_with_outliers() {
+        let mut millis = vec![1; 96];
+        millis.extend([100; 4]);
+        let latencies = latencies(&millis);
+        assert_close(latencies.percentile_95(), 1.0);
+        assert_close(latencies.percentile_99(), 100.0);
+    }
+
+    #[test]
+    fn percentile_rounds_to_nearest_rank_for_small_samples() {
+        let one = latencies(&[7]);
+        assert_close(one.percentile(50.0), 7.0);
+        assert_close(one.percentile_99(), 7.0);
+
+        let two = latencies(&[1, 2]);
+        assert_close(two.percentile(50.0), 1.0);
+        assert_close(two.percentile(51.0), 2.0);
+        assert_close(two.percentile_95(), 2.0);
+
+        let ten = latencies(&[1, 2, 3, 4, 5, 6, 7, 8, 9, 10]);
+        assert_close(ten.percentile(50.0), 5.0);
+        assert_close(ten.percentile(90.0), 9.0);
+        assert_close(ten.percentile_95(), 10.0);
+        assert_close(ten.percentile_99(), 10.0);
+    }
+
+    #[test]
+    fn empty_measurements_format_without_panicking() {
+        let measurements = Measurements::new(Duration::from_secs(1));
+        let text = measurements.to_string();
+        assert!(text.contains("No samples collected"), "{text}");
+        serde_json::from_str::<serde_json::Value>(&measurements.to_json()).unwrap();
+    }
+
+    #[test]
+    fn conflict_retries_are_reported_once_set() {
+        let mut measurements = Measurements::new(Duration::from_secs(1));
+        assert!(!measurements.to_string().contains("Conflict Retries"));
+        let json: serde_json::Value = serde_json::from_str(&measurements.to_json()).unwrap();
+        assert!(json["writes"]["conflict_retries"].is_null());
+
+        measurements.set_conflict_retries(3);
+        assert!(measurements.to_string().contains("Conflict Retries: 3"));
+        let json: serde_json::Value = serde_json::from_str(&measurements.to_json()).unwrap();
+        assert_eq!(json["writes"]["conflict_retries"], 3);
+    }
+
+    #[test]
+    fn rows_read_are_reported_per_operation() {
+        let mut measurements = Measurements::new(Duration::from_secs(1));
+        for (operation, rows) in [
+            (ReadOperation::TopPerformers, 10),
+            (ReadOperation::TopPerformers, 20),
+            (ReadOperation::MarketOverview, 3),
+        ] {
+            measurements.push(operation, Duration::from_millis(1));
+            measurements.push_rows(operation, rows);
+        }
+        measurements.push(ReadOperation::RowFreshness, Duration::from_millis(1));
+
+        let report = measurements.to_string();
+        assert!(report.contains("Rows Read: 33\n"), "{report}");
+        assert!(
+            report.contains("Average Rows per Operation: 11.00\n"),
+            "{report}"
+        );
+        let json: serde_json::Value = serde_json::from_str(&measurements.to_json()).unwrap();
+        assert_eq!(json["rows_read"]["total"], 33);
+        assert_eq!(
+            json["operations"]["top_performers"]["rows_read"]["total"],
+            30
+        );
+        assert_eq!(
+            json["operations"]["top_performers"]["rows_read"]["average_per_operation"],
+            15.0
+        );
+        assert!(json["operations"]["row_freshness"]["rows_read"].is_null());
+    }
+
+    #[test]
+    fn percentiles_stay_within_precision_over_a_million_samples() {
+        let mut latencies = Latencies::default();
+        for micros in 1..=1_000_000 {
+            latencies.push(Duration::from_micros(micros));
+        }
+        assert_eq!(latencies.len(), 1_000_000);
+        assert_close(latencies.min(), 0.001);
+        assert_close(latencies.median(), 500.0);
+        assert_close(latencies.percentile_95(), 950.0);
+        assert_close(latencies.percentile_99(), 990.0);
+        assert_close(latencies.max(), 1000.0);
+        assert_close(latencies.average(), 500.0005);
+    }
+
+    #[test]
+    fn fractional_percentiles_over_a_million_samples() {
+        let mut latencies = Latencies::default();
+        for micros in 1..=1_000_000 {
+            latencies.push(Duration::from_micros(micros));
+        }
+        assert_close(latencies.percentile(90.0), 900.0);
+        assert_close(latencies.percentile(99.9), 999.0);
+        assert_close(latencies.percentile(99.99), 999.9);
+
+        // The slowest 0.1% of samples are outliers, so p99.9 is the
+        // last of the fast samples and anything above it an outlier.
+        let mut latencies = Latencies::default();
+        for _ in 0..999_000 {
+            latencies.push(Duration::from_millis(1));
+        }
+        for _ in 0..1_000 {
+            latencies.push(Duration::from_millis(100));
+        }
+        assert_close(latencies.percentile_99(), 1.0);
+        assert_close(latencies.percentile(99.9), 1.0);
+        assert_close(latencies.percentile(99.95), 100.0);
+    }
+
+    #[test]
+    fn percentiles_are_reported_in_the_order_given() {
+        let mut measurements = Measurements::new(Duration::from_secs(1));
+        for ms in 1..=1000 {
+            measurements.push(ReadOperation::TopPerformers, Duration::from_millis(ms));
+        }
+        let text = measurements.to_string();
+        for line in [
+            "90th Percentile Latency",
+            "95th Percentile Latency",
+            "99th Percentile Latency",
+            "99.9th Percentile Latency",
+        ] {
+            assert!(text.contains(line), "missing {line}");
+        }
+
+        measurements.set_percentiles(vec![99.9, 1.0, 50.0, 22.0]);
+        let text = measurements.to_string();
+        let labels: Vec<&str> = text
+            .lines()
+            .filter_map(|line| line.split_once(" Percentile Latency:"))
+            .map(|(label, _)| label)
+            .collect();
+        assert_eq!(labels, ["99.9th", "1st", "50th", "22nd"]);
+        assert!(!text.contains("95th Percentile Latency"));
+
+        let report: serde_json::Value = serde_json::from_str(&measurements.to_json()).unwrap();
+        assert_eq!(report["percentiles"][0]["percentile"], 99.9);
+        assert!((report["percentiles"][0]["latency_ms"].as_f64().unwrap() - 999.0).abs() < 1.0);
+        assert_eq!(report["percentiles"][3]["percentile"], 22.0);
+    }
+
+    #[test]
+    fn oneline_is_a_single_line_of_pairs() {
+        let mut measurements = Measurements::new(Duration::from_secs(1));
+        assert_eq!(
+            measurements.oneline(),
+            "transactions=0 tps=0.00 p50=n/a p90=n/a p95=n/a p99=n/a p99.9=n/a errors=0"
+        );
+
+        for ms in 1..=100 {
+            measurements.push(ReadOperation::TopPerformers, Duration::from_millis(ms));
+        }
+        measurements.set_percentiles(vec![50.0, 99.0]);
+        measurements.set_errors(2);
+        measurements.set_timeouts(1);
+        let line = measurements.oneline();
+        let pairs: Vec<(&str, &str)> = line
+            .split(' ')
+            .map(|pair| pair.split_once('=').unwrap())
+            .collect();
+        let keys: Vec<&str> = pairs.iter().map(|(key, _)| *key).collect();
+        assert_eq!(
+            keys,
+            ["transactions", "tps", "p50", "p99", "errors", "timeouts"]
+        );
+        assert_eq!(pairs[0].1, "100");
+        assert!(pairs[2].1.starts_with("50.0") && pairs[2].1.ends_with("ms"));
+        assert!(pairs[3].1.starts_with("99.0") && pairs[3].1.ends_with("ms"));
+        assert_eq!(&pairs[4..], [("errors", "2"), ("timeouts", "1")]);
+    }
+
+    #[test]
+    fn standard_deviation_keeps_sub_millisecond_precision() {
+        let mut latencies = Latencies::default();
+        for micros in [100, 200, 300, 400] {
+            latencies.push(Duration::from_micros(micros));
+        }
+        // The mean is 250us, and the squared deviations sum to
+        // 2 * 150^2 + 2 * 50^2 = 50000us^2 over 4 samples.
+        assert_close(latencies.average(), 0.25);
+        assert_close(latencies.standard_deviation(), 12500f64.sqrt() / 1000.0);
+    }
+
+    #[test]
+    fn percentile_bounds() {
+        let one = latencies(&[5]);
+        assert_close(one.percentile(0.0), 5.0);
+        assert_close(one.percentile(50.0), 5.0);
+        assert_close(one.percentile(100.0), 5.0);
+
+        let four = latencies(&[4, 1, 3, 2]);
+        assert_close(four.percentile(0.0), 1.0);
+        assert_close(four.percentile(50.0), 2.0);
+        assert_close(four.percentile(100.0), 4.0);
+    }
+
+    #[test]
+    fn growth_is_reported_with_its_sign() {
+        let mut measurements = Measurements::new(Duration::from_secs(1));
+        measurements.set_growth(Growth {
+            start: 2048,
+            end: 1024,
+            peak: 2048,
+        });
+        assert!(measurements
+            .to_string()
+            .contains("Growth: -1.00 KiB (-50.00%)"));
+
+        measurements.set_growth(Growth {
+            start: 0,
+            end: 1024,
+            peak: 1024,
+        });
+        assert!(measurements.to_string().contains("Growth: +1.00 KiB\n"));
+        let report: serde_json::Value = serde_json::from_str(&measurements.to_json()).unwrap();
+        assert!(report["growth"]["growth_pct"].is_null());
     }
 }
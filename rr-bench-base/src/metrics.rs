@@ -0,0 +1,23 @@
+use crate::measurements::Latencies;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// `Metrics` collects measurements that only a backend can take, such as
+/// the time spent waiting for a pooled connection, so they can be reported
+/// alongside the benchmark results. Clones share the same measurements.
+#[derive(Clone, Default)]
+pub struct Metrics {
+    pool_wait: Arc<Mutex<Latencies>>,
+}
+
+impl Metrics {
+    /// Records the time spent acquiring a connection from a connection pool.
+    pub fn record_pool_wait(&self, wait: Duration) {
+        self.pool_wait.lock().unwrap().push(wait);
+    }
+
+    /// Returns the pool wait times recorded so far, leaving none behind.
+    pub(crate) fn take_pool_wait(&self) -> Latencies {
+        std::mem::take(&mut *self.pool_wait.lock().unwrap())
+    }
+}
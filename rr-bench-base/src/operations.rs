@@ -1,5 +1,6 @@
-use strum_macros::EnumIter;
+use strum_macros::{AsRefStr, EnumIter, EnumString, IntoStaticStr};
 
+#[derive(Clone)]
 pub enum WriteOperation {
     InsertCustomer {
         name: String,
@@ -29,7 +30,7 @@ pub enum WriteOperation {
         security_id: i32,
         order_type: String,
         quantity: i32,
-        limit_price: f64,
+        limit_price: Option<f64>,
         status: String,
         parent_order_id: Option<i32>,
     },
@@ -53,7 +54,7 @@ pub enum WriteOperation {
     UpdateOrder {
         order_id: i32,
         status: String,
-        limit_price: f64,
+        limit_price: Option<f64>,
     },
     UpdateMarketData {
         market_data_id: i32,
@@ -81,7 +82,104 @@ pub enum WriteOperation {
     },
 }
 
-#[derive(EnumIter, Debug, PartialEq)]
+/// The kinds of ids that `PrimaryDatabase::get_random_ids` samples, one for
+/// each table with an integer primary key.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IdKind {
+    /// `customers.customer_id`
+    Customer,
+    /// `accounts.account_id`
+    Account,
+    /// `securities.security_id`
+    Security,
+    /// `trades.trade_id`
+    Trade,
+    /// `orders.order_id`
+    Order,
+    /// `market_data.market_data_id`
+    MarketData,
+}
+
+impl IdKind {
+    /// The table the ids are the primary key of.
+    pub fn table(self) -> &'static str {
+        match self {
+            IdKind::Customer => "customers",
+            IdKind::Account => "accounts",
+            IdKind::Security => "securities",
+            IdKind::Trade => "trades",
+            IdKind::Order => "orders",
+            IdKind::MarketData => "market_data",
+        }
+    }
+
+    /// The primary key column of `table`.
+    pub fn column(self) -> &'static str {
+        match self {
+            IdKind::Customer => "customer_id",
+            IdKind::Account => "account_id",
+            IdKind::Security => "security_id",
+            IdKind::Trade => "trade_id",
+            IdKind::Order => "order_id",
+            IdKind::MarketData => "market_data_id",
+        }
+    }
+}
+
+/// The tables written by the primary database simulator.
+#[derive(
+    EnumIter,
+    EnumString,
+    AsRefStr,
+    IntoStaticStr,
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+)]
+#[strum(serialize_all = "snake_case")]
+pub enum Table {
+    Customers,
+    Accounts,
+    Securities,
+    Trades,
+    Orders,
+    MarketData,
+}
+
+impl Table {
+    /// Whether the workload has an update operation for the table.
+    /// Securities are only ever inserted and deleted.
+    pub fn is_updatable(self) -> bool {
+        self != Table::Securities
+    }
+
+    /// Whether no other table references the table, so deleting
+    /// from it does not rely on ON DELETE CASCADE.
+    pub fn is_leaf(self) -> bool {
+        matches!(self, Table::Trades | Table::Orders | Table::MarketData)
+    }
+}
+
+#[derive(
+    EnumIter,
+    EnumString,
+    AsRefStr,
+    IntoStaticStr,
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+)]
+#[strum(serialize_all = "snake_case")]
 pub enum ReadOperation {
     CustomerPortfolio,
     TopPerformers,
@@ -98,4 +196,8 @@ pub enum ReadOperation {
     RecentTradesByAccount,
     OrderFulfillmentRates,
     SectorOrderActivity,
+    CascadingOrderCancellationAlert,
+    /// Checks whether the market data price most recently inserted into the
+    /// primary database is visible on the read replica.
+    RowFreshness,
 }
@@ -1,8 +1,41 @@
+use crate::config::LatencyUnit;
 use std::fmt;
+use std::sync::OnceLock;
 use std::time::Duration;
 
+/// The unit every `PrettyDuration` is printed in, set once from `--latency-unit`.
+static UNIT: OnceLock<LatencyUnit> = OnceLock::new();
+
+/// Prints every subsequent `PrettyDuration` in `unit`. Defaults to milliseconds.
+pub fn set_unit(unit: LatencyUnit) {
+    let _ = UNIT.set(unit);
+}
+
 pub struct PrettyDuration(Duration);
 
+impl PrettyDuration {
+    /// Formats the duration like `Display`, but without a space between
+    /// the number and the unit, e.g. `1.234567ms`.
+    pub fn compact(&self) -> String {
+        self.format(unit(), "")
+    }
+
+    /// Formats the duration in `unit`, with as many decimals as
+    /// it takes to show whole nanoseconds.
+    fn format(&self, unit: LatencyUnit, separator: &str) -> String {
+        let secs = self.0.as_secs_f64();
+        match unit {
+            LatencyUnit::Us => format!("{:.3}{separator}us", secs * 1_000_000.0),
+            LatencyUnit::Ms => format!("{:.6}{separator}ms", secs * 1000.0),
+            LatencyUnit::S => format!("{:.9}{separator}s", secs),
+        }
+    }
+}
+
+fn unit() -> LatencyUnit {
+    UNIT.get().copied().unwrap_or(LatencyUnit::Ms)
+}
+
 impl From<Duration> for PrettyDuration {
     fn from(value: Duration) -> Self {
         Self(value)
@@ -17,7 +50,19 @@ impl From<PrettyDuration> for Duration {
 
 impl fmt::Display for PrettyDuration {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let millis = self.0.as_secs_f64() * 1000.0;
-        write!(f, "{:.6} ms", millis)
+        f.pad(&self.format(unit(), " "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_unit_shows_whole_nanoseconds() {
+        let duration = PrettyDuration::from(Duration::from_nanos(1_234_567));
+        assert_eq!(duration.format(LatencyUnit::Us, " "), "1234.567 us");
+        assert_eq!(duration.format(LatencyUnit::Ms, " "), "1.234567 ms");
+        assert_eq!(duration.format(LatencyUnit::S, ""), "0.001234567s");
     }
 }
@@ -1,54 +1,342 @@
+use crate::config::WritePattern;
+use crate::freshness::{FreshnessChannel, PriceWrite};
+use crate::measurements::Latencies;
+use crate::operations::Table;
+use crate::retry::{with_conflict_retries, with_retries, ErrorCounter};
 use crate::task_handle::TaskCompletion;
 use crate::{PrimaryDatabase, WriteOperation};
 use anyhow::{Context, Result};
+use indicatif::HumanBytes;
+use log::{info, warn};
 use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
 use rand::{Rng, SeedableRng};
 use rr_bench_core::DataGenerator;
+use std::str::FromStr;
 use std::thread::sleep;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+use strum::IntoEnumIterator;
 
-const INSERT_PERCENTAGE: u32 = 45;
-const UPDATE_PERCENTAGE: u32 = 45;
+/// The length of each busy and each idle window of `WritePattern::Burst`.
+const BURST_WINDOW: Duration = Duration::from_secs(1);
+
+/// How often the size of the primary database is checked against
+/// `max_db_size`.
+const SIZE_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// The percentage of primary database operations that are inserts,
+/// updates, and deletes. The three values always sum to 100.
+#[derive(Clone, Copy, Debug)]
+pub struct WriteMix {
+    pub insert: u32,
+    pub update: u32,
+    pub delete: u32,
+}
+
+impl WriteMix {
+    pub const INSERTS: Self = Self {
+        insert: 100,
+        update: 0,
+        delete: 0,
+    };
+    pub const UPDATES: Self = Self {
+        insert: 0,
+        update: 100,
+        delete: 0,
+    };
+    pub const DELETES: Self = Self {
+        insert: 0,
+        update: 0,
+        delete: 100,
+    };
+
+    /// The same mix with deletes in place of inserts, which keeps the
+    /// database from growing.
+    fn without_inserts(self) -> Self {
+        Self {
+            insert: 0,
+            update: self.update,
+            delete: self.delete + self.insert,
+        }
+    }
+}
+
+/// A period of the benchmark in which the primary database simulator
+/// executes only one kind of operation, e.g. `insert:2m`.
+#[derive(Clone, Copy, Debug)]
+pub struct Phase {
+    pub write_mix: WriteMix,
+    pub duration: Duration,
+}
+
+/// Settings for a `PrimarySimulator`.
+#[derive(Clone, Debug)]
+pub struct PrimaryConfig {
+    /// The number of write operations to execute per second.
+    pub tps: u32,
+    /// How the transactions are spaced out. Every pattern averages `tps`.
+    pub write_pattern: WritePattern,
+    /// The proportion of inserts, updates, and deletes.
+    pub write_mix: WriteMix,
+    /// Phases that replace `write_mix`, one after another, from the start
+    /// of the benchmark. Once they are over, `write_mix` applies again.
+    pub phases: Vec<Phase>,
+    /// The seed for the simulator's random number and data generators.
+    pub seed: u64,
+    /// The number of times a failed write operation is retried.
+    pub max_retries: u32,
+    /// The number of times a transaction that failed with a serialization
+    /// failure or a deadlock is executed again, with the same operations,
+    /// before it counts as a failed attempt for `max_retries`.
+    pub conflict_retries: u32,
+    /// Whether a transaction that fails after `max_retries` retries stops
+    /// the simulator with its error, rather than only being counted.
+    pub fail_on_error: bool,
+    /// The number of write operations executed in each transaction.
+    pub batch_size: u32,
+    /// The percentage of inserted accounts, trades, and orders
+    /// that reference an existing row of the same table as their parent.
+    pub hierarchy_pct: u32,
+    /// The percentage of deletes that target a leaf table, one that no other
+    /// table references, instead of a table picked at random.
+    pub delete_leaf_bias: u32,
+    /// The size of the primary database, in bytes, at which inserts are
+    /// replaced by deletes until it is smaller again.
+    pub max_db_size: Option<u64>,
+    /// The tables that are inserted into, updated, and deleted from. Must
+    /// include an updatable table when updates are enabled, and a leaf
+    /// table when `delete_leaf_bias` is set.
+    pub write_tables: Vec<Table>,
+}
 
 pub struct PrimarySimulator<DB: PrimaryDatabase> {
     db: DB,
-    tps: u32,
+    config: PrimaryConfig,
     rng: StdRng,
     gen: DataGenerator,
+    /// The subsets of `config.write_tables` that support updates
+    /// and that are leaves.
+    update_tables: Vec<Table>,
+    leaf_tables: Vec<Table>,
+    errors: ErrorCounter,
+    /// When the size of the primary database was last checked against
+    /// `config.max_db_size`, and whether it was at least that large.
+    size_checked: Option<Instant>,
+    full: bool,
+    /// Receives the market data prices inserted by each committed
+    /// transaction, for the read clients to check.
+    freshness: FreshnessChannel,
     completion_tracker: TaskCompletion,
 }
 
 impl<DB: PrimaryDatabase> PrimarySimulator<DB> {
-    pub fn new(db: DB, tps: u32, seed: u64, completion_tracker: TaskCompletion) -> Self {
-        let rng = StdRng::seed_from_u64(seed);
-        let gen = DataGenerator::new(seed);
+    pub fn new(
+        db: DB,
+        config: PrimaryConfig,
+        errors: ErrorCounter,
+        freshness: FreshnessChannel,
+        completion_tracker: TaskCompletion,
+    ) -> Self {
+        let rng = StdRng::seed_from_u64(config.seed);
+        let gen = DataGenerator::new(config.seed);
+        let update_tables = config
+            .write_tables
+            .iter()
+            .copied()
+            .filter(|table| table.is_updatable())
+            .collect();
+        let leaf_tables = config
+            .write_tables
+            .iter()
+            .copied()
+            .filter(|table| table.is_leaf())
+            .collect();
         PrimarySimulator {
             db,
-            tps,
+            config,
             gen,
+            update_tables,
+            leaf_tables,
             rng,
+            errors,
+            size_checked: None,
+            full: false,
+            freshness,
             completion_tracker,
         }
     }
 
-    pub fn run(&mut self) -> Result<()> {
-        let interval = Duration::from_secs(1) / self.tps;
+    /// Executes write transactions until the benchmark completes, returning
+    /// the latency of every transaction that succeeded. With
+    /// `fail_on_error`, returns the error of the first transaction that
+    /// fails instead, which is counted all the same.
+    pub fn run(&mut self) -> Result<Latencies> {
+        let interval = Duration::from_secs(1) / self.config.tps;
+        let mut latencies = Latencies::default();
+        let start = Instant::now();
         while !self.completion_tracker.is_done() {
-            let op = self.generate_operations()?;
-            if let Err(e) = self.db.execute_command(op) {
-                return Err(e).context("failed to execute command");
+            self.check_size()?;
+            let mut write_mix = self.write_mix(start.elapsed());
+            if self.full {
+                write_mix = write_mix.without_inserts();
+            }
+            match with_retries(self.config.max_retries, || {
+                self.execute_transaction(write_mix)
+            }) {
+                Ok(latency) => latencies.push(latency),
+                Err(e) => {
+                    self.errors.record(&e);
+                    if self.config.fail_on_error {
+                        return Err(e);
+                    }
+                }
             }
-            sleep(interval);
+            let pause = self.pause(interval, start.elapsed());
+            sleep(pause);
+        }
+
+        Ok(latencies)
+    }
+
+    /// Checks whether the primary database has reached `config.max_db_size`,
+    /// at most once every `SIZE_CHECK_INTERVAL`. The first check fails, e.g.
+    /// when the backend cannot report its size; later failures are logged and
+    /// the previous result is kept.
+    fn check_size(&mut self) -> Result<()> {
+        let Some(max_db_size) = self.config.max_db_size else {
+            return Ok(());
+        };
+        if self
+            .size_checked
+            .is_some_and(|checked| checked.elapsed() < SIZE_CHECK_INTERVAL)
+        {
+            return Ok(());
         }
 
+        match self.db.database_size() {
+            Ok(size) => {
+                let full = size >= max_db_size;
+                if full && !self.full {
+                    info!(
+                        "primary database reached --max-db-size at {}, pausing inserts",
+                        HumanBytes(size)
+                    );
+                } else if self.full && !full {
+                    info!(
+                        "primary database shrank to {}, resuming inserts",
+                        HumanBytes(size)
+                    );
+                }
+                self.full = full;
+            }
+            Err(e) if self.size_checked.is_none() => {
+                return Err(e.context("failed to query the size of the primary database"));
+            }
+            Err(e) => warn!("failed to query the size of the primary database: {e:#}"),
+        }
+        self.size_checked = Some(Instant::now());
         Ok(())
     }
 
-    fn generate_operations(&mut self) -> Result<WriteOperation> {
+    /// The write mix of the phase that `elapsed` falls into, or the
+    /// configured write mix once every phase is over.
+    fn write_mix(&self, elapsed: Duration) -> WriteMix {
+        let mut end = Duration::ZERO;
+        for phase in &self.config.phases {
+            end += phase.duration;
+            if elapsed < end {
+                return phase.write_mix;
+            }
+        }
+        self.config.write_mix
+    }
+
+    /// How long to pause after a transaction, given the uniform `interval`
+    /// and the time since the simulator started.
+    ///
+    /// With `WritePattern::Poisson` the pause is drawn from an exponential
+    /// distribution whose mean is `interval`, so over a long run the
+    /// transactions average the same rate as `WritePattern::Uniform`, while
+    /// any single pause may be much shorter or longer. With
+    /// `WritePattern::Burst` transactions run at twice the rate during a
+    /// busy window and pause until the end of the following idle window.
+    fn pause(&mut self, interval: Duration, elapsed: Duration) -> Duration {
+        match self.config.write_pattern {
+            WritePattern::Uniform => interval,
+            WritePattern::Poisson => {
+                // Inverse transform sampling. 1 - u is in (0, 1], so the
+                // logarithm is always finite.
+                let u: f64 = self.rng.gen();
+                interval.mul_f64(-(1.0 - u).ln())
+            }
+            WritePattern::Burst => {
+                let cycle = BURST_WINDOW * 2;
+                let into_cycle =
+                    Duration::from_nanos((elapsed.as_nanos() % cycle.as_nanos()) as u64);
+                if into_cycle < BURST_WINDOW {
+                    interval / 2
+                } else {
+                    cycle - into_cycle
+                }
+            }
+        }
+    }
+
+    /// Generates and executes a single transaction, returning how long the
+    /// primary database took to execute it. Generating the operations, which
+    /// may query the primary database for random ids, is not included. A
+    /// transaction that conflicts with another one is executed again with
+    /// the same operations, and only the attempt that succeeded is timed.
+    fn execute_transaction(&mut self, write_mix: WriteMix) -> Result<Duration> {
+        let (latency, price) = if self.config.batch_size == 1 {
+            let op = self.generate_operations(write_mix)?;
+            let price = PriceWrite::from_operation(&op);
+            let latency = self.execute_with_conflict_retries(|db| {
+                db.execute_command(op.clone())
+                    .context("failed to execute command")
+            })?;
+            (latency, price)
+        } else {
+            let ops = (0..self.config.batch_size)
+                .map(|_| self.generate_operations(write_mix))
+                .collect::<Result<Vec<_>>>()?;
+            let price = ops.iter().rev().find_map(PriceWrite::from_operation);
+            let latency = self.execute_with_conflict_retries(|db| {
+                db.execute_batch(ops.clone())
+                    .context("failed to execute batch")
+            })?;
+            (latency, price)
+        };
+
+        if let Some(price) = price {
+            self.freshness.send(price);
+        }
+        Ok(latency)
+    }
+
+    /// Runs `execute` with `config.conflict_retries`, returning how long
+    /// the attempt that succeeded took.
+    fn execute_with_conflict_retries(
+        &self,
+        execute: impl Fn(&DB) -> Result<()>,
+    ) -> Result<Duration> {
+        with_conflict_retries(
+            self.config.conflict_retries,
+            &self.errors,
+            |e| self.db.is_conflict(e),
+            || {
+                let start = Instant::now();
+                execute(&self.db)?;
+                Ok(start.elapsed())
+            },
+        )
+    }
+
+    fn generate_operations(&mut self, write_mix: WriteMix) -> Result<WriteOperation> {
         let op_type = self.rng.gen_range(0..100);
-        if op_type < INSERT_PERCENTAGE {
+        if op_type < write_mix.insert {
             self.generate_insert()
-        } else if op_type < UPDATE_PERCENTAGE {
+        } else if op_type < write_mix.insert + write_mix.update {
             self.generate_update()
         } else {
             self.generate_delete()
@@ -56,25 +344,26 @@ impl<DB: PrimaryDatabase> PrimarySimulator<DB> {
     }
 
     fn generate_insert(&mut self) -> Result<WriteOperation> {
-        let operation = match self.rng.gen_range(0..6) {
-            0 => {
+        let table = choose_table(&mut self.rng, &self.config.write_tables);
+        let operation = match table {
+            Table::Customers => {
                 let customer = self.gen.generate_customer();
                 WriteOperation::InsertCustomer {
                     name: customer.name,
                     address: customer.address,
                 }
             }
-            1 => {
-                let customer_id = self.db.get_random_customer_id()?;
+            Table::Accounts => {
+                let customer_id = self.db.get_random_customer_id(&mut self.rng)?;
                 let account = self.gen.generate_account();
                 WriteOperation::InsertAccount {
                     customer_id,
                     account_type: account.account_type,
                     balance: account.balance,
-                    parent_account_id: None,
+                    parent_account_id: self.parent_id(DB::get_random_account_id)?,
                 }
             }
-            2 => {
+            Table::Securities => {
                 let security = self.gen.generate_security();
                 WriteOperation::InsertSecurity {
                     ticker: security.ticker,
@@ -82,9 +371,9 @@ impl<DB: PrimaryDatabase> PrimarySimulator<DB> {
                     sector: security.sector,
                 }
             }
-            3 => {
-                let account_id = self.db.get_random_account_id()?;
-                let security_id = self.db.get_random_security_id()?;
+            Table::Trades => {
+                let account_id = self.db.get_random_account_id(&mut self.rng)?;
+                let security_id = self.db.get_random_security_id(&mut self.rng)?;
 
                 let trade = self.gen.generate_trade();
 
@@ -94,12 +383,12 @@ impl<DB: PrimaryDatabase> PrimarySimulator<DB> {
                     trade_type: trade.trade_type,
                     quantity: trade.quantity,
                     price: trade.price,
-                    parent_trade_id: None,
+                    parent_trade_id: self.parent_id(DB::get_random_trade_id)?,
                 }
             }
-            4 => {
-                let account_id = self.db.get_random_account_id()?;
-                let security_id = self.db.get_random_security_id()?;
+            Table::Orders => {
+                let account_id = self.db.get_random_account_id(&mut self.rng)?;
+                let security_id = self.db.get_random_security_id(&mut self.rng)?;
 
                 let order = self.gen.generate_order();
                 WriteOperation::InsertOrder {
@@ -109,11 +398,11 @@ impl<DB: PrimaryDatabase> PrimarySimulator<DB> {
                     quantity: order.quantity,
                     limit_price: order.limit_price,
                     status: order.status,
-                    parent_order_id: None,
+                    parent_order_id: self.parent_id(DB::get_random_order_id)?,
                 }
             }
-            _ => {
-                let security_id = self.db.get_random_security_id()?;
+            Table::MarketData => {
+                let security_id = self.db.get_random_security_id(&mut self.rng)?;
                 let market_data = self.gen.generate_market_data();
                 WriteOperation::InsertMarketData {
                     security_id,
@@ -126,34 +415,49 @@ impl<DB: PrimaryDatabase> PrimarySimulator<DB> {
         Ok(operation)
     }
 
+    /// Picks an existing row to be the parent of an inserted row for
+    /// `hierarchy_pct` of inserts. The inserted row is always assigned a
+    /// new id, so it can never be its own parent and no cycles can form.
+    fn parent_id(
+        &mut self,
+        get_random_id: impl FnOnce(&mut DB, &mut StdRng) -> Result<i32>,
+    ) -> Result<Option<i32>> {
+        if self.rng.gen_range(0..100) < self.config.hierarchy_pct {
+            get_random_id(&mut self.db, &mut self.rng).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
     fn generate_update(&mut self) -> Result<WriteOperation> {
-        let operation = match self.rng.gen_range(0..5) {
-            0 => {
-                let customer_id = self.db.get_random_customer_id()?;
+        let table = choose_table(&mut self.rng, &self.update_tables);
+        let operation = match table {
+            Table::Customers => {
+                let customer_id = self.db.get_random_customer_id(&mut self.rng)?;
                 let customer = self.gen.generate_customer();
                 WriteOperation::UpdateCustomer {
                     customer_id,
                     address: customer.address,
                 }
             }
-            1 => {
-                let account_id = self.db.get_random_account_id()?;
+            Table::Accounts => {
+                let account_id = self.db.get_random_account_id(&mut self.rng)?;
                 let account = self.gen.generate_account();
                 WriteOperation::UpdateAccount {
                     account_id,
                     balance: account.balance,
                 }
             }
-            2 => {
-                let trade_id = self.db.get_random_trade_id()?;
+            Table::Trades => {
+                let trade_id = self.db.get_random_trade_id(&mut self.rng)?;
                 let trade = self.gen.generate_trade();
                 WriteOperation::UpdateTrade {
                     trade_id,
                     price: trade.price,
                 }
             }
-            3 => {
-                let order_id = self.db.get_random_order_id()?;
+            Table::Orders => {
+                let order_id = self.db.get_random_order_id(&mut self.rng)?;
                 let order = self.gen.generate_order();
                 WriteOperation::UpdateOrder {
                     order_id,
@@ -161,8 +465,8 @@ impl<DB: PrimaryDatabase> PrimarySimulator<DB> {
                     limit_price: order.limit_price,
                 }
             }
-            _ => {
-                let market_data_id = self.db.get_random_market_data_id()?;
+            Table::MarketData => {
+                let market_data_id = self.db.get_random_market_data_id(&mut self.rng)?;
                 let market_data = self.gen.generate_market_data();
                 WriteOperation::UpdateMarketData {
                     market_data_id,
@@ -170,33 +474,231 @@ impl<DB: PrimaryDatabase> PrimarySimulator<DB> {
                     volume: market_data.volume,
                 }
             }
+            Table::Securities => unreachable!("securities are never updated"),
         };
 
         Ok(operation)
     }
 
     fn generate_delete(&mut self) -> Result<WriteOperation> {
-        let operation = match self.rng.gen_range(0..6) {
-            0 => WriteOperation::DeleteCustomer {
-                customer_id: self.db.get_random_customer_id()?,
+        // Deleting from a leaf table does not rely on ON DELETE CASCADE.
+        let table = if self.rng.gen_range(0..100) < self.config.delete_leaf_bias {
+            choose_table(&mut self.rng, &self.leaf_tables)
+        } else {
+            choose_table(&mut self.rng, &self.config.write_tables)
+        };
+        let operation = match table {
+            Table::Customers => WriteOperation::DeleteCustomer {
+                customer_id: self.db.get_random_customer_id(&mut self.rng)?,
             },
-            1 => WriteOperation::DeleteAccount {
-                account_id: self.db.get_random_account_id()?,
+            Table::Accounts => WriteOperation::DeleteAccount {
+                account_id: self.db.get_random_account_id(&mut self.rng)?,
             },
-            2 => WriteOperation::DeleteSecurity {
-                security_id: self.db.get_random_security_id()?,
+            Table::Securities => WriteOperation::DeleteSecurity {
+                security_id: self.db.get_random_security_id(&mut self.rng)?,
             },
-            3 => WriteOperation::DeleteTrade {
-                trade_id: self.db.get_random_trade_id()?,
+            Table::Trades => WriteOperation::DeleteTrade {
+                trade_id: self.db.get_random_trade_id(&mut self.rng)?,
             },
-            4 => WriteOperation::DeleteOrder {
-                order_id: self.db.get_random_order_id()?,
+            Table::Orders => WriteOperation::DeleteOrder {
+                order_id: self.db.get_random_order_id(&mut self.rng)?,
             },
-            _ => WriteOperation::DeleteMarketData {
-                market_data_id: self.db.get_random_market_data_id()?,
+            Table::MarketData => WriteOperation::DeleteMarketData {
+                market_data_id: self.db.get_random_market_data_id(&mut self.rng)?,
             },
         };
 
         Ok(operation)
     }
 }
+
+/// Picks one of `tables` at random. The tables are validated when the
+/// arguments are parsed, so there is always one to pick for every kind of
+/// operation that can be generated.
+fn choose_table(rng: &mut StdRng, tables: &[Table]) -> Table {
+    *tables.choose(rng).expect("no table to write to")
+}
+
+/// Parses a table from its name, e.g. `market_data`.
+pub fn parse_table(name: &str) -> Result<Table, String> {
+    Table::from_str(name.trim()).map_err(|_| {
+        let valid: Vec<&str> = Table::iter().map(|table| table.into()).collect();
+        format!(
+            "unknown table '{name}', expected one of: {}",
+            valid.join(", ")
+        )
+    })
+}
+
+/// Parses a comma separated list of phases, e.g. `insert:2m,delete:1m`.
+/// Each phase is `insert`, `update`, or `delete` and its duration.
+pub fn parse_phases(s: &str) -> Result<Vec<Phase>, String> {
+    s.split(',')
+        .map(|phase| {
+            let (kind, duration) = phase
+                .split_once(':')
+                .ok_or_else(|| format!("invalid phase '{phase}', expected KIND:DURATION"))?;
+            let write_mix = match kind.trim() {
+                "insert" => WriteMix::INSERTS,
+                "update" => WriteMix::UPDATES,
+                "delete" => WriteMix::DELETES,
+                _ => {
+                    return Err(format!(
+                        "unknown phase '{kind}', expected insert, update, or delete"
+                    ))
+                }
+            };
+            let duration = humantime::parse_duration(duration.trim())
+                .map_err(|_| format!("invalid duration '{duration}' in phase '{phase}'"))?;
+            Ok(Phase {
+                write_mix,
+                duration,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::task_handle::new_task_handles;
+    use crate::testing::FakeDatabase;
+
+    fn simulator(write_mix: WriteMix) -> PrimarySimulator<FakeDatabase> {
+        let config = PrimaryConfig {
+            tps: 10,
+            write_pattern: WritePattern::Uniform,
+            write_mix,
+            phases: vec![],
+            max_db_size: None,
+            seed: 0,
+            max_retries: 0,
+            conflict_retries: 0,
+            fail_on_error: false,
+            batch_size: 1,
+            hierarchy_pct: 0,
+            delete_leaf_bias: 0,
+            write_tables: Table::iter().collect(),
+        };
+        let (_handle, tracker) = new_task_handles();
+        PrimarySimulator::new(
+            FakeDatabase,
+            config,
+            ErrorCounter::default(),
+            FreshnessChannel::default(),
+            tracker,
+        )
+    }
+
+    /// Returns the number of inserts, updates, and deletes among `count`
+    /// generated operations.
+    fn count_kinds(simulator: &mut PrimarySimulator<FakeDatabase>, count: usize) -> [u32; 3] {
+        let write_mix = simulator.config.write_mix;
+        let mut kinds = [0; 3];
+        for _ in 0..count {
+            let kind = match simulator.generate_operations(write_mix).unwrap() {
+                WriteOperation::InsertCustomer { .. }
+                | WriteOperation::InsertAccount { .. }
+                | WriteOperation::InsertSecurity { .. }
+                | WriteOperation::InsertTrade { .. }
+                | WriteOperation::InsertOrder { .. }
+                | WriteOperation::InsertMarketData { .. } => 0,
+                WriteOperation::UpdateCustomer { .. }
+                | WriteOperation::UpdateAccount { .. }
+                | WriteOperation::UpdateTrade { .. }
+                | WriteOperation::UpdateOrder { .. }
+                | WriteOperation::UpdateMarketData { .. } => 1,
+                WriteOperation::DeleteCustomer { .. }
+                | WriteOperation::DeleteAccount { .. }
+                | WriteOperation::DeleteSecurity { .. }
+                | WriteOperation::DeleteTrade { .. }
+                | WriteOperation::DeleteOrder { .. }
+                | WriteOperation::DeleteMarketData { .. } => 2,
+            };
+            kinds[kind] += 1;
+        }
+        kinds
+    }
+
+    #[test]
+    fn write_mix_matches_configured_ratios() {
+        const OPERATIONS: usize = 20_000;
+        let write_mix = WriteMix {
+            insert: 60,
+            update: 30,
+            delete: 10,
+        };
+        let kinds = count_kinds(&mut simulator(write_mix), OPERATIONS);
+
+        let expected = [write_mix.insert, write_mix.update, write_mix.delete];
+        for (count, percent) in kinds.into_iter().zip(expected) {
+            let actual = count as f64 / OPERATIONS as f64 * 100.0;
+            assert!(
+                (actual - percent as f64).abs() < 1.5,
+                "expected {percent}%, got {actual}% in {kinds:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn write_mix_without_updates_never_updates() {
+        let write_mix = WriteMix {
+            insert: 50,
+            update: 0,
+            delete: 50,
+        };
+        assert_eq!(count_kinds(&mut simulator(write_mix), 1_000)[1], 0);
+    }
+
+    #[test]
+    fn phases_replace_the_write_mix_until_they_are_over() {
+        let write_mix = WriteMix {
+            insert: 45,
+            update: 45,
+            delete: 10,
+        };
+        let mut simulator = simulator(write_mix);
+        simulator.config.phases = parse_phases("insert:2m, delete:1m").unwrap();
+
+        let kinds_at = |secs| {
+            let mix = simulator.write_mix(Duration::from_secs(secs));
+            [mix.insert, mix.update, mix.delete]
+        };
+        assert_eq!(kinds_at(0), [100, 0, 0]);
+        assert_eq!(kinds_at(119), [100, 0, 0]);
+        assert_eq!(kinds_at(120), [0, 0, 100]);
+        assert_eq!(kinds_at(179), [0, 0, 100]);
+        assert_eq!(kinds_at(180), [45, 45, 10]);
+    }
+
+    #[test]
+    fn malformed_phases_are_errors() {
+        assert_eq!(
+            parse_phases("insert").unwrap_err(),
+            "invalid phase 'insert', expected KIND:DURATION"
+        );
+        assert_eq!(
+            parse_phases("truncate:1m").unwrap_err(),
+            "unknown phase 'truncate', expected insert, update, or delete"
+        );
+        assert_eq!(
+            parse_phases("insert:soon").unwrap_err(),
+            "invalid duration 'soon' in phase 'insert:soon'"
+        );
+    }
+
+    #[test]
+    fn without_inserts_deletes_instead() {
+        let write_mix = WriteMix {
+            insert: 45,
+            update: 45,
+            delete: 10,
+        }
+        .without_inserts();
+        assert_eq!(
+            [write_mix.insert, write_mix.update, write_mix.delete],
+            [0, 45, 55]
+        );
+        assert_eq!(count_kinds(&mut simulator(write_mix), 1_000)[0], 0);
+    }
+}
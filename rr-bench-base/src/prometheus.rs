@@ -0,0 +1,179 @@
+use crate::operations::ReadOperation;
+use anyhow::{anyhow, Result};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use tiny_http::{Header, Response, Server};
+
+/// Upper bounds, in seconds, of the read latency histogram buckets.
+const BUCKETS: [f64; 16] = [
+    0.0001, 0.00025, 0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5,
+    5.0, 10.0,
+];
+
+/// `PrometheusExporter` serves live benchmark metrics in the Prometheus text
+/// format on a background thread. The server shuts down when it is dropped.
+pub struct PrometheusExporter {
+    metrics: Arc<Mutex<Metrics>>,
+    server: Arc<Server>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl PrometheusExporter {
+    pub fn start(port: u16) -> Result<Self> {
+        let server = Server::http(("0.0.0.0", port))
+            .map_err(|e| anyhow!("failed to start prometheus server on port {port}: {e}"))?;
+        let server = Arc::new(server);
+        let metrics = Arc::new(Mutex::new(Metrics::new()));
+
+        let thread = {
+            let server = server.clone();
+            let metrics = metrics.clone();
+            thread::spawn(move || {
+                let content_type =
+                    Header::from_bytes("Content-Type", "text/plain; version=0.0.4").unwrap();
+                for request in server.incoming_requests() {
+                    let body = metrics.lock().unwrap().render();
+                    let response = Response::from_string(body).with_header(content_type.clone());
+                    // The scraper hanging up early is not our problem.
+                    let _ = request.respond(response);
+                }
+            })
+        };
+
+        Ok(Self {
+            metrics,
+            server,
+            thread: Some(thread),
+        })
+    }
+
+    pub fn set_errors(&self, errors: usize) {
+        self.metrics.lock().unwrap().errors = errors;
+    }
+
+    pub fn observe(&self, operation: ReadOperation, latency: Duration) {
+        self.metrics.lock().unwrap().observe(operation, latency);
+    }
+}
+
+impl Drop for PrometheusExporter {
+    fn drop(&mut self) {
+        self.server.unblock();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+struct Metrics {
+    start: Instant,
+    reads: BTreeMap<ReadOperation, Histogram>,
+    errors: usize,
+}
+
+impl Metrics {
+    fn new() -> Self {
+        Self {
+            start: Instant::now(),
+            reads: BTreeMap::new(),
+            errors: 0,
+        }
+    }
+
+    fn observe(&mut self, operation: ReadOperation, latency: Duration) {
+        self.reads
+            .entry(operation)
+            .or_default()
+            .observe(latency.as_secs_f64());
+    }
+
+    fn render(&self) -> String {
+        let mut out = String::new();
+        let total: u64 = self.reads.values().map(|h| h.count).sum();
+
+        writeln!(out, "# HELP rr_bench_read_latency_seconds Latency of read operations against the read replica.").unwrap();
+        writeln!(out, "# TYPE rr_bench_read_latency_seconds histogram").unwrap();
+        for (operation, histogram) in &self.reads {
+            let operation = operation.as_ref();
+            let mut cumulative = 0;
+            for (bound, count) in BUCKETS.iter().zip(&histogram.buckets) {
+                cumulative += count;
+                writeln!(
+                    out,
+                    "rr_bench_read_latency_seconds_bucket{{operation=\"{operation}\",le=\"{bound}\"}} {cumulative}"
+                )
+                .unwrap();
+            }
+            writeln!(
+                out,
+                "rr_bench_read_latency_seconds_bucket{{operation=\"{operation}\",le=\"+Inf\"}} {}",
+                histogram.count
+            )
+            .unwrap();
+            writeln!(
+                out,
+                "rr_bench_read_latency_seconds_sum{{operation=\"{operation}\"}} {}",
+                histogram.sum
+            )
+            .unwrap();
+            writeln!(
+                out,
+                "rr_bench_read_latency_seconds_count{{operation=\"{operation}\"}} {}",
+                histogram.count
+            )
+            .unwrap();
+        }
+
+        writeln!(
+            out,
+            "# HELP rr_bench_reads_total Number of completed read operations."
+        )
+        .unwrap();
+        writeln!(out, "# TYPE rr_bench_reads_total counter").unwrap();
+        writeln!(out, "rr_bench_reads_total {total}").unwrap();
+
+        writeln!(
+            out,
+            "# HELP rr_bench_tps Average read transactions per second since the benchmark started."
+        )
+        .unwrap();
+        writeln!(out, "# TYPE rr_bench_tps gauge").unwrap();
+        writeln!(
+            out,
+            "rr_bench_tps {}",
+            total as f64 / self.start.elapsed().as_secs_f64()
+        )
+        .unwrap();
+
+        writeln!(
+            out,
+            "# HELP rr_bench_errors_total Number of operations that failed after exhausting their retries."
+        )
+        .unwrap();
+        writeln!(out, "# TYPE rr_bench_errors_total counter").unwrap();
+        writeln!(out, "rr_bench_errors_total {}", self.errors).unwrap();
+
+        out
+    }
+}
+
+#[derive(Default)]
+struct Histogram {
+    /// Per-bucket counts; not cumulative.
+    buckets: [u64; BUCKETS.len()],
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn observe(&mut self, value: f64) {
+        if let Some(i) = BUCKETS.iter().position(|bound| value <= *bound) {
+            self.buckets[i] += 1;
+        }
+        self.sum += value;
+        self.count += 1;
+    }
+}
@@ -0,0 +1,82 @@
+use anyhow::{bail, Result};
+use rand::Rng;
+use std::time::{Duration, Instant};
+
+/// How long a cached id range is used before it is reloaded.
+const DEFAULT_REFRESH_INTERVAL: Duration = Duration::from_secs(10);
+
+/// `RandomIdCache` samples random ids from the cached `MIN`/`MAX` range of a
+/// table's primary key. Backends can use it in their `get_random_*` methods in
+/// place of `ORDER BY random() LIMIT 1`, which scans and sorts the entire table
+/// on every call, by selecting the first existing row at or after the sampled id.
+///
+/// The range is reloaded periodically so that rows inserted during the
+/// benchmark are eventually sampled as well.
+pub struct RandomIdCache {
+    range: Option<(i32, i32)>,
+    loaded_at: Instant,
+    refresh_interval: Duration,
+}
+
+impl Default for RandomIdCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_REFRESH_INTERVAL)
+    }
+}
+
+impl RandomIdCache {
+    pub fn new(refresh_interval: Duration) -> Self {
+        Self {
+            range: None,
+            loaded_at: Instant::now(),
+            refresh_interval,
+        }
+    }
+
+    /// Returns a random id in the cached range, drawn from `rng`, calling
+    /// `load` to fetch the range when it has not been loaded yet or is out of
+    /// date. `load` returns `None` when the table is empty.
+    pub fn sample(
+        &mut self,
+        rng: &mut impl Rng,
+        load: impl FnOnce() -> Result<Option<(i32, i32)>>,
+    ) -> Result<i32> {
+        if self.range.is_none() || self.loaded_at.elapsed() >= self.refresh_interval {
+            self.range = load()?;
+            self.loaded_at = Instant::now();
+        }
+
+        match self.range {
+            Some((min, max)) if min <= max => Ok(rng.gen_range(min..=max)),
+            _ => bail!("cannot sample an id from an empty table"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn sample_ids(seed: u64) -> Vec<i32> {
+        let mut cache = RandomIdCache::default();
+        let mut rng = StdRng::seed_from_u64(seed);
+        (0..100)
+            .map(|_| cache.sample(&mut rng, || Ok(Some((1, 1_000_000)))).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn same_seed_samples_same_ids() {
+        assert_eq!(sample_ids(42), sample_ids(42));
+        assert_ne!(sample_ids(42), sample_ids(43));
+    }
+
+    #[test]
+    fn empty_table_is_an_error() {
+        let mut cache = RandomIdCache::default();
+        let mut rng = StdRng::seed_from_u64(0);
+        assert!(cache.sample(&mut rng, || Ok(None)).is_err());
+    }
+}
@@ -0,0 +1,185 @@
+use crate::operations::ReadOperation;
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::seq::SliceRandom;
+use rand::Rng;
+use std::str::FromStr;
+use strum::IntoEnumIterator;
+
+/// `ReadSelector` decides which `ReadOperation` a reader executes next.
+pub enum ReadSelector {
+    /// Cycles through every operation with equal frequency, in a
+    /// new random order each cycle.
+    Shuffled {
+        operations: Vec<ReadOperation>,
+        position: usize,
+    },
+    /// Draws each operation from a weighted distribution.
+    Weighted(WeightedReadSelector),
+}
+
+impl ReadSelector {
+    /// Cycles through `operations`, shuffling them at the start of every
+    /// cycle. The order is determined entirely by the `rng` passed to `next`.
+    pub fn shuffled(operations: Vec<ReadOperation>) -> Self {
+        ReadSelector::Shuffled {
+            operations,
+            position: 0,
+        }
+    }
+
+    pub fn next<R: Rng>(&mut self, rng: &mut R) -> ReadOperation {
+        match self {
+            ReadSelector::Shuffled {
+                operations,
+                position,
+            } => {
+                if *position == 0 {
+                    operations.shuffle(rng);
+                }
+                let operation = operations[*position];
+                *position = (*position + 1) % operations.len();
+                operation
+            }
+            ReadSelector::Weighted(selector) => selector.sample(rng),
+        }
+    }
+}
+
+/// `WeightedReadSelector` samples read operations in proportion to their weights.
+///
+/// Operations without an explicit weight default to a weight of 1, so
+/// `customer_portfolio=5` runs `customer_portfolio` five times as often as
+/// every other operation. A weight of 0 excludes an operation entirely.
+#[derive(Clone, Debug)]
+pub struct WeightedReadSelector {
+    operations: Vec<ReadOperation>,
+    /// The weight of each of `operations`.
+    weights: Vec<u32>,
+    distribution: WeightedIndex<u32>,
+}
+
+impl WeightedReadSelector {
+    pub fn new(weights: &[(ReadOperation, u32)]) -> Result<Self, String> {
+        let operations: Vec<ReadOperation> = ReadOperation::iter().collect();
+        let weights = operations
+            .iter()
+            .map(|operation| {
+                weights
+                    .iter()
+                    .rev()
+                    .find(|(op, _)| op == operation)
+                    .map_or(1, |(_, weight)| *weight)
+            })
+            .collect();
+        Self::from_weights(operations, weights)
+            .map_err(|_| "at least one read operation must have a non-zero weight".to_string())
+    }
+
+    /// Excludes every operation but `operations`, keeping their weights.
+    pub fn only(&self, operations: &[ReadOperation]) -> Result<Self, String> {
+        let weights = self
+            .operations
+            .iter()
+            .zip(&self.weights)
+            .map(|(operation, &weight)| {
+                if operations.contains(operation) {
+                    weight
+                } else {
+                    0
+                }
+            })
+            .collect();
+        Self::from_weights(self.operations.clone(), weights).map_err(|_| {
+            "at least one of the read operations must have a non-zero weight".to_string()
+        })
+    }
+
+    fn from_weights(
+        operations: Vec<ReadOperation>,
+        weights: Vec<u32>,
+    ) -> Result<Self, rand::distributions::WeightedError> {
+        let distribution = WeightedIndex::new(&weights)?;
+        Ok(Self {
+            operations,
+            weights,
+            distribution,
+        })
+    }
+}
+
+impl Distribution<ReadOperation> for WeightedReadSelector {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> ReadOperation {
+        self.operations[self.distribution.sample(rng)]
+    }
+}
+
+impl FromStr for WeightedReadSelector {
+    type Err = String;
+
+    /// Parses a comma separated list of `operation=weight` pairs,
+    /// e.g. `customer_portfolio=5,top_performers=1`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let weights = s
+            .split(',')
+            .map(|pair| {
+                let (name, weight) = pair.split_once('=').ok_or_else(|| {
+                    format!("invalid read weight '{pair}', expected OPERATION=WEIGHT")
+                })?;
+                let operation = parse_read_operation(name.trim())?;
+                let weight = weight
+                    .trim()
+                    .parse::<u32>()
+                    .map_err(|_| format!("invalid weight '{weight}' for {name}"))?;
+                Ok((operation, weight))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        WeightedReadSelector::new(&weights)
+    }
+}
+
+/// Parses a read operation from its view name, e.g. `customer_portfolio`.
+pub fn parse_read_operation(name: &str) -> Result<ReadOperation, String> {
+    ReadOperation::from_str(name).map_err(|_| {
+        let valid: Vec<&str> = ReadOperation::iter().map(|op| op.into()).collect();
+        format!(
+            "unknown read operation '{name}', expected one of: {}",
+            valid.join(", ")
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn only_keeps_the_weights_of_the_given_operations() {
+        let selector: WeightedReadSelector = "top_performers=3,market_overview=0".parse().unwrap();
+        let only = selector
+            .only(&[
+                ReadOperation::TopPerformers,
+                ReadOperation::CustomerPortfolio,
+            ])
+            .unwrap();
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut counts = [0; 2];
+        for _ in 0..4000 {
+            match only.sample(&mut rng) {
+                ReadOperation::TopPerformers => counts[0] += 1,
+                ReadOperation::CustomerPortfolio => counts[1] += 1,
+                operation => panic!("{operation:?} was not selected"),
+            }
+        }
+        // Top performers has three times the weight of customer portfolio.
+        assert!((2700..3300).contains(&counts[0]), "{counts:?}");
+
+        let error = selector.only(&[ReadOperation::MarketOverview]).unwrap_err();
+        assert_eq!(
+            error,
+            "at least one of the read operations must have a non-zero weight"
+        );
+    }
+}
@@ -1,130 +1,502 @@
+use crate::config::{DurationMode, StopCondition};
+use crate::freshness::{FreshnessChannel, NO_PRICE};
+use crate::id_cache::IdCache;
 use crate::operations::ReadOperation;
+use crate::read_selector::{ReadSelector, WeightedReadSelector};
+use crate::read_your_writes::ReadYourWritesCheck;
+use crate::retry::{with_retries, ErrorCounter};
 use crate::task_handle::TaskHandle;
-use crate::{PrimaryDatabase, ReadReplica};
+use crate::ReadReplica;
 use anyhow::Result;
-use indicatif::ProgressBar;
+use console::Term;
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget};
+use log::debug;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use std::sync::mpsc::Sender;
+use std::sync::Arc;
+use std::thread;
 use std::time::{Duration, Instant};
-use strum::IntoEnumIterator;
+
+/// Per-client settings for a `ReaderSimulator`.
+#[derive(Clone, Debug)]
+pub struct ReaderConfig {
+    /// When to stop measuring. `StopCondition::Operations` holds the
+    /// number of read operations run by this client.
+    pub stop: StopCondition,
+    /// Whether durations are measured in experiment or wall-clock time.
+    pub duration_mode: DurationMode,
+    /// How long to wait before starting, which staggers the clients over
+    /// `--ramp`.
+    pub start_delay: Duration,
+    /// The amount of time to run before measurements are recorded.
+    pub warmup: Duration,
+    /// The seed for this client's random number generator.
+    pub seed: u64,
+    /// The read operations that are run.
+    pub read_operations: Vec<ReadOperation>,
+    /// Relative weights of each read operation, which already exclude
+    /// those not in `read_operations`. When `None`, every operation runs
+    /// once per cycle, in a random order.
+    pub read_weights: Option<WeightedReadSelector>,
+    /// The pause between consecutive read operations.
+    pub think_time: Duration,
+    /// The maximum random amount of time added to each pause.
+    pub think_time_jitter: Duration,
+    /// Reconnect to the read replica before every this many read
+    /// operations. When `None`, the client keeps its connection.
+    pub reconnect_every: Option<u32>,
+    /// The number of times a failed read operation is retried.
+    pub max_retries: u32,
+    /// Whether the latency of an operation that succeeded after being
+    /// retried includes the time spent on the attempts that failed, or is
+    /// only that of the attempt that succeeded.
+    pub latency_includes_retries: bool,
+    /// Whether a measured read operation that fails after `max_retries`
+    /// retries stops the client with its error, rather than only being
+    /// counted.
+    pub fail_on_error: bool,
+    /// The rate at which this client issues read operations. When `None`,
+    /// operations are issued as fast as the read replica can serve them.
+    pub target_tps: Option<f64>,
+    /// Whether this client reads from `--compare-reader-url`. It runs the
+    /// same operations as the client with the same seed.
+    pub comparison: bool,
+}
+
+/// How often a client waiting to start checks whether the benchmark has
+/// been interrupted.
+const CANCEL_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// A single measured read operation.
+pub struct Sample {
+    pub operation: ReadOperation,
+    pub latency: Duration,
+    /// When the operation completed.
+    pub timestamp: Instant,
+    /// Whether a `RowFreshness` check found the read replica stale. `None`
+    /// for every other operation, and for checks made before the primary
+    /// database simulator inserted any market data.
+    pub stale: Option<bool>,
+    /// How long reconnecting to the read replica took, when the client
+    /// reconnected before the operation. Not included in `latency`.
+    pub reconnect: Option<Duration>,
+    /// Whether the operation ran against `--compare-reader-url`.
+    pub comparison: bool,
+    /// The number of rows the operation read. `None` for `RowFreshness`
+    /// checks, which only report staleness.
+    pub rows: Option<u64>,
+}
+
+/// The outcome of a read operation that succeeded.
+struct Measurement {
+    latency: Duration,
+    stale: Option<bool>,
+    reconnect: Option<Duration>,
+    rows: Option<u64>,
+}
 
 /// `ReaderSimulator` runs a series of read operations against a `ReadReplica`.
 ///
-/// Timing is based on the cumulative duration of the read operations (experiment duration),
-/// rather than real-world time. This ensures that the simulation focuses on measuring the
-/// performance of the read replica itself, without including time spent on other tasks,
-/// such as querying the primary database.
-pub struct ReaderSimulator<R: ReadReplica, P: PrimaryDatabase> {
+/// By default, timing is based on the cumulative duration of the read operations (experiment
+/// duration), rather than real-world time. This ensures that the simulation focuses on measuring
+/// the performance of the read replica itself, without including time spent on other tasks,
+/// such as thinking between operations. With `DurationMode::Wallclock` the simulator instead
+/// runs for a fixed amount of real time.
+pub struct ReaderSimulator<'a, R: ReadReplica> {
     reader: InstrumentedReader<R>,
-    primary: P,
-    duration: Duration,
-    timings: Sender<Duration>,
+    ids: Arc<IdCache>,
+    config: ReaderConfig,
+    rng: StdRng,
+    timings: Sender<Sample>,
+    freshness: FreshnessChannel,
     pb: ExperimentProgressBar,
-    /// This handle is used solely for its `Drop` implementation, which triggers cleanup
-    /// or signaling when the `ReaderSimulator` is completed.
-    _handle: TaskHandle,
+    errors: ErrorCounter,
+    pacer: Option<Pacer>,
+    /// Set when the client checks read-your-writes consistency.
+    read_your_writes: Option<ReadYourWritesCheck<'a>>,
+    /// Signals completion when the `ReaderSimulator` is dropped and
+    /// reports whether the benchmark has been interrupted.
+    handle: TaskHandle,
 }
 
-impl<R: ReadReplica, P: PrimaryDatabase> ReaderSimulator<R, P> {
+impl<'a, R: ReadReplica> ReaderSimulator<'a, R> {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         reader: R,
-        primary: P,
-        duration: Duration,
-        timings: Sender<Duration>,
+        ids: Arc<IdCache>,
+        config: ReaderConfig,
+        timings: Sender<Sample>,
+        freshness: FreshnessChannel,
         pb: ProgressBar,
+        errors: ErrorCounter,
         handle: TaskHandle,
     ) -> Self {
         Self {
             reader: InstrumentedReader::new(reader),
-            primary,
-            duration,
+            ids,
+            rng: StdRng::seed_from_u64(config.seed),
+            pacer: config.target_tps.map(Pacer::new),
+            config,
             timings,
+            freshness,
             pb: ExperimentProgressBar::new(pb),
-            _handle: handle,
+            errors,
+            read_your_writes: None,
+            handle,
         }
     }
 
-    pub fn run(&mut self) -> Result<()> {
-        let mut iter = ReadOperation::iter().cycle();
+    /// Makes the client run `check` between its measured read operations.
+    pub fn with_read_your_writes(mut self, check: ReadYourWritesCheck<'a>) -> Self {
+        self.read_your_writes = Some(check);
+        self
+    }
 
-        while self.reader.experiment_duration < self.duration {
-            let measurement = match iter.next().unwrap() {
-                ReadOperation::CustomerPortfolio => {
-                    let customer_id = self.primary.get_random_customer_id()?;
-                    self.reader.customer_portfolio(customer_id)?
-                }
-                ReadOperation::TopPerformers => self.reader.top_performers()?,
-                ReadOperation::MarketOverview => {
-                    let sector = self.primary.get_random_sector()?;
-                    self.reader.market_overview(&sector)?
-                }
-                ReadOperation::RecentLargeTrades => {
-                    let account_id = self.primary.get_random_account_id()?;
-                    self.reader.recent_large_trades(account_id)?
-                }
-                ReadOperation::CustomerOrderBook => {
-                    let customer_id = self.primary.get_random_customer_id()?;
-                    self.reader.customer_order_book(customer_id)?
-                }
-                ReadOperation::SectorPerformance => {
-                    let sector = self.primary.get_random_sector()?;
-                    self.reader.sector_performance(sector)?
-                }
-                ReadOperation::AccountActivitySummary => {
-                    let account_id = self.primary.get_random_account_id()?;
-                    self.reader.account_activity_summary(account_id)?
-                }
-                ReadOperation::DailyMarketMovements => {
-                    let security_id = self.primary.get_random_security_id()?;
-                    self.reader.daily_market_movements(security_id)?
-                }
-                ReadOperation::HighValueCustomers => self.reader.high_value_customers()?,
-                ReadOperation::PendingOrdersSummary => {
-                    let ticker = self.primary.get_random_ticker()?;
-                    self.reader.pending_orders_summary(&ticker)?
+    /// Runs read operations until the stop condition is reached, and
+    /// returns the amount of time measured after the warmup. With
+    /// `fail_on_error`, returns the error of the first measured read
+    /// operation or read-your-writes check that fails instead, which is
+    /// counted all the same.
+    pub fn run(&mut self) -> Result<Duration> {
+        // Each client has its own seed, so concurrent clients run the
+        // operations in different orders that are reproducible with --seed.
+        let mut selector = match self.config.read_weights.clone() {
+            Some(weights) => ReadSelector::Weighted(weights),
+            None => ReadSelector::shuffled(self.config.read_operations.clone()),
+        };
+
+        self.delay_start();
+
+        // Warmup operations are executed but never recorded, and their
+        // time does not count against the benchmark duration.
+        if !self.config.warmup.is_zero() {
+            self.pb.start_warmup();
+            let start = Instant::now();
+            let mut operations = 0;
+            while self.elapsed(start) < self.config.warmup && !self.handle.is_cancelled() {
+                self.pace();
+                let operation = selector.next(&mut self.rng);
+                if let Err(e) = self.attempt(operation, operations) {
+                    debug!("warmup read operation failed: {e:#}");
                 }
-                ReadOperation::TradeVolumeByHour => self.reader.trade_volume_by_hour()?,
-                ReadOperation::TopSecuritiesBySector => {
-                    let sector = self.primary.get_random_sector()?;
-                    self.reader.top_securities_by_sector(sector)?
+                operations += 1;
+                self.think();
+            }
+            self.reader.experiment_duration = Duration::from_secs(0);
+            self.pb.finish_warmup();
+        }
+
+        let start = Instant::now();
+        let mut reported = Duration::from_secs(0);
+        let mut operations = 0;
+        while !self.is_finished(start, operations) && !self.handle.is_cancelled() {
+            self.pace();
+            let operation = selector.next(&mut self.rng);
+            let measurement = match self.attempt(operation, operations) {
+                Ok(measurement) => Some(measurement),
+                Err(e) => {
+                    self.record_error(&e);
+                    if self.config.fail_on_error {
+                        return Err(e);
+                    }
+                    None
                 }
-                ReadOperation::RecentTradesByAccount => {
-                    let account_id = self.primary.get_random_account_id()?;
-                    self.reader.recent_trades_by_account(account_id)?
+            };
+            operations += 1;
+
+            match self.config.stop {
+                StopCondition::Duration(_) => {
+                    let elapsed = self.elapsed(start);
+                    self.pb.inc(elapsed.saturating_sub(reported));
+                    reported = elapsed;
                 }
-                ReadOperation::OrderFulfillmentRates => {
-                    let customer_id = self.primary.get_random_customer_id()?;
-                    self.reader.order_fulfillment_rates(customer_id)?
+                StopCondition::Operations(_) => self.pb.inc_operation(),
+            }
+
+            if let Some(measurement) = measurement {
+                let sample = Sample {
+                    operation,
+                    latency: measurement.latency,
+                    timestamp: Instant::now(),
+                    stale: measurement.stale,
+                    reconnect: measurement.reconnect,
+                    comparison: self.config.comparison,
+                    rows: measurement.rows,
+                };
+                if self.timings.send(sample).is_err() {
+                    break;
                 }
-                ReadOperation::SectorOrderActivity => {
-                    let sector = self.primary.get_random_sector()?;
-                    self.reader.sector_order_activity(sector)?
+            }
+
+            // Checks happen outside of the `InstrumentedReader`, so their
+            // reads are neither sampled nor counted towards the experiment
+            // duration.
+            if let Some(check) = &mut self.read_your_writes {
+                if check.is_due(operations) {
+                    if let Err(e) = check.run(&mut self.reader.handle, &self.handle) {
+                        self.errors.record(&e);
+                        if self.config.fail_on_error {
+                            return Err(e);
+                        }
+                    }
                 }
-            };
+            }
 
-            self.pb.inc(measurement);
-            if self.timings.send(measurement).is_err() {
+            self.think();
+        }
+
+        self.pb.finish();
+        Ok(self.elapsed(start))
+    }
+
+    /// Waits out the start delay, or until the benchmark is interrupted.
+    fn delay_start(&self) {
+        let start = Instant::now() + self.config.start_delay;
+        while !self.handle.is_cancelled() {
+            let now = Instant::now();
+            if now >= start {
                 break;
             }
+            thread::sleep((start - now).min(CANCEL_POLL_INTERVAL));
         }
+    }
 
-        self.pb.finish();
-        Ok(())
+    /// Whether the stop condition has been reached after running
+    /// `operations` read operations since `start`.
+    fn is_finished(&self, start: Instant, operations: u64) -> bool {
+        match self.config.stop {
+            StopCondition::Duration(duration) => self.elapsed(start) >= duration,
+            StopCondition::Operations(count) => operations >= count,
+        }
+    }
+
+    /// The time that counts against the benchmark duration since `start`.
+    fn elapsed(&self, start: Instant) -> Duration {
+        match self.config.duration_mode {
+            DurationMode::Experiment => self.reader.experiment_duration,
+            DurationMode::Wallclock => start.elapsed(),
+        }
+    }
+
+    /// Waits until the next operation is due when running at a target rate.
+    /// Happens outside of the `InstrumentedReader`, so it is never measured.
+    fn pace(&mut self) {
+        if let Some(pacer) = &mut self.pacer {
+            pacer.wait();
+        }
+    }
+
+    /// Executes a read operation, retrying it on failure, and returns its
+    /// latency, staleness, and the number of rows it read. The latency is
+    /// that of the attempt that succeeded, plus that of the attempts that
+    /// failed before it with `latency_includes_retries`; backoff and
+    /// reconnects are never included. Returns the last error if every
+    /// attempt failed, in which case the time spent on the failed attempts
+    /// is charged to the experiment duration so that a broken database
+    /// cannot stall the benchmark.
+    ///
+    /// With `reconnect_every`, the client first reconnects when `operations`,
+    /// the number of operations it has run so far, is a multiple of it, and
+    /// again before each retry.
+    fn attempt(&mut self, operation: ReadOperation, operations: u64) -> Result<Measurement> {
+        let start = Instant::now();
+        let experiment_duration = self.reader.experiment_duration;
+        self.reader.failed_attempts = Duration::ZERO;
+        let reconnect = self
+            .config
+            .reconnect_every
+            .is_some_and(|every| operations.is_multiple_of(every as u64));
+        let result = with_retries(self.config.max_retries, || {
+            let reconnect = if reconnect {
+                Some(self.reader.reconnect()?)
+            } else {
+                None
+            };
+            let (latency, stale, rows) = self.execute(operation)?;
+            Ok(Measurement {
+                latency,
+                stale,
+                reconnect,
+                rows,
+            })
+        });
+        match result {
+            Ok(mut measurement) => {
+                if self.config.latency_includes_retries {
+                    measurement.latency += self.reader.failed_attempts;
+                }
+                Ok(measurement)
+            }
+            Err(e) => {
+                self.reader.experiment_duration = experiment_duration + start.elapsed();
+                Err(e)
+            }
+        }
+    }
+
+    /// Counts a read operation that failed after the warmup, either as a
+    /// timeout or as an error.
+    fn record_error(&self, error: &anyhow::Error) {
+        if self.reader.handle.is_timeout(error) {
+            self.errors.record_timeout(error);
+        } else {
+            self.errors.record(error);
+        }
+    }
+
+    /// Pauses between read operations. Think time happens outside of the
+    /// `InstrumentedReader`, so it never counts towards the experiment duration.
+    fn think(&mut self) {
+        let mut pause = self.config.think_time;
+        if !self.config.think_time_jitter.is_zero() {
+            pause += self
+                .rng
+                .gen_range(Duration::ZERO..=self.config.think_time_jitter);
+        }
+
+        if !pause.is_zero() {
+            thread::sleep(pause);
+        }
+    }
+
+    /// Executes a read operation once, returning its latency, its staleness
+    /// if it checked row freshness, and otherwise the number of rows it read.
+    fn execute(
+        &mut self,
+        operation: ReadOperation,
+    ) -> Result<(Duration, Option<bool>, Option<u64>)> {
+        let (measurement, rows) = match operation {
+            ReadOperation::CustomerPortfolio => {
+                let customer_id = self.ids.customer_id(&mut self.rng);
+                self.reader.customer_portfolio(customer_id)?
+            }
+            ReadOperation::TopPerformers => self.reader.top_performers()?,
+            ReadOperation::MarketOverview => {
+                let sector = self.ids.sector(&mut self.rng);
+                self.reader.market_overview(&sector)?
+            }
+            ReadOperation::RecentLargeTrades => {
+                let account_id = self.ids.account_id(&mut self.rng);
+                self.reader.recent_large_trades(account_id)?
+            }
+            ReadOperation::CustomerOrderBook => {
+                let customer_id = self.ids.customer_id(&mut self.rng);
+                self.reader.customer_order_book(customer_id)?
+            }
+            ReadOperation::SectorPerformance => {
+                let sector = self.ids.sector(&mut self.rng);
+                self.reader.sector_performance(sector)?
+            }
+            ReadOperation::AccountActivitySummary => {
+                let account_id = self.ids.account_id(&mut self.rng);
+                self.reader.account_activity_summary(account_id)?
+            }
+            ReadOperation::DailyMarketMovements => {
+                let security_id = self.ids.security_id(&mut self.rng);
+                self.reader.daily_market_movements(security_id)?
+            }
+            ReadOperation::HighValueCustomers => self.reader.high_value_customers()?,
+            ReadOperation::PendingOrdersSummary => {
+                let ticker = self.ids.ticker(&mut self.rng);
+                self.reader.pending_orders_summary(&ticker)?
+            }
+            ReadOperation::TradeVolumeByHour => self.reader.trade_volume_by_hour()?,
+            ReadOperation::TopSecuritiesBySector => {
+                let sector = self.ids.sector(&mut self.rng);
+                self.reader.top_securities_by_sector(sector)?
+            }
+            ReadOperation::RecentTradesByAccount => {
+                let account_id = self.ids.account_id(&mut self.rng);
+                self.reader.recent_trades_by_account(account_id)?
+            }
+            ReadOperation::OrderFulfillmentRates => {
+                let customer_id = self.ids.customer_id(&mut self.rng);
+                self.reader.order_fulfillment_rates(customer_id)?
+            }
+            ReadOperation::SectorOrderActivity => {
+                let sector = self.ids.sector(&mut self.rng);
+                self.reader.sector_order_activity(sector)?
+            }
+            ReadOperation::CascadingOrderCancellationAlert => {
+                self.reader.cascading_order_cancellation_alert()?
+            }
+            ReadOperation::RowFreshness => return self.check_row_freshness(),
+        };
+
+        Ok((measurement, None, Some(rows)))
+    }
+
+    /// Checks whether the read replica reflects the market data price most
+    /// recently inserted into the primary database.
+    fn check_row_freshness(&mut self) -> Result<(Duration, Option<bool>, Option<u64>)> {
+        match self.freshness.latest() {
+            Some(write) => {
+                let (measurement, stale) = self
+                    .reader
+                    .check_row_freshness(write.security_id, write.price)?;
+                Ok((measurement, Some(stale), None))
+            }
+            // Until market data has been inserted, a random security is read
+            // so that the check costs the same, but its result is not counted.
+            None => {
+                let security_id = self.ids.security_id(&mut self.rng);
+                let (measurement, _) = self.reader.check_row_freshness(security_id, NO_PRICE)?;
+                Ok((measurement, None, None))
+            }
+        }
+    }
+}
+
+/// `Pacer` spaces operations evenly to hold a client at a target rate. A client
+/// that falls behind does not burst to catch up, so the achieved rate reflects
+/// what the read replica can sustain.
+struct Pacer {
+    interval: Duration,
+    next: Instant,
+}
+
+impl Pacer {
+    fn new(tps: f64) -> Self {
+        Self {
+            interval: Duration::from_secs_f64(1.0 / tps),
+            next: Instant::now(),
+        }
+    }
+
+    fn wait(&mut self) {
+        let now = Instant::now();
+        if self.next > now {
+            thread::sleep(self.next - now);
+        }
+        self.next = self.next.max(now) + self.interval;
     }
 }
 
 struct ExperimentProgressBar {
     pb: ProgressBar,
+    label: String,
     offset: Duration,
 }
 
 impl ExperimentProgressBar {
     fn new(pb: ProgressBar) -> Self {
         Self {
+            label: pb.message(),
             pb,
             offset: Duration::from_secs(0),
         }
     }
 
+    fn start_warmup(&self) {
+        self.pb.set_message(format!("{} (warmup)", self.label));
+    }
+
+    fn finish_warmup(&self) {
+        self.pb.set_message(self.label.clone());
+        self.pb.reset();
+    }
+
     fn inc(&mut self, duration: Duration) {
         self.offset += duration;
         if self.offset.as_secs() > 0 {
@@ -133,17 +505,116 @@ impl ExperimentProgressBar {
         }
     }
 
+    fn inc_operation(&self) {
+        self.pb.inc(1);
+    }
+
     fn finish(&self) {
         self.pb.finish()
     }
 }
 
+/// `SummaryProgressBar` displays a single progress bar in place of one bar per
+/// client. Each client still reports to its own hidden progress bar, and the
+/// summary follows whichever client has made the least progress.
+pub struct SummaryProgressBar {
+    pb: ProgressBar,
+    clients: Vec<ProgressBar>,
+    updated: Instant,
+}
+
+impl SummaryProgressBar {
+    /// How often the summary is recomputed from the client progress bars.
+    const UPDATE_INTERVAL: Duration = Duration::from_millis(100);
+
+    pub fn new(pb: ProgressBar) -> Self {
+        Self {
+            pb,
+            clients: vec![],
+            updated: Instant::now(),
+        }
+    }
+
+    /// Returns a hidden progress bar of length `len` for a client to report to.
+    pub fn add_client(&mut self, len: u64) -> ProgressBar {
+        let pb = ProgressBar::hidden();
+        pb.set_length(len);
+        self.clients.push(pb.clone());
+        pb
+    }
+
+    pub fn update(&mut self) {
+        if self.updated.elapsed() < Self::UPDATE_INTERVAL {
+            return;
+        }
+        self.updated = Instant::now();
+
+        let progress = self
+            .clients
+            .iter()
+            .map(|pb| match pb.length() {
+                Some(len) if len > 0 => pb.position() as f64 / len as f64,
+                _ => 1.0,
+            })
+            .fold(1.0, f64::min);
+        let len = self.pb.length().unwrap_or(0);
+        self.pb.set_position((progress * len as f64) as u64);
+    }
+
+    pub fn finish(&self) {
+        self.pb.finish()
+    }
+}
+
+/// `ProgressBars` owns the progress bars drawn while the read clients run.
+/// When it is dropped, on every way out of the benchmark, bars that never
+/// finished because a client or the benchmark failed are cleared instead of
+/// being left half-drawn, nothing is drawn afterwards, and the cursor is shown.
+pub struct ProgressBars {
+    progress: MultiProgress,
+    bars: Vec<ProgressBar>,
+}
+
+impl ProgressBars {
+    pub fn new(progress: MultiProgress) -> Self {
+        Self {
+            progress,
+            bars: vec![],
+        }
+    }
+
+    /// Draws `pb` below the bars added before it.
+    pub fn add(&mut self, pb: ProgressBar) -> ProgressBar {
+        let pb = self.progress.add(pb);
+        self.bars.push(pb.clone());
+        pb
+    }
+}
+
+impl Drop for ProgressBars {
+    fn drop(&mut self) {
+        for pb in &self.bars {
+            if !pb.is_finished() {
+                pb.finish_and_clear();
+            }
+        }
+        self.progress.set_draw_target(ProgressDrawTarget::hidden());
+        let term = Term::stderr();
+        if term.is_term() {
+            let _ = term.show_cursor();
+        }
+    }
+}
+
 /// `InstrumentedReader` wraps a `ReadReplica` and times individual read operations.
 /// It’s used in benchmarking to accurately measure how long each operation takes,
-/// without including time spent on other tasks like querying the primary database.
+/// without including time spent on other tasks like pacing or thinking.
 struct InstrumentedReader<R> {
     handle: R,
     experiment_duration: Duration,
+    /// The time spent on read operations that failed since it was last
+    /// reset, which `ReaderSimulator` does before every retried operation.
+    failed_attempts: Duration,
 }
 
 impl<R> InstrumentedReader<R> {
@@ -151,128 +622,316 @@ impl<R> InstrumentedReader<R> {
         Self {
             handle: reader,
             experiment_duration: Duration::from_secs(0),
+            failed_attempts: Duration::ZERO,
         }
     }
 }
 
 impl<R: ReadReplica> InstrumentedReader<R> {
-    fn customer_portfolio(&mut self, customer_id: i32) -> Result<Duration> {
+    /// Reconnects to the read replica and returns how long it took. The time
+    /// is reported separately, so it does not count towards the experiment
+    /// duration.
+    fn reconnect(&mut self) -> Result<Duration> {
         let start = Instant::now();
-        self.handle.customer_portfolio(customer_id)?;
-        let duration = start.elapsed();
-        self.experiment_duration += duration;
-        Ok(duration)
+        self.handle.reconnect()?;
+        Ok(start.elapsed())
     }
 
-    fn top_performers(&mut self) -> Result<Duration> {
+    /// Runs `operation` and returns how long it took. The time counts towards
+    /// the experiment duration if it succeeded, and towards `failed_attempts`
+    /// otherwise.
+    fn time<T>(&mut self, operation: impl FnOnce(&mut R) -> Result<T>) -> Result<(Duration, T)> {
         let start = Instant::now();
-        self.handle.top_performers()?;
+        let result = operation(&mut self.handle);
         let duration = start.elapsed();
-        self.experiment_duration += duration;
-        Ok(duration)
+        match result {
+            Ok(value) => {
+                self.experiment_duration += duration;
+                Ok((duration, value))
+            }
+            Err(e) => {
+                self.failed_attempts += duration;
+                Err(e)
+            }
+        }
     }
 
-    fn market_overview(&mut self, sector: &str) -> Result<Duration> {
-        let start = Instant::now();
-        self.handle.market_overview(sector)?;
-        let duration = start.elapsed();
-        self.experiment_duration += duration;
-        Ok(duration)
+    fn customer_portfolio(&mut self, customer_id: i32) -> Result<(Duration, u64)> {
+        self.time(|handle| handle.customer_portfolio(customer_id))
     }
 
-    fn recent_large_trades(&mut self, account_id: i32) -> Result<Duration> {
-        let start = Instant::now();
-        self.handle.recent_large_trades(account_id)?;
-        let duration = start.elapsed();
-        self.experiment_duration += duration;
-        Ok(duration)
+    fn top_performers(&mut self) -> Result<(Duration, u64)> {
+        self.time(|handle| handle.top_performers())
     }
 
-    fn customer_order_book(&mut self, customer_id: i32) -> Result<Duration> {
-        let start = Instant::now();
-        self.handle.customer_order_book(customer_id)?;
-        let duration = start.elapsed();
-        self.experiment_duration += duration;
-        Ok(duration)
+    fn market_overview(&mut self, sector: &str) -> Result<(Duration, u64)> {
+        self.time(|handle| handle.market_overview(sector))
     }
 
-    fn sector_performance(&mut self, sector: String) -> Result<Duration> {
-        let start = Instant::now();
-        self.handle.sector_performance(sector)?;
-        let duration = start.elapsed();
-        self.experiment_duration += duration;
-        Ok(duration)
+    fn recent_large_trades(&mut self, account_id: i32) -> Result<(Duration, u64)> {
+        self.time(|handle| handle.recent_large_trades(account_id))
     }
 
-    fn account_activity_summary(&mut self, account_id: i32) -> Result<Duration> {
-        let start = Instant::now();
-        self.handle.account_activity_summary(account_id)?;
-        let duration = start.elapsed();
-        self.experiment_duration += duration;
-        Ok(duration)
+    fn customer_order_book(&mut self, customer_id: i32) -> Result<(Duration, u64)> {
+        self.time(|handle| handle.customer_order_book(customer_id))
     }
 
-    fn daily_market_movements(&mut self, security_id: i32) -> Result<Duration> {
-        let start = Instant::now();
-        self.handle.daily_market_movements(security_id)?;
-        let duration = start.elapsed();
-        self.experiment_duration += duration;
-        Ok(duration)
+    fn sector_performance(&mut self, sector: String) -> Result<(Duration, u64)> {
+        self.time(|handle| handle.sector_performance(sector))
     }
 
-    fn high_value_customers(&mut self) -> Result<Duration> {
-        let start = Instant::now();
-        self.handle.high_value_customers()?;
-        let duration = start.elapsed();
-        self.experiment_duration += duration;
-        Ok(duration)
+    fn account_activity_summary(&mut self, account_id: i32) -> Result<(Duration, u64)> {
+        self.time(|handle| handle.account_activity_summary(account_id))
     }
 
-    fn pending_orders_summary(&mut self, ticker: &str) -> Result<Duration> {
-        let start = Instant::now();
-        self.handle.pending_orders_summary(ticker)?;
-        let duration = start.elapsed();
-        self.experiment_duration += duration;
-        Ok(duration)
+    fn daily_market_movements(&mut self, security_id: i32) -> Result<(Duration, u64)> {
+        self.time(|handle| handle.daily_market_movements(security_id))
     }
 
-    fn trade_volume_by_hour(&mut self) -> Result<Duration> {
-        let start = Instant::now();
-        self.handle.trade_volume_by_hour()?;
-        let duration = start.elapsed();
-        self.experiment_duration += duration;
-        Ok(duration)
+    fn high_value_customers(&mut self) -> Result<(Duration, u64)> {
+        self.time(|handle| handle.high_value_customers())
     }
 
-    fn top_securities_by_sector(&mut self, sector: String) -> Result<Duration> {
-        let start = Instant::now();
-        self.handle.top_securities_by_sector(sector)?;
-        let duration = start.elapsed();
-        self.experiment_duration += duration;
-        Ok(duration)
+    fn pending_orders_summary(&mut self, ticker: &str) -> Result<(Duration, u64)> {
+        self.time(|handle| handle.pending_orders_summary(ticker))
     }
 
-    fn recent_trades_by_account(&mut self, account_id: i32) -> Result<Duration> {
-        let start = Instant::now();
-        self.handle.recent_trades_by_account(account_id)?;
-        let duration = start.elapsed();
-        self.experiment_duration += duration;
-        Ok(duration)
+    fn trade_volume_by_hour(&mut self) -> Result<(Duration, u64)> {
+        self.time(|handle| handle.trade_volume_by_hour())
     }
 
-    fn order_fulfillment_rates(&mut self, customer_id: i32) -> Result<Duration> {
-        let start = Instant::now();
-        self.handle.order_fulfillment_rates(customer_id)?;
-        let duration = start.elapsed();
-        self.experiment_duration += duration;
-        Ok(duration)
+    fn top_securities_by_sector(&mut self, sector: String) -> Result<(Duration, u64)> {
+        self.time(|handle| handle.top_securities_by_sector(sector))
+    }
+
+    fn recent_trades_by_account(&mut self, account_id: i32) -> Result<(Duration, u64)> {
+        self.time(|handle| handle.recent_trades_by_account(account_id))
     }
 
-    fn sector_order_activity(&mut self, sector: String) -> Result<Duration> {
+    fn order_fulfillment_rates(&mut self, customer_id: i32) -> Result<(Duration, u64)> {
+        self.time(|handle| handle.order_fulfillment_rates(customer_id))
+    }
+
+    fn sector_order_activity(&mut self, sector: String) -> Result<(Duration, u64)> {
+        self.time(|handle| handle.sector_order_activity(sector))
+    }
+
+    fn cascading_order_cancellation_alert(&mut self) -> Result<(Duration, u64)> {
+        self.time(|handle| handle.cascading_order_cancellation_alert())
+    }
+
+    fn check_row_freshness(
+        &mut self,
+        security_id: i32,
+        expected_price: f64,
+    ) -> Result<(Duration, bool)> {
+        self.time(|handle| handle.check_row_freshness(security_id, expected_price))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::task_handle::new_task_handles;
+    use crate::testing::{FakeDatabase, FakeReplica};
+    use std::sync::mpsc::{self, Receiver};
+    use strum::IntoEnumIterator;
+
+    fn config(seed: u64, stop: StopCondition) -> ReaderConfig {
+        ReaderConfig {
+            stop,
+            duration_mode: DurationMode::Wallclock,
+            start_delay: Duration::ZERO,
+            warmup: Duration::ZERO,
+            seed,
+            read_operations: ReadOperation::iter().collect(),
+            read_weights: None,
+            think_time: Duration::ZERO,
+            think_time_jitter: Duration::ZERO,
+            reconnect_every: None,
+            max_retries: 0,
+            latency_includes_retries: false,
+            fail_on_error: false,
+            target_tps: None,
+            comparison: false,
+        }
+    }
+
+    /// Creates a client against `reader`, returning it with the samples it
+    /// records and the errors it counts.
+    fn simulator<R: ReadReplica>(
+        reader: R,
+        config: ReaderConfig,
+    ) -> (ReaderSimulator<'static, R>, Receiver<Sample>, ErrorCounter) {
+        let mut rng = StdRng::seed_from_u64(0);
+        let ids = Arc::new(IdCache::load(&mut FakeDatabase, &mut rng).unwrap());
+        let (timings, samples) = mpsc::channel();
+        let (handle, _tracker) = new_task_handles();
+        let errors = ErrorCounter::default();
+        let simulator = ReaderSimulator::new(
+            reader,
+            ids,
+            config,
+            timings,
+            FreshnessChannel::default(),
+            ProgressBar::hidden(),
+            errors.clone(),
+            handle,
+        );
+        (simulator, samples, errors)
+    }
+
+    /// Runs a client against `reader`, returning the operation of every
+    /// sample it recorded and the errors it counted.
+    fn run<R: ReadReplica>(reader: R, config: ReaderConfig) -> (Vec<ReadOperation>, ErrorCounter) {
+        let (mut simulator, samples, errors) = simulator(reader, config);
+        simulator.run().unwrap();
+        (
+            samples.try_iter().map(|sample| sample.operation).collect(),
+            errors,
+        )
+    }
+
+    #[test]
+    fn same_seed_runs_the_same_operations() {
+        let operations = |seed| {
+            run(
+                FakeReplica::default(),
+                config(seed, StopCondition::Operations(100)),
+            )
+            .0
+        };
+        let first = operations(7);
+        assert_eq!(first.len(), 100);
+        assert_eq!(first, operations(7));
+        assert_ne!(first, operations(8));
+    }
+
+    #[test]
+    fn only_the_read_operations_are_run() {
+        let read_operations = vec![ReadOperation::TopPerformers, ReadOperation::MarketOverview];
+        let config = ReaderConfig {
+            read_operations: read_operations.clone(),
+            ..config(0, StopCondition::Operations(20))
+        };
+        let (operations, _) = run(FakeReplica::default(), config);
+        assert_eq!(operations.len(), 20);
+        assert!(operations
+            .iter()
+            .all(|operation| read_operations.contains(operation)));
+        assert!(read_operations
+            .iter()
+            .all(|operation| operations.contains(operation)));
+    }
+
+    #[test]
+    fn warmup_failures_are_not_counted_as_errors() {
+        let reader = FakeReplica {
+            failures: 10,
+            ..FakeReplica::default()
+        };
+        let config = ReaderConfig {
+            warmup: Duration::from_millis(50),
+            ..config(0, StopCondition::Operations(20))
+        };
+        let (operations, errors) = run(reader, config);
+        assert_eq!(operations.len(), 20);
+        assert_eq!(errors.count(), 0);
+    }
+
+    #[test]
+    fn measured_failures_are_counted_as_errors() {
+        let reader = FakeReplica {
+            failures: 5,
+            ..FakeReplica::default()
+        };
+        let (operations, errors) = run(reader, config(0, StopCondition::Operations(20)));
+        assert_eq!(operations.len(), 15);
+        assert_eq!(errors.count(), 5);
+    }
+
+    #[test]
+    fn fail_on_error_stops_at_the_first_counted_failure() {
+        let reader = FakeReplica {
+            failures: 5,
+            ..FakeReplica::default()
+        };
+        let config = ReaderConfig {
+            fail_on_error: true,
+            ..config(0, StopCondition::Operations(20))
+        };
+        let (mut simulator, samples, errors) = simulator(reader, config);
+        let error = simulator.run().unwrap_err();
+        assert!(error.to_string().ends_with(" failed"));
+        assert_eq!(errors.count(), 1);
+        assert_eq!(samples.try_iter().count(), 0);
+    }
+
+    /// Returns the latency of a read operation whose first attempt fails
+    /// after `FAILURE_DELAY` and whose retry succeeds.
+    fn retried_latency(latency_includes_retries: bool) -> Duration {
+        const FAILURE_DELAY: Duration = Duration::from_millis(50);
+        let reader = FakeReplica {
+            failures: 1,
+            failure_delay: FAILURE_DELAY,
+            ..FakeReplica::default()
+        };
+        let config = ReaderConfig {
+            max_retries: 1,
+            latency_includes_retries,
+            ..config(0, StopCondition::Operations(1))
+        };
+        let (mut simulator, _, _) = simulator(reader, config);
+        let measurement = simulator.attempt(ReadOperation::TopPerformers, 0).unwrap();
+        assert_eq!(simulator.reader.handle.calls.len(), 2);
+        // The failed attempt never counts towards the experiment duration.
+        assert!(simulator.reader.experiment_duration < FAILURE_DELAY);
+        measurement.latency
+    }
+
+    #[test]
+    fn latency_includes_retries_only_when_asked() {
+        assert!(retried_latency(false) < Duration::from_millis(50));
+        assert!(retried_latency(true) >= Duration::from_millis(50));
+    }
+
+    #[test]
+    fn rows_are_measured_except_for_freshness_checks() {
+        let reader = FakeReplica {
+            rows: 5,
+            ..FakeReplica::default()
+        };
+        let (mut simulator, _, _) = simulator(reader, config(0, StopCondition::Operations(1)));
+        let mut rows = |operation| simulator.attempt(operation, 0).unwrap().rows;
+        assert_eq!(rows(ReadOperation::TopPerformers), Some(5));
+        assert_eq!(rows(ReadOperation::RowFreshness), None);
+    }
+
+    #[test]
+    fn clients_wait_out_their_start_delay() {
+        let config = ReaderConfig {
+            start_delay: Duration::from_millis(100),
+            ..config(0, StopCondition::Operations(1))
+        };
         let start = Instant::now();
-        self.handle.sector_order_activity(sector)?;
-        let duration = start.elapsed();
-        self.experiment_duration += duration;
-        Ok(duration)
+        let (operations, _) = run(FakeReplica::default(), config);
+        assert_eq!(operations.len(), 1);
+        assert!(start.elapsed() >= Duration::from_millis(100));
+    }
+
+    #[test]
+    fn dropping_the_progress_bars_clears_unfinished_ones() {
+        let m = MultiProgress::with_draw_target(ProgressDrawTarget::hidden());
+        let mut bars = ProgressBars::new(m.clone());
+        let finished = bars.add(ProgressBar::new(10));
+        finished.set_position(10);
+        finished.finish();
+        let failed = bars.add(ProgressBar::new(10));
+        failed.set_position(3);
+
+        drop(bars);
+        assert!(failed.is_finished());
+        assert_eq!(finished.position(), 10);
+        assert!(m.is_hidden());
     }
 }
@@ -0,0 +1,221 @@
+use crate::measurements::Latencies;
+use crate::task_handle::TaskHandle;
+use crate::{PrimaryDatabase, ReadReplica};
+use anyhow::{Context, Result};
+use std::sync::{Arc, Mutex};
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+/// How long a client polls the read replica for a customer it inserted
+/// before counting the customer as not visible.
+pub const VISIBILITY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long to wait between polls of the read replica.
+const POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+/// The address of every customer inserted by a read-your-writes check.
+const ADDRESS: &str = "read-your-writes";
+
+/// The outcomes of the read-your-writes checks made by the read clients.
+#[derive(Default)]
+pub struct ReadYourWrites {
+    pub checks: usize,
+    /// The checks whose first read of the read replica found the customer.
+    pub visible: usize,
+    /// The checks that did not find the customer within `VISIBILITY_TIMEOUT`.
+    pub not_visible: usize,
+    /// The time from the commit of each customer to the read that found it.
+    pub delays: Latencies,
+}
+
+impl ReadYourWrites {
+    /// The fraction of checks whose first read found the customer,
+    /// or `None` if no checks were made.
+    pub fn success_rate(&self) -> Option<f64> {
+        (self.checks > 0).then(|| self.visible as f64 / self.checks as f64)
+    }
+}
+
+/// `ReadYourWritesResults` collects the outcomes of the checks made by every
+/// read client. Clones share the same results.
+#[derive(Clone, Default)]
+pub struct ReadYourWritesResults {
+    results: Arc<Mutex<ReadYourWrites>>,
+}
+
+impl ReadYourWritesResults {
+    /// Records a check that found the customer `delay` after it was
+    /// committed, on the first read if `first_read`, or that gave up
+    /// looking for it when `delay` is `None`.
+    fn record(&self, delay: Option<Duration>, first_read: bool) {
+        let mut results = self.results.lock().unwrap();
+        results.checks += 1;
+        match delay {
+            Some(delay) => {
+                results.visible += first_read as usize;
+                results.delays.push(delay);
+            }
+            None => results.not_visible += 1,
+        }
+    }
+
+    /// Takes the outcomes recorded so far.
+    pub fn take(&self) -> ReadYourWrites {
+        std::mem::take(&mut *self.results.lock().unwrap())
+    }
+}
+
+/// `ReadYourWritesCheck` makes a read client insert a customer into the
+/// primary database every so many read operations, and read it back from the
+/// read replica straight after the insert commits, polling until it becomes
+/// visible. The customer is looked up by the id the insert returned, and
+/// recognized by its name, which is unique to the client and the check.
+pub struct ReadYourWritesCheck<'a> {
+    primary: Box<dyn PrimaryDatabase + 'a>,
+    every: u32,
+    /// Tells apart the customers inserted by different clients.
+    client: String,
+    checks: u64,
+    results: ReadYourWritesResults,
+}
+
+impl<'a> ReadYourWritesCheck<'a> {
+    /// Checks every `every` read operations, with a `primary` connection of
+    /// the client's own. `client` names the client in the customers' names.
+    pub fn new(
+        primary: Box<dyn PrimaryDatabase + 'a>,
+        every: u32,
+        client: String,
+        results: ReadYourWritesResults,
+    ) -> Self {
+        Self {
+            primary,
+            every,
+            client,
+            checks: 0,
+            results,
+        }
+    }
+
+    /// Whether a check is due after `operations` read operations.
+    pub fn is_due(&self, operations: u64) -> bool {
+        operations > 0 && operations.is_multiple_of(self.every as u64)
+    }
+
+    /// Inserts a customer and polls `reader` until it is visible, the
+    /// benchmark is interrupted, or `VISIBILITY_TIMEOUT` passes. Only
+    /// completed checks are recorded; failed inserts and reads are returned.
+    pub fn run<R: ReadReplica>(&mut self, reader: &mut R, handle: &TaskHandle) -> Result<()> {
+        self.checks += 1;
+        let name = format!("{ADDRESS} {} {}", self.client, self.checks);
+        let customer_id = self
+            .primary
+            .insert_customer(&name, ADDRESS)
+            .context("failed to insert read-your-writes customer")?;
+        let committed = Instant::now();
+
+        let mut first_read = true;
+        loop {
+            let found = reader
+                .fetch_customer(customer_id)
+                .with_context(|| format!("failed to read customer {customer_id}"))?;
+            if found.as_deref() == Some(name.as_str()) {
+                self.results.record(Some(committed.elapsed()), first_read);
+                return Ok(());
+            }
+            if handle.is_cancelled() {
+                return Ok(());
+            }
+            if committed.elapsed() >= VISIBILITY_TIMEOUT {
+                self.results.record(None, false);
+                return Ok(());
+            }
+            first_read = false;
+            sleep(POLL_INTERVAL);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::task_handle::new_task_handles;
+    use crate::testing::{FakeDatabase, FakeReplica};
+
+    /// Runs one check against `reader` and returns its outcome.
+    fn check(reader: &mut FakeReplica) -> ReadYourWrites {
+        let results = ReadYourWritesResults::default();
+        let mut check = ReadYourWritesCheck::new(
+            Box::new(FakeDatabase),
+            10,
+            "0-0".to_string(),
+            results.clone(),
+        );
+        let (handle, _tracker) = new_task_handles();
+        check.run(reader, &handle).unwrap();
+        results.take()
+    }
+
+    #[test]
+    fn checks_are_due_every_n_operations() {
+        let results = ReadYourWritesResults::default();
+        let check = ReadYourWritesCheck::new(Box::new(FakeDatabase), 3, "0-0".to_string(), results);
+        let due: Vec<u64> = (0..10)
+            .filter(|&operations| check.is_due(operations))
+            .collect();
+        assert_eq!(due, [3, 6, 9]);
+    }
+
+    #[test]
+    fn write_visible_on_first_read_is_a_success() {
+        let mut reader = FakeReplica {
+            customer: Some("read-your-writes 0-0 1".to_string()),
+            ..FakeReplica::default()
+        };
+        let outcome = check(&mut reader);
+        assert_eq!(
+            (outcome.checks, outcome.visible, outcome.not_visible),
+            (1, 1, 0)
+        );
+        assert_eq!(outcome.success_rate(), Some(1.0));
+        assert_eq!(outcome.delays.len(), 1);
+        assert_eq!(reader.calls, ["fetch_customer"]);
+    }
+
+    #[test]
+    fn write_visible_after_polling_records_the_delay() {
+        let mut reader = FakeReplica {
+            customer: Some("read-your-writes 0-0 1".to_string()),
+            hidden_fetches: 3,
+            ..FakeReplica::default()
+        };
+        let outcome = check(&mut reader);
+        assert_eq!(
+            (outcome.checks, outcome.visible, outcome.not_visible),
+            (1, 0, 0)
+        );
+        assert_eq!(outcome.success_rate(), Some(0.0));
+        assert_eq!(outcome.delays.len(), 1);
+        assert_eq!(reader.calls.len(), 4);
+    }
+
+    #[test]
+    fn failed_read_is_not_recorded() {
+        let mut reader = FakeReplica {
+            failures: 1,
+            ..FakeReplica::default()
+        };
+        let results = ReadYourWritesResults::default();
+        let mut check = ReadYourWritesCheck::new(
+            Box::new(FakeDatabase),
+            1,
+            "0-0".to_string(),
+            results.clone(),
+        );
+        let (handle, _tracker) = new_task_handles();
+        assert!(check.run(&mut reader, &handle).is_err());
+        let outcome = results.take();
+        assert_eq!(outcome.checks, 0);
+        assert_eq!(outcome.success_rate(), None);
+    }
+}
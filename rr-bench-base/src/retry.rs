@@ -0,0 +1,311 @@
+use anyhow::Result;
+use clap::ArgMatches;
+use log::{debug, error, warn};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+/// The delay before the first retry. Each subsequent retry waits twice as long.
+const INITIAL_BACKOFF: Duration = Duration::from_millis(10);
+
+/// Runs `f`, retrying up to `max_retries` times with exponential backoff.
+/// Returns the last error if every attempt fails.
+pub fn with_retries<T>(max_retries: u32, mut f: impl FnMut() -> Result<T>) -> Result<T> {
+    let mut backoff = INITIAL_BACKOFF;
+    let mut attempt = 0;
+    loop {
+        match f() {
+            Ok(value) => return Ok(value),
+            Err(e) if attempt >= max_retries => return Err(e),
+            Err(e) => {
+                warn!(
+                    "retrying in {} (attempt {} of {}) after error: {e:#}",
+                    humantime::format_duration(backoff),
+                    attempt + 1,
+                    max_retries
+                );
+                sleep(backoff);
+                backoff *= 2;
+                attempt += 1;
+            }
+        }
+    }
+}
+
+/// The delay before the first conflict retry. Conflicting transactions are
+/// usually done within milliseconds, so this is much shorter than
+/// `INITIAL_BACKOFF`.
+const CONFLICT_BACKOFF: Duration = Duration::from_millis(2);
+
+/// Runs `f`, running it again up to `retries` times while it fails with an
+/// error that `is_conflict` accepts, first after `CONFLICT_BACKOFF` and then
+/// twice as long each time. Every retry is recorded with `errors`. Any other
+/// error, or the last conflict, is returned.
+pub fn with_conflict_retries<T>(
+    retries: u32,
+    errors: &ErrorCounter,
+    is_conflict: impl Fn(&anyhow::Error) -> bool,
+    mut f: impl FnMut() -> Result<T>,
+) -> Result<T> {
+    let mut backoff = CONFLICT_BACKOFF;
+    let mut attempt = 0;
+    loop {
+        match f() {
+            Ok(value) => return Ok(value),
+            Err(e) if attempt < retries && is_conflict(&e) => {
+                debug!(
+                    "retrying conflicting transaction in {} (attempt {} of {}): {e:#}",
+                    humantime::format_duration(backoff),
+                    attempt + 1,
+                    retries
+                );
+                errors.record_conflict_retry();
+                sleep(backoff);
+                backoff *= 2;
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// The longest `connect_with_retries` waits between attempts in total.
+pub const MAX_CONNECT_WAIT: Duration = Duration::from_secs(120);
+
+/// Connects to `database` with `connect`, retrying up to `retries` times,
+/// first after `backoff` and then twice as long each time. Gives up early
+/// with the last error once the next wait would take the time spent waiting
+/// past `MAX_CONNECT_WAIT`.
+pub fn connect_with_retries<T>(
+    database: &str,
+    retries: u32,
+    backoff: Duration,
+    mut connect: impl FnMut() -> Result<T>,
+) -> Result<T> {
+    let mut backoff = backoff;
+    let mut waited = Duration::ZERO;
+    let mut attempt = 0;
+    loop {
+        match connect() {
+            Ok(value) => return Ok(value),
+            Err(e) if attempt == 0 && retries == 0 => return Err(e),
+            Err(e) if attempt >= retries => {
+                return Err(e.context(format!(
+                    "failed to connect to the {database} after {} attempts",
+                    attempt + 1
+                )));
+            }
+            Err(e) if waited + backoff > MAX_CONNECT_WAIT => {
+                return Err(e.context(format!(
+                    "gave up connecting to the {database} after {} attempts, since \
+                     retrying would wait more than {} in total",
+                    attempt + 1,
+                    humantime::format_duration(MAX_CONNECT_WAIT)
+                )));
+            }
+            Err(e) => {
+                warn!(
+                    "failed to connect to the {database}, retrying in {} (attempt {} of {}): {e:#}",
+                    humantime::format_duration(backoff),
+                    attempt + 1,
+                    retries
+                );
+                let start = Instant::now();
+                sleep(backoff);
+                waited += start.elapsed();
+                backoff *= 2;
+                attempt += 1;
+            }
+        }
+    }
+}
+
+/// `ErrorCounter` counts operations that failed after exhausting their retries,
+/// read operations that were cancelled by `--query-timeout`, and write
+/// transactions that were retried with `--conflict-retries`. It is shared
+/// between every simulator so failures can be reported at the end of the
+/// benchmark instead of aborting it.
+#[derive(Clone, Default)]
+pub struct ErrorCounter {
+    count: Arc<AtomicUsize>,
+    timeouts: Arc<AtomicUsize>,
+    conflict_retries: Arc<AtomicUsize>,
+}
+
+impl ErrorCounter {
+    /// Records a failed operation. Only the first error is logged as an
+    /// error to avoid flooding the terminal when a database goes away.
+    pub fn record(&self, error: &anyhow::Error) {
+        if self.count.fetch_add(1, Ordering::SeqCst) == 0 {
+            error!("{:?}", error);
+            error!("continuing; further errors will only be counted, or logged at debug level");
+        } else {
+            debug!("{error:#}");
+        }
+    }
+
+    /// Records a read operation that exceeded `--query-timeout`. Timeouts are
+    /// expected from a struggling read replica, so they are only logged at
+    /// debug level.
+    pub fn record_timeout(&self, error: &anyhow::Error) {
+        self.timeouts.fetch_add(1, Ordering::SeqCst);
+        debug!("{error:#}");
+    }
+
+    /// Records a write transaction that is executed again after a
+    /// serialization failure or a deadlock.
+    pub fn record_conflict_retry(&self) {
+        self.conflict_retries.fetch_add(1, Ordering::SeqCst);
+    }
+
+    pub fn count(&self) -> usize {
+        self.count.load(Ordering::SeqCst)
+    }
+
+    pub fn timeouts(&self) -> usize {
+        self.timeouts.load(Ordering::SeqCst)
+    }
+
+    pub fn conflict_retries(&self) -> usize {
+        self.conflict_retries.load(Ordering::SeqCst)
+    }
+}
+
+/// Connects to `database` with `connect_with_retries`, retrying as often as
+/// the `--connect-retries` and `--connect-backoff` in `args` allow. For the
+/// backends that connect while they are built, e.g. to fill a writer pool.
+pub fn connect_with_retries_from<T>(
+    args: &ArgMatches,
+    database: &str,
+    connect: impl FnMut() -> Result<T>,
+) -> Result<T> {
+    connect_with_retries(
+        database,
+        *args.get_one::<u32>("connect_retries").unwrap(),
+        *args.get_one::<Duration>("connect_backoff").unwrap(),
+        connect,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Args;
+    use anyhow::bail;
+    use clap::Arg;
+
+    /// Connects with `connect_with_retries`, failing the first `failures`
+    /// attempts, and returns the result and the number of attempts made.
+    fn connect(failures: u32, retries: u32, backoff: Duration) -> (Result<()>, u32) {
+        let mut attempts = 0;
+        let result = connect_with_retries("read replica", retries, backoff, || {
+            attempts += 1;
+            if attempts <= failures {
+                bail!("connection refused");
+            }
+            Ok(())
+        });
+        (result, attempts)
+    }
+
+    /// Runs `with_conflict_retries` with `retries`, failing with each of
+    /// `failures` in turn, and returns the result and the number of attempts.
+    fn conflict(failures: &[&str], retries: u32, errors: &ErrorCounter) -> (Result<()>, usize) {
+        let mut attempts = 0;
+        let result = with_conflict_retries(
+            retries,
+            errors,
+            |e| e.to_string() == "deadlock detected",
+            || {
+                attempts += 1;
+                match failures.get(attempts - 1) {
+                    Some(failure) => bail!("{failure}"),
+                    None => Ok(()),
+                }
+            },
+        );
+        (result, attempts)
+    }
+
+    #[test]
+    fn conflicts_are_retried_and_counted() {
+        let errors = ErrorCounter::default();
+        let (result, attempts) = conflict(&["deadlock detected"; 2], 3, &errors);
+        assert!(result.is_ok());
+        assert_eq!(attempts, 3);
+        assert_eq!(errors.conflict_retries(), 2);
+        assert_eq!(errors.count(), 0);
+
+        // The last conflict is returned once the retries run out.
+        let (result, attempts) = conflict(&["deadlock detected"; 5], 2, &errors);
+        assert_eq!(result.unwrap_err().to_string(), "deadlock detected");
+        assert_eq!(attempts, 3);
+        assert_eq!(errors.conflict_retries(), 4);
+    }
+
+    #[test]
+    fn other_errors_are_not_retried_as_conflicts() {
+        let errors = ErrorCounter::default();
+        let (result, attempts) = conflict(&["connection reset"], 3, &errors);
+        assert_eq!(result.unwrap_err().to_string(), "connection reset");
+        assert_eq!(attempts, 1);
+        assert_eq!(errors.conflict_retries(), 0);
+    }
+
+    #[test]
+    fn connecting_is_retried() {
+        let (result, attempts) = connect(2, 3, Duration::from_millis(1));
+        assert!(result.is_ok());
+        assert_eq!(attempts, 3);
+    }
+
+    #[test]
+    fn connecting_fails_once_the_retries_run_out() {
+        let (result, attempts) = connect(5, 2, Duration::from_millis(1));
+        assert_eq!(
+            format!("{:#}", result.unwrap_err()),
+            "failed to connect to the read replica after 3 attempts: connection refused"
+        );
+        assert_eq!(attempts, 3);
+
+        // Without retries, the error is returned as is.
+        let (result, attempts) = connect(1, 0, Duration::from_millis(1));
+        assert_eq!(result.unwrap_err().to_string(), "connection refused");
+        assert_eq!(attempts, 1);
+    }
+
+    #[test]
+    fn connecting_is_retried_as_the_command_line_asks() {
+        let cli = Args::new(Vec::<Arg>::new()).parse_from(&[
+            "--duration",
+            "1s",
+            "--connect-retries",
+            "2",
+            "--connect-backoff",
+            "1ms",
+        ]);
+        let mut attempts = 0;
+        let result = connect_with_retries_from(&cli.matches, "primary database", || {
+            attempts += 1;
+            if attempts < 3 {
+                bail!("connection refused");
+            }
+            Ok(())
+        });
+        assert!(result.is_ok());
+        assert_eq!(attempts, 3);
+    }
+
+    #[test]
+    fn connecting_gives_up_before_waiting_too_long() {
+        // The first wait would already exceed the limit.
+        let (result, attempts) = connect(5, 10, MAX_CONNECT_WAIT + Duration::from_secs(1));
+        let error = result.unwrap_err();
+        assert!(error
+            .to_string()
+            .starts_with("gave up connecting to the read replica after 1 attempts"));
+        assert_eq!(format!("{:#}", error.root_cause()), "connection refused");
+        assert_eq!(attempts, 1);
+    }
+}
@@ -0,0 +1,81 @@
+use crate::measurements::Measurements;
+use crate::pretty_duration::PrettyDuration;
+use std::time::Duration;
+
+/// The exit code used when the benchmark completes but violates one of
+/// its service level thresholds, distinct from the exit code for errors.
+pub const SLA_VIOLATION_EXIT_CODE: i32 = 3;
+
+/// Optional thresholds that the benchmark results must meet. Thresholds
+/// that are not set are not evaluated.
+#[derive(Clone, Debug, Default)]
+pub struct Sla {
+    /// The maximum acceptable 99th percentile read latency.
+    pub max_p99: Option<Duration>,
+    /// The minimum acceptable read throughput.
+    pub min_tps: Option<f64>,
+}
+
+impl Sla {
+    /// Returns a description of every threshold the measurements violate.
+    pub fn violations(&self, measurements: &Measurements) -> Vec<String> {
+        let mut violations = vec![];
+
+        if let Some(max_p99) = self.max_p99 {
+            // A run without a single successful read cannot show
+            // that it met the threshold.
+            match measurements.latencies().percentile_99().map(Duration::from) {
+                Some(p99) if p99 <= max_p99 => {}
+                Some(p99) => violations.push(format!(
+                    "99th percentile latency {} exceeds --max-p99 {}",
+                    PrettyDuration::from(p99),
+                    humantime::format_duration(max_p99)
+                )),
+                None => violations.push(format!(
+                    "no read samples were collected to compare against --max-p99 {}",
+                    humantime::format_duration(max_p99)
+                )),
+            }
+        }
+
+        if let Some(min_tps) = self.min_tps {
+            let tps = measurements.tps();
+            if tps.is_nan() || tps < min_tps {
+                violations.push(format!(
+                    "throughput {:.2} TPS is below --min-tps {}",
+                    tps, min_tps
+                ));
+            }
+        }
+
+        violations
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::operations::ReadOperation;
+
+    fn max_p99(millis: u64) -> Sla {
+        Sla {
+            max_p99: Some(Duration::from_millis(millis)),
+            min_tps: None,
+        }
+    }
+
+    #[test]
+    fn max_p99_without_samples_is_a_violation() {
+        let measurements = Measurements::new(Duration::from_secs(1));
+        assert_eq!(max_p99(200).violations(&measurements).len(), 1);
+        assert!(Sla::default().violations(&measurements).is_empty());
+    }
+
+    #[test]
+    fn max_p99_compares_the_99th_percentile() {
+        let mut measurements = Measurements::new(Duration::from_secs(1));
+        measurements.push(ReadOperation::TopPerformers, Duration::from_millis(100));
+        assert!(max_p99(200).violations(&measurements).is_empty());
+        assert_eq!(max_p99(50).violations(&measurements).len(), 1);
+    }
+}
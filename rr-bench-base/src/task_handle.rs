@@ -1,18 +1,29 @@
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Arc;
 
 pub fn new_task_handles() -> (TaskHandle, TaskCompletion) {
     let count = Arc::new(AtomicUsize::new(1));
+    let cancelled = Arc::new(AtomicBool::new(false));
     (
         TaskHandle {
             count: count.clone(),
+            cancelled: cancelled.clone(),
         },
-        TaskCompletion { count },
+        TaskCompletion { count, cancelled },
     )
 }
 
 pub struct TaskHandle {
     count: Arc<AtomicUsize>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl TaskHandle {
+    /// Returns true once the benchmark has been interrupted and
+    /// the task should stop as soon as possible.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
 }
 
 impl Clone for TaskHandle {
@@ -20,6 +31,7 @@ impl Clone for TaskHandle {
         self.count.fetch_add(1, Ordering::SeqCst);
         Self {
             count: self.count.clone(),
+            cancelled: self.cancelled.clone(),
         }
     }
 }
@@ -30,12 +42,26 @@ impl Drop for TaskHandle {
     }
 }
 
+#[derive(Clone)]
 pub struct TaskCompletion {
     count: Arc<AtomicUsize>,
+    cancelled: Arc<AtomicBool>,
 }
 
 impl TaskCompletion {
+    /// Returns true once every `TaskHandle` has been dropped
+    /// or the benchmark has been interrupted.
     pub fn is_done(&self) -> bool {
-        self.count.load(Ordering::SeqCst) == 0
+        self.is_cancelled() || self.count.load(Ordering::SeqCst) == 0
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Signals every task to stop early. Returns true
+    /// if the benchmark had already been cancelled.
+    pub fn cancel(&self) -> bool {
+        self.cancelled.swap(true, Ordering::SeqCst)
     }
 }
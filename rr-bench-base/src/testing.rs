@@ -0,0 +1,162 @@
+//! Fake databases for unit tests.
+
+use crate::{PrimaryDatabase, ReadReplica, WriteOperation};
+use anyhow::{bail, Result};
+use rand::rngs::StdRng;
+use std::thread::sleep;
+use std::time::Duration;
+
+/// A primary database that finds a row in every table and discards
+/// every write.
+pub struct FakeDatabase;
+
+impl PrimaryDatabase for FakeDatabase {
+    fn get_random_customer_id(&mut self, _rng: &mut StdRng) -> Result<i32> {
+        Ok(1)
+    }
+
+    fn get_random_account_id(&mut self, _rng: &mut StdRng) -> Result<i32> {
+        Ok(1)
+    }
+
+    fn get_random_security_id(&mut self, _rng: &mut StdRng) -> Result<i32> {
+        Ok(1)
+    }
+
+    fn get_random_trade_id(&mut self, _rng: &mut StdRng) -> Result<i32> {
+        Ok(1)
+    }
+
+    fn get_random_order_id(&mut self, _rng: &mut StdRng) -> Result<i32> {
+        Ok(1)
+    }
+
+    fn get_random_market_data_id(&mut self, _rng: &mut StdRng) -> Result<i32> {
+        Ok(1)
+    }
+
+    fn get_random_ticker(&mut self, _rng: &mut StdRng) -> Result<String> {
+        Ok("ACME".to_string())
+    }
+
+    fn get_random_sector(&mut self, _rng: &mut StdRng) -> Result<String> {
+        Ok("Industrials".to_string())
+    }
+
+    fn execute_command(&self, _op: WriteOperation) -> Result<()> {
+        Ok(())
+    }
+
+    fn insert_customer(&self, _name: &str, _address: &str) -> Result<i32> {
+        Ok(1)
+    }
+}
+
+/// A read replica that records the name of every read method called on it.
+#[derive(Default)]
+pub struct FakeReplica {
+    pub calls: Vec<&'static str>,
+    /// The number of calls that fail before the first one succeeds.
+    pub failures: usize,
+    /// How long each failing call takes before it fails.
+    pub failure_delay: Duration,
+    /// The number of rows every read operation returns.
+    pub rows: u64,
+    /// The name `fetch_customer` returns for every customer, once
+    /// `hidden_fetches` calls have found no customer.
+    pub customer: Option<String>,
+    pub hidden_fetches: usize,
+}
+
+impl FakeReplica {
+    /// Records a call to `method` and returns the number of rows it read.
+    fn call(&mut self, method: &'static str) -> Result<u64> {
+        self.calls.push(method);
+        if self.failures > 0 {
+            self.failures -= 1;
+            sleep(self.failure_delay);
+            bail!("{method} failed");
+        }
+        Ok(self.rows)
+    }
+}
+
+impl ReadReplica for FakeReplica {
+    fn customer_portfolio(&mut self, _customer_id: i32) -> Result<u64> {
+        self.call("customer_portfolio")
+    }
+
+    fn top_performers(&mut self) -> Result<u64> {
+        self.call("top_performers")
+    }
+
+    fn market_overview(&mut self, _sector: &str) -> Result<u64> {
+        self.call("market_overview")
+    }
+
+    fn recent_large_trades(&mut self, _account_id: i32) -> Result<u64> {
+        self.call("recent_large_trades")
+    }
+
+    fn customer_order_book(&mut self, _customer_id: i32) -> Result<u64> {
+        self.call("customer_order_book")
+    }
+
+    fn sector_performance(&mut self, _sector: String) -> Result<u64> {
+        self.call("sector_performance")
+    }
+
+    fn account_activity_summary(&mut self, _account_id: i32) -> Result<u64> {
+        self.call("account_activity_summary")
+    }
+
+    fn daily_market_movements(&mut self, _security_id: i32) -> Result<u64> {
+        self.call("daily_market_movements")
+    }
+
+    fn high_value_customers(&mut self) -> Result<u64> {
+        self.call("high_value_customers")
+    }
+
+    fn pending_orders_summary(&mut self, _ticker: &str) -> Result<u64> {
+        self.call("pending_orders_summary")
+    }
+
+    fn trade_volume_by_hour(&mut self) -> Result<u64> {
+        self.call("trade_volume_by_hour")
+    }
+
+    fn top_securities_by_sector(&mut self, _sector: String) -> Result<u64> {
+        self.call("top_securities_by_sector")
+    }
+
+    fn recent_trades_by_account(&mut self, _account_id: i32) -> Result<u64> {
+        self.call("recent_trades_by_account")
+    }
+
+    fn order_fulfillment_rates(&mut self, _customer_id: i32) -> Result<u64> {
+        self.call("order_fulfillment_rates")
+    }
+
+    fn sector_order_activity(&mut self, _sector: String) -> Result<u64> {
+        self.call("sector_order_activity")
+    }
+
+    fn cascading_order_cancellation_alert(&mut self) -> Result<u64> {
+        self.call("cascading_order_cancellation_alert")
+    }
+
+    fn check_row_freshness(&mut self, _security_id: i32, _expected_price: f64) -> Result<bool> {
+        self.call("check_row_freshness")?;
+        Ok(true)
+    }
+
+    fn fetch_customer(&mut self, _customer_id: i32) -> Result<Option<String>> {
+        self.call("fetch_customer")?;
+        if self.hidden_fetches > 0 {
+            self.hidden_fetches -= 1;
+            return Ok(None);
+        }
+        Ok(self.customer.clone())
+    }
+}
@@ -0,0 +1,137 @@
+use crate::measurements::Latencies;
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::io::Write;
+use std::time::Duration;
+
+/// A row in the `--timeseries-out` CSV file, summarizing the reads
+/// completed within one second of the benchmark.
+#[derive(Serialize)]
+struct TimeSeriesRecord {
+    /// Seconds since the read clients were started, at the start of the second.
+    offset_secs: u64,
+    operations: usize,
+    tps: f64,
+    /// Empty when no reads completed within the second.
+    p95_us: Option<f64>,
+}
+
+/// Aggregates read latencies into one `TimeSeriesRecord` per second.
+///
+/// Samples are assigned to a second by the time they completed. A sample that
+/// arrives after a later second has already begun is counted in that second.
+pub struct TimeSeries<W: Write> {
+    writer: csv::Writer<W>,
+    /// The second currently being accumulated.
+    second: u64,
+    latencies: Latencies,
+}
+
+impl<W: Write> TimeSeries<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer: csv::Writer::from_writer(writer),
+            second: 0,
+            latencies: Latencies::default(),
+        }
+    }
+
+    /// Records a read that completed `offset` after the read clients started.
+    pub fn push(&mut self, offset: Duration, latency: Duration) -> Result<()> {
+        self.advance(offset)?;
+        self.latencies.push(latency);
+        Ok(())
+    }
+
+    /// Writes a row for every second that ended before `elapsed`,
+    /// including those in which no reads completed.
+    pub fn advance(&mut self, elapsed: Duration) -> Result<()> {
+        while self.second < elapsed.as_secs() {
+            self.write(Duration::from_secs(1))?;
+            self.second += 1;
+        }
+        Ok(())
+    }
+
+    /// Writes the rows up to `elapsed`, including the final partial second,
+    /// whose rate is computed over the part of it that has passed.
+    pub fn finish(mut self, elapsed: Duration) -> Result<()> {
+        self.advance(elapsed)?;
+        let partial = elapsed.saturating_sub(Duration::from_secs(self.second));
+        if !partial.is_zero() || !self.latencies.is_empty() {
+            self.write(partial)?;
+        }
+        self.writer
+            .flush()
+            .context("failed to flush the latency time series")
+    }
+
+    /// Writes the row for the current second, which lasted `length`,
+    /// and clears its latencies.
+    fn write(&mut self, length: Duration) -> Result<()> {
+        let operations = self.latencies.len();
+        let tps = if length.is_zero() {
+            0.0
+        } else {
+            operations as f64 / length.as_secs_f64()
+        };
+        self.writer
+            .serialize(TimeSeriesRecord {
+                offset_secs: self.second,
+                operations,
+                tps,
+                p95_us: self
+                    .latencies
+                    .percentile_95()
+                    .map(|p95| Duration::from(p95).as_secs_f64() * 1_000_000.0),
+            })
+            .context("failed to write latency time series")?;
+        self.latencies = Latencies::default();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn one_row_per_second_and_a_final_partial_second() {
+        let mut buffer = vec![];
+        let mut timeseries = TimeSeries::new(&mut buffer);
+        let ms = Duration::from_millis;
+        timeseries.push(ms(100), ms(10)).unwrap();
+        timeseries.push(ms(900), ms(20)).unwrap();
+        // Nothing completes in the second second.
+        timeseries.advance(ms(2100)).unwrap();
+        timeseries.push(ms(2200), ms(30)).unwrap();
+        timeseries.finish(ms(2500)).unwrap();
+
+        let csv = String::from_utf8(buffer).unwrap();
+        let rows: Vec<Vec<&str>> = csv.lines().map(|row| row.split(',').collect()).collect();
+        assert_eq!(rows[0], ["offset_secs", "operations", "tps", "p95_us"]);
+        assert_eq!(rows[1][..3], ["0", "2", "2.0"]);
+        assert_eq!(rows[2], ["1", "0", "0.0", ""]);
+        // The last 500ms had one read.
+        assert_eq!(rows[3][..3], ["2", "1", "2.0"]);
+        assert_eq!(rows.len(), 4);
+
+        // Latencies are recorded with 3 significant figures.
+        let p95 = |row: &[&str]| row[3].parse::<f64>().unwrap();
+        assert!((p95(&rows[1]) - 20_000.0).abs() < 20.0);
+        assert!((p95(&rows[3]) - 30_000.0).abs() < 30.0);
+    }
+
+    #[test]
+    fn no_partial_row_when_the_run_ends_on_a_second() {
+        let mut buffer = vec![];
+        let mut timeseries = TimeSeries::new(&mut buffer);
+        timeseries
+            .push(Duration::from_millis(500), Duration::from_millis(1))
+            .unwrap();
+        timeseries.finish(Duration::from_secs(1)).unwrap();
+
+        let csv = String::from_utf8(buffer).unwrap();
+        assert_eq!(csv.lines().count(), 2);
+    }
+}
@@ -1,23 +1,58 @@
 use fake::faker::address::raw::StreetName;
 use fake::faker::company::raw::{CompanyName, Industry};
 use fake::faker::name::raw::Name;
-use fake::locales::EN;
+use fake::locales::{Data, EN};
 use fake::Fake;
-use rand::distributions::Alphanumeric;
+use rand::distributions::{Alphanumeric, WeightedIndex};
 use rand::prelude::{SliceRandom, StdRng};
 use rand::{Rng, SeedableRng};
+use std::collections::HashSet;
+
+/// The number of distinct 4 character alphanumeric tickers. Once a few
+/// thousand securities have been generated, random tickers start to repeat,
+/// and are then made unique with a suffix; see `generate_ticker`.
+pub const TICKER_SPACE: u64 = 62u64.pow(4);
+
+/// The sectors that securities are generated in, in order of popularity
+/// when they are skewed with `DataGenerator::with_sector_skew`.
+pub const SECTORS: &[&str] = <EN as Data>::COMPANY_INDUSTRY;
 
 pub struct DataGenerator {
     rng: StdRng,
+    /// Every ticker generated so far.
+    tickers: HashSet<String>,
+    /// Picks the index of each security's sector in `SECTORS`, or `None`
+    /// to pick them uniformly.
+    sectors: Option<WeightedIndex<f64>>,
 }
 
 impl DataGenerator {
     pub fn new(seed: u64) -> Self {
         Self {
             rng: StdRng::seed_from_u64(seed),
+            tickers: HashSet::new(),
+            sectors: None,
         }
     }
 
+    /// Draws the sectors of generated securities from a Zipf distribution
+    /// over `SECTORS` with exponent `skew`: the sector of rank k is picked
+    /// with a probability proportional to 1 / k^skew, so a few sectors hold
+    /// most securities. A skew of 0 picks every sector equally often.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `skew` is negative or not finite.
+    pub fn with_sector_skew(mut self, skew: f64) -> Self {
+        assert!(
+            skew.is_finite() && skew >= 0.0,
+            "sector skew must be a non-negative number, got {skew}"
+        );
+        let weights = (1..=SECTORS.len()).map(|rank| (rank as f64).powf(-skew));
+        self.sectors = Some(WeightedIndex::new(weights).unwrap());
+        self
+    }
+
     pub fn generate_customer(&mut self) -> Customer {
         Customer {
             name: self.generate_name(),
@@ -52,7 +87,7 @@ impl DataGenerator {
         Order {
             order_type: self.generate_order_type(),
             quantity: self.rng.gen_range(1..1000),
-            limit_price: self.rng.gen_range(1..1000) as f64,
+            limit_price: self.generate_limit_price(),
             status: self.generate_status(),
         }
     }
@@ -77,13 +112,28 @@ impl DataGenerator {
     }
 
     fn generate_industry(&mut self) -> String {
-        Industry(EN).fake_with_rng(&mut self.rng)
+        match &self.sectors {
+            Some(sectors) => SECTORS[self.rng.sample(sectors)].to_string(),
+            None => Industry(EN).fake_with_rng(&mut self.rng),
+        }
     }
 
+    /// Returns a ticker that no earlier call returned: 4 random alphanumeric
+    /// characters, followed, if those were already used, by the number of
+    /// tickers generated before it. The suffix is unique, and the characters
+    /// before it always number 4, so suffixed tickers never clash either.
     fn generate_ticker(&mut self) -> String {
-        (0..4)
+        let ticker: String = (0..4)
             .map(|_| self.rng.sample(Alphanumeric) as char)
-            .collect()
+            .collect();
+        let ticker = if self.tickers.contains(&ticker) {
+            format!("{ticker}{}", self.tickers.len())
+        } else {
+            ticker
+        };
+        let inserted = self.tickers.insert(ticker.clone());
+        debug_assert!(inserted, "generated duplicate ticker {ticker}");
+        ticker
     }
 
     fn generate_trade_type(&mut self) -> String {
@@ -94,6 +144,15 @@ impl DataGenerator {
         ["buy", "sell"].choose(&mut self.rng).unwrap().to_string()
     }
 
+    /// Market orders, a fifth of all orders, have no limit price.
+    fn generate_limit_price(&mut self) -> Option<f64> {
+        if self.rng.gen_bool(0.2) {
+            None
+        } else {
+            Some(self.rng.gen_range(1..1000) as f64)
+        }
+    }
+
     fn generate_account_type(&mut self) -> String {
         ["Savings", "Checking", "Brokerage", "Investment"]
             .choose(&mut self.rng)
@@ -134,7 +193,7 @@ pub struct Trade {
 pub struct Order {
     pub order_type: String,
     pub quantity: i32,
-    pub limit_price: f64,
+    pub limit_price: Option<f64>,
     pub status: String,
 }
 
@@ -142,3 +201,72 @@ pub struct MarketData {
     pub price: f64,
     pub volume: i32,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn some_orders_are_market_orders() {
+        let mut gen = DataGenerator::new(0);
+        let market_orders = (0..1000)
+            .filter(|_| gen.generate_order().limit_price.is_none())
+            .count();
+        assert!((100..300).contains(&market_orders), "{market_orders}");
+    }
+
+    #[test]
+    fn tickers_are_unique() {
+        let mut gen = DataGenerator::new(0);
+        // Far more than the roughly sqrt(TICKER_SPACE) tickers after which
+        // random ones start to repeat.
+        let tickers: Vec<String> = (0..100_000).map(|_| gen.generate_ticker()).collect();
+        let unique: HashSet<&String> = tickers.iter().collect();
+        assert_eq!(unique.len(), tickers.len());
+        assert!(tickers.iter().any(|ticker| ticker.len() > 4));
+        assert!(tickers.iter().all(|ticker| ticker.len() >= 4));
+    }
+
+    /// Returns how many of `count` generated securities are in each sector,
+    /// most common first.
+    fn sector_counts(gen: &mut DataGenerator, count: usize) -> Vec<usize> {
+        let mut counts = vec![0; SECTORS.len()];
+        for _ in 0..count {
+            let sector = gen.generate_industry();
+            counts[SECTORS.iter().position(|s| *s == sector).unwrap()] += 1;
+        }
+        counts.sort_unstable_by(|a, b| b.cmp(a));
+        counts
+    }
+
+    #[test]
+    fn sector_skew_concentrates_securities_in_a_few_sectors() {
+        const SECURITIES: usize = 100_000;
+        let uniform = sector_counts(&mut DataGenerator::new(0), SECURITIES);
+        let top_uniform: usize = uniform[..5].iter().sum();
+        assert!(top_uniform < SECURITIES / 10, "{top_uniform}");
+
+        let mut gen = DataGenerator::new(0).with_sector_skew(1.2);
+        let skewed = sector_counts(&mut gen, SECURITIES);
+        let top_skewed: usize = skewed[..5].iter().sum();
+        assert!(top_skewed > SECURITIES / 2, "{top_skewed}");
+
+        // The most popular sector is the first, and holds about 1 / H(N, s)
+        // of the securities, where H is the generalized harmonic number.
+        let harmonic: f64 = (1..=SECTORS.len()).map(|k| (k as f64).powf(-1.2)).sum();
+        let expected = SECURITIES as f64 / harmonic;
+        assert!((skewed[0] as f64 - expected).abs() < expected * 0.05);
+        let first = (0..1000)
+            .filter(|_| gen.generate_industry() == SECTORS[0])
+            .count();
+        assert!(first > 200, "{first}");
+    }
+
+    #[test]
+    fn zero_sector_skew_is_uniform() {
+        let counts = sector_counts(&mut DataGenerator::new(0).with_sector_skew(0.0), 100_000);
+        let expected = 100_000 / SECTORS.len();
+        assert!(counts[0] < expected * 3 / 2, "{counts:?}");
+        assert!(counts[counts.len() - 1] > expected / 2, "{counts:?}");
+    }
+}
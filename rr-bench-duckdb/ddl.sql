@@ -0,0 +1,74 @@
+-- Table definitions for DuckDB.
+--
+-- DuckDB has no AUTOINCREMENT, so ids are drawn from sequences, and it
+-- does not support ON DELETE CASCADE, so the foreign keys are omitted
+-- to let the benchmark delete rows that are still referenced.
+
+CREATE SEQUENCE customers_seq;
+CREATE SEQUENCE accounts_seq;
+CREATE SEQUENCE trades_seq;
+CREATE SEQUENCE orders_seq;
+CREATE SEQUENCE market_data_seq;
+CREATE SEQUENCE securities_seq;
+
+CREATE TABLE customers (
+    customer_id INTEGER PRIMARY KEY DEFAULT nextval('customers_seq'),
+    name TEXT NOT NULL,
+    address TEXT,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE accounts (
+    account_id INTEGER PRIMARY KEY DEFAULT nextval('accounts_seq'),
+    customer_id INTEGER,
+    parent_account_id INTEGER,
+    account_type TEXT NOT NULL,
+    balance DECIMAL(18, 2) NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE trades (
+    trade_id INTEGER PRIMARY KEY DEFAULT nextval('trades_seq'),
+    account_id INTEGER,
+    security_id INTEGER,
+    parent_trade_id INTEGER,
+    trade_type TEXT NOT NULL CHECK (trade_type IN ('buy', 'sell')),
+    quantity INTEGER NOT NULL,
+    price DECIMAL(18, 4) NOT NULL,
+    trade_date TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE orders (
+    order_id INTEGER PRIMARY KEY DEFAULT nextval('orders_seq'),
+    account_id INTEGER,
+    security_id INTEGER,
+    parent_order_id INTEGER,
+    order_type TEXT NOT NULL CHECK (order_type IN ('buy', 'sell')),
+    quantity INTEGER NOT NULL,
+    limit_price DECIMAL(18, 4),
+    status TEXT NOT NULL CHECK (status IN ('pending', 'completed', 'canceled')),
+    order_date TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE market_data (
+    market_data_id INTEGER PRIMARY KEY DEFAULT nextval('market_data_seq'),
+    security_id INTEGER,
+    price DECIMAL(18, 4) NOT NULL,
+    volume INTEGER NOT NULL,
+    market_date TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE securities (
+    security_id INTEGER PRIMARY KEY DEFAULT nextval('securities_seq'),
+    ticker TEXT NOT NULL,
+    name TEXT,
+    sector TEXT,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Tokens written by the primary and polled on the read
+-- replica when measuring replication lag (--measure-lag).
+CREATE TABLE replication_lag_tokens (
+    token BIGINT PRIMARY KEY,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
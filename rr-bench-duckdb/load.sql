@@ -0,0 +1,18 @@
+-- Loads the CSV files written by rr-data-gen into a database
+-- created from ddl.sql. Run from the directory containing the CSVs.
+
+COPY customers FROM 'customers.csv' (HEADER);
+COPY accounts (account_id, customer_id, account_type, balance, created_at) FROM 'accounts.csv' (HEADER);
+COPY securities FROM 'securities.csv' (HEADER);
+COPY trades (trade_id, account_id, security_id, trade_type, quantity, price, trade_date) FROM 'trades.csv' (HEADER);
+COPY orders (order_id, account_id, security_id, order_type, quantity, limit_price, status, order_date) FROM 'orders.csv' (HEADER);
+COPY market_data FROM 'market_data.csv' (HEADER);
+
+-- DuckDB sequences cannot be reset, so advance each one
+-- past the largest id that was loaded.
+SELECT max(nextval('customers_seq')) FROM range((SELECT max(customer_id) FROM customers));
+SELECT max(nextval('accounts_seq')) FROM range((SELECT max(account_id) FROM accounts));
+SELECT max(nextval('securities_seq')) FROM range((SELECT max(security_id) FROM securities));
+SELECT max(nextval('trades_seq')) FROM range((SELECT max(trade_id) FROM trades));
+SELECT max(nextval('orders_seq')) FROM range((SELECT max(order_id) FROM orders));
+SELECT max(nextval('market_data_seq')) FROM range((SELECT max(market_data_id) FROM market_data));
@@ -0,0 +1,578 @@
+use anyhow::{bail, Context, Result};
+use duckdb::types::FromSql;
+use duckdb::{params, Connection, OptionalExt};
+use rr_bench_base::benchmark;
+use rr_bench_base::clap::{Arg, ArgMatches};
+use rr_bench_base::operations::WriteOperation;
+use rr_bench_base::rand::rngs::StdRng;
+use rr_bench_base::{Benchmark, Consume, Metrics, PrimaryDatabase, RandomIdCache, ReadReplica};
+
+/// The views created by --apply-schema.
+const VIEWS: &str = include_str!("../views.sql");
+
+fn main() {
+    benchmark(
+        || {
+            [Arg::new("dbpath")
+                .long("db-path")
+                .required(true)
+                .help("The path to the DuckDB database file")]
+        },
+        DuckDbBenchmark::new,
+    )
+}
+
+/// DuckDB only allows a database file to be opened once per process,
+/// so every client is a clone of the same underlying connection.
+struct DuckDbBenchmark {
+    conn: Connection,
+    view_prefix: String,
+    session_settings: Vec<(String, String)>,
+}
+
+struct DuckDbConnection {
+    conn: Connection,
+    ids: RandomIds,
+    view_prefix: String,
+}
+
+/// The cached id ranges used to pick random rows from each table.
+#[derive(Default)]
+struct RandomIds {
+    customers: RandomIdCache,
+    accounts: RandomIdCache,
+    securities: RandomIdCache,
+    trades: RandomIdCache,
+    orders: RandomIdCache,
+    market_data: RandomIdCache,
+}
+
+impl DuckDbBenchmark {
+    fn new(matches: ArgMatches) -> Result<Self> {
+        if *matches.get_one::<Consume>("consume").unwrap() != Consume::AllRows {
+            bail!("only --consume all-rows is supported by DuckDB");
+        }
+        if matches.contains_id("query_timeout") {
+            bail!("--query-timeout is not supported by DuckDB");
+        }
+        let dbpath = matches
+            .get_one::<String>("dbpath")
+            .context("missing required parameter db-path")?;
+        let conn = Connection::open(dbpath).context("failed to open DuckDB database")?;
+        Ok(Self {
+            conn,
+            view_prefix: matches
+                .get_one::<String>("view_prefix")
+                .cloned()
+                .unwrap_or_default(),
+            session_settings: matches
+                .get_many::<(String, String)>("session_setting")
+                .map(|settings| settings.cloned().collect())
+                .unwrap_or_default(),
+        })
+    }
+
+    fn connect(&self) -> Result<DuckDbConnection> {
+        let conn = self
+            .conn
+            .try_clone()
+            .context("failed to open DuckDB connection")?;
+        for (key, value) in &self.session_settings {
+            conn.execute_batch(&format!("SET {key} = '{}'", value.replace('\'', "''")))
+                .with_context(|| format!("failed to set {key}"))?;
+        }
+        Ok(DuckDbConnection {
+            conn,
+            ids: RandomIds::default(),
+            view_prefix: self.view_prefix.clone(),
+        })
+    }
+}
+
+impl Benchmark<'_> for DuckDbBenchmark {
+    type Writer = DuckDbConnection;
+    type Reader = DuckDbConnection;
+
+    fn primary_database(&self, _metrics: &Metrics) -> Result<Self::Writer> {
+        self.connect()
+    }
+
+    fn read_replica(&self) -> Result<Self::Reader> {
+        self.connect()
+    }
+
+    fn apply_schema(&self) -> Result<()> {
+        self.connect()?
+            .conn
+            .execute_batch(VIEWS)
+            .context("failed to create views")
+    }
+}
+
+/// Returns `column` from a random row of `table`, chosen by sampling an id from
+/// the cached id range and selecting the first row at or after it. When rows at
+/// the end of the range have been deleted, the last row before it is used instead.
+fn random_row<T: FromSql>(
+    conn: &Connection,
+    cache: &mut RandomIdCache,
+    rng: &mut StdRng,
+    table: &str,
+    id: &str,
+    column: &str,
+) -> Result<T> {
+    let sampled = cache.sample(rng, || {
+        conn.query_row(
+            &format!("SELECT MIN({id}), MAX({id}) FROM {table}"),
+            [],
+            |row| Ok(row.get::<_, Option<i32>>(0)?.zip(row.get(1)?)),
+        )
+        .with_context(|| format!("failed to retrieve the {id} range"))
+    })?;
+
+    let after = conn
+        .query_row(
+            &format!("SELECT {column} FROM {table} WHERE {id} >= ?1 ORDER BY {id} LIMIT 1"),
+            [sampled],
+            |row| row.get(0),
+        )
+        .optional()?;
+
+    match after {
+        Some(value) => Ok(value),
+        None => conn
+            .query_row(
+                &format!("SELECT {column} FROM {table} WHERE {id} < ?1 ORDER BY {id} DESC LIMIT 1"),
+                [sampled],
+                |row| row.get(0),
+            )
+            .map_err(Into::into),
+    }
+}
+
+impl PrimaryDatabase for DuckDbConnection {
+    fn get_random_customer_id(&mut self, rng: &mut StdRng) -> Result<i32> {
+        random_row(
+            &self.conn,
+            &mut self.ids.customers,
+            rng,
+            "customers",
+            "customer_id",
+            "customer_id",
+        )
+        .context("failed to retrieve customer_id")
+    }
+
+    fn get_random_account_id(&mut self, rng: &mut StdRng) -> Result<i32> {
+        random_row(
+            &self.conn,
+            &mut self.ids.accounts,
+            rng,
+            "accounts",
+            "account_id",
+            "account_id",
+        )
+        .context("failed to retrieve account_id")
+    }
+
+    fn get_random_security_id(&mut self, rng: &mut StdRng) -> Result<i32> {
+        random_row(
+            &self.conn,
+            &mut self.ids.securities,
+            rng,
+            "securities",
+            "security_id",
+            "security_id",
+        )
+        .context("failed to retrieve security_id")
+    }
+
+    fn get_random_trade_id(&mut self, rng: &mut StdRng) -> Result<i32> {
+        random_row(
+            &self.conn,
+            &mut self.ids.trades,
+            rng,
+            "trades",
+            "trade_id",
+            "trade_id",
+        )
+        .context("failed to retrieve trade_id")
+    }
+
+    fn get_random_order_id(&mut self, rng: &mut StdRng) -> Result<i32> {
+        random_row(
+            &self.conn,
+            &mut self.ids.orders,
+            rng,
+            "orders",
+            "order_id",
+            "order_id",
+        )
+        .context("failed to retrieve order_id")
+    }
+
+    fn get_random_market_data_id(&mut self, rng: &mut StdRng) -> Result<i32> {
+        random_row(
+            &self.conn,
+            &mut self.ids.market_data,
+            rng,
+            "market_data",
+            "market_data_id",
+            "market_data_id",
+        )
+        .context("failed to retrieve market_data_id")
+    }
+
+    fn get_random_ticker(&mut self, rng: &mut StdRng) -> Result<String> {
+        random_row(
+            &self.conn,
+            &mut self.ids.securities,
+            rng,
+            "securities",
+            "security_id",
+            "ticker",
+        )
+        .context("failed to retrieve ticker")
+    }
+
+    fn get_random_sector(&mut self, rng: &mut StdRng) -> Result<String> {
+        random_row(
+            &self.conn,
+            &mut self.ids.securities,
+            rng,
+            "securities",
+            "security_id",
+            "sector",
+        )
+        .context("failed to retrieve sector")
+    }
+
+    fn execute_command(&self, op: WriteOperation) -> Result<()> {
+        match op {
+            WriteOperation::InsertCustomer { name, address } => self.conn.execute(
+                "INSERT INTO customers (name, address) VALUES (?1, ?2)", params![name, address])
+                .map(|_| ())
+                .context("failed to insert customer"),
+            WriteOperation::InsertAccount { customer_id, account_type, balance, parent_account_id } => {
+                match parent_account_id {
+                    None => {
+                        self.conn.execute("INSERT INTO accounts (customer_id, account_type, balance) VALUES (?1, ?2, ?3)", params![customer_id, account_type, balance])
+                            .map(|_| ())
+                            .context("failed to insert account")
+                    }
+                    Some(parent_account_id) => {
+                        self.conn.execute("INSERT INTO accounts (customer_id, account_type, balance, parent_account_id) VALUES (?1, ?2, ?3, ?4)", params![customer_id, account_type, balance, parent_account_id])
+                            .map(|_| ())
+                            .context("failed to insert account")
+                    }
+                }
+            }
+            WriteOperation::InsertSecurity { ticker, name, sector } => {
+                self.conn.execute("INSERT INTO securities (ticker, name, sector) VALUES (?1, ?2, ?3)", params![ticker, name, sector])
+                    .map(|_| ())
+                    .context("failed to insert security")
+            }
+            WriteOperation::InsertTrade { account_id, security_id, trade_type, quantity, price, parent_trade_id } => {
+                match parent_trade_id {
+                    None =>
+                        self.conn.execute("INSERT INTO trades (account_id, security_id, trade_type, quantity, price) VALUES (?1, ?2, ?3, ?4, ?5)", params![account_id, security_id, trade_type, quantity, price])
+                            .map(|_| ())
+                            .context("failed to insert trades"),
+                    Some(parent_trade_id) => self.conn.execute("INSERT INTO trades (account_id, security_id, trade_type, quantity, price, parent_trade_id) VALUES (?1, ?2, ?3, ?4, ?5, ?6)", params![account_id, security_id, trade_type, quantity, price, parent_trade_id])
+                        .map(|_| ())
+                        .context("failed to insert trades")
+                }
+            }
+            WriteOperation::InsertOrder { account_id, security_id, order_type, quantity, limit_price,  status, parent_order_id} => {
+                match parent_order_id  {
+                    None => self.conn.execute("INSERT INTO orders (account_id, security_id, order_type, quantity, limit_price, status) VALUES (?1, ?2, ?3, ?4, ?5, ?6)", params![account_id, security_id, order_type, quantity, limit_price, status])
+                        .map(|_| ())
+                        .context("failed to insert order"),
+                    Some(parent_order_id) => self.conn.execute("INSERT INTO orders (account_id, security_id, order_type, quantity, limit_price, status, parent_order_id) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)", params![account_id, security_id, order_type, quantity, limit_price, status, parent_order_id])
+                        .map(|_| ())
+                        .context("failed to insert order"),
+                }
+            }
+            WriteOperation::InsertMarketData { security_id, price, volume } => self.conn.execute("INSERT INTO market_data (security_id, price, volume) VALUES (?1, ?2, ?3)", params![security_id, price, volume])
+                .map(|_| ())
+                .context("failed to insert market data"),
+            WriteOperation::UpdateCustomer { customer_id, address } => self.conn.execute("UPDATE customers SET address = ?1 WHERE customer_id = ?2", params![address, customer_id])
+                .map(|_| ())
+                .context("failed to update customer"),
+            WriteOperation::UpdateAccount { account_id, balance } => self.conn.execute("UPDATE accounts SET balance = ?1 WHERE account_id = ?2",
+                                                                                       params![balance, account_id])
+                .map(|_| ())
+                .context("failed to update account"),
+            WriteOperation::UpdateTrade { trade_id, price } => self.conn.execute("UPDATE trades SET price = ?1 WHERE trade_id = ?2",
+                                                                                 params![price, trade_id])
+                .map(|_| ())
+                .context("failed to update trades"),
+            WriteOperation::UpdateOrder { order_id, status, limit_price } => self.conn.execute("UPDATE orders SET status = ?1, limit_price = ?2 WHERE order_id = ?3",
+                                                                                               params![status, limit_price, order_id])
+                .map(|_| ())
+                .context("failed to update orders"),
+            WriteOperation::UpdateMarketData { market_data_id, price, volume } => self.conn.execute("UPDATE market_data SET price = ?1, volume = ?2, market_date = CURRENT_TIMESTAMP WHERE market_data_id = ?3",
+                                                                                                    params![price, volume, market_data_id])
+                .map(|_| ())
+                .context("failed to update market_data"),
+            WriteOperation::DeleteCustomer { customer_id } => self.conn.execute("DELETE FROM customers WHERE customer_id = ?1", params![customer_id])
+                .map(|_| ())
+                .context("failed to delete customer"),
+            WriteOperation::DeleteAccount { account_id } => self.conn.execute("DELETE FROM accounts WHERE account_id = ?1", params![account_id])
+                .map(|_| ())
+                .context("failed to delete accounts"),
+            WriteOperation::DeleteSecurity { security_id } => self.conn.execute("DELETE FROM securities WHERE security_id = ?1", params![security_id])
+                .map(|_| ())
+                .context("failed to delete security"),
+            WriteOperation::DeleteTrade { trade_id } => self.conn.execute("DELETE FROM trades WHERE trade_id = ?1", params![trade_id])
+                .map(|_| ())
+                .context("failed to delete trades"),
+            WriteOperation::DeleteOrder { order_id } => self.conn.execute("DELETE FROM orders WHERE order_id = ?1", params![order_id])
+                .map(|_| ())
+                .context("failed to delete orders"),
+            WriteOperation::DeleteMarketData { market_data_id } => self.conn.execute("DELETE FROM market_data WHERE market_data_id = ?1", params![market_data_id])
+                .map(|_| ())
+                .context("failed to delete market_data")
+        }
+    }
+
+    fn insert_lag_token(&self, token: i64) -> Result<()> {
+        self.conn
+            .execute(
+                "INSERT INTO replication_lag_tokens (token) VALUES (?1)",
+                params![token],
+            )
+            .map(|_| ())
+            .context("failed to insert replication lag token")
+    }
+
+    fn insert_customer(&self, name: &str, address: &str) -> Result<i32> {
+        self.conn
+            .query_row(
+                "INSERT INTO customers (name, address) VALUES (?1, ?2) RETURNING customer_id",
+                params![name, address],
+                |row| row.get(0),
+            )
+            .context("failed to insert customer")
+    }
+
+    fn database_size(&mut self) -> Result<u64> {
+        self.conn
+            .query_row(
+                "SELECT total_blocks * block_size FROM pragma_database_size()",
+                [],
+                |row| row.get::<_, i64>(0),
+            )
+            .map(|size| size as u64)
+            .context("failed to query database size")
+    }
+}
+
+/// Steps through every row of a read query, which runs it to completion,
+/// and returns the number of rows.
+fn count_rows(mut rows: duckdb::Rows<'_>) -> duckdb::Result<u64> {
+    let mut count = 0;
+    while rows.next()?.is_some() {
+        count += 1;
+    }
+    Ok(count)
+}
+
+impl ReadReplica for DuckDbConnection {
+    fn customer_portfolio(&mut self, customer_id: i32) -> Result<u64> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT * FROM {}customer_portfolio WHERE customer_id = ?1",
+            self.view_prefix
+        ))?;
+
+        stmt.query(params![customer_id])
+            .and_then(count_rows)
+            .with_context(|| format!("failed to query customer profile {customer_id}"))
+    }
+
+    fn top_performers(&mut self) -> Result<u64> {
+        let mut stmt = self
+            .conn
+            .prepare(&format!("SELECT * FROM {}top_performers", self.view_prefix))?;
+        stmt.query(params![])
+            .and_then(count_rows)
+            .with_context(|| "failed to query top_performers".to_string())
+    }
+
+    fn market_overview(&mut self, sector: &str) -> Result<u64> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT * FROM {}market_overview WHERE sector = ?1",
+            self.view_prefix
+        ))?;
+        stmt.query(params![sector])
+            .and_then(count_rows)
+            .with_context(|| "failed to query market_overview".to_string())
+    }
+
+    fn recent_large_trades(&mut self, account_id: i32) -> Result<u64> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT * FROM {}recent_large_trades WHERE account_id = ?1",
+            self.view_prefix
+        ))?;
+        stmt.query(params![account_id])
+            .and_then(count_rows)
+            .with_context(|| "failed to query recent_large_trades".to_string())
+    }
+
+    fn customer_order_book(&mut self, customer_id: i32) -> Result<u64> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT * FROM {}customer_order_book WHERE customer_id = ?1",
+            self.view_prefix
+        ))?;
+        stmt.query(params![customer_id])
+            .and_then(count_rows)
+            .with_context(|| format!("failed to query customer_order_book {customer_id}"))
+    }
+
+    fn sector_performance(&mut self, sector: String) -> Result<u64> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT * FROM {}sector_performance WHERE sector = ?1",
+            self.view_prefix
+        ))?;
+        stmt.query(params![sector])
+            .and_then(count_rows)
+            .with_context(|| "failed to query sector_performance".to_string())
+    }
+
+    fn account_activity_summary(&mut self, account_id: i32) -> Result<u64> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT * FROM {}account_activity_summary WHERE account_id = ?1",
+            self.view_prefix
+        ))?;
+        stmt.query(params![account_id])
+            .and_then(count_rows)
+            .with_context(|| format!("failed to query account_activity_summary {account_id}"))
+    }
+
+    fn daily_market_movements(&mut self, security_id: i32) -> Result<u64> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT * FROM {}daily_market_movements WHERE security_id = ?1",
+            self.view_prefix
+        ))?;
+        stmt.query(params![security_id])
+            .and_then(count_rows)
+            .with_context(|| format!("failed to query daily_market_movements {security_id}"))
+    }
+
+    fn high_value_customers(&mut self) -> Result<u64> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT * FROM {}high_value_customers",
+            self.view_prefix
+        ))?;
+        stmt.query(params![])
+            .and_then(count_rows)
+            .with_context(|| "failed to query high_value_customers".to_string())
+    }
+
+    fn pending_orders_summary(&mut self, ticker: &str) -> Result<u64> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT * FROM {}pending_orders_summary WHERE ticker = ?1",
+            self.view_prefix
+        ))?;
+        stmt.query(params![ticker])
+            .and_then(count_rows)
+            .with_context(|| "failed to query pending_orders_summary".to_string())
+    }
+
+    fn trade_volume_by_hour(&mut self) -> Result<u64> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT * FROM {}trade_volume_by_hour",
+            self.view_prefix
+        ))?;
+        stmt.query(params![])
+            .and_then(count_rows)
+            .with_context(|| "failed to query trade_volume_by_hour".to_string())
+    }
+
+    fn top_securities_by_sector(&mut self, sector: String) -> Result<u64> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT * FROM {}top_securities_by_sector WHERE sector = ?1",
+            self.view_prefix
+        ))?;
+        stmt.query(params![sector])
+            .and_then(count_rows)
+            .with_context(|| "failed to query top_securities_by_sector".to_string())
+    }
+
+    fn recent_trades_by_account(&mut self, account_id: i32) -> Result<u64> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT * FROM {}recent_trades_by_account WHERE account_id = ?1",
+            self.view_prefix
+        ))?;
+        stmt.query(params![account_id])
+            .and_then(count_rows)
+            .with_context(|| format!("failed to query recent_trades_by_account {account_id}"))
+    }
+
+    fn order_fulfillment_rates(&mut self, customer_id: i32) -> Result<u64> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT * FROM {}order_fulfillment_rates WHERE customer_id = ?1",
+            self.view_prefix
+        ))?;
+        stmt.query(params![customer_id])
+            .and_then(count_rows)
+            .with_context(|| format!("failed to query order_fulfillment_rates {customer_id}"))
+    }
+
+    fn sector_order_activity(&mut self, sector: String) -> Result<u64> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT * FROM {}sector_order_activity WHERE sector = ?1",
+            self.view_prefix
+        ))?;
+        stmt.query(params![sector])
+            .and_then(count_rows)
+            .with_context(|| "failed to query sector_order_activity".to_string())
+    }
+
+    fn cascading_order_cancellation_alert(&mut self) -> Result<u64> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT * FROM {}cascading_order_cancellation_alert",
+            self.view_prefix
+        ))?;
+        stmt.query(params![])
+            .and_then(count_rows)
+            .with_context(|| "failed to query cascading_order_cancellation_alert".to_string())
+    }
+
+    fn check_row_freshness(&mut self, security_id: i32, expected_price: f64) -> Result<bool> {
+        // Prices are stored with four decimal places.
+        self.conn
+            .query_row(
+                "SELECT EXISTS (SELECT 1 FROM market_data \
+                 WHERE security_id = ?1 AND abs(price - ?2) < 0.0001)",
+                params![security_id, expected_price],
+                |row| row.get::<_, bool>(0),
+            )
+            .map(|fresh| !fresh)
+            .with_context(|| format!("failed to check freshness of market data {security_id}"))
+    }
+
+    fn fetch_customer(&mut self, customer_id: i32) -> Result<Option<String>> {
+        self.conn
+            .query_row(
+                "SELECT name FROM customers WHERE customer_id = ?1",
+                params![customer_id],
+                |row| row.get(0),
+            )
+            .optional()
+            .with_context(|| format!("failed to fetch customer {customer_id}"))
+    }
+
+    fn ping(&mut self) -> Result<()> {
+        self.conn
+            .query_row("SELECT 1", [], |_| Ok(()))
+            .context("failed to ping the read replica")
+    }
+
+    fn max_observed_token(&mut self) -> Result<Option<i64>> {
+        self.conn
+            .query_row("SELECT MAX(token) FROM replication_lag_tokens", [], |row| {
+                row.get(0)
+            })
+            .context("failed to query replication lag token")
+    }
+}
@@ -0,0 +1,901 @@
+use anyhow::{bail, Context, Result};
+use mongodb::action::Action;
+use mongodb::bson::{doc, Bson, DateTime, Document};
+use mongodb::error::ErrorKind;
+use mongodb::options::{ClientOptions, ReturnDocument};
+use mongodb::sync::{Client, Collection, Database};
+use mongodb::IndexModel;
+use rr_bench_base::clap::{value_parser, Arg, ArgMatches};
+use rr_bench_base::log::info;
+use rr_bench_base::operations::WriteOperation;
+use rr_bench_base::rand::rngs::StdRng;
+use rr_bench_base::{
+    benchmark, reader_url, writer_url, Benchmark, Consume, Credentials, Metrics, PrimaryDatabase,
+    ReadReplica,
+};
+use rusqlite::types::ValueRef;
+use rusqlite::OpenFlags;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+mod pipelines;
+
+/// The collections loaded by --load-db from the tables of the same name,
+/// and the column of each table that becomes the `_id` of its documents.
+const COLLECTIONS: &[(&str, &str)] = &[
+    ("customers", "customer_id"),
+    ("accounts", "account_id"),
+    ("securities", "security_id"),
+    ("trades", "trade_id"),
+    ("orders", "order_id"),
+    ("market_data", "market_data_id"),
+];
+
+/// The fields the read operations look documents up by, which stand in
+/// for the foreign keys and indexes of the SQL schema.
+const INDEXES: &[(&str, &str)] = &[
+    ("accounts", "customer_id"),
+    ("trades", "account_id"),
+    ("trades", "security_id"),
+    ("orders", "account_id"),
+    ("orders", "security_id"),
+    ("market_data", "security_id"),
+    ("securities", "sector"),
+    ("securities", "ticker"),
+];
+
+/// The collection holding the counter new `_id`s of each collection are
+/// allocated from, in a document whose `_id` is the collection name.
+const COUNTERS: &str = "counters";
+
+/// The collection replication lag tokens are written to.
+const LAG_TOKENS: &str = "replication_lag_tokens";
+
+/// The number of documents --load-db inserts at a time.
+const LOAD_BATCH_SIZE: usize = 1000;
+
+/// The server error code of an operation that ran longer than its `maxTimeMS`.
+const MAX_TIME_MS_EXPIRED: i32 = 50;
+
+fn main() {
+    benchmark(
+        || {
+            [
+                Arg::new("writer")
+                    .long("writer-url")
+                    .help("The URL to the primary MongoDB server, including the database (e.g., mongodb://host:27017/bench). Defaults to $RR_BENCH_WRITER_URL"),
+                Arg::new("reader")
+                    .long("reader-url")
+                    .help("The URL to the replica MongoDB server, including the database. Add readPreference=secondary to read from a secondary of a replica set. Defaults to $RR_BENCH_READER_URL"),
+                Arg::new("credentials")
+                    .long("credentials")
+                    .value_name("PATH")
+                    .value_parser(value_parser!(PathBuf))
+                    .help("A file with user and password lines that replace the credentials in --writer-url and --reader-url"),
+                Arg::new("load_db")
+                    .long("load-db")
+                    .value_name("PATH")
+                    .value_parser(value_parser!(PathBuf))
+                    .help("Load the rows of a SQLite database written by rr-data-gen --keep-db into the primary before running the benchmark. The collections must be empty"),
+            ]
+        },
+        MongoBenchmark::new,
+    )
+}
+
+struct MongoBenchmark {
+    writer: Database,
+    reader_options: ClientOptions,
+    load_db: Option<PathBuf>,
+    query_timeout: Option<Duration>,
+    consume: Consume,
+}
+
+impl MongoBenchmark {
+    fn new(args: ArgMatches) -> Result<Self> {
+        if args.contains_id("view_prefix") {
+            bail!("--view-prefix is not supported by MongoDB");
+        }
+        if args.contains_id("session_setting") {
+            bail!("--session-setting is not supported by MongoDB");
+        }
+        let credentials = args
+            .get_one::<PathBuf>("credentials")
+            .map(|path| Credentials::load(path))
+            .transpose()?;
+
+        let (writer, writer_source) = writer_url(&args)?;
+        let writer = parse_url(&writer, writer_source, credentials.as_ref())?;
+        let writer = connect(writer)?;
+
+        let (reader, reader_source) = reader_url(&args)?;
+        let reader_options = parse_url(&reader, reader_source, credentials.as_ref())?;
+
+        Ok(Self {
+            writer,
+            reader_options,
+            load_db: args.get_one::<PathBuf>("load_db").cloned(),
+            query_timeout: args.get_one::<Duration>("query_timeout").copied(),
+            consume: *args.get_one::<Consume>("consume").unwrap(),
+        })
+    }
+}
+
+/// Parses the URL passed to `flag`, which must name a database, and replaces
+/// its user and password with those in `credentials`.
+fn parse_url(url: &str, flag: &str, credentials: Option<&Credentials>) -> Result<ClientOptions> {
+    let mut options = ClientOptions::parse(url)
+        .run()
+        .with_context(|| format!("invalid {flag}"))?;
+    if options.default_database.is_none() {
+        bail!("invalid {flag}: missing database, expected a URL like mongodb://host:27017/bench");
+    }
+    if let Some(credentials) = credentials {
+        let credential = options.credential.get_or_insert_with(Default::default);
+        if let Some(user) = credentials.user() {
+            credential.username = Some(user.to_string());
+        }
+        if let Some(password) = credentials.password() {
+            credential.password = Some(password.to_string());
+        }
+    }
+    Ok(options)
+}
+
+/// Creates a client with `options` and returns the database named in its URL.
+/// The client connects lazily, on its first operation.
+fn connect(options: ClientOptions) -> Result<Database> {
+    Client::with_options(options)
+        .context("failed to create MongoDB client")?
+        .default_database()
+        .context("missing database")
+}
+
+impl Benchmark<'_> for MongoBenchmark {
+    type Writer = MongoPrimary;
+    type Reader = MongoReplica;
+
+    fn primary_database(&self, _metrics: &Metrics) -> Result<Self::Writer> {
+        Ok(MongoPrimary {
+            db: self.writer.clone(),
+        })
+    }
+
+    /// Every read client has a client of its own, with its own
+    /// connection pool, so that clients do not share connections.
+    fn read_replica(&self) -> Result<Self::Reader> {
+        Ok(MongoReplica {
+            db: connect(self.reader_options.clone())?,
+            options: self.reader_options.clone(),
+            query_timeout: self.query_timeout,
+            consume: self.consume,
+        })
+    }
+
+    /// Loads --load-db, creates the indexes the read operations rely on,
+    /// and starts the `_id` counters after the largest `_id`s.
+    fn setup(&self) -> Result<()> {
+        if let Some(path) = &self.load_db {
+            load(&self.writer, path)
+                .with_context(|| format!("failed to load {}", path.display()))?;
+        }
+        for (collection, field) in INDEXES {
+            self.writer
+                .collection::<Document>(collection)
+                .create_index(IndexModel::builder().keys(doc! { *field: 1 }).build())
+                .run()
+                .with_context(|| format!("failed to index {collection}.{field}"))?;
+        }
+        for (collection, _) in COLLECTIONS {
+            let last = self
+                .writer
+                .collection::<Document>(collection)
+                .find_one(doc! {})
+                .sort(doc! { "_id": -1 })
+                .run()
+                .with_context(|| format!("failed to find the largest _id of {collection}"))?;
+            let last = last.map_or(Ok(0), |last| id(&last))?;
+            self.writer
+                .collection::<Document>(COUNTERS)
+                .update_one(
+                    doc! { "_id": *collection },
+                    doc! { "$max": { "seq": i64::from(last) } },
+                )
+                .upsert(true)
+                .run()
+                .with_context(|| format!("failed to initialize the _id counter of {collection}"))?;
+        }
+        Ok(())
+    }
+}
+
+/// The `_id` of `document`, which the benchmark's ids all fit in an `i32`.
+fn id(document: &Document) -> Result<i32> {
+    match document.get("_id") {
+        Some(Bson::Int32(id)) => Ok(*id),
+        Some(Bson::Int64(id)) => i32::try_from(*id).context("_id out of range"),
+        Some(Bson::Double(id)) if id.fract() == 0.0 => Ok(*id as i32),
+        _ => bail!("_id is not an integer"),
+    }
+}
+
+/// Loads the rows of the SQLite database at `path`, written by
+/// `rr-data-gen --keep-db`, into the empty collections of `db`.
+fn load(db: &Database, path: &Path) -> Result<()> {
+    for (collection, _) in COLLECTIONS {
+        let documents = db
+            .collection::<Document>(collection)
+            .estimated_document_count()
+            .run()
+            .with_context(|| format!("failed to count documents in {collection}"))?;
+        if documents > 0 {
+            bail!("the {collection} collection is not empty, it holds {documents} documents");
+        }
+    }
+
+    let sqlite = rusqlite::Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .context("failed to open SQLite database")?;
+    for (collection, id) in COLLECTIONS {
+        let documents = load_table(db, &sqlite, collection, id)
+            .with_context(|| format!("failed to load table {collection}"))?;
+        info!("Loaded {documents} documents into {collection}");
+    }
+    Ok(())
+}
+
+/// Inserts each row of `table` into the collection of the same name, with
+/// `id` as its `_id`. Returns the number of rows.
+fn load_table(
+    db: &Database,
+    sqlite: &rusqlite::Connection,
+    table: &str,
+    id: &str,
+) -> Result<usize> {
+    let collection = db.collection::<Document>(table);
+    let mut stmt = sqlite.prepare(&format!("SELECT * FROM {table}"))?;
+    let columns: Vec<String> = stmt.column_names().into_iter().map(String::from).collect();
+    let mut rows = stmt.query([])?;
+
+    let mut count = 0;
+    let mut batch = Vec::with_capacity(LOAD_BATCH_SIZE);
+    while let Some(row) = rows.next()? {
+        let mut document = Document::new();
+        for (i, column) in columns.iter().enumerate() {
+            let name = if column == id { "_id" } else { column };
+            if let Some(value) = bson_value(column, row.get_ref(i)?) {
+                document.insert(name, value);
+            }
+        }
+        batch.push(document);
+
+        count += 1;
+        if batch.len() == LOAD_BATCH_SIZE {
+            collection.insert_many(batch.drain(..)).run()?;
+        }
+    }
+    if !batch.is_empty() {
+        collection.insert_many(batch).run()?;
+    }
+
+    Ok(count)
+}
+
+/// Converts a SQLite value of `column` to BSON. Integers that fit are stored
+/// as 32-bit integers, like the ids the benchmark queries with, and the
+/// timestamps of `created_at` and the `_date` columns as dates, so that the
+/// read operations can compare them with the current time. Nulls are left out.
+fn bson_value(column: &str, value: ValueRef) -> Option<Bson> {
+    match value {
+        ValueRef::Null | ValueRef::Blob(_) => None,
+        ValueRef::Integer(i) => Some(i32::try_from(i).map_or(Bson::Int64(i), Bson::Int32)),
+        ValueRef::Real(f) => Some(Bson::Double(f)),
+        ValueRef::Text(text) => {
+            let text = String::from_utf8_lossy(text);
+            if column == "created_at" || column.ends_with("_date") {
+                // SQLite's CURRENT_TIMESTAMP is UTC, as `YYYY-MM-DD HH:MM:SS`.
+                let rfc3339 = format!("{}Z", text.replace(' ', "T"));
+                if let Ok(date) = DateTime::parse_rfc3339_str(rfc3339) {
+                    return Some(Bson::DateTime(date));
+                }
+            }
+            Some(Bson::String(text.into_owned()))
+        }
+    }
+}
+
+struct MongoPrimary {
+    db: Database,
+}
+
+impl MongoPrimary {
+    fn collection(&self, name: &str) -> Collection<Document> {
+        self.db.collection(name)
+    }
+
+    /// Returns `field` of a random document of `collection`,
+    /// chosen by the server with `$sample`.
+    fn sample(&self, collection: &str, field: &str) -> Result<Bson> {
+        let mut cursor = self
+            .collection(collection)
+            .aggregate([
+                doc! { "$sample": { "size": 1 } },
+                doc! { "$project": { field: 1 } },
+            ])
+            .run()?;
+        let document = cursor
+            .next()
+            .transpose()?
+            .with_context(|| format!("no documents found in {collection}"))?;
+        document
+            .get(field)
+            .cloned()
+            .with_context(|| format!("{collection} document has no {field}"))
+    }
+
+    fn random_id(&self, collection: &str) -> Result<i32> {
+        let id = self.sample(collection, "_id")?;
+        self::id(&doc! { "_id": id })
+    }
+
+    fn random_security_field(&self, field: &str) -> Result<String> {
+        match self.sample("securities", field)? {
+            Bson::String(value) => Ok(value),
+            value => bail!("security {field} {value} is not a string"),
+        }
+    }
+
+    /// Inserts `document` into `collection` under a newly allocated `_id`,
+    /// and returns the `_id`.
+    fn insert(&self, collection: &str, mut document: Document) -> Result<i64> {
+        let counter = self
+            .collection(COUNTERS)
+            .find_one_and_update(
+                doc! { "_id": collection },
+                doc! { "$inc": { "seq": 1_i64 } },
+            )
+            .upsert(true)
+            .return_document(ReturnDocument::After)
+            .run()?
+            .context("missing _id counter")?;
+        let id = counter.get_i64("seq").context("invalid _id counter")?;
+        document.insert(
+            "_id",
+            i32::try_from(id).map_or(Bson::Int64(id), Bson::Int32),
+        );
+        self.collection(collection).insert_one(document).run()?;
+        Ok(id)
+    }
+
+    fn update(&self, collection: &str, id: i32, update: Document) -> Result<()> {
+        self.collection(collection)
+            .update_one(doc! { "_id": id }, update)
+            .run()?;
+        Ok(())
+    }
+
+    /// Deletes a document. Documents that reference it are left in place,
+    /// and are dropped by the joins of the read operations.
+    fn delete(&self, collection: &str, id: i32) -> Result<()> {
+        self.collection(collection)
+            .delete_one(doc! { "_id": id })
+            .run()?;
+        Ok(())
+    }
+}
+
+impl PrimaryDatabase for MongoPrimary {
+    fn get_random_customer_id(&mut self, _rng: &mut StdRng) -> Result<i32> {
+        self.random_id("customers")
+            .context("failed to retrieve customer_id")
+    }
+
+    fn get_random_account_id(&mut self, _rng: &mut StdRng) -> Result<i32> {
+        self.random_id("accounts")
+            .context("failed to retrieve account_id")
+    }
+
+    fn get_random_security_id(&mut self, _rng: &mut StdRng) -> Result<i32> {
+        self.random_id("securities")
+            .context("failed to retrieve security_id")
+    }
+
+    fn get_random_trade_id(&mut self, _rng: &mut StdRng) -> Result<i32> {
+        self.random_id("trades")
+            .context("failed to retrieve trade_id")
+    }
+
+    fn get_random_order_id(&mut self, _rng: &mut StdRng) -> Result<i32> {
+        self.random_id("orders")
+            .context("failed to retrieve order_id")
+    }
+
+    fn get_random_market_data_id(&mut self, _rng: &mut StdRng) -> Result<i32> {
+        self.random_id("market_data")
+            .context("failed to retrieve market_data_id")
+    }
+
+    fn get_random_ticker(&mut self, _rng: &mut StdRng) -> Result<String> {
+        self.random_security_field("ticker")
+            .context("failed to retrieve ticker")
+    }
+
+    fn get_random_sector(&mut self, _rng: &mut StdRng) -> Result<String> {
+        self.random_security_field("sector")
+            .context("failed to retrieve sector")
+    }
+
+    fn execute_command(&self, op: WriteOperation) -> Result<()> {
+        let now = DateTime::now();
+        match op {
+            WriteOperation::InsertCustomer { name, address } => self
+                .insert(
+                    "customers",
+                    doc! { "name": name, "address": address, "created_at": now },
+                )
+                .map(|_| ())
+                .context("failed to insert customer"),
+            WriteOperation::InsertAccount {
+                customer_id,
+                account_type,
+                balance,
+                parent_account_id,
+            } => {
+                let mut account = doc! {
+                    "customer_id": customer_id,
+                    "account_type": account_type,
+                    "balance": balance,
+                    "created_at": now,
+                };
+                if let Some(parent_account_id) = parent_account_id {
+                    account.insert("parent_account_id", parent_account_id);
+                }
+                self.insert("accounts", account)
+                    .map(|_| ())
+                    .context("failed to insert account")
+            }
+            WriteOperation::InsertSecurity {
+                ticker,
+                name,
+                sector,
+            } => self
+                .insert(
+                    "securities",
+                    doc! { "ticker": ticker, "name": name, "sector": sector, "created_at": now },
+                )
+                .map(|_| ())
+                .context("failed to insert security"),
+            WriteOperation::InsertTrade {
+                account_id,
+                security_id,
+                trade_type,
+                quantity,
+                price,
+                parent_trade_id,
+            } => {
+                let mut trade = doc! {
+                    "account_id": account_id,
+                    "security_id": security_id,
+                    "trade_type": trade_type,
+                    "quantity": quantity,
+                    "price": price,
+                    "trade_date": now,
+                };
+                if let Some(parent_trade_id) = parent_trade_id {
+                    trade.insert("parent_trade_id", parent_trade_id);
+                }
+                self.insert("trades", trade)
+                    .map(|_| ())
+                    .context("failed to insert trade")
+            }
+            WriteOperation::InsertOrder {
+                account_id,
+                security_id,
+                order_type,
+                quantity,
+                limit_price,
+                status,
+                parent_order_id,
+            } => {
+                let mut order = doc! {
+                    "account_id": account_id,
+                    "security_id": security_id,
+                    "order_type": order_type,
+                    "quantity": quantity,
+                    "status": status,
+                    "order_date": now,
+                };
+                if let Some(limit_price) = limit_price {
+                    order.insert("limit_price", limit_price);
+                }
+                if let Some(parent_order_id) = parent_order_id {
+                    order.insert("parent_order_id", parent_order_id);
+                }
+                self.insert("orders", order)
+                    .map(|_| ())
+                    .context("failed to insert order")
+            }
+            WriteOperation::InsertMarketData {
+                security_id,
+                price,
+                volume,
+            } => self
+                .insert(
+                    "market_data",
+                    doc! {
+                        "security_id": security_id,
+                        "price": price,
+                        "volume": volume,
+                        "market_date": now,
+                    },
+                )
+                .map(|_| ())
+                .context("failed to insert market data"),
+            WriteOperation::UpdateCustomer {
+                customer_id,
+                address,
+            } => self
+                .update(
+                    "customers",
+                    customer_id,
+                    doc! { "$set": { "address": address } },
+                )
+                .context("failed to update customer"),
+            WriteOperation::UpdateAccount {
+                account_id,
+                balance,
+            } => self
+                .update(
+                    "accounts",
+                    account_id,
+                    doc! { "$set": { "balance": balance } },
+                )
+                .context("failed to update account"),
+            WriteOperation::UpdateTrade { trade_id, price } => self
+                .update("trades", trade_id, doc! { "$set": { "price": price } })
+                .context("failed to update trade"),
+            WriteOperation::UpdateOrder {
+                order_id,
+                status,
+                limit_price,
+            } => {
+                let update = match limit_price {
+                    Some(limit_price) => {
+                        doc! { "$set": { "status": status, "limit_price": limit_price } }
+                    }
+                    // A market order has no limit price, so the field is removed.
+                    None => doc! { "$set": { "status": status }, "$unset": { "limit_price": "" } },
+                };
+                self.update("orders", order_id, update)
+                    .context("failed to update order")
+            }
+            WriteOperation::UpdateMarketData {
+                market_data_id,
+                price,
+                volume,
+            } => self
+                .update(
+                    "market_data",
+                    market_data_id,
+                    doc! { "$set": { "price": price, "volume": volume, "market_date": now } },
+                )
+                .context("failed to update market data"),
+            WriteOperation::DeleteCustomer { customer_id } => self
+                .delete("customers", customer_id)
+                .context("failed to delete customer"),
+            WriteOperation::DeleteAccount { account_id } => self
+                .delete("accounts", account_id)
+                .context("failed to delete account"),
+            WriteOperation::DeleteSecurity { security_id } => self
+                .delete("securities", security_id)
+                .context("failed to delete security"),
+            WriteOperation::DeleteTrade { trade_id } => self
+                .delete("trades", trade_id)
+                .context("failed to delete trade"),
+            WriteOperation::DeleteOrder { order_id } => self
+                .delete("orders", order_id)
+                .context("failed to delete order"),
+            WriteOperation::DeleteMarketData { market_data_id } => self
+                .delete("market_data", market_data_id)
+                .context("failed to delete market data"),
+        }
+    }
+
+    fn insert_lag_token(&self, token: i64) -> Result<()> {
+        self.collection(LAG_TOKENS)
+            .insert_one(doc! { "_id": token, "created_at": DateTime::now() })
+            .run()
+            .map(|_| ())
+            .context("failed to insert replication lag token")
+    }
+
+    fn insert_customer(&self, name: &str, address: &str) -> Result<i32> {
+        let id = self
+            .insert(
+                "customers",
+                doc! { "name": name, "address": address, "created_at": DateTime::now() },
+            )
+            .context("failed to insert customer")?;
+        i32::try_from(id).context("customer id out of range")
+    }
+
+    /// The storage allocated to the documents and indexes of the database.
+    fn database_size(&mut self) -> Result<u64> {
+        let stats = self
+            .db
+            .run_command(doc! { "dbStats": 1 })
+            .run()
+            .context("failed to query database size")?;
+        let size = |field: &str| -> Result<f64> {
+            match stats.get(field) {
+                Some(Bson::Int32(size)) => Ok(f64::from(*size)),
+                Some(Bson::Int64(size)) => Ok(*size as f64),
+                Some(Bson::Double(size)) => Ok(*size),
+                _ => bail!("dbStats returned no {field}"),
+            }
+        };
+        Ok((size("storageSize")? + size("indexSize")?) as u64)
+    }
+}
+
+struct MongoReplica {
+    db: Database,
+    /// The options `db` was connected with, kept to reconnect.
+    options: ClientOptions,
+    query_timeout: Option<Duration>,
+    consume: Consume,
+}
+
+impl MongoReplica {
+    /// Runs the pipeline of a read operation, reads the documents it returns
+    /// as --consume asks, and returns the number of documents. With
+    /// `first-row`, the cursor fetches a batch of one document and is killed
+    /// after it; with `count`, a `$count` stage is appended to the pipeline
+    /// and the count it outputs is returned.
+    fn aggregate(&self, (collection, mut pipeline): (&str, Vec<Document>)) -> Result<u64> {
+        if self.consume == Consume::Count {
+            pipeline.push(doc! { "$count": "count" });
+        }
+        let mut cursor = self
+            .db
+            .collection::<Document>(collection)
+            .aggregate(pipeline)
+            .optional(self.query_timeout, |aggregate, timeout| {
+                aggregate.max_time(timeout)
+            })
+            .optional(
+                (self.consume == Consume::FirstRow).then_some(1),
+                |aggregate, size| aggregate.batch_size(size),
+            )
+            .run()?;
+        match self.consume {
+            Consume::AllRows => {
+                let mut count = 0;
+                for document in cursor {
+                    document?;
+                    count += 1;
+                }
+                Ok(count)
+            }
+            Consume::FirstRow => Ok(cursor.next().transpose()?.is_some() as u64),
+            // `$count` outputs no document when the pipeline matches nothing.
+            Consume::Count => match cursor.next().transpose()? {
+                Some(document) => Ok(document.get_i32("count")? as u64),
+                None => Ok(0),
+            },
+        }
+    }
+}
+
+impl ReadReplica for MongoReplica {
+    fn customer_portfolio(&mut self, customer_id: i32) -> Result<u64> {
+        self.aggregate(pipelines::customer_portfolio(customer_id))
+            .with_context(|| format!("failed to query customer profile {customer_id}"))
+    }
+
+    fn top_performers(&mut self) -> Result<u64> {
+        self.aggregate(pipelines::top_performers())
+            .context("failed to query top_performers")
+    }
+
+    fn market_overview(&mut self, sector: &str) -> Result<u64> {
+        self.aggregate(pipelines::market_overview(sector))
+            .context("failed to query market_overview")
+    }
+
+    fn recent_large_trades(&mut self, account_id: i32) -> Result<u64> {
+        self.aggregate(pipelines::recent_large_trades(account_id))
+            .context("failed to query recent_large_trades")
+    }
+
+    fn customer_order_book(&mut self, customer_id: i32) -> Result<u64> {
+        self.aggregate(pipelines::customer_order_book(customer_id))
+            .with_context(|| format!("failed to query customer_order_book {customer_id}"))
+    }
+
+    fn sector_performance(&mut self, sector: String) -> Result<u64> {
+        self.aggregate(pipelines::sector_performance(&sector))
+            .context("failed to query sector_performance")
+    }
+
+    fn account_activity_summary(&mut self, account_id: i32) -> Result<u64> {
+        self.aggregate(pipelines::account_activity_summary(account_id))
+            .with_context(|| format!("failed to query account_activity_summary {account_id}"))
+    }
+
+    fn daily_market_movements(&mut self, security_id: i32) -> Result<u64> {
+        self.aggregate(pipelines::daily_market_movements(security_id))
+            .with_context(|| format!("failed to query daily_market_movements {security_id}"))
+    }
+
+    fn high_value_customers(&mut self) -> Result<u64> {
+        self.aggregate(pipelines::high_value_customers())
+            .context("failed to query high_value_customers")
+    }
+
+    fn pending_orders_summary(&mut self, ticker: &str) -> Result<u64> {
+        self.aggregate(pipelines::pending_orders_summary(ticker))
+            .context("failed to query pending_orders_summary")
+    }
+
+    fn trade_volume_by_hour(&mut self) -> Result<u64> {
+        self.aggregate(pipelines::trade_volume_by_hour())
+            .context("failed to query trade_volume_by_hour")
+    }
+
+    fn top_securities_by_sector(&mut self, sector: String) -> Result<u64> {
+        self.aggregate(pipelines::top_securities_by_sector(&sector))
+            .context("failed to query top_securities_by_sector")
+    }
+
+    fn recent_trades_by_account(&mut self, account_id: i32) -> Result<u64> {
+        self.aggregate(pipelines::recent_trades_by_account(account_id))
+            .with_context(|| format!("failed to query recent_trades_by_account {account_id}"))
+    }
+
+    fn order_fulfillment_rates(&mut self, customer_id: i32) -> Result<u64> {
+        self.aggregate(pipelines::order_fulfillment_rates(customer_id))
+            .with_context(|| format!("failed to query order_fulfillment_rates {customer_id}"))
+    }
+
+    fn sector_order_activity(&mut self, sector: String) -> Result<u64> {
+        self.aggregate(pipelines::sector_order_activity(&sector))
+            .context("failed to query sector_order_activity")
+    }
+
+    fn cascading_order_cancellation_alert(&mut self) -> Result<u64> {
+        self.aggregate(pipelines::cascading_order_cancellation_alert())
+            .context("failed to query cascading_order_cancellation_alert")
+    }
+
+    fn check_row_freshness(&mut self, security_id: i32, expected_price: f64) -> Result<bool> {
+        // Prices are compared with the same tolerance as the SQL backends.
+        self.db
+            .collection::<Document>("market_data")
+            .find_one(doc! {
+                "security_id": security_id,
+                "price": { "$gt": expected_price - 0.0001, "$lt": expected_price + 0.0001 },
+            })
+            .projection(doc! { "_id": 1 })
+            .run()
+            .map(|fresh| fresh.is_none())
+            .with_context(|| format!("failed to check freshness of market data {security_id}"))
+    }
+
+    fn fetch_customer(&mut self, customer_id: i32) -> Result<Option<String>> {
+        let customer = self
+            .db
+            .collection::<Document>("customers")
+            .find_one(doc! { "_id": customer_id })
+            .projection(doc! { "name": 1 })
+            .run()
+            .with_context(|| format!("failed to fetch customer {customer_id}"))?;
+        customer
+            .map(|customer| customer.get_str("name").map(str::to_string))
+            .transpose()
+            .with_context(|| format!("invalid customer {customer_id}"))
+    }
+
+    fn reconnect(&mut self) -> Result<()> {
+        self.db = connect(self.options.clone())?;
+        Ok(())
+    }
+
+    /// `maxTimeMS` fails the aggregation with `MaxTimeMSExpired`.
+    fn is_timeout(&self, error: &anyhow::Error) -> bool {
+        matches!(
+            error.downcast_ref::<mongodb::error::Error>().map(|e| &*e.kind),
+            Some(ErrorKind::Command(e)) if e.code == MAX_TIME_MS_EXPIRED
+        )
+    }
+
+    fn ping(&mut self) -> Result<()> {
+        self.db
+            .run_command(doc! { "ping": 1 })
+            .run()
+            .map(|_| ())
+            .context("failed to ping the read replica")
+    }
+
+    fn max_observed_token(&mut self) -> Result<Option<i64>> {
+        let token = self
+            .db
+            .collection::<Document>(LAG_TOKENS)
+            .find_one(doc! {})
+            .sort(doc! { "_id": -1 })
+            .run()
+            .context("failed to query replication lag token")?;
+        token
+            .map(|token| token.get_i64("_id"))
+            .transpose()
+            .context("invalid replication lag token")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rows_are_converted_to_documents() {
+        let sqlite = rusqlite::Connection::open_in_memory().unwrap();
+        sqlite
+            .execute_batch(
+                "CREATE TABLE orders (order_id INTEGER, quantity INTEGER, limit_price REAL, \
+                     status TEXT, order_date DATETIME);
+                 INSERT INTO orders VALUES (7, 10, NULL, 'pending', '2024-05-01 12:30:00');",
+            )
+            .unwrap();
+        let mut stmt = sqlite.prepare("SELECT * FROM orders").unwrap();
+        let row: Vec<Option<Bson>> = stmt
+            .query_row([], |row| {
+                Ok([
+                    "order_id",
+                    "quantity",
+                    "limit_price",
+                    "status",
+                    "order_date",
+                ]
+                .iter()
+                .enumerate()
+                .map(|(i, column)| bson_value(column, row.get_ref(i).unwrap()))
+                .collect())
+            })
+            .unwrap();
+
+        assert_eq!(
+            row,
+            [
+                Some(Bson::Int32(7)),
+                Some(Bson::Int32(10)),
+                None,
+                Some(Bson::String("pending".to_string())),
+                Some(Bson::DateTime(
+                    DateTime::parse_rfc3339_str("2024-05-01T12:30:00Z").unwrap()
+                )),
+            ]
+        );
+    }
+
+    #[test]
+    fn ids_of_any_integer_type_are_read() {
+        assert_eq!(id(&doc! { "_id": 3_i32 }).unwrap(), 3);
+        assert_eq!(id(&doc! { "_id": 3_i64 }).unwrap(), 3);
+        assert_eq!(id(&doc! { "_id": 3.0 }).unwrap(), 3);
+        assert!(id(&doc! { "_id": i64::MAX }).is_err());
+        assert!(id(&doc! { "_id": "3" }).is_err());
+    }
+
+    #[test]
+    fn urls_must_name_a_database() {
+        let error = parse_url("mongodb://localhost:27017", "--reader-url", None).unwrap_err();
+        assert!(error.to_string().contains("missing database"), "{error}");
+        let options = parse_url(
+            "mongodb://localhost:27017,replica:27017/bench?readPreference=secondary",
+            "--reader-url",
+            None,
+        )
+        .unwrap();
+        assert_eq!(options.default_database.as_deref(), Some("bench"));
+        assert_eq!(options.hosts.len(), 2);
+    }
+}
@@ -0,0 +1,364 @@
+//! The aggregation pipelines that stand in for the SQL views queried by the
+//! read operations, e.g. rr-bench-sqlite/views.sql. Each function returns the
+//! collection a pipeline runs against and its stages, with the parameter the
+//! SQL backends filter the view by matched as early as possible.
+//!
+//! The joins of the views are `$lookup`s followed by an `$unwind`, which, like
+//! an inner join, drops documents without a match, e.g. trades whose account
+//! has been deleted.
+
+use mongodb::bson::{doc, Bson, Document};
+
+/// `$$NOW` minus `amount` of `unit`, e.g. `hour`, for comparisons inside `$expr`.
+fn ago(unit: &str, amount: i32) -> Document {
+    doc! { "$dateSubtract": { "startDate": "$$NOW", "unit": unit, "amount": amount } }
+}
+
+/// Joins the document of `from` whose `_id` is `local_field` as `as_field`,
+/// dropping documents without one.
+fn join(from: &str, local_field: &str, as_field: &str) -> [Document; 2] {
+    [
+        doc! { "$lookup": {
+            "from": from,
+            "localField": local_field,
+            "foreignField": "_id",
+            "as": as_field,
+        } },
+        doc! { "$unwind": format!("${as_field}") },
+    ]
+}
+
+/// Joins each document of `from` whose `foreign_field` is the `_id` of the
+/// input document as `as_field`, one output document per match.
+fn join_many(from: &str, foreign_field: &str, as_field: &str) -> [Document; 2] {
+    [
+        doc! { "$lookup": {
+            "from": from,
+            "localField": "_id",
+            "foreignField": foreign_field,
+            "as": as_field,
+        } },
+        doc! { "$unwind": format!("${as_field}") },
+    ]
+}
+
+/// 1 if `field` is `value`, 0 otherwise, to count matches with `$sum`.
+fn count_if(field: &str, value: &str) -> Document {
+    doc! { "$cond": [{ "$eq": [field, value] }, 1, 0] }
+}
+
+pub fn customer_portfolio(customer_id: i32) -> (&'static str, Vec<Document>) {
+    let mut pipeline = vec![doc! { "$match": { "customer_id": customer_id } }];
+    pipeline.extend(join("customers", "customer_id", "customer"));
+    pipeline.extend(join_many("trades", "account_id", "trade"));
+    pipeline.extend(join("securities", "trade.security_id", "security"));
+    pipeline.push(doc! { "$group": {
+        "_id": {
+            "customer_id": "$customer_id",
+            "name": "$customer.name",
+            "account_id": "$_id",
+            "ticker": "$security.ticker",
+            "security_name": "$security.name",
+        },
+        "total_value": { "$sum": { "$multiply": ["$trade.quantity", "$trade.price"] } },
+    } });
+    ("accounts", pipeline)
+}
+
+pub fn top_performers() -> (&'static str, Vec<Document>) {
+    let mut pipeline = vec![doc! { "$group": {
+        "_id": "$security_id",
+        "volume": { "$sum": "$quantity" },
+    } }];
+    pipeline.extend(join("securities", "_id", "security"));
+    pipeline.extend([
+        doc! { "$group": {
+            "_id": { "ticker": "$security.ticker", "name": "$security.name" },
+            "total_traded_volume": { "$sum": "$volume" },
+        } },
+        doc! { "$sort": { "total_traded_volume": -1 } },
+        doc! { "$limit": 10 },
+        doc! { "$setWindowFields": {
+            "sortBy": { "total_traded_volume": -1 },
+            "output": { "rank": { "$documentNumber": {} } },
+        } },
+    ]);
+    ("trades", pipeline)
+}
+
+pub fn market_overview(sector: &str) -> (&'static str, Vec<Document>) {
+    let pipeline = vec![
+        doc! { "$match": { "sector": sector } },
+        doc! { "$lookup": {
+            "from": "market_data",
+            "localField": "_id",
+            "foreignField": "security_id",
+            "as": "market_data",
+        } },
+        doc! { "$unwind": { "path": "$market_data", "preserveNullAndEmptyArrays": true } },
+        doc! { "$group": {
+            "_id": "$sector",
+            "avg_price": { "$avg": "$market_data.price" },
+            "total_volume": { "$sum": "$market_data.volume" },
+            "last_update": { "$max": "$market_data.market_date" },
+        } },
+        doc! { "$match": { "$expr": { "$gt": ["$last_update", ago("minute", 5)] } } },
+    ];
+    ("securities", pipeline)
+}
+
+pub fn recent_large_trades(account_id: i32) -> (&'static str, Vec<Document>) {
+    let mut pipeline = vec![
+        doc! { "$match": {
+            "account_id": account_id,
+            "$expr": { "$gt": ["$trade_date", ago("hour", 1)] },
+        } },
+        // The average quantity of every trade, as in the SQL subquery.
+        doc! { "$lookup": {
+            "from": "trades",
+            "pipeline": [{ "$group": { "_id": Bson::Null, "quantity": { "$avg": "$quantity" } } }],
+            "as": "average",
+        } },
+        doc! { "$unwind": "$average" },
+        doc! { "$match": {
+            "$expr": { "$gt": ["$quantity", { "$multiply": ["$average.quantity", 5] }] },
+        } },
+    ];
+    pipeline.extend(join("accounts", "account_id", "account"));
+    pipeline.extend(join("securities", "security_id", "security"));
+    pipeline.push(doc! { "$project": {
+        "_id": 0,
+        "trade_id": "$_id",
+        "account_id": 1,
+        "ticker": "$security.ticker",
+        "quantity": 1,
+        "price": 1,
+        "trade_date": 1,
+    } });
+    ("trades", pipeline)
+}
+
+pub fn customer_order_book(customer_id: i32) -> (&'static str, Vec<Document>) {
+    let mut pipeline = vec![doc! { "$match": { "customer_id": customer_id } }];
+    pipeline.extend(join("customers", "customer_id", "customer"));
+    pipeline.extend(join_many("orders", "account_id", "order"));
+    pipeline.push(doc! { "$group": {
+        "_id": { "customer_id": "$customer_id", "name": "$customer.name" },
+        "open_orders": { "$sum": 1 },
+        "completed_orders": { "$sum": count_if("$order.status", "completed") },
+    } });
+    ("accounts", pipeline)
+}
+
+pub fn sector_performance(sector: &str) -> (&'static str, Vec<Document>) {
+    let mut pipeline = vec![doc! { "$match": { "sector": sector } }];
+    pipeline.extend(join_many("trades", "security_id", "trade"));
+    pipeline.push(doc! { "$group": {
+        "_id": "$sector",
+        "avg_trade_price": { "$avg": "$trade.price" },
+        "trade_count": { "$sum": 1 },
+        "total_volume": { "$sum": "$trade.quantity" },
+    } });
+    ("securities", pipeline)
+}
+
+pub fn account_activity_summary(account_id: i32) -> (&'static str, Vec<Document>) {
+    let pipeline = vec![
+        doc! { "$match": { "_id": account_id } },
+        doc! { "$lookup": {
+            "from": "trades",
+            "localField": "_id",
+            "foreignField": "account_id",
+            "as": "trades",
+        } },
+        doc! { "$project": {
+            "_id": 0,
+            "account_id": "$_id",
+            "trade_count": { "$size": "$trades" },
+            "total_trade_value": { "$sum": { "$map": {
+                "input": "$trades",
+                "in": { "$multiply": ["$$this.quantity", "$$this.price"] },
+            } } },
+            "last_trade_date": { "$max": "$trades.trade_date" },
+        } },
+    ];
+    ("accounts", pipeline)
+}
+
+pub fn daily_market_movements(security_id: i32) -> (&'static str, Vec<Document>) {
+    let mut pipeline = vec![doc! { "$match": {
+        "security_id": security_id,
+        "$expr": { "$gt": ["$market_date", ago("day", 1)] },
+    } }];
+    pipeline.extend(join("securities", "security_id", "security"));
+    pipeline.extend([
+        doc! { "$setWindowFields": {
+            "partitionBy": "$security_id",
+            "sortBy": { "market_date": 1 },
+            "output": { "previous_price": { "$shift": { "output": "$price", "by": -1 } } },
+        } },
+        doc! { "$project": {
+            "_id": 0,
+            "security_id": 1,
+            "ticker": "$security.ticker",
+            "name": "$security.name",
+            "current_price": "$price",
+            "previous_price": 1,
+            "price_change": { "$subtract": ["$price", "$previous_price"] },
+            "market_date": 1,
+        } },
+    ]);
+    ("market_data", pipeline)
+}
+
+pub fn high_value_customers() -> (&'static str, Vec<Document>) {
+    let mut pipeline = vec![
+        doc! { "$group": { "_id": "$customer_id", "total_balance": { "$sum": "$balance" } } },
+        doc! { "$match": { "total_balance": { "$gt": 1_000_000 } } },
+    ];
+    pipeline.extend(join("customers", "_id", "customer"));
+    pipeline.push(doc! { "$project": {
+        "_id": 0,
+        "customer_id": "$_id",
+        "name": "$customer.name",
+        "total_balance": 1,
+    } });
+    ("accounts", pipeline)
+}
+
+pub fn pending_orders_summary(ticker: &str) -> (&'static str, Vec<Document>) {
+    let mut pipeline = vec![doc! { "$match": { "ticker": ticker } }];
+    pipeline.extend(join_many("orders", "security_id", "order"));
+    pipeline.extend([
+        doc! { "$match": { "order.status": "pending" } },
+        doc! { "$group": {
+            "_id": { "ticker": "$ticker", "name": "$name" },
+            "pending_order_count": { "$sum": 1 },
+            "pending_volume": { "$sum": "$order.quantity" },
+            "avg_limit_price": { "$avg": "$order.limit_price" },
+        } },
+    ]);
+    ("securities", pipeline)
+}
+
+pub fn trade_volume_by_hour() -> (&'static str, Vec<Document>) {
+    let pipeline = vec![doc! { "$group": {
+        "_id": { "$hour": "$trade_date" },
+        "trade_count": { "$sum": 1 },
+        "total_quantity": { "$sum": "$quantity" },
+    } }];
+    ("trades", pipeline)
+}
+
+pub fn top_securities_by_sector(sector: &str) -> (&'static str, Vec<Document>) {
+    let pipeline = vec![
+        doc! { "$match": { "sector": sector } },
+        doc! { "$lookup": {
+            "from": "trades",
+            "localField": "_id",
+            "foreignField": "security_id",
+            "as": "trades",
+        } },
+        // Securities without trades are left out, as by the SQL join.
+        doc! { "$match": { "trades.0": { "$exists": true } } },
+        doc! { "$group": {
+            "_id": { "sector": "$sector", "ticker": "$ticker", "name": "$name" },
+            "total_volume": { "$sum": { "$sum": "$trades.quantity" } },
+        } },
+        doc! { "$setWindowFields": {
+            "partitionBy": "$_id.sector",
+            "sortBy": { "total_volume": -1 },
+            "output": { "sector_rank": { "$documentNumber": {} } },
+        } },
+        doc! { "$match": { "sector_rank": { "$lte": 5 } } },
+    ];
+    ("securities", pipeline)
+}
+
+pub fn recent_trades_by_account(account_id: i32) -> (&'static str, Vec<Document>) {
+    let mut pipeline = vec![doc! { "$match": {
+        "account_id": account_id,
+        "$expr": { "$gt": ["$trade_date", ago("day", 1)] },
+    } }];
+    pipeline.extend(join("accounts", "account_id", "account"));
+    pipeline.extend(join("securities", "security_id", "security"));
+    pipeline.push(doc! { "$project": {
+        "_id": 0,
+        "account_id": 1,
+        "ticker": "$security.ticker",
+        "quantity": 1,
+        "price": 1,
+        "trade_date": 1,
+    } });
+    ("trades", pipeline)
+}
+
+pub fn order_fulfillment_rates(customer_id: i32) -> (&'static str, Vec<Document>) {
+    let mut pipeline = vec![doc! { "$match": { "customer_id": customer_id } }];
+    pipeline.extend(join("customers", "customer_id", "customer"));
+    pipeline.extend(join_many("orders", "account_id", "order"));
+    pipeline.extend([
+        doc! { "$group": {
+            "_id": { "customer_id": "$customer_id", "name": "$customer.name" },
+            "total_orders": { "$sum": 1 },
+            "fulfilled_orders": { "$sum": count_if("$order.status", "completed") },
+        } },
+        doc! { "$set": { "fulfillment_rate": {
+            "$multiply": [{ "$divide": ["$fulfilled_orders", "$total_orders"] }, 100],
+        } } },
+    ]);
+    ("accounts", pipeline)
+}
+
+pub fn sector_order_activity(sector: &str) -> (&'static str, Vec<Document>) {
+    let mut pipeline = vec![doc! { "$match": { "sector": sector } }];
+    pipeline.extend(join_many("orders", "security_id", "order"));
+    pipeline.push(doc! { "$group": {
+        "_id": "$sector",
+        "order_count": { "$sum": 1 },
+        "total_quantity": { "$sum": "$order.quantity" },
+        "avg_limit_price": { "$avg": "$order.limit_price" },
+    } });
+    ("securities", pipeline)
+}
+
+/// The recursive view starts from each security's latest order, when it is
+/// canceled, and follows canceled orders of the same security placed after
+/// it. Since no order is placed after the latest one, the recursion ends
+/// after its first step, which a single `$lookup` performs.
+pub fn cascading_order_cancellation_alert() -> (&'static str, Vec<Document>) {
+    let pipeline = vec![
+        doc! { "$setWindowFields": {
+            "partitionBy": "$security_id",
+            "output": { "last_order_date": { "$max": "$order_date" } },
+        } },
+        doc! { "$match": {
+            "status": "canceled",
+            "$expr": { "$eq": ["$order_date", "$last_order_date"] },
+        } },
+        doc! { "$lookup": {
+            "from": "orders",
+            "let": { "security_id": "$security_id", "order_date": "$order_date" },
+            "pipeline": [{ "$match": {
+                "status": "canceled",
+                "$expr": { "$and": [
+                    { "$eq": ["$security_id", "$$security_id"] },
+                    { "$gt": ["$order_date", "$$order_date"] },
+                ] },
+            } }],
+            "as": "cancellation",
+        } },
+        doc! { "$unwind": "$cancellation" },
+        doc! { "$project": {
+            "_id": 0,
+            "order_id": "$cancellation._id",
+            "account_id": "$cancellation.account_id",
+            "security_id": "$cancellation.security_id",
+            "status": "$cancellation.status",
+            "order_date": "$cancellation.order_date",
+            "parent_order_id": "$_id",
+            "cancellation_depth": { "$literal": 1 },
+        } },
+    ];
+    ("orders", pipeline)
+}
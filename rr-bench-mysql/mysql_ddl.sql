@@ -0,0 +1,72 @@
+--- Table definitions for MySQL and MariaDB.
+
+CREATE TABLE customers (
+    customer_id INT AUTO_INCREMENT PRIMARY KEY,
+    name VARCHAR(255) NOT NULL,
+    address VARCHAR(255),
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE accounts (
+    account_id INT AUTO_INCREMENT PRIMARY KEY,
+    customer_id INT,
+    parent_account_id INT,
+    account_type VARCHAR(50) NOT NULL,
+    balance DECIMAL(18, 2) NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (customer_id) REFERENCES customers(customer_id) ON DELETE CASCADE,
+    FOREIGN KEY (parent_account_id) REFERENCES accounts(account_id) ON DELETE SET NULL
+);
+
+CREATE TABLE securities (
+    security_id INT AUTO_INCREMENT PRIMARY KEY,
+    ticker VARCHAR(10) NOT NULL,
+    name VARCHAR(255),
+    sector VARCHAR(50),
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE trades (
+    trade_id INT AUTO_INCREMENT PRIMARY KEY,
+    account_id INT,
+    security_id INT,
+    parent_trade_id INT,
+    trade_type VARCHAR(10) NOT NULL CHECK (trade_type IN ('buy', 'sell')),
+    quantity INT NOT NULL,
+    price DECIMAL(18, 4) NOT NULL,
+    trade_date TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (account_id) REFERENCES accounts(account_id) ON DELETE CASCADE,
+    FOREIGN KEY (security_id) REFERENCES securities(security_id) ON DELETE CASCADE,
+    FOREIGN KEY (parent_trade_id) REFERENCES trades(trade_id) ON DELETE SET NULL
+);
+
+CREATE TABLE orders (
+    order_id INT AUTO_INCREMENT PRIMARY KEY,
+    account_id INT,
+    security_id INT,
+    parent_order_id INT,
+    order_type VARCHAR(10) NOT NULL CHECK (order_type IN ('buy', 'sell')),
+    quantity INT NOT NULL,
+    limit_price DECIMAL(18, 4),
+    status VARCHAR(10) NOT NULL CHECK (status IN ('pending', 'completed', 'canceled')),
+    order_date TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (account_id) REFERENCES accounts(account_id) ON DELETE CASCADE,
+    FOREIGN KEY (security_id) REFERENCES securities(security_id) ON DELETE CASCADE,
+    FOREIGN KEY (parent_order_id) REFERENCES orders(order_id) ON DELETE SET NULL
+);
+
+CREATE TABLE market_data (
+    market_data_id INT AUTO_INCREMENT PRIMARY KEY,
+    security_id INT,
+    price DECIMAL(18, 4) NOT NULL,
+    volume INT NOT NULL,
+    market_date TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (security_id) REFERENCES securities(security_id) ON DELETE CASCADE
+);
+
+-- Tokens written by the primary and polled on the read
+-- replica when measuring replication lag (--measure-lag).
+CREATE TABLE replication_lag_tokens (
+    token BIGINT PRIMARY KEY,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,731 @@
+use anyhow::{bail, Context, Result};
+use mysql::prelude::{FromValue, Queryable};
+use mysql::{Conn, Opts, OptsBuilder, Pool, PooledConn, Row};
+use rr_bench_base::clap::{value_parser, Arg, ArgMatches};
+use rr_bench_base::operations::WriteOperation;
+use rr_bench_base::rand::rngs::StdRng;
+use rr_bench_base::{
+    benchmark, connect_with_retries_from, reader_url, writer_url, Benchmark, ConnectionString,
+    Consume, Credentials, Metrics, PrimaryDatabase, RandomIdCache, ReadReplica,
+};
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// The URL schemes accepted for --writer-url and --reader-url.
+const SCHEMES: &[&str] = &["mysql"];
+
+/// The views created by --apply-schema.
+const VIEWS: &str = include_str!("../mysql_views.sql");
+
+fn main() {
+    benchmark(
+        || {
+            [
+                Arg::new("writer")
+                    .long("writer-url")
+                    .help("The URL to the writer node. Defaults to $RR_BENCH_WRITER_URL"),
+                Arg::new("reader")
+                    .long("reader-url")
+                    .help("The URL to the reader node. Defaults to $RR_BENCH_READER_URL"),
+                Arg::new("credentials")
+                    .long("credentials")
+                    .value_name("PATH")
+                    .value_parser(value_parser!(PathBuf))
+                    .help("A file with user and password lines that replace the credentials in --writer-url and --reader-url"),
+            ]
+        },
+        MySqlBenchmark::new,
+    )
+}
+
+struct MySqlBenchmark {
+    reader_url: String,
+    compare_reader_url: Option<String>,
+    pool: Pool,
+    /// The number of connections `pool` keeps open.
+    pool_size: usize,
+    view_prefix: String,
+    query_timeout: Option<Duration>,
+    session_settings: Vec<String>,
+}
+
+impl MySqlBenchmark {
+    fn new(args: ArgMatches) -> Result<Self> {
+        if *args.get_one::<Consume>("consume").unwrap() != Consume::AllRows {
+            bail!("only --consume all-rows is supported by MySQL");
+        }
+        let credentials = args
+            .get_one::<PathBuf>("credentials")
+            .map(|path| Credentials::load(path))
+            .transpose()?;
+
+        let (writer, writer_source) = writer_url(&args)?;
+        let writer = parse_url(&writer, writer_source, credentials.as_ref())?;
+
+        let (reader, reader_source) = reader_url(&args)?;
+        let reader_url = parse_url(&reader, reader_source, credentials.as_ref())?;
+        let compare_reader_url = args
+            .get_one::<String>("compare_reader_url")
+            .map(|reader| parse_url(reader, "--compare-reader-url", credentials.as_ref()))
+            .transpose()?;
+
+        let session_settings: Vec<String> = args
+            .get_many::<(String, String)>("session_setting")
+            .into_iter()
+            .flatten()
+            .map(|(key, value)| set_session(key, value))
+            .collect();
+
+        let opts = Opts::from_url(&writer).context("invalid writer-url")?;
+        let opts: Opts = OptsBuilder::from_opts(opts)
+            .init(session_settings.clone())
+            .into();
+        let pool_size = opts.get_pool_opts().constraints().min();
+        // The pool connects when it is created, so creating it is retried
+        // like every other connection.
+        let pool = connect_with_retries_from(&args, "primary database", || {
+            Pool::new(opts.clone()).context("failed to create connection pool")
+        })?;
+
+        Ok(Self {
+            reader_url,
+            compare_reader_url,
+            pool,
+            pool_size,
+            view_prefix: args
+                .get_one::<String>("view_prefix")
+                .cloned()
+                .unwrap_or_default(),
+            query_timeout: args.get_one::<Duration>("query_timeout").copied(),
+            session_settings,
+        })
+    }
+
+    fn connect_reader(&self, url: &str, query_timeout: Option<Duration>) -> Result<MySqlClient> {
+        MySqlClient::from_url(
+            url,
+            &self.view_prefix,
+            query_timeout,
+            &self.session_settings,
+        )
+    }
+}
+
+/// The statement that sets the `--session-setting` `key` to `value`. Numbers
+/// are left unquoted, since MySQL rejects strings for numeric variables.
+fn set_session(key: &str, value: &str) -> String {
+    if value.parse::<f64>().is_ok_and(f64::is_finite) {
+        format!("SET SESSION {key} = {value}")
+    } else {
+        format!(
+            "SET SESSION {key} = '{}'",
+            value.replace('\\', "\\\\").replace('\'', "''")
+        )
+    }
+}
+
+/// Validates the URL passed to `flag` and replaces its
+/// user and password with those in `credentials`.
+fn parse_url(url: &str, flag: &str, credentials: Option<&Credentials>) -> Result<String> {
+    let mut url =
+        ConnectionString::parse(url, SCHEMES).with_context(|| format!("invalid {flag}"))?;
+    if url.pool_size().is_some() {
+        bail!("invalid {flag}: pool_size is not supported by MySQL");
+    }
+    if let Some(credentials) = credentials {
+        url.set_credentials(credentials);
+    }
+    Ok(url.url().to_string())
+}
+
+impl Benchmark<'_> for MySqlBenchmark {
+    type Writer = MySqlPooledClient;
+    type Reader = MySqlClient;
+
+    fn primary_database(&self, _metrics: &Metrics) -> Result<Self::Writer> {
+        Ok(MySqlPooledClient {
+            pool: self.pool.clone(),
+            ids: RandomIds::default(),
+        })
+    }
+
+    fn read_replica(&self) -> Result<Self::Reader> {
+        self.connect_reader(&self.reader_url, self.query_timeout)
+    }
+
+    fn compare_read_replica(&self) -> Result<Self::Reader> {
+        let url = self
+            .compare_reader_url
+            .as_deref()
+            .context("missing --compare-reader-url")?;
+        self.connect_reader(url, self.query_timeout)
+    }
+
+    /// Holds every connection at once, so that each is a different one.
+    fn prime_pool(&self) -> Result<u32> {
+        let mut conns = Vec::with_capacity(self.pool_size);
+        for _ in 0..self.pool_size {
+            let mut conn = self
+                .pool
+                .get_conn()
+                .context("failed to acquire connection from pool")?;
+            conn.query_drop("SELECT 1")
+                .context("failed to ping the primary database")?;
+            conns.push(conn);
+        }
+        Ok(self.pool_size as u32)
+    }
+
+    fn apply_schema(&self) -> Result<()> {
+        self.connect_reader(&self.reader_url, None)?
+            .conn
+            .query_drop(VIEWS)
+            .context("failed to create views")
+    }
+}
+
+struct MySqlPooledClient {
+    pool: Pool,
+    ids: RandomIds,
+}
+
+/// The cached id ranges used to pick random rows from each table.
+#[derive(Default)]
+struct RandomIds {
+    customers: RandomIdCache,
+    accounts: RandomIdCache,
+    securities: RandomIdCache,
+    trades: RandomIdCache,
+    orders: RandomIdCache,
+    market_data: RandomIdCache,
+}
+
+impl MySqlPooledClient {
+    fn conn(&self) -> Result<PooledConn> {
+        self.pool
+            .get_conn()
+            .context("failed to acquire connection from pool")
+    }
+}
+
+struct MySqlClient {
+    conn: Conn,
+    view_prefix: String,
+}
+
+impl MySqlClient {
+    fn from_url(
+        url: &str,
+        view_prefix: &str,
+        query_timeout: Option<Duration>,
+        session_settings: &[String],
+    ) -> Result<Self> {
+        let opts = Opts::from_url(url).context("invalid reader-url")?;
+        let opts = OptsBuilder::from_opts(opts).init(session_settings.to_vec());
+        let mut conn = Conn::new(opts).context("failed to open mysql client to read replica")?;
+        if let Some(timeout) = query_timeout {
+            // MySQL limits SELECT statements in milliseconds, MariaDB limits
+            // every statement in seconds.
+            conn.query_drop(format!(
+                "SET SESSION max_execution_time = {}",
+                timeout.as_millis()
+            ))
+            .or_else(|_| {
+                conn.query_drop(format!(
+                    "SET SESSION max_statement_time = {}",
+                    timeout.as_secs_f64()
+                ))
+            })
+            .context("failed to set the statement timeout")?;
+        }
+        Ok(MySqlClient {
+            conn,
+            view_prefix: view_prefix.to_string(),
+        })
+    }
+}
+
+/// Returns `column` from a random row of `table`, chosen by sampling an id from
+/// the cached id range and selecting the first row at or after it. When rows at
+/// the end of the range have been deleted, the last row before it is used instead.
+fn random_row<T: FromValue>(
+    conn: &mut PooledConn,
+    cache: &mut RandomIdCache,
+    rng: &mut StdRng,
+    table: &str,
+    id: &str,
+    column: &str,
+) -> Result<T> {
+    let sampled = cache.sample(rng, || {
+        let range: Option<(Option<i32>, Option<i32>)> = conn
+            .query_first(format!("SELECT MIN({id}), MAX({id}) FROM {table}"))
+            .with_context(|| format!("failed to query the {id} range"))?;
+        Ok(range.and_then(|(min, max)| min.zip(max)))
+    })?;
+
+    let after = conn.exec_first(
+        format!("SELECT {column} FROM {table} WHERE {id} >= ? ORDER BY {id} LIMIT 1"),
+        (sampled,),
+    )?;
+    match after {
+        Some(value) => Ok(value),
+        None => conn
+            .exec_first(
+                format!("SELECT {column} FROM {table} WHERE {id} < ? ORDER BY {id} DESC LIMIT 1"),
+                (sampled,),
+            )?
+            .with_context(|| format!("no rows found in {table}")),
+    }
+}
+
+impl PrimaryDatabase for MySqlPooledClient {
+    fn get_random_customer_id(&mut self, rng: &mut StdRng) -> Result<i32> {
+        random_row(
+            &mut self.conn()?,
+            &mut self.ids.customers,
+            rng,
+            "customers",
+            "customer_id",
+            "customer_id",
+        )
+        .context("failed to query customer id")
+    }
+
+    fn get_random_account_id(&mut self, rng: &mut StdRng) -> Result<i32> {
+        random_row(
+            &mut self.conn()?,
+            &mut self.ids.accounts,
+            rng,
+            "accounts",
+            "account_id",
+            "account_id",
+        )
+        .context("failed to query account id")
+    }
+
+    fn get_random_security_id(&mut self, rng: &mut StdRng) -> Result<i32> {
+        random_row(
+            &mut self.conn()?,
+            &mut self.ids.securities,
+            rng,
+            "securities",
+            "security_id",
+            "security_id",
+        )
+        .context("failed to query security id")
+    }
+
+    fn get_random_trade_id(&mut self, rng: &mut StdRng) -> Result<i32> {
+        random_row(
+            &mut self.conn()?,
+            &mut self.ids.trades,
+            rng,
+            "trades",
+            "trade_id",
+            "trade_id",
+        )
+        .context("failed to query trade id")
+    }
+
+    fn get_random_order_id(&mut self, rng: &mut StdRng) -> Result<i32> {
+        random_row(
+            &mut self.conn()?,
+            &mut self.ids.orders,
+            rng,
+            "orders",
+            "order_id",
+            "order_id",
+        )
+        .context("failed to query order id")
+    }
+
+    fn get_random_market_data_id(&mut self, rng: &mut StdRng) -> Result<i32> {
+        random_row(
+            &mut self.conn()?,
+            &mut self.ids.market_data,
+            rng,
+            "market_data",
+            "market_data_id",
+            "market_data_id",
+        )
+        .context("failed to query market data id")
+    }
+
+    fn get_random_ticker(&mut self, rng: &mut StdRng) -> Result<String> {
+        random_row(
+            &mut self.conn()?,
+            &mut self.ids.securities,
+            rng,
+            "securities",
+            "security_id",
+            "ticker",
+        )
+        .context("failed to query ticker")
+    }
+
+    fn get_random_sector(&mut self, rng: &mut StdRng) -> Result<String> {
+        random_row(
+            &mut self.conn()?,
+            &mut self.ids.securities,
+            rng,
+            "securities",
+            "security_id",
+            "sector",
+        )
+        .context("failed to query sector")
+    }
+
+    fn execute_command(&self, op: WriteOperation) -> Result<()> {
+        let mut conn = self.conn()?;
+        // DECIMAL columns are bound as doubles and converted by the server.
+        match op {
+            WriteOperation::InsertCustomer { name, address } => conn
+                .exec_drop("INSERT INTO customers (name, address) VALUES (?, ?)", (name, address))
+                .context("failed to insert customer"),
+            WriteOperation::InsertAccount { customer_id, account_type, balance, parent_account_id } => {
+                match parent_account_id {
+                    None => conn
+                        .exec_drop("INSERT INTO accounts (customer_id, account_type, balance) VALUES (?, ?, ?)",
+                                   (customer_id, account_type, balance))
+                        .context("failed to insert account"),
+                    Some(parent_account_id) => conn
+                        .exec_drop("INSERT INTO accounts (customer_id, account_type, balance, parent_account_id) VALUES (?, ?, ?, ?)",
+                                   (customer_id, account_type, balance, parent_account_id))
+                        .context("failed to insert account"),
+                }
+            }
+            WriteOperation::InsertSecurity { ticker, name, sector } => conn
+                .exec_drop("INSERT INTO securities (ticker, name, sector) VALUES (?, ?, ?)", (ticker, name, sector))
+                .context("failed to insert security"),
+            WriteOperation::InsertTrade { account_id, security_id, trade_type, quantity, price, parent_trade_id } => {
+                match parent_trade_id {
+                    None => conn
+                        .exec_drop("INSERT INTO trades (account_id, security_id, trade_type, quantity, price) VALUES (?, ?, ?, ?, ?)",
+                                   (account_id, security_id, trade_type, quantity, price))
+                        .context("failed to insert trades"),
+                    Some(parent_trade_id) => conn
+                        .exec_drop("INSERT INTO trades (account_id, security_id, trade_type, quantity, price, parent_trade_id) VALUES (?, ?, ?, ?, ?, ?)",
+                                   (account_id, security_id, trade_type, quantity, price, parent_trade_id))
+                        .context("failed to insert trades"),
+                }
+            }
+            WriteOperation::InsertOrder { account_id, security_id, order_type, quantity, limit_price, status, parent_order_id } => {
+                match parent_order_id {
+                    None => conn
+                        .exec_drop("INSERT INTO orders (account_id, security_id, order_type, quantity, limit_price, status) VALUES (?, ?, ?, ?, ?, ?)",
+                                   (account_id, security_id, order_type, quantity, limit_price, status))
+                        .context("failed to insert order"),
+                    Some(parent_order_id) => conn
+                        .exec_drop("INSERT INTO orders (account_id, security_id, order_type, quantity, limit_price, status, parent_order_id) VALUES (?, ?, ?, ?, ?, ?, ?)",
+                                   (account_id, security_id, order_type, quantity, limit_price, status, parent_order_id))
+                        .context("failed to insert order"),
+                }
+            }
+            WriteOperation::InsertMarketData { security_id, price, volume } => conn
+                .exec_drop("INSERT INTO market_data (security_id, price, volume) VALUES (?, ?, ?)", (security_id, price, volume))
+                .context("failed to insert market data"),
+            WriteOperation::UpdateCustomer { customer_id, address } => conn
+                .exec_drop("UPDATE customers SET address = ? WHERE customer_id = ?", (address, customer_id))
+                .context("failed to update customer"),
+            WriteOperation::UpdateAccount { account_id, balance } => conn
+                .exec_drop("UPDATE accounts SET balance = ? WHERE account_id = ?", (balance, account_id))
+                .context("failed to update account"),
+            WriteOperation::UpdateTrade { trade_id, price } => conn
+                .exec_drop("UPDATE trades SET price = ? WHERE trade_id = ?", (price, trade_id))
+                .context("failed to update trades"),
+            WriteOperation::UpdateOrder { order_id, status, limit_price } => conn
+                .exec_drop("UPDATE orders SET status = ?, limit_price = ? WHERE order_id = ?", (status, limit_price, order_id))
+                .context("failed to update orders"),
+            WriteOperation::UpdateMarketData { market_data_id, price, volume } => conn
+                .exec_drop("UPDATE market_data SET price = ?, volume = ?, market_date = CURRENT_TIMESTAMP WHERE market_data_id = ?",
+                           (price, volume, market_data_id))
+                .context("failed to update market_data"),
+            WriteOperation::DeleteCustomer { customer_id } => conn
+                .exec_drop("DELETE FROM customers WHERE customer_id = ?", (customer_id,))
+                .context("failed to delete customer"),
+            WriteOperation::DeleteAccount { account_id } => conn
+                .exec_drop("DELETE FROM accounts WHERE account_id = ?", (account_id,))
+                .context("failed to delete accounts"),
+            WriteOperation::DeleteSecurity { security_id } => conn
+                .exec_drop("DELETE FROM securities WHERE security_id = ?", (security_id,))
+                .context("failed to delete security"),
+            WriteOperation::DeleteTrade { trade_id } => conn
+                .exec_drop("DELETE FROM trades WHERE trade_id = ?", (trade_id,))
+                .context("failed to delete trades"),
+            WriteOperation::DeleteOrder { order_id } => conn
+                .exec_drop("DELETE FROM orders WHERE order_id = ?", (order_id,))
+                .context("failed to delete orders"),
+            WriteOperation::DeleteMarketData { market_data_id } => conn
+                .exec_drop("DELETE FROM market_data WHERE market_data_id = ?", (market_data_id,))
+                .context("failed to delete market_data"),
+        }
+    }
+
+    fn insert_lag_token(&self, token: i64) -> Result<()> {
+        self.conn()?
+            .exec_drop(
+                "INSERT INTO replication_lag_tokens (token) VALUES (?)",
+                (token,),
+            )
+            .context("failed to insert replication lag token")
+    }
+
+    fn insert_customer(&self, name: &str, address: &str) -> Result<i32> {
+        let mut conn = self.conn()?;
+        conn.exec_drop(
+            "INSERT INTO customers (name, address) VALUES (?, ?)",
+            (name, address),
+        )
+        .context("failed to insert customer")?;
+        i32::try_from(conn.last_insert_id()).context("customer id out of range")
+    }
+
+    fn database_size(&mut self) -> Result<u64> {
+        // The sizes in information_schema are estimates, refreshed
+        // according to information_schema_stats_expiry.
+        self.conn()?
+            .query_first::<u64, _>(
+                "SELECT CAST(COALESCE(SUM(data_length + index_length), 0) AS UNSIGNED) \
+                 FROM information_schema.tables WHERE table_schema = DATABASE()",
+            )
+            .context("failed to query database size")?
+            .context("database size query returned no rows")
+    }
+}
+
+impl ReadReplica for MySqlClient {
+    fn customer_portfolio(&mut self, customer_id: i32) -> Result<u64> {
+        self.conn
+            .exec::<Row, _, _>(
+                &format!(
+                    "SELECT * FROM {}customer_portfolio WHERE customer_id = ?",
+                    self.view_prefix
+                ),
+                (customer_id,),
+            )
+            .map(|rows| rows.len() as u64)
+            .with_context(|| format!("failed to query customer profile {customer_id}"))
+    }
+
+    fn top_performers(&mut self) -> Result<u64> {
+        self.conn
+            .query::<Row, _>(&format!("SELECT * FROM {}top_performers", self.view_prefix))
+            .map(|rows| rows.len() as u64)
+            .with_context(|| "failed to query top_performers".to_string())
+    }
+
+    fn market_overview(&mut self, sector: &str) -> Result<u64> {
+        self.conn
+            .exec::<Row, _, _>(
+                &format!(
+                    "SELECT * FROM {}market_overview WHERE sector = ?",
+                    self.view_prefix
+                ),
+                (sector,),
+            )
+            .map(|rows| rows.len() as u64)
+            .with_context(|| "failed to query market_overview".to_string())
+    }
+
+    fn recent_large_trades(&mut self, account_id: i32) -> Result<u64> {
+        self.conn
+            .exec::<Row, _, _>(
+                &format!(
+                    "SELECT * FROM {}recent_large_trades WHERE account_id = ?",
+                    self.view_prefix
+                ),
+                (account_id,),
+            )
+            .map(|rows| rows.len() as u64)
+            .with_context(|| "failed to query recent_large_trades".to_string())
+    }
+
+    fn customer_order_book(&mut self, customer_id: i32) -> Result<u64> {
+        self.conn
+            .exec::<Row, _, _>(
+                &format!(
+                    "SELECT * FROM {}customer_order_book WHERE customer_id = ?",
+                    self.view_prefix
+                ),
+                (customer_id,),
+            )
+            .map(|rows| rows.len() as u64)
+            .with_context(|| format!("failed to query customer_order_book {customer_id}"))
+    }
+
+    fn sector_performance(&mut self, sector: String) -> Result<u64> {
+        self.conn
+            .exec::<Row, _, _>(
+                &format!(
+                    "SELECT * FROM {}sector_performance WHERE sector = ?",
+                    self.view_prefix
+                ),
+                (sector,),
+            )
+            .map(|rows| rows.len() as u64)
+            .with_context(|| "failed to query sector_performance".to_string())
+    }
+
+    fn account_activity_summary(&mut self, account_id: i32) -> Result<u64> {
+        self.conn
+            .exec::<Row, _, _>(
+                &format!(
+                    "SELECT * FROM {}account_activity_summary WHERE account_id = ?",
+                    self.view_prefix
+                ),
+                (account_id,),
+            )
+            .map(|rows| rows.len() as u64)
+            .with_context(|| format!("failed to query account_activity_summary {account_id}"))
+    }
+
+    fn daily_market_movements(&mut self, security_id: i32) -> Result<u64> {
+        self.conn
+            .exec::<Row, _, _>(
+                &format!(
+                    "SELECT * FROM {}daily_market_movements WHERE security_id = ?",
+                    self.view_prefix
+                ),
+                (security_id,),
+            )
+            .map(|rows| rows.len() as u64)
+            .with_context(|| format!("failed to query daily_market_movements {security_id}"))
+    }
+
+    fn high_value_customers(&mut self) -> Result<u64> {
+        self.conn
+            .query::<Row, _>(&format!(
+                "SELECT * FROM {}high_value_customers",
+                self.view_prefix
+            ))
+            .map(|rows| rows.len() as u64)
+            .with_context(|| "failed to query high_value_customers".to_string())
+    }
+
+    fn pending_orders_summary(&mut self, ticker: &str) -> Result<u64> {
+        self.conn
+            .exec::<Row, _, _>(
+                &format!(
+                    "SELECT * FROM {}pending_orders_summary WHERE ticker = ?",
+                    self.view_prefix
+                ),
+                (ticker,),
+            )
+            .map(|rows| rows.len() as u64)
+            .with_context(|| "failed to query pending_orders_summary".to_string())
+    }
+
+    fn trade_volume_by_hour(&mut self) -> Result<u64> {
+        self.conn
+            .query::<Row, _>(&format!(
+                "SELECT * FROM {}trade_volume_by_hour",
+                self.view_prefix
+            ))
+            .map(|rows| rows.len() as u64)
+            .with_context(|| "failed to query trade_volume_by_hour".to_string())
+    }
+
+    fn top_securities_by_sector(&mut self, sector: String) -> Result<u64> {
+        self.conn
+            .exec::<Row, _, _>(
+                &format!(
+                    "SELECT * FROM {}top_securities_by_sector WHERE sector = ?",
+                    self.view_prefix
+                ),
+                (sector,),
+            )
+            .map(|rows| rows.len() as u64)
+            .with_context(|| "failed to query top_securities_by_sector".to_string())
+    }
+
+    fn recent_trades_by_account(&mut self, account_id: i32) -> Result<u64> {
+        self.conn
+            .exec::<Row, _, _>(
+                &format!(
+                    "SELECT * FROM {}recent_trades_by_account WHERE account_id = ?",
+                    self.view_prefix
+                ),
+                (account_id,),
+            )
+            .map(|rows| rows.len() as u64)
+            .with_context(|| format!("failed to query recent_trades_by_account {account_id}"))
+    }
+
+    fn order_fulfillment_rates(&mut self, customer_id: i32) -> Result<u64> {
+        self.conn
+            .exec::<Row, _, _>(
+                &format!(
+                    "SELECT * FROM {}order_fulfillment_rates WHERE customer_id = ?",
+                    self.view_prefix
+                ),
+                (customer_id,),
+            )
+            .map(|rows| rows.len() as u64)
+            .with_context(|| format!("failed to query order_fulfillment_rates {customer_id}"))
+    }
+
+    fn sector_order_activity(&mut self, sector: String) -> Result<u64> {
+        self.conn
+            .exec::<Row, _, _>(
+                &format!(
+                    "SELECT * FROM {}sector_order_activity WHERE sector = ?",
+                    self.view_prefix
+                ),
+                (sector,),
+            )
+            .map(|rows| rows.len() as u64)
+            .with_context(|| "failed to query sector_order_activity".to_string())
+    }
+
+    fn cascading_order_cancellation_alert(&mut self) -> Result<u64> {
+        self.conn
+            .query::<Row, _>(&format!(
+                "SELECT * FROM {}cascading_order_cancellation_alert",
+                self.view_prefix
+            ))
+            .map(|rows| rows.len() as u64)
+            .with_context(|| "failed to query cascading_order_cancellation_alert".to_string())
+    }
+
+    fn check_row_freshness(&mut self, security_id: i32, expected_price: f64) -> Result<bool> {
+        // Prices are stored with four decimal places.
+        self.conn
+            .exec_first::<bool, _, _>(
+                "SELECT EXISTS (SELECT 1 FROM market_data \
+                 WHERE security_id = ? AND ABS(price - ?) < 0.0001)",
+                (security_id, expected_price),
+            )
+            .map(|fresh| fresh != Some(true))
+            .with_context(|| format!("failed to check freshness of market data {security_id}"))
+    }
+
+    fn fetch_customer(&mut self, customer_id: i32) -> Result<Option<String>> {
+        self.conn
+            .exec_first(
+                "SELECT name FROM customers WHERE customer_id = ?",
+                (customer_id,),
+            )
+            .with_context(|| format!("failed to fetch customer {customer_id}"))
+    }
+
+    fn ping(&mut self) -> Result<()> {
+        self.conn
+            .query_drop("SELECT 1")
+            .context("failed to ping the read replica")
+    }
+
+    fn max_observed_token(&mut self) -> Result<Option<i64>> {
+        self.conn
+            .query_first::<Option<i64>, _>("SELECT MAX(token) FROM replication_lag_tokens")
+            .map(Option::flatten)
+            .context("failed to query replication lag token")
+    }
+
+    /// ER_QUERY_TIMEOUT on MySQL, ER_STATEMENT_TIMEOUT on MariaDB.
+    fn is_timeout(&self, error: &anyhow::Error) -> bool {
+        matches!(
+            error.downcast_ref::<mysql::Error>(),
+            Some(mysql::Error::MySqlError(e)) if e.code == 3024 || e.code == 1969
+        )
+    }
+}
@@ -0,0 +1,831 @@
+use anyhow::{bail, Context, Result};
+use openssl::ssl::{SslConnector, SslMethod, SslVerifyMode};
+use pg_bigdecimal::{BigDecimal, PgNumeric};
+use postgres_openssl::MakeTlsConnector;
+use rr_bench_base::clap::{value_parser, Arg, ArgAction, ArgMatches};
+use rr_bench_base::operations::WriteOperation;
+use rr_bench_base::rand::rngs::StdRng;
+use rr_bench_base::{
+    benchmark, reader_urls, writer_url, Benchmark, ConnectionString, Consume, Credentials, Metrics,
+    PrimaryDatabase, RandomIdCache, ReadReplica,
+};
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::runtime::{Builder, Runtime};
+use tokio_postgres::config::Host;
+use tokio_postgres::error::SqlState;
+use tokio_postgres::types::{FromSql, ToSql};
+use tokio_postgres::{Client, Config, NoTls};
+
+/// The URL schemes accepted for --writer-url and --reader-url.
+const SCHEMES: &[&str] = &["postgres", "postgresql"];
+
+/// The views created by --apply-schema, shared with rr-bench-postgres.
+const PG_VIEWS: &str = include_str!("../../rr-bench-postgres/pg_views.sql");
+
+fn main() {
+    benchmark(
+        || {
+            [
+                Arg::new("writer")
+                    .long("writer-url")
+                    .help("The URL to the writer node. Defaults to $RR_BENCH_WRITER_URL"),
+                Arg::new("reader")
+                    .long("reader-url")
+                    .action(ArgAction::Append)
+                    .help("The URL to a reader node. Repeat to spread clients across several read replicas. Defaults to $RR_BENCH_READER_URL"),
+                Arg::new("runtime_threads")
+                    .long("runtime-threads")
+                    .value_name("THREADS")
+                    .default_value("1")
+                    .value_parser(value_parser!(u32).range(1..))
+                    .help("The number of threads driving every database connection"),
+                Arg::new("credentials")
+                    .long("credentials")
+                    .value_name("PATH")
+                    .value_parser(value_parser!(PathBuf))
+                    .help("A file with user and password lines that replace the credentials in --writer-url and --reader-url"),
+            ]
+        },
+        AsyncPostgresBenchmark::new,
+    )
+}
+
+/// Every connection is driven by a task on a single shared runtime, so the
+/// connections' I/O is multiplexed onto `--runtime-threads` threads instead
+/// of being performed by the benchmark's client threads.
+struct AsyncPostgresBenchmark {
+    writer_url: String,
+    reader_urls: Vec<String>,
+    compare_reader_url: Option<String>,
+    /// Round-robin position into `reader_urls`.
+    next_reader: AtomicUsize,
+    rt: Arc<Runtime>,
+    view_prefix: String,
+    query_timeout: Option<Duration>,
+    session_settings: Vec<(String, String)>,
+    consume: Consume,
+}
+
+impl AsyncPostgresBenchmark {
+    fn new(args: ArgMatches) -> Result<Self> {
+        let credentials = args
+            .get_one::<PathBuf>("credentials")
+            .map(|path| Credentials::load(path))
+            .transpose()?;
+        let parse_url = |url: &str, flag: &str| -> Result<String> {
+            let mut url =
+                ConnectionString::parse(url, SCHEMES).with_context(|| format!("invalid {flag}"))?;
+            if url.pool_size().is_some() {
+                bail!("invalid {flag}: pool_size is not supported, every client opens its own connection");
+            }
+            if let Some(credentials) = &credentials {
+                url.set_credentials(credentials);
+            }
+            Ok(url.url().to_string())
+        };
+
+        let (writer, writer_source) = writer_url(&args)?;
+        let writer_url = parse_url(&writer, writer_source)?;
+        let (readers, reader_source) = reader_urls(&args)?;
+        let reader_urls = readers
+            .iter()
+            .map(|reader| parse_url(reader, reader_source))
+            .collect::<Result<Vec<_>>>()?;
+        let compare_reader_url = args
+            .get_one::<String>("compare_reader_url")
+            .map(|reader| parse_url(reader, "--compare-reader-url"))
+            .transpose()?;
+
+        let rt = Builder::new_multi_thread()
+            .worker_threads(*args.get_one::<u32>("runtime_threads").unwrap() as usize)
+            .enable_all()
+            .build()
+            .context("failed to start the async runtime")?;
+
+        Ok(Self {
+            writer_url,
+            reader_urls,
+            compare_reader_url,
+            next_reader: AtomicUsize::new(0),
+            rt: Arc::new(rt),
+            view_prefix: args
+                .get_one::<String>("view_prefix")
+                .cloned()
+                .unwrap_or_default(),
+            query_timeout: args.get_one::<Duration>("query_timeout").copied(),
+            session_settings: args
+                .get_many::<(String, String)>("session_setting")
+                .map(|settings| settings.cloned().collect())
+                .unwrap_or_default(),
+            consume: *args.get_one::<Consume>("consume").unwrap(),
+        })
+    }
+
+    /// Connects to `url`, setting `statement_timeout` to `query_timeout`.
+    fn connect(&self, url: &str, query_timeout: Option<Duration>) -> Result<AsyncPostgresClient> {
+        Ok(AsyncPostgresClient {
+            client: connect(&self.rt, url, query_timeout, &self.session_settings)?,
+            url: url.to_string(),
+            rt: self.rt.clone(),
+            ids: RandomIds::default(),
+            view_prefix: self.view_prefix.clone(),
+            query_timeout,
+            session_settings: self.session_settings.clone(),
+            consume: self.consume,
+        })
+    }
+}
+
+impl Benchmark<'_> for AsyncPostgresBenchmark {
+    type Writer = AsyncPostgresClient;
+    type Reader = AsyncPostgresClient;
+
+    fn primary_database(&self, _metrics: &Metrics) -> Result<Self::Writer> {
+        self.connect(&self.writer_url, None)
+    }
+
+    fn read_replica(&self) -> Result<Self::Reader> {
+        let next = self.next_reader.fetch_add(1, Ordering::Relaxed);
+        self.connect(
+            &self.reader_urls[next % self.reader_urls.len()],
+            self.query_timeout,
+        )
+    }
+
+    fn compare_read_replica(&self) -> Result<Self::Reader> {
+        let url = self
+            .compare_reader_url
+            .as_deref()
+            .context("missing --compare-reader-url")?;
+        self.connect(url, self.query_timeout)
+    }
+
+    /// Creates the views on the first read replica only, since
+    /// replicas of the same database share its views.
+    fn apply_schema(&self) -> Result<()> {
+        let reader = self.connect(&self.reader_urls[0], None)?;
+        self.rt
+            .block_on(reader.client.batch_execute(PG_VIEWS))
+            .context("failed to create views")
+    }
+}
+
+/// Opens a connection to `url`, spawns the task that drives it on `rt`, and
+/// applies `query_timeout` and the `--session-setting`s to it.
+///
+/// Local Unix domain sockets, such as `host=/var/run/postgresql`, are
+/// connected to without TLS.
+fn connect(
+    rt: &Runtime,
+    url: &str,
+    query_timeout: Option<Duration>,
+    session_settings: &[(String, String)],
+) -> Result<Client> {
+    let config = url.parse::<Config>().context("invalid postgres URL")?;
+    let hosts = config.get_hosts();
+    let client = if !hosts.is_empty() && hosts.iter().all(|host| matches!(host, Host::Unix(_))) {
+        let (client, connection) = rt
+            .block_on(config.connect(NoTls))
+            .context("failed to open postgres client")?;
+        rt.spawn(drive(connection));
+        client
+    } else {
+        let mut builder =
+            SslConnector::builder(SslMethod::tls()).context("Error creating ssl builder")?;
+        builder.set_verify(SslVerifyMode::NONE);
+        let tls = MakeTlsConnector::new(builder.build());
+        let (client, connection) = rt
+            .block_on(config.connect(tls))
+            .context("failed to open postgres client")?;
+        rt.spawn(drive(connection));
+        client
+    };
+    if let Some(timeout) = query_timeout {
+        rt.block_on(
+            client.batch_execute(&format!("SET statement_timeout = {}", timeout.as_millis())),
+        )
+        .context("failed to set statement_timeout")?;
+    }
+    if !session_settings.is_empty() {
+        let statements = session_settings
+            .iter()
+            .map(|(key, value)| format!("SET {key} = '{}';", value.replace('\'', "''")))
+            .collect::<String>();
+        rt.block_on(client.batch_execute(&statements))
+            .context("failed to apply --session-setting")?;
+    }
+    Ok(client)
+}
+
+/// Performs the I/O of a connection until it is closed.
+async fn drive(connection: impl Future<Output = Result<(), tokio_postgres::Error>>) {
+    if let Err(e) = connection.await {
+        rr_bench_base::log::error!("postgres connection closed: {e}");
+    }
+}
+
+/// A connection that is driven by the shared runtime. The benchmark's client
+/// threads block on each query until the runtime has completed it.
+struct AsyncPostgresClient {
+    client: Client,
+    /// The URL `client` is connected to, kept to reconnect.
+    url: String,
+    rt: Arc<Runtime>,
+    ids: RandomIds,
+    view_prefix: String,
+    /// The `statement_timeout` set on the connection, kept to reconnect.
+    query_timeout: Option<Duration>,
+    /// The `--session-setting`s set on the connection, kept to reconnect.
+    session_settings: Vec<(String, String)>,
+    consume: Consume,
+}
+
+/// The cached id ranges used to pick random rows from each table.
+#[derive(Default)]
+struct RandomIds {
+    customers: RandomIdCache,
+    accounts: RandomIdCache,
+    securities: RandomIdCache,
+    trades: RandomIdCache,
+    orders: RandomIdCache,
+    market_data: RandomIdCache,
+}
+
+impl AsyncPostgresClient {
+    /// Runs a read query on the runtime, consuming its result as --consume
+    /// asks, and returns the number of rows it produced. As in
+    /// rr-bench-postgres, `first-row` fetches a single row from a portal and
+    /// `count` wraps the query in `SELECT count(*)`.
+    fn query(
+        &mut self,
+        query: &str,
+        params: &[&(dyn ToSql + Sync)],
+    ) -> Result<u64, tokio_postgres::Error> {
+        let client = &mut self.client;
+        self.rt.block_on(async {
+            match self.consume {
+                Consume::AllRows => Ok(client.query(query, params).await?.len() as u64),
+                Consume::FirstRow => {
+                    let transaction = client.transaction().await?;
+                    let portal = transaction.bind(query, params).await?;
+                    let rows = transaction.query_portal(&portal, 1).await?;
+                    transaction.rollback().await?;
+                    Ok(rows.len() as u64)
+                }
+                Consume::Count => {
+                    let row = client
+                        .query_one(&format!("SELECT count(*) FROM ({query}) AS result"), params)
+                        .await?;
+                    Ok(row.get::<_, i64>(0) as u64)
+                }
+            }
+        })
+    }
+
+    /// The name of `view`, including the --view-prefix.
+    fn view(&self, view: &str) -> String {
+        format!("{}{view}", self.view_prefix)
+    }
+}
+
+/// Returns `column` from a random row of `table`, chosen by sampling an id from
+/// the cached id range and selecting the first row at or after it. When rows at
+/// the end of the range have been deleted, the last row before it is used instead.
+fn random_row<T: for<'a> FromSql<'a>>(
+    rt: &Runtime,
+    client: &Client,
+    cache: &mut RandomIdCache,
+    rng: &mut StdRng,
+    table: &str,
+    id: &str,
+    column: &str,
+) -> Result<T> {
+    let sampled = cache.sample(rng, || {
+        let row = rt
+            .block_on(client.query_one(&format!("SELECT MIN({id}), MAX({id}) FROM {table}"), &[]))
+            .with_context(|| format!("failed to query the {id} range"))?;
+        Ok(row.get::<_, Option<i32>>(0).zip(row.get(1)))
+    })?;
+
+    let after = format!("SELECT {column} FROM {table} WHERE {id} >= $1 ORDER BY {id} LIMIT 1");
+    let before = format!("SELECT {column} FROM {table} WHERE {id} < $1 ORDER BY {id} DESC LIMIT 1");
+    rt.block_on(async {
+        let row = match client.query_opt(&after, &[&sampled]).await? {
+            Some(row) => row,
+            None => client.query_one(&before, &[&sampled]).await?,
+        };
+
+        Ok(row.get(0))
+    })
+}
+
+impl PrimaryDatabase for AsyncPostgresClient {
+    fn get_random_customer_id(&mut self, rng: &mut StdRng) -> Result<i32> {
+        random_row(
+            &self.rt,
+            &self.client,
+            &mut self.ids.customers,
+            rng,
+            "customers",
+            "customer_id",
+            "customer_id",
+        )
+        .context("failed to query customer id")
+    }
+
+    fn get_random_account_id(&mut self, rng: &mut StdRng) -> Result<i32> {
+        random_row(
+            &self.rt,
+            &self.client,
+            &mut self.ids.accounts,
+            rng,
+            "accounts",
+            "account_id",
+            "account_id",
+        )
+        .context("failed to query account id")
+    }
+
+    fn get_random_security_id(&mut self, rng: &mut StdRng) -> Result<i32> {
+        random_row(
+            &self.rt,
+            &self.client,
+            &mut self.ids.securities,
+            rng,
+            "securities",
+            "security_id",
+            "security_id",
+        )
+        .context("failed to query security id")
+    }
+
+    fn get_random_trade_id(&mut self, rng: &mut StdRng) -> Result<i32> {
+        random_row(
+            &self.rt,
+            &self.client,
+            &mut self.ids.trades,
+            rng,
+            "trades",
+            "trade_id",
+            "trade_id",
+        )
+        .context("failed to query trade id")
+    }
+
+    fn get_random_order_id(&mut self, rng: &mut StdRng) -> Result<i32> {
+        random_row(
+            &self.rt,
+            &self.client,
+            &mut self.ids.orders,
+            rng,
+            "orders",
+            "order_id",
+            "order_id",
+        )
+        .context("failed to query order id")
+    }
+
+    fn get_random_market_data_id(&mut self, rng: &mut StdRng) -> Result<i32> {
+        random_row(
+            &self.rt,
+            &self.client,
+            &mut self.ids.market_data,
+            rng,
+            "market_data",
+            "market_data_id",
+            "market_data_id",
+        )
+        .context("failed to query market data id")
+    }
+
+    fn get_random_ticker(&mut self, rng: &mut StdRng) -> Result<String> {
+        random_row(
+            &self.rt,
+            &self.client,
+            &mut self.ids.securities,
+            rng,
+            "securities",
+            "security_id",
+            "ticker",
+        )
+        .context("failed to query ticker")
+    }
+
+    fn get_random_sector(&mut self, rng: &mut StdRng) -> Result<String> {
+        random_row(
+            &self.rt,
+            &self.client,
+            &mut self.ids.securities,
+            rng,
+            "securities",
+            "security_id",
+            "sector",
+        )
+        .context("failed to query sector")
+    }
+
+    fn execute_command(&self, op: WriteOperation) -> Result<()> {
+        self.rt.block_on(execute(&self.client, op))
+    }
+
+    fn execute_batch(&self, ops: Vec<WriteOperation>) -> Result<()> {
+        self.rt.block_on(async {
+            self.client
+                .batch_execute("BEGIN")
+                .await
+                .context("failed to begin transaction")?;
+            for op in ops {
+                if let Err(e) = execute(&self.client, op).await {
+                    // The error that aborted the batch is more useful than a failed rollback.
+                    let _ = self.client.batch_execute("ROLLBACK").await;
+                    return Err(e);
+                }
+            }
+            self.client
+                .batch_execute("COMMIT")
+                .await
+                .context("failed to commit transaction")
+        })
+    }
+
+    /// Serialization failures and deadlocks abort the whole transaction,
+    /// which can be executed again.
+    fn is_conflict(&self, error: &anyhow::Error) -> bool {
+        error
+            .downcast_ref::<tokio_postgres::Error>()
+            .and_then(tokio_postgres::Error::code)
+            .is_some_and(|code| {
+                *code == SqlState::T_R_SERIALIZATION_FAILURE
+                    || *code == SqlState::T_R_DEADLOCK_DETECTED
+            })
+    }
+
+    fn insert_lag_token(&self, token: i64) -> Result<()> {
+        self.rt
+            .block_on(self.client.execute(
+                "INSERT INTO replication_lag_tokens (token) VALUES ($1)",
+                &[&token],
+            ))
+            .map(|_| ())
+            .context("failed to insert replication lag token")
+    }
+
+    fn insert_customer(&self, name: &str, address: &str) -> Result<i32> {
+        self.rt
+            .block_on(self.client.query_one(
+                "INSERT INTO customers (name, address) VALUES ($1, $2) RETURNING customer_id",
+                &[&name, &address],
+            ))
+            .map(|row| row.get("customer_id"))
+            .context("failed to insert customer")
+    }
+
+    fn database_size(&mut self) -> Result<u64> {
+        self.rt
+            .block_on(
+                self.client
+                    .query_one("SELECT pg_database_size(current_database())", &[]),
+            )
+            .map(|row| row.get::<_, i64>(0) as u64)
+            .context("failed to query database size")
+    }
+}
+
+/// Executes a single write operation on `client`.
+async fn execute(client: &Client, op: WriteOperation) -> Result<()> {
+    match op {
+        WriteOperation::InsertCustomer { name, address } => client.execute(
+            "INSERT INTO customers (name, address) VALUES ($1, $2)", &[&name, &address])
+            .await.map(|_| ())
+            .context("failed to insert customer"),
+        WriteOperation::InsertAccount { customer_id, account_type, balance, parent_account_id } => {
+            match parent_account_id {
+                None => {
+                    client.execute("INSERT INTO accounts (customer_id, account_type, balance) VALUES ($1, $2, $3)", &[&customer_id, &account_type, &PgNumeric::new(Some(BigDecimal::try_from(balance).unwrap()))])
+                        .await.map(|_| ())
+                        .context("failed to insert account")
+                }
+                Some(parent_account_id) => {
+                    client.execute("INSERT INTO accounts (customer_id, account_type, balance, parent_account_id) VALUES ($1, $2, $3, $4)", &[&customer_id, &account_type, &PgNumeric::new(Some(BigDecimal::try_from(balance).unwrap())), &parent_account_id])
+                        .await.map(|_| ())
+                        .context("failed to insert account")
+                }
+            }
+        },
+        WriteOperation::InsertSecurity { ticker, name, sector } => {
+            client.execute("INSERT INTO securities (ticker, name, sector) VALUES ($1, $2, $3)", &[&ticker, &name, &sector])
+                .await.map(|_| ())
+                .context("failed to insert security")
+        },
+
+        WriteOperation::InsertTrade { account_id, security_id, trade_type, quantity, price, parent_trade_id } => {
+            match parent_trade_id {
+                None =>
+                    client.execute("INSERT INTO trades (account_id, security_id, trade_type, quantity, price) VALUES ($1, $2, $3, $4, $5)", &[&account_id, &security_id, &trade_type, &quantity, &PgNumeric::new(Some(BigDecimal::try_from(price).unwrap()))])
+                        .await.map(|_| ())
+                        .context("failed to insert trades"),
+                Some(parent_trade_id) => client.execute("INSERT INTO trades (account_id, security_id, trade_type, quantity, price, parent_trade_id) VALUES ($1, $2, $3, $4, $5, $6)", &[&account_id, &security_id, &trade_type, &quantity, &PgNumeric::new(Some(BigDecimal::try_from(price).unwrap())), &parent_trade_id])
+                    .await.map(|_| ())
+                    .context("failed to insert trades")
+            }
+        },
+
+        WriteOperation::InsertOrder { account_id, security_id, order_type, quantity, limit_price,  status, parent_order_id} => {
+            match parent_order_id  {
+                None => client
+                    .execute("INSERT INTO orders (account_id, security_id, order_type, quantity, limit_price, status) VALUES ($1, $2, $3, $4, $5, $6)",
+                                       &[&account_id, &security_id, &order_type, &quantity, &limit_price.map(|p| PgNumeric::new(Some(BigDecimal::try_from(p).unwrap()))), &status])
+                    .await.map(|_| ())
+                    .context("failed to insert order"),
+                Some(parent_order_id) => client
+                    .execute("INSERT INTO orders (account_id, security_id, order_type, quantity, limit_price, status, parent_order_id) VALUES ($1, $2, $3, $4, $5, $6, $7)",
+                             &[&account_id, &security_id, &order_type, &quantity, &limit_price.map(|p| PgNumeric::new(Some(BigDecimal::try_from(p).unwrap()))), &status, &parent_order_id])
+                    .await.map(|_| ())
+                    .context("failed to insert order"),
+            }
+        },
+        WriteOperation::InsertMarketData { security_id, price, volume } => client
+            .execute("INSERT INTO market_data (security_id, price, volume) VALUES ($1, $2, $3)",
+                     &[&security_id, &PgNumeric::new(Some(BigDecimal::try_from(price).unwrap())), &volume])
+            .await.map(|_| ())
+            .context("failed to insert market data"),
+        WriteOperation::UpdateCustomer { customer_id, address } => client
+            .execute("UPDATE customers SET address = $1 WHERE customer_id = $2",&[&address, &customer_id])
+            .await.map(|_| ())
+            .context("failed to update customer"),
+        WriteOperation::UpdateAccount { account_id, balance } => client
+            .execute("UPDATE accounts SET balance = $1 WHERE account_id = $2", &[
+                &PgNumeric::new(Some(BigDecimal::try_from(balance).unwrap())),
+                &account_id
+            ]).await.map(|_| ())
+            .context("failed to update account"),
+        WriteOperation::UpdateTrade { trade_id, price } => client
+            .execute("UPDATE trades SET price = $1 WHERE trade_id = $2", &[
+                    &PgNumeric::new(Some(BigDecimal::try_from(price).unwrap())),
+                &trade_id
+            ]).await.map(|_| ())
+            .context("failed to update trades"),
+        WriteOperation::UpdateOrder { order_id, status, limit_price } => client
+            .execute("UPDATE orders SET status = $1, limit_price = $2 WHERE order_id = $3",&[
+                    &status,
+                    &limit_price.map(|p| PgNumeric::new(Some(BigDecimal::try_from(p).unwrap()))),
+                    &order_id
+            ]).await.map(|_| ())
+            .context("failed to update orders"),
+        WriteOperation::UpdateMarketData { market_data_id, price, volume } => client
+            .execute("UPDATE market_data SET price = $1, volume = $2, market_date = CURRENT_TIMESTAMP WHERE market_data_id = $3", &[
+                        &PgNumeric::new(Some(BigDecimal::try_from(price).unwrap())),
+                        &volume,
+                        &market_data_id
+            ]).await.map(|_| ())
+            .context("failed to update market_data"),
+        WriteOperation::DeleteCustomer { customer_id } => client
+            .execute("DELETE FROM customers WHERE customer_id = $1", &[&customer_id])
+            .await.map(|_| ())
+            .context("failed to delete customer"),
+        WriteOperation::DeleteAccount { account_id } => client.execute("DELETE FROM accounts WHERE account_id = $1", &[&account_id])
+            .await.map(|_| ())
+            .context("failed to delete accounts"),
+        WriteOperation::DeleteSecurity { security_id } => client.execute("DELETE FROM securities WHERE security_id = $1", &[&security_id])
+            .await.map(|_| ())
+            .context("failed to delete security"),
+        WriteOperation::DeleteTrade { trade_id } => client.execute("DELETE FROM trades WHERE trade_id = $1", &[&trade_id])
+            .await.map(|_| ())
+            .context("failed to delete trades"),
+        WriteOperation::DeleteOrder { order_id } => client.execute("DELETE FROM orders WHERE order_id = $1", &[&order_id])
+            .await.map(|_| ())
+            .context("failed to delete orders"),
+        WriteOperation::DeleteMarketData { market_data_id } => client.execute("DELETE FROM market_data WHERE market_data_id = $1", &[&market_data_id])
+            .await.map(|_| ())
+            .context("failed to delete market_data")
+    }
+}
+
+impl ReadReplica for AsyncPostgresClient {
+    fn customer_portfolio(&mut self, customer_id: i32) -> Result<u64> {
+        self.query(
+            &format!(
+                "SELECT * FROM {} WHERE customer_id = $1",
+                self.view("customer_portfolio")
+            ),
+            &[&customer_id],
+        )
+        .with_context(|| format!("failed to query customer profile {customer_id}"))
+    }
+
+    fn top_performers(&mut self) -> Result<u64> {
+        self.query(
+            &format!("SELECT * FROM {}", self.view("top_performers")),
+            &[],
+        )
+        .with_context(|| "failed to query top_performers".to_string())
+    }
+
+    fn market_overview(&mut self, sector: &str) -> Result<u64> {
+        self.query(
+            &format!(
+                "SELECT * FROM {} WHERE sector = $1",
+                self.view("market_overview")
+            ),
+            &[&sector],
+        )
+        .with_context(|| "failed to query market_overview".to_string())
+    }
+
+    fn recent_large_trades(&mut self, account_id: i32) -> Result<u64> {
+        self.query(
+            &format!(
+                "SELECT * FROM {} WHERE account_id = $1",
+                self.view("recent_large_trades")
+            ),
+            &[&account_id],
+        )
+        .with_context(|| "failed to query recent_large_trades".to_string())
+    }
+
+    fn customer_order_book(&mut self, customer_id: i32) -> Result<u64> {
+        self.query(
+            &format!(
+                "SELECT * FROM {} WHERE customer_id = $1",
+                self.view("customer_order_book")
+            ),
+            &[&customer_id],
+        )
+        .with_context(|| format!("failed to query customer_order_book {customer_id}"))
+    }
+
+    fn sector_performance(&mut self, sector: String) -> Result<u64> {
+        self.query(
+            &format!(
+                "SELECT * FROM {} WHERE sector = $1",
+                self.view("sector_performance")
+            ),
+            &[&sector],
+        )
+        .with_context(|| "failed to query sector_performance".to_string())
+    }
+
+    fn account_activity_summary(&mut self, account_id: i32) -> Result<u64> {
+        self.query(
+            &format!(
+                "SELECT * FROM {} WHERE account_id = $1",
+                self.view("account_activity_summary")
+            ),
+            &[&account_id],
+        )
+        .with_context(|| format!("failed to query account_activity_summary {account_id}"))
+    }
+
+    fn daily_market_movements(&mut self, security_id: i32) -> Result<u64> {
+        self.query(
+            &format!(
+                "SELECT * FROM {} WHERE security_id = $1",
+                self.view("daily_market_movements")
+            ),
+            &[&security_id],
+        )
+        .with_context(|| format!("failed to query daily_market_movements {security_id}"))
+    }
+
+    fn high_value_customers(&mut self) -> Result<u64> {
+        self.query(
+            &format!("SELECT * FROM {}", self.view("high_value_customers")),
+            &[],
+        )
+        .with_context(|| "failed to query high_value_customers".to_string())
+    }
+
+    fn pending_orders_summary(&mut self, ticker: &str) -> Result<u64> {
+        self.query(
+            &format!(
+                "SELECT * FROM {} WHERE ticker = $1",
+                self.view("pending_orders_summary")
+            ),
+            &[&ticker],
+        )
+        .with_context(|| "failed to query pending_orders_summary".to_string())
+    }
+
+    fn trade_volume_by_hour(&mut self) -> Result<u64> {
+        self.query(
+            &format!("SELECT * FROM {}", self.view("trade_volume_by_hour")),
+            &[],
+        )
+        .with_context(|| "failed to query trade_volume_by_hour".to_string())
+    }
+
+    fn top_securities_by_sector(&mut self, sector: String) -> Result<u64> {
+        self.query(
+            &format!(
+                "SELECT * FROM {} WHERE sector = $1",
+                self.view("top_securities_by_sector")
+            ),
+            &[&sector],
+        )
+        .with_context(|| "failed to query top_securities_by_sector".to_string())
+    }
+
+    fn recent_trades_by_account(&mut self, account_id: i32) -> Result<u64> {
+        self.query(
+            &format!(
+                "SELECT * FROM {} WHERE account_id = $1",
+                self.view("recent_trades_by_account")
+            ),
+            &[&account_id],
+        )
+        .with_context(|| format!("failed to query recent_trades_by_account {account_id}"))
+    }
+
+    fn order_fulfillment_rates(&mut self, customer_id: i32) -> Result<u64> {
+        self.query(
+            &format!(
+                "SELECT * FROM {} WHERE customer_id = $1",
+                self.view("order_fulfillment_rates")
+            ),
+            &[&customer_id],
+        )
+        .with_context(|| format!("failed to query order_fulfillment_rates {customer_id}"))
+    }
+
+    fn sector_order_activity(&mut self, sector: String) -> Result<u64> {
+        self.query(
+            &format!(
+                "SELECT * FROM {} WHERE sector = $1",
+                self.view("sector_order_activity")
+            ),
+            &[&sector],
+        )
+        .with_context(|| "failed to query sector_order_activity".to_string())
+    }
+
+    fn cascading_order_cancellation_alert(&mut self) -> Result<u64> {
+        self.query(
+            &format!(
+                "SELECT * FROM {}",
+                self.view("cascading_order_cancellation_alert")
+            ),
+            &[],
+        )
+        .with_context(|| "failed to query cascading_order_cancellation_alert".to_string())
+    }
+
+    fn check_row_freshness(&mut self, security_id: i32, expected_price: f64) -> Result<bool> {
+        // Prices are stored with four decimal places.
+        self.rt
+            .block_on(self.client.query_one(
+                "SELECT EXISTS (SELECT 1 FROM market_data \
+                 WHERE security_id = $1 AND abs(price::float8 - $2) < 0.0001) AS fresh",
+                &[&security_id, &expected_price],
+            ))
+            .map(|row| !row.get::<_, bool>("fresh"))
+            .with_context(|| format!("failed to check freshness of market data {security_id}"))
+    }
+
+    fn fetch_customer(&mut self, customer_id: i32) -> Result<Option<String>> {
+        self.rt
+            .block_on(self.client.query_opt(
+                "SELECT name FROM customers WHERE customer_id = $1",
+                &[&customer_id],
+            ))
+            .map(|row| row.map(|row| row.get("name")))
+            .with_context(|| format!("failed to fetch customer {customer_id}"))
+    }
+
+    fn reconnect(&mut self) -> Result<()> {
+        self.client = connect(
+            &self.rt,
+            &self.url,
+            self.query_timeout,
+            &self.session_settings,
+        )?;
+        Ok(())
+    }
+
+    /// `statement_timeout` cancels the query with `query_canceled`.
+    fn is_timeout(&self, error: &anyhow::Error) -> bool {
+        error
+            .downcast_ref::<tokio_postgres::Error>()
+            .and_then(tokio_postgres::Error::code)
+            == Some(&SqlState::QUERY_CANCELED)
+    }
+
+    fn ping(&mut self) -> Result<()> {
+        self.rt
+            .block_on(self.client.batch_execute("SELECT 1"))
+            .context("failed to ping the read replica")
+    }
+
+    fn max_observed_token(&mut self) -> Result<Option<i64>> {
+        self.rt
+            .block_on(self.client.query_one(
+                "SELECT MAX(token) AS token FROM replication_lag_tokens",
+                &[],
+            ))
+            .context("failed to query replication lag token")
+            .map(|row| row.get::<_, Option<i64>>("token"))
+    }
+}
@@ -10,6 +10,7 @@ CREATE TABLE customers (
 CREATE TABLE accounts (
     account_id SERIAL PRIMARY KEY,
     customer_id INT REFERENCES customers(customer_id) ON DELETE CASCADE,
+    parent_account_id INT REFERENCES accounts(account_id) ON DELETE SET NULL,
     account_type VARCHAR(50) NOT NULL,
     balance DECIMAL(18, 2) NOT NULL,
     created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
@@ -27,6 +28,7 @@ CREATE TABLE trades (
     trade_id SERIAL PRIMARY KEY,
     account_id INT REFERENCES accounts(account_id) ON DELETE CASCADE,
     security_id INT REFERENCES securities(security_id) ON DELETE CASCADE,
+    parent_trade_id INT REFERENCES trades(trade_id) ON DELETE SET NULL,
     trade_type VARCHAR(10) NOT NULL CHECK (trade_type IN ('buy', 'sell')),
     quantity INT NOT NULL,
     price DECIMAL(18, 4) NOT NULL,
@@ -37,6 +39,7 @@ CREATE TABLE orders (
     order_id SERIAL PRIMARY KEY,
     account_id INT REFERENCES accounts(account_id) ON DELETE CASCADE,
     security_id INT REFERENCES securities(security_id) ON DELETE CASCADE,
+    parent_order_id INT REFERENCES orders(order_id) ON DELETE SET NULL,
     order_type VARCHAR(10) NOT NULL CHECK (order_type IN ('buy', 'sell')),
     quantity INT NOT NULL,
     limit_price DECIMAL(18, 4),
@@ -51,3 +54,10 @@ CREATE TABLE market_data (
     volume INT NOT NULL,
     market_date TIMESTAMP DEFAULT CURRENT_TIMESTAMP
 );
+
+-- Tokens written by the primary and polled on the read
+-- replica when measuring replication lag (--measure-lag).
+CREATE TABLE replication_lag_tokens (
+    token BIGINT PRIMARY KEY,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
@@ -74,7 +74,7 @@ WITH last_two_days AS (
             WHERE md.security_id = grp.security_id AND md.market_date + INTERVAL '1 day' > now()
             ORDER BY md.market_date DESC
             LIMIT 2
-        )
+        ) recent
 ),
 
 stg AS (
@@ -130,7 +130,7 @@ FROM (SELECT DISTINCT sector FROM securities) grp,
         GROUP BY s.sector, s.ticker, s.name
         ORDER BY total_volume DESC
         LIMIT 5
-);
+    ) top;
 
 
 CREATE VIEW recent_trades_by_account AS
@@ -159,3 +159,40 @@ FROM orders o
 JOIN securities s ON o.security_id = s.security_id
 GROUP BY s.sector;
 
+CREATE VIEW cascading_order_cancellation_alert AS
+WITH RECURSIVE order_cancellations AS (
+    SELECT
+        o.order_id,
+        o.account_id,
+        o.security_id,
+        o.status,
+        o.order_date,
+        NULL::INTEGER AS parent_order_id,
+        0 AS cancellation_depth
+    FROM orders o
+    WHERE o.status = 'canceled'
+    AND o.order_date = (
+        SELECT MAX(o2.order_date)
+        FROM orders o2
+        WHERE o.security_id = o2.security_id
+    )
+
+    UNION ALL
+
+    SELECT
+        o.order_id,
+        o.account_id,
+        o.security_id,
+        o.status,
+        o.order_date,
+        oc.order_id AS parent_order_id,
+        oc.cancellation_depth + 1 AS cancellation_depth
+    FROM orders o
+    JOIN order_cancellations oc
+    ON o.security_id = oc.security_id
+    AND o.status = 'canceled'
+    AND o.order_date > oc.order_date
+)
+SELECT *
+FROM order_cancellations
+WHERE cancellation_depth > 0;
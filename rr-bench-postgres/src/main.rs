@@ -1,13 +1,36 @@
-use anyhow::{Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use openssl::ssl::{SslConnector, SslMethod, SslVerifyMode};
 use pg_bigdecimal::{BigDecimal, PgNumeric};
-use postgres::Client;
+use postgres::config::Host;
+use postgres::error::SqlState;
+use postgres::fallible_iterator::FallibleIterator;
+use postgres::types::{FromSql, ToSql};
+use postgres::{Client, Config, GenericClient, NoTls};
 use postgres_openssl::MakeTlsConnector;
-use r2d2_postgres::r2d2::Pool;
+use r2d2_postgres::r2d2::{self, Pool, PooledConnection, State};
 use r2d2_postgres::PostgresConnectionManager;
-use rr_bench_base::clap::{Arg, ArgMatches};
-use rr_bench_base::operations::WriteOperation;
-use rr_bench_base::{benchmark, Benchmark, PrimaryDatabase, ReadReplica};
+use rr_bench_base::clap::{value_parser, Arg, ArgAction, ArgMatches};
+use rr_bench_base::log::warn;
+use rr_bench_base::operations::{IdKind, WriteOperation};
+use rr_bench_base::rand::rngs::StdRng;
+use rr_bench_base::{
+    benchmark, connect_with_retries_from, reader_urls, writer_url, Benchmark, ConnectionString,
+    Consume, Credentials, Metrics, PrimaryDatabase, RandomIdCache, ReadReplica,
+};
+use std::ops::{Deref, DerefMut};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+/// The URL schemes accepted for --writer-url and --reader-url.
+const SCHEMES: &[&str] = &["postgres", "postgresql"];
+
+/// The views created by --apply-schema on Postgres, and on Materialize,
+/// which maintains them incrementally.
+const PG_VIEWS: &str = include_str!("../pg_views.sql");
+const MZ_VIEWS: &str = include_str!("../mz_views.sql");
+/// The indexes created on the primary database by --apply-schema.
+const PG_INDEXES: &str = include_str!("../pg_indexes.sql");
 
 fn main() {
     benchmark(
@@ -15,12 +38,41 @@ fn main() {
             [
                 Arg::new("writer")
                     .long("writer-url")
-                    .required(true)
-                    .help("The URL to the writer node"),
+                    .help("The URL to the writer node. Defaults to $RR_BENCH_WRITER_URL"),
                 Arg::new("reader")
                     .long("reader-url")
-                    .required(true)
-                    .help("The URL to the reader node"),
+                    .action(ArgAction::Append)
+                    .help("The URL to a reader node. Repeat to spread clients across several read replicas. Defaults to $RR_BENCH_READER_URL"),
+                Arg::new("pool_size")
+                    .long("pool-size")
+                    .default_value("10")
+                    .value_parser(value_parser!(u32).range(1..))
+                    .help("The maximum number of connections to the writer node. Overridden by a pool_size parameter in --writer-url"),
+                Arg::new("pool_timeout")
+                    .long("pool-timeout")
+                    .default_value("30s")
+                    .value_parser(humantime::parse_duration)
+                    .help(
+                        "How long to wait for a connection to the writer node (e.g., 500ms, 30s)",
+                    ),
+                Arg::new("crdb")
+                    .long("crdb")
+                    .action(ArgAction::SetTrue)
+                    .help("Run against CockroachDB instead of Postgres"),
+                Arg::new("follower_reads")
+                    .long("follower-reads")
+                    .action(ArgAction::SetTrue)
+                    .requires("crdb")
+                    .help("Run every read query AS OF SYSTEM TIME follower_read_timestamp(), so CockroachDB can serve it from the nearest replica"),
+                Arg::new("credentials")
+                    .long("credentials")
+                    .value_name("PATH")
+                    .value_parser(value_parser!(PathBuf))
+                    .help("A file with user and password lines that replace the credentials in --writer-url and --reader-url"),
+                Arg::new("allow_same_endpoint")
+                    .long("allow-same-endpoint")
+                    .action(ArgAction::SetTrue)
+                    .help("Don't warn when a --reader-url connects to the same database as --writer-url"),
             ]
         },
         PostgresBenchmark::new,
@@ -28,31 +80,131 @@ fn main() {
 }
 
 struct PostgresBenchmark {
-    reader_url: String,
-    pool: Pool<PostgresConnectionManager<MakeTlsConnector>>,
+    reader_urls: Vec<String>,
+    compare_reader_url: Option<String>,
+    /// Round-robin position into `reader_urls`.
+    next_reader: AtomicUsize,
+    pool: WriterPool,
+    view_prefix: String,
+    crdb: bool,
+    follower_reads: bool,
+    query_timeout: Option<Duration>,
+    session_settings: Vec<(String, String)>,
+    consume: Consume,
+    apply_schema: bool,
 }
 
 impl PostgresBenchmark {
     fn new(args: ArgMatches) -> Result<Self> {
-        let writer = args
-            .get_one::<String>("writer")
-            .context("missing required argument writer-url")?
-            .to_string();
+        let follower_reads = args.get_flag("follower_reads");
+        let consume = *args.get_one::<Consume>("consume").unwrap();
+        // CockroachDB only accepts AS OF SYSTEM TIME on the outermost query
+        // of an implicit transaction.
+        if follower_reads && consume != Consume::AllRows {
+            bail!("--follower-reads only supports --consume all-rows");
+        }
 
-        let reader_url = args
-            .get_one::<String>("reader")
-            .context("missing required argument writer-url")?
-            .to_string();
+        let credentials = args
+            .get_one::<PathBuf>("credentials")
+            .map(|path| Credentials::load(path))
+            .transpose()?;
 
-        let mut builder =
-            SslConnector::builder(SslMethod::tls()).context("Error creating ssl builder")?;
-        builder.set_verify(SslVerifyMode::NONE);
-        let tls = MakeTlsConnector::new(builder.build());
+        let (writer, writer_source) = writer_url(&args)?;
+        let mut writer = ConnectionString::parse(&writer, SCHEMES)
+            .with_context(|| format!("invalid {writer_source}"))?;
+        if let Some(credentials) = &credentials {
+            writer.set_credentials(credentials);
+        }
+        let config = writer
+            .url()
+            .parse::<Config>()
+            .with_context(|| format!("invalid {writer_source}"))?;
 
-        let manager = PostgresConnectionManager::new(writer.parse().unwrap(), tls);
-        let pool = Pool::new(manager).context("failed to create connection pool")?;
+        let parse_reader = |reader: &str, flag: &str| -> Result<String> {
+            let mut reader = ConnectionString::parse(reader, SCHEMES)
+                .with_context(|| format!("invalid {flag}"))?;
+            if reader.pool_size().is_some() {
+                bail!("invalid {flag}: pool_size is only supported in --writer-url");
+            }
+            if let Some(credentials) = &credentials {
+                reader.set_credentials(credentials);
+            }
+            Ok(reader.url().to_string())
+        };
+        let (readers, reader_source) = reader_urls(&args)?;
+        let reader_urls = readers
+            .iter()
+            .map(|reader| parse_reader(reader, reader_source))
+            .collect::<Result<Vec<_>>>()?;
+        if !args.get_flag("allow_same_endpoint") {
+            for reader in &reader_urls {
+                let reader = reader
+                    .parse::<Config>()
+                    .with_context(|| format!("invalid {reader_source}"))?;
+                if endpoint(&reader) == endpoint(&config) {
+                    warn!(
+                        "{reader_source} connects to the same database as {writer_source}, so \
+                         reads are not served by a read replica; pass --allow-same-endpoint \
+                         if this is intended"
+                    );
+                    break;
+                }
+            }
+        }
+        let compare_reader_url = args
+            .get_one::<String>("compare_reader_url")
+            .map(|reader| parse_reader(reader, "--compare-reader-url"))
+            .transpose()?;
+
+        let pool_size = match writer.pool_size() {
+            Some(pool_size) => pool_size,
+            None => *args.get_one::<u32>("pool_size").unwrap(),
+        };
+        let pool_timeout = *args.get_one::<Duration>("pool_timeout").unwrap();
+        let session_settings: Vec<(String, String)> = args
+            .get_many::<(String, String)>("session_setting")
+            .map(|settings| settings.cloned().collect())
+            .unwrap_or_default();
+        // The pool connects when it is built, so building it is retried like
+        // every other connection.
+        let pool = connect_with_retries_from(&args, "primary database", || {
+            let customizer = SessionSettings(session_settings.clone());
+            if is_unix_socket(&config) {
+                let manager = PostgresConnectionManager::new(config.clone(), NoTls);
+                Pool::builder()
+                    .max_size(pool_size)
+                    .connection_timeout(pool_timeout)
+                    .connection_customizer(Box::new(customizer))
+                    .build(manager)
+                    .map(WriterPool::NoTls)
+            } else {
+                let manager = PostgresConnectionManager::new(config.clone(), make_tls()?);
+                Pool::builder()
+                    .max_size(pool_size)
+                    .connection_timeout(pool_timeout)
+                    .connection_customizer(Box::new(customizer))
+                    .build(manager)
+                    .map(WriterPool::Tls)
+            }
+            .context("failed to create connection pool")
+        })?;
 
-        Ok(Self { reader_url, pool })
+        Ok(Self {
+            reader_urls,
+            compare_reader_url,
+            next_reader: AtomicUsize::new(0),
+            pool,
+            view_prefix: args
+                .get_one::<String>("view_prefix")
+                .cloned()
+                .unwrap_or_default(),
+            crdb: args.get_flag("crdb"),
+            follower_reads,
+            query_timeout: args.get_one::<Duration>("query_timeout").copied(),
+            session_settings,
+            consume,
+            apply_schema: args.get_flag("apply_schema"),
+        })
     }
 }
 
@@ -60,388 +212,1146 @@ impl Benchmark<'_> for PostgresBenchmark {
     type Writer = PostgresPooledClient;
     type Reader = PostgresClient;
 
-    fn primary_database(&self) -> Result<Self::Writer> {
+    fn primary_database(&self, metrics: &Metrics) -> Result<Self::Writer> {
         Ok(PostgresPooledClient {
             pool: self.pool.clone(),
+            metrics: metrics.clone(),
+            ids: RandomIds::default(),
+            crdb: self.crdb,
         })
     }
 
     fn read_replica(&self) -> Result<Self::Reader> {
-        PostgresClient::from_url(&self.reader_url)
+        let next = self.next_reader.fetch_add(1, Ordering::Relaxed);
+        PostgresClient::from_url(
+            &self.reader_urls[next % self.reader_urls.len()],
+            &self.view_prefix,
+            self.follower_reads,
+            self.query_timeout,
+            &self.session_settings,
+            self.consume,
+        )
+    }
+
+    fn compare_read_replica(&self) -> Result<Self::Reader> {
+        PostgresClient::from_url(
+            self.compare_reader_url
+                .as_deref()
+                .context("missing --compare-reader-url")?,
+            &self.view_prefix,
+            self.follower_reads,
+            self.query_timeout,
+            &self.session_settings,
+            self.consume,
+        )
+    }
+
+    /// Holds every connection at once, so that each is a different one.
+    fn prime_pool(&self) -> Result<u32> {
+        let mut conns = Vec::with_capacity(self.pool.max_size() as usize);
+        for _ in 0..self.pool.max_size() {
+            let mut conn = self
+                .pool
+                .get()
+                .context("failed to acquire connection from pool")?;
+            conn.batch_execute("SELECT 1")
+                .context("failed to ping the primary database")?;
+            conns.push(conn);
+        }
+        Ok(self.pool.max_size())
+    }
+
+    /// Creates the views on the first read replica only, since
+    /// replicas of the same database share its views.
+    fn apply_schema(&self) -> Result<()> {
+        let mut reader = PostgresClient::from_url(
+            &self.reader_urls[0],
+            "",
+            false,
+            None,
+            &self.session_settings,
+            Consume::AllRows,
+        )?;
+        // mz_version() only exists on Materialize.
+        let views = match reader.client.query_one("SELECT mz_version()", &[]) {
+            Ok(_) => MZ_VIEWS,
+            Err(_) => PG_VIEWS,
+        };
+        reader
+            .client
+            .batch_execute(views)
+            .context("failed to create views")
+    }
+
+    /// Creates the indexes the views rely on on the primary database when
+    /// run with `--apply-schema`, from where they replicate to the replicas.
+    fn setup(&self) -> Result<()> {
+        if !self.apply_schema {
+            return Ok(());
+        }
+        self.pool
+            .get()
+            .context("failed to acquire connection from pool")?
+            .batch_execute(PG_INDEXES)
+            .context("failed to create indexes")
+    }
+}
+
+/// Applies the `--session-setting`s to every connection the writer pool opens.
+#[derive(Debug)]
+struct SessionSettings(Vec<(String, String)>);
+
+impl r2d2::CustomizeConnection<Client, postgres::Error> for SessionSettings {
+    fn on_acquire(&self, client: &mut Client) -> Result<(), postgres::Error> {
+        set_session(client, &self.0)
+    }
+}
+
+/// Runs a SET statement for each of `settings` on `client`.
+fn set_session(client: &mut Client, settings: &[(String, String)]) -> Result<(), postgres::Error> {
+    if settings.is_empty() {
+        return Ok(());
+    }
+    let statements = settings
+        .iter()
+        .map(|(key, value)| format!("SET {key} = '{}';", value.replace('\'', "''")))
+        .collect::<String>();
+    client.batch_execute(&statements)
+}
+
+/// Whether every host in `config` is a Unix domain socket, such as
+/// `host=/var/run/postgresql`. Local sockets are connected to without TLS.
+fn is_unix_socket(config: &Config) -> bool {
+    let hosts = config.get_hosts();
+    !hosts.is_empty() && hosts.iter().all(|host| matches!(host, Host::Unix(_)))
+}
+
+/// Returns the hosts, ports, and database that `config` connects to, filling
+/// in the defaults libpq uses when the URL omits the port or database, so that
+/// two URLs that reach the same database compare equal.
+fn endpoint(config: &Config) -> (&[Host], Vec<u16>, Option<&str>) {
+    let ports = match config.get_ports() {
+        [] => vec![5432],
+        ports => ports.to_vec(),
+    };
+    let dbname = config.get_dbname().or(config.get_user());
+    (config.get_hosts(), ports, dbname)
+}
+
+fn make_tls() -> Result<MakeTlsConnector> {
+    let mut builder =
+        SslConnector::builder(SslMethod::tls()).context("Error creating ssl builder")?;
+    builder.set_verify(SslVerifyMode::NONE);
+    Ok(MakeTlsConnector::new(builder.build()))
+}
+
+/// The connection pool to the writer node, with or without TLS.
+#[derive(Clone)]
+enum WriterPool {
+    Tls(Pool<PostgresConnectionManager<MakeTlsConnector>>),
+    NoTls(Pool<PostgresConnectionManager<NoTls>>),
+}
+
+enum WriterConnection {
+    Tls(PooledConnection<PostgresConnectionManager<MakeTlsConnector>>),
+    NoTls(PooledConnection<PostgresConnectionManager<NoTls>>),
+}
+
+impl WriterPool {
+    fn get(&self) -> Result<WriterConnection, r2d2::Error> {
+        match self {
+            WriterPool::Tls(pool) => pool.get().map(WriterConnection::Tls),
+            WriterPool::NoTls(pool) => pool.get().map(WriterConnection::NoTls),
+        }
+    }
+
+    fn state(&self) -> State {
+        match self {
+            WriterPool::Tls(pool) => pool.state(),
+            WriterPool::NoTls(pool) => pool.state(),
+        }
+    }
+
+    fn max_size(&self) -> u32 {
+        match self {
+            WriterPool::Tls(pool) => pool.max_size(),
+            WriterPool::NoTls(pool) => pool.max_size(),
+        }
+    }
+
+    fn connection_timeout(&self) -> Duration {
+        match self {
+            WriterPool::Tls(pool) => pool.connection_timeout(),
+            WriterPool::NoTls(pool) => pool.connection_timeout(),
+        }
+    }
+}
+
+impl Deref for WriterConnection {
+    type Target = Client;
+
+    fn deref(&self) -> &Client {
+        match self {
+            WriterConnection::Tls(conn) => conn,
+            WriterConnection::NoTls(conn) => conn,
+        }
+    }
+}
+
+impl DerefMut for WriterConnection {
+    fn deref_mut(&mut self) -> &mut Client {
+        match self {
+            WriterConnection::Tls(conn) => conn,
+            WriterConnection::NoTls(conn) => conn,
+        }
     }
 }
 
 struct PostgresPooledClient {
-    pool: Pool<PostgresConnectionManager<MakeTlsConnector>>,
+    pool: WriterPool,
+    /// Records the time spent waiting for a connection from `pool`.
+    metrics: Metrics,
+    ids: RandomIds,
+    /// Whether the primary database is CockroachDB.
+    crdb: bool,
+}
+
+/// The cached id ranges used to pick random rows from each table.
+#[derive(Default)]
+struct RandomIds {
+    customers: RandomIdCache,
+    accounts: RandomIdCache,
+    securities: RandomIdCache,
+    trades: RandomIdCache,
+    orders: RandomIdCache,
+    market_data: RandomIdCache,
+}
+
+impl RandomIds {
+    fn get(&mut self, kind: IdKind) -> &mut RandomIdCache {
+        match kind {
+            IdKind::Customer => &mut self.customers,
+            IdKind::Account => &mut self.accounts,
+            IdKind::Security => &mut self.securities,
+            IdKind::Trade => &mut self.trades,
+            IdKind::Order => &mut self.orders,
+            IdKind::MarketData => &mut self.market_data,
+        }
+    }
 }
 
 struct PostgresClient {
     client: Client,
+    /// The URL `client` is connected to, kept to reconnect.
+    url: String,
+    view_prefix: String,
+    follower_reads: bool,
+    /// The `statement_timeout` set on every connection, kept to reconnect.
+    query_timeout: Option<Duration>,
+    /// The `--session-setting`s set on every connection, kept to reconnect.
+    session_settings: Vec<(String, String)>,
+    consume: Consume,
 }
 
 impl PostgresClient {
-    fn from_url(url: &str) -> Result<Self> {
-        let mut builder =
-            SslConnector::builder(SslMethod::tls()).context("Error creating ssl builder")?;
-        builder.set_verify(SslVerifyMode::NONE);
-        let tls = MakeTlsConnector::new(builder.build());
-        let client = Client::connect(url, tls)
-            .context("failed to open postgres client to primary database")?;
-        Ok(PostgresClient { client })
+    fn from_url(
+        url: &str,
+        view_prefix: &str,
+        follower_reads: bool,
+        query_timeout: Option<Duration>,
+        session_settings: &[(String, String)],
+        consume: Consume,
+    ) -> Result<Self> {
+        Ok(PostgresClient {
+            client: Self::connect(url, query_timeout, session_settings)?,
+            url: url.to_string(),
+            view_prefix: view_prefix.to_string(),
+            follower_reads,
+            query_timeout,
+            session_settings: session_settings.to_vec(),
+            consume,
+        })
     }
+
+    fn connect(
+        url: &str,
+        query_timeout: Option<Duration>,
+        session_settings: &[(String, String)],
+    ) -> Result<Client> {
+        let config = url.parse::<Config>().context("invalid --reader-url")?;
+        let client = if is_unix_socket(&config) {
+            config.connect(NoTls)
+        } else {
+            config.connect(make_tls()?)
+        };
+        let mut client = client.context("failed to open postgres client to primary database")?;
+        if let Some(timeout) = query_timeout {
+            client
+                .batch_execute(&format!("SET statement_timeout = {}", timeout.as_millis()))
+                .context("failed to set statement_timeout")?;
+        }
+        set_session(&mut client, session_settings).context("failed to apply --session-setting")?;
+        Ok(client)
+    }
+
+    /// Runs a read query and returns the number of rows it produced, consuming
+    /// the result as --consume asks.
+    ///
+    /// With `all-rows`, the rows are counted as they arrive and dropped, rather
+    /// than collected like `query` does, so the time measured is the replica
+    /// producing the result and sending it, not the client buffering it.
+    /// With `first-row`, the query is bound to a portal, which requires a
+    /// transaction, and a single row is fetched from it. With `count`, the
+    /// query is wrapped in `SELECT count(*)`.
+    fn read(
+        &mut self,
+        query: &str,
+        params: &[&(dyn ToSql + Sync)],
+    ) -> Result<u64, postgres::Error> {
+        match self.consume {
+            Consume::AllRows => {
+                let mut rows = self.client.query_raw(query, params.iter().copied())?;
+                let mut count = 0;
+                while rows.next()?.is_some() {
+                    count += 1;
+                }
+                Ok(count)
+            }
+            Consume::FirstRow => {
+                let mut transaction = self.client.transaction()?;
+                let portal = transaction.bind(query, params)?;
+                let rows = transaction.query_portal(&portal, 1)?;
+                transaction.rollback()?;
+                Ok(rows.len() as u64)
+            }
+            Consume::Count => {
+                let row = self
+                    .client
+                    .query_one(&format!("SELECT count(*) FROM ({query}) AS result"), params)?;
+                Ok(row.get::<_, i64>(0) as u64)
+            }
+        }
+    }
+
+    /// The name of `view` as it appears in the FROM clause of a read query.
+    fn view(&self, view: &str) -> String {
+        if self.follower_reads {
+            format!(
+                "{}{view} AS OF SYSTEM TIME follower_read_timestamp()",
+                self.view_prefix
+            )
+        } else {
+            format!("{}{view}", self.view_prefix)
+        }
+    }
+}
+
+impl PostgresPooledClient {
+    fn conn(&self) -> Result<WriterConnection> {
+        let start = Instant::now();
+        let conn = self.pool.get().map_err(|e| {
+            let state = self.pool.state();
+            if state.idle_connections == 0 && state.connections == self.pool.max_size() {
+                anyhow!(
+                    "timed out after {} waiting for a writer connection: all {} connections \
+                     in the pool are in use, consider raising --pool-size",
+                    humantime::format_duration(self.pool.connection_timeout()),
+                    self.pool.max_size()
+                )
+            } else {
+                anyhow!(e).context("failed to acquire connection from pool")
+            }
+        })?;
+        self.metrics.record_pool_wait(start.elapsed());
+        Ok(conn)
+    }
+}
+
+/// Samples an id from the cached id range of `table`, loading the range when
+/// it is out of date.
+///
+/// CockroachDB widens the result of `MIN` and `MAX` over an `INT4` column to
+/// `INT8`, so with `crdb` the bounds are cast back to `INT4`.
+fn sample_id(
+    client: &mut Client,
+    crdb: bool,
+    cache: &mut RandomIdCache,
+    rng: &mut StdRng,
+    table: &str,
+    id: &str,
+) -> Result<i32> {
+    let range = if crdb {
+        format!("SELECT MIN({id})::INT4, MAX({id})::INT4 FROM {table}")
+    } else {
+        format!("SELECT MIN({id}), MAX({id}) FROM {table}")
+    };
+    cache.sample(rng, || {
+        let row = client
+            .query_one(&range, &[])
+            .with_context(|| format!("failed to query the {id} range"))?;
+        Ok(row.get::<_, Option<i32>>(0).zip(row.get(1)))
+    })
+}
+
+/// Returns `column` from a random row of `table`, chosen by sampling an id from
+/// the cached id range and selecting the first row at or after it. When rows at
+/// the end of the range have been deleted, the last row before it is used instead.
+fn random_row<T: for<'a> FromSql<'a>>(
+    client: &mut Client,
+    crdb: bool,
+    cache: &mut RandomIdCache,
+    rng: &mut StdRng,
+    table: &str,
+    id: &str,
+    column: &str,
+) -> Result<T> {
+    let sampled = sample_id(client, crdb, cache, rng, table, id)?;
+
+    let after = client.query_opt(
+        &format!("SELECT {column} FROM {table} WHERE {id} >= $1 ORDER BY {id} LIMIT 1"),
+        &[&sampled],
+    )?;
+    let row = match after {
+        Some(row) => row,
+        None => client.query_one(
+            &format!("SELECT {column} FROM {table} WHERE {id} < $1 ORDER BY {id} DESC LIMIT 1"),
+            &[&sampled],
+        )?,
+    };
+
+    Ok(row.get(0))
+}
+
+/// Returns `n` random ids of `kind`, sampled like `random_row` but resolved to
+/// existing rows in a single query over the array of sampled ids.
+fn random_ids(
+    client: &mut Client,
+    crdb: bool,
+    cache: &mut RandomIdCache,
+    rng: &mut StdRng,
+    kind: IdKind,
+    n: usize,
+) -> Result<Vec<i32>> {
+    let (table, id) = (kind.table(), kind.column());
+    let sampled = (0..n)
+        .map(|_| sample_id(client, crdb, cache, rng, table, id))
+        .collect::<Result<Vec<_>>>()?;
+
+    let rows = client.query(
+        &format!(
+            "SELECT COALESCE(
+                (SELECT {id} FROM {table} WHERE {id} >= s ORDER BY {id} LIMIT 1),
+                (SELECT {id} FROM {table} WHERE {id} < s ORDER BY {id} DESC LIMIT 1))
+            FROM unnest($1::INT4[]) WITH ORDINALITY AS sampled (s, n)
+            ORDER BY n"
+        ),
+        &[&sampled],
+    )?;
+    rows.iter()
+        .map(|row| {
+            row.get::<_, Option<i32>>(0)
+                .with_context(|| format!("{table} is empty"))
+        })
+        .collect()
 }
 
 impl PrimaryDatabase for PostgresPooledClient {
-    fn get_random_customer_id(&mut self) -> Result<i32> {
-        self.pool
-            .get()
-            .context("failed to acquire connection from pool")?
-            .query_one(
-                "SELECT customer_id FROM customers ORDER BY random() LIMIT 1",
-                &[],
-            )
-            .context("failed to query customer id")
-            .map(|row| row.get::<_, i32>("customer_id"))
+    fn get_random_customer_id(&mut self, rng: &mut StdRng) -> Result<i32> {
+        random_row(
+            &mut *self.conn()?,
+            self.crdb,
+            &mut self.ids.customers,
+            rng,
+            "customers",
+            "customer_id",
+            "customer_id",
+        )
+        .context("failed to query customer id")
     }
 
-    fn get_random_account_id(&mut self) -> Result<i32> {
-        self.pool
-            .get()
-            .context("failed to acquire connection from pool")?
-            .query_one(
-                "SELECT account_id FROM accounts ORDER BY random() LIMIT 1",
-                &[],
-            )
-            .context("failed to query account id")
-            .map(|row| row.get::<_, i32>("account_id"))
+    fn get_random_account_id(&mut self, rng: &mut StdRng) -> Result<i32> {
+        random_row(
+            &mut *self.conn()?,
+            self.crdb,
+            &mut self.ids.accounts,
+            rng,
+            "accounts",
+            "account_id",
+            "account_id",
+        )
+        .context("failed to query account id")
     }
 
-    fn get_random_security_id(&mut self) -> Result<i32> {
-        self.pool
-            .get()
-            .context("failed to acquire connection from pool")?
-            .query_one(
-                "SELECT security_id FROM securities ORDER BY random() LIMIT 1",
-                &[],
-            )
-            .context("failed to query security id")
-            .map(|row| row.get::<_, i32>("security_id"))
+    fn get_random_security_id(&mut self, rng: &mut StdRng) -> Result<i32> {
+        random_row(
+            &mut *self.conn()?,
+            self.crdb,
+            &mut self.ids.securities,
+            rng,
+            "securities",
+            "security_id",
+            "security_id",
+        )
+        .context("failed to query security id")
     }
 
-    fn get_random_trade_id(&mut self) -> Result<i32> {
-        self.pool
-            .get()
-            .context("failed to acquire connection from pool")?
-            .query_one("SELECT trade_id FROM trades ORDER BY random() LIMIT 1", &[])
-            .context("failed to query trade id")
-            .map(|row| row.get::<_, i32>("trade_id"))
+    fn get_random_trade_id(&mut self, rng: &mut StdRng) -> Result<i32> {
+        random_row(
+            &mut *self.conn()?,
+            self.crdb,
+            &mut self.ids.trades,
+            rng,
+            "trades",
+            "trade_id",
+            "trade_id",
+        )
+        .context("failed to query trade id")
     }
 
-    fn get_random_order_id(&mut self) -> Result<i32> {
-        self.pool
-            .get()
-            .context("failed to acquire connection from pool")?
-            .query_one("SELECT order_id FROM orders ORDER BY random() LIMIT 1", &[])
-            .context("failed to query order id")
-            .map(|row| row.get::<_, i32>("order_id"))
+    fn get_random_order_id(&mut self, rng: &mut StdRng) -> Result<i32> {
+        random_row(
+            &mut *self.conn()?,
+            self.crdb,
+            &mut self.ids.orders,
+            rng,
+            "orders",
+            "order_id",
+            "order_id",
+        )
+        .context("failed to query order id")
     }
 
-    fn get_random_market_data_id(&mut self) -> Result<i32> {
-        self.pool
-            .get()
-            .context("failed to acquire connection from pool")?
-            .query_one(
-                "SELECT market_data_id FROM market_data ORDER BY random() LIMIT 1",
-                &[],
-            )
-            .context("failed to query market data id")
-            .map(|row| row.get::<_, i32>("market_data_id"))
+    fn get_random_market_data_id(&mut self, rng: &mut StdRng) -> Result<i32> {
+        random_row(
+            &mut *self.conn()?,
+            self.crdb,
+            &mut self.ids.market_data,
+            rng,
+            "market_data",
+            "market_data_id",
+            "market_data_id",
+        )
+        .context("failed to query market data id")
     }
 
-    fn get_random_ticker(&mut self) -> Result<String> {
-        self.pool
-            .get()
-            .context("failed to acquire connection from pool")?
-            .query_one(
-                "SELECT ticker FROM securities ORDER BY random() LIMIT 1",
-                &[],
+    fn get_random_ticker(&mut self, rng: &mut StdRng) -> Result<String> {
+        random_row(
+            &mut *self.conn()?,
+            self.crdb,
+            &mut self.ids.securities,
+            rng,
+            "securities",
+            "security_id",
+            "ticker",
+        )
+        .context("failed to query ticker")
+    }
+
+    fn get_random_sector(&mut self, rng: &mut StdRng) -> Result<String> {
+        random_row(
+            &mut *self.conn()?,
+            self.crdb,
+            &mut self.ids.securities,
+            rng,
+            "securities",
+            "security_id",
+            "sector",
+        )
+        .context("failed to query sector")
+    }
+
+    fn get_random_ids(&mut self, kind: IdKind, rng: &mut StdRng, n: usize) -> Result<Vec<i32>> {
+        random_ids(
+            &mut *self.conn()?,
+            self.crdb,
+            self.ids.get(kind),
+            rng,
+            kind,
+            n,
+        )
+        .with_context(|| format!("failed to query {} ids", kind.table()))
+    }
+
+    fn execute_command(&self, op: WriteOperation) -> Result<()> {
+        execute(&mut *self.conn()?, op)
+    }
+
+    fn execute_batch(&self, ops: Vec<WriteOperation>) -> Result<()> {
+        let mut client = self.conn()?;
+        let mut tx = client
+            .transaction()
+            .context("failed to begin transaction")?;
+        for op in ops {
+            execute(&mut tx, op)?;
+        }
+        tx.commit().context("failed to commit transaction")
+    }
+
+    /// Serialization failures and deadlocks abort the whole transaction,
+    /// which can be executed again.
+    fn is_conflict(&self, error: &anyhow::Error) -> bool {
+        error
+            .downcast_ref::<postgres::Error>()
+            .and_then(postgres::Error::code)
+            .is_some_and(|code| {
+                *code == SqlState::T_R_SERIALIZATION_FAILURE
+                    || *code == SqlState::T_R_DEADLOCK_DETECTED
+            })
+    }
+
+    fn insert_lag_token(&self, token: i64) -> Result<()> {
+        self.conn()?
+            .execute(
+                "INSERT INTO replication_lag_tokens (token) VALUES ($1)",
+                &[&token],
             )
-            .context("failed to query sector")
-            .map(|row| row.get("ticker"))
+            .map(|_| ())
+            .context("failed to insert replication lag token")
     }
 
-    fn get_random_sector(&mut self) -> Result<String> {
-        self.pool
-            .get()
-            .context("failed to acquire connection from pool")?
+    fn insert_customer(&self, name: &str, address: &str) -> Result<i32> {
+        self.conn()?
             .query_one(
-                "SELECT sector FROM securities ORDER BY random() LIMIT 1",
-                &[],
+                "INSERT INTO customers (name, address) VALUES ($1, $2) RETURNING customer_id",
+                &[&name, &address],
             )
-            .context("failed to query sector")
-            .map(|row| row.get("sector"))
+            .map(|row| row.get("customer_id"))
+            .context("failed to insert customer")
     }
 
-    fn execute_command(&self, op: WriteOperation) -> Result<()> {
-        let mut client = self
-            .pool
-            .get()
-            .context("failed to acquire connection from pool")?;
-        match op {
-            WriteOperation::InsertCustomer { name, address } => client.execute(
-                "INSERT INTO customers (name, address) VALUES ($1, $2)", &[&name, &address])
-                .map(|_| ())
-                .context("failed to insert customer"),
-            WriteOperation::InsertAccount { customer_id, account_type, balance, parent_account_id } => {
-                match parent_account_id {
-                    None => {
-                        client.execute("INSERT INTO accounts (customer_id, account_type, balance) VALUES ($1, $2, $3)", &[&customer_id, &account_type, &PgNumeric::new(Some(BigDecimal::try_from(balance).unwrap()))])
-                            .map(|_| ())
-                            .context("failed to insert account")
-                    }
-                    Some(parent_account_id) => {
-                        client.execute("INSERT INTO accounts (customer_id, account_type, balance, parent_account_id) VALUES ($1, $2, $3, $4)", &[&customer_id, &account_type, &PgNumeric::new(Some(BigDecimal::try_from(balance).unwrap())), &parent_account_id])
-                            .map(|_| ())
-                            .context("failed to insert account")
-                    }
-                }
-            },
-            WriteOperation::InsertSecurity { ticker, name, sector } => {
-                client.execute("INSERT INTO securities (ticker, name, sector) VALUES ($1, $2, $3)", &[&ticker, &name, &sector])
-                    .map(|_| ())
-                    .context("failed to insert security")
-            },
-
-            WriteOperation::InsertTrade { account_id, security_id, trade_type, quantity, price, parent_trade_id } => {
-                match parent_trade_id {
-                    None =>
-                        client.execute("INSERT INTO trades (account_id, security_id, trade_type, quantity, price) VALUES ($1, $2, $3, $4, $5)", &[&account_id, &security_id, &trade_type, &quantity, &PgNumeric::new(Some(BigDecimal::try_from(price).unwrap()))])
-                            .map(|_| ())
-                            .context("failed to insert trades"),
-                    Some(parent_trade_id) => client.execute("INSERT INTO trades (account_id, security_id, trade_type, quantity, price, parent_trade_id) VALUES ($1, $2, $3, $4, $5, $6)", &[&account_id, &security_id, &trade_type, &quantity, &PgNumeric::new(Some(BigDecimal::try_from(price).unwrap())), &parent_trade_id])
-                        .map(|_| ())
-                        .context("failed to insert trades")
-                }
-            },
+    fn database_size(&mut self) -> Result<u64> {
+        if self.crdb {
+            bail!("--track-growth is not supported with --crdb");
+        }
+        self.conn()?
+            .query_one("SELECT pg_database_size(current_database())", &[])
+            .map(|row| row.get::<_, i64>(0) as u64)
+            .context("failed to query database size")
+    }
+}
 
-            WriteOperation::InsertOrder { account_id, security_id, order_type, quantity, limit_price,  status, parent_order_id} => {
-                match parent_order_id  {
-                    None => client
-                        .execute("INSERT INTO orders (account_id, security_id, order_type, quantity, limit_price, status) VALUES ($1, $2, $3, $4, $5, $6)",
-                                           &[&account_id, &security_id, &order_type, &quantity, &PgNumeric::new(Some(BigDecimal::try_from(limit_price).unwrap())), &status])
+/// Executes a single write operation on `client`, which is either a
+/// connection or a transaction.
+fn execute(client: &mut impl GenericClient, op: WriteOperation) -> Result<()> {
+    match op {
+        WriteOperation::InsertCustomer { name, address } => client.execute(
+            "INSERT INTO customers (name, address) VALUES ($1, $2)", &[&name, &address])
+            .map(|_| ())
+            .context("failed to insert customer"),
+        WriteOperation::InsertAccount { customer_id, account_type, balance, parent_account_id } => {
+            match parent_account_id {
+                None => {
+                    client.execute("INSERT INTO accounts (customer_id, account_type, balance) VALUES ($1, $2, $3)", &[&customer_id, &account_type, &PgNumeric::new(Some(BigDecimal::try_from(balance).unwrap()))])
                         .map(|_| ())
-                        .context("failed to insert order"),
-                    Some(parent_order_id) => client
-                        .execute("INSERT INTO orders (account_id, security_id, order_type, quantity, limit_price, status, parent_order_id) VALUES ($1, $2, $3, $4, $5, $6)",
-                                 &[&account_id, &security_id, &order_type, &quantity, &PgNumeric::new(Some(BigDecimal::try_from(limit_price).unwrap())), &status, &parent_order_id])
+                        .context("failed to insert account")
+                }
+                Some(parent_account_id) => {
+                    client.execute("INSERT INTO accounts (customer_id, account_type, balance, parent_account_id) VALUES ($1, $2, $3, $4)", &[&customer_id, &account_type, &PgNumeric::new(Some(BigDecimal::try_from(balance).unwrap())), &parent_account_id])
                         .map(|_| ())
-                        .context("failed to insert order"),
+                        .context("failed to insert account")
                 }
-            },
-            WriteOperation::InsertMarketData { security_id, price, volume } => client
-                .execute("INSERT INTO market_data (security_id, price, volume) VALUES ($1, $2, $3)",
-                         &[&security_id, &PgNumeric::new(Some(BigDecimal::try_from(price).unwrap())), &volume])
-                .map(|_| ())
-                .context("failed to insert market data"),
-            WriteOperation::UpdateCustomer { customer_id, address } => client
-                .execute("UPDATE customers SET address = $1 WHERE customer_id = $2",&[&address, &customer_id])
+            }
+        },
+        WriteOperation::InsertSecurity { ticker, name, sector } => {
+            client.execute("INSERT INTO securities (ticker, name, sector) VALUES ($1, $2, $3)", &[&ticker, &name, &sector])
                 .map(|_| ())
-                .context("failed to update customer"),
-            WriteOperation::UpdateAccount { account_id, balance } => client
-                .execute("UPDATE accounts SET balance = $1 WHERE customer_id = $2", &[
-                    &PgNumeric::new(Some(BigDecimal::try_from(balance).unwrap())),
-                    &account_id
-                ]).map(|_| ())
-                .context("failed to update account"),
-            WriteOperation::UpdateTrade { trade_id, price } => client
-                .execute("UPDATE trades SET price = $1 WHERE trade_id = $2", &[
+                .context("failed to insert security")
+        },
+
+        WriteOperation::InsertTrade { account_id, security_id, trade_type, quantity, price, parent_trade_id } => {
+            match parent_trade_id {
+                None =>
+                    client.execute("INSERT INTO trades (account_id, security_id, trade_type, quantity, price) VALUES ($1, $2, $3, $4, $5)", &[&account_id, &security_id, &trade_type, &quantity, &PgNumeric::new(Some(BigDecimal::try_from(price).unwrap()))])
+                        .map(|_| ())
+                        .context("failed to insert trades"),
+                Some(parent_trade_id) => client.execute("INSERT INTO trades (account_id, security_id, trade_type, quantity, price, parent_trade_id) VALUES ($1, $2, $3, $4, $5, $6)", &[&account_id, &security_id, &trade_type, &quantity, &PgNumeric::new(Some(BigDecimal::try_from(price).unwrap())), &parent_trade_id])
+                    .map(|_| ())
+                    .context("failed to insert trades")
+            }
+        },
+
+        WriteOperation::InsertOrder { account_id, security_id, order_type, quantity, limit_price,  status, parent_order_id} => {
+            match parent_order_id  {
+                None => client
+                    .execute("INSERT INTO orders (account_id, security_id, order_type, quantity, limit_price, status) VALUES ($1, $2, $3, $4, $5, $6)",
+                                       &[&account_id, &security_id, &order_type, &quantity, &limit_price.map(|p| PgNumeric::new(Some(BigDecimal::try_from(p).unwrap()))), &status])
+                    .map(|_| ())
+                    .context("failed to insert order"),
+                Some(parent_order_id) => client
+                    .execute("INSERT INTO orders (account_id, security_id, order_type, quantity, limit_price, status, parent_order_id) VALUES ($1, $2, $3, $4, $5, $6, $7)",
+                             &[&account_id, &security_id, &order_type, &quantity, &limit_price.map(|p| PgNumeric::new(Some(BigDecimal::try_from(p).unwrap()))), &status, &parent_order_id])
+                    .map(|_| ())
+                    .context("failed to insert order"),
+            }
+        },
+        WriteOperation::InsertMarketData { security_id, price, volume } => client
+            .execute("INSERT INTO market_data (security_id, price, volume) VALUES ($1, $2, $3)",
+                     &[&security_id, &PgNumeric::new(Some(BigDecimal::try_from(price).unwrap())), &volume])
+            .map(|_| ())
+            .context("failed to insert market data"),
+        WriteOperation::UpdateCustomer { customer_id, address } => client
+            .execute("UPDATE customers SET address = $1 WHERE customer_id = $2",&[&address, &customer_id])
+            .map(|_| ())
+            .context("failed to update customer"),
+        WriteOperation::UpdateAccount { account_id, balance } => client
+            .execute("UPDATE accounts SET balance = $1 WHERE account_id = $2", &[
+                &PgNumeric::new(Some(BigDecimal::try_from(balance).unwrap())),
+                &account_id
+            ]).map(|_| ())
+            .context("failed to update account"),
+        WriteOperation::UpdateTrade { trade_id, price } => client
+            .execute("UPDATE trades SET price = $1 WHERE trade_id = $2", &[
+                    &PgNumeric::new(Some(BigDecimal::try_from(price).unwrap())),
+                &trade_id
+            ]).map(|_| ())
+            .context("failed to update trades"),
+        WriteOperation::UpdateOrder { order_id, status, limit_price } => client
+            .execute("UPDATE orders SET status = $1, limit_price = $2 WHERE order_id = $3",&[
+                    &status,
+                    &limit_price.map(|p| PgNumeric::new(Some(BigDecimal::try_from(p).unwrap()))),
+                    &order_id
+            ]).map(|_| ())
+            .context("failed to update orders"),
+        WriteOperation::UpdateMarketData { market_data_id, price, volume } => client
+            .execute("UPDATE market_data SET price = $1, volume = $2, market_date = CURRENT_TIMESTAMP WHERE market_data_id = $3", &[
                         &PgNumeric::new(Some(BigDecimal::try_from(price).unwrap())),
-                    &trade_id
-                ]).map(|_| ())
-                .context("failed to update trades"),
-            WriteOperation::UpdateOrder { order_id, status, limit_price } => client
-                .execute("UPDATE orders SET status = $1, limit_price = $2 WHERE order_id = $3",&[
-                        &status,
-                        &PgNumeric::new(Some(BigDecimal::try_from(limit_price).unwrap())),
-                        &order_id
-                ]).map(|_| ())
-                .context("failed to update orders"),
-            WriteOperation::UpdateMarketData { .. } => Ok(()),/*client
-                .execute("UPDATE market_data SET price = $1, volume = $2, market_date = CURRENT_TIMESTAMP WHERE market_data_id = $3", &[
-                            &PgNumeric::new(Some(BigDecimal::try_from(price).unwrap())),
-                            &PgNumeric::new(Some(BigDecimal::try_from(volume).unwrap())),
-                            &market_data_id
-                ]).map(|_| ())
-                .context("failed to update market_data"),*/
-            WriteOperation::DeleteCustomer { customer_id } => client
-                .execute("DELETE FROM customers WHERE customer_id = $1", &[&customer_id])
-                .map(|_| ())
-                .context("failed to delete customer"),
-            WriteOperation::DeleteAccount { account_id } => client.execute("DELETE FROM accounts WHERE account_id = $1", &[&account_id])
-                .map(|_| ())
-                .context("failed to delete accounts"),
-            WriteOperation::DeleteSecurity { security_id } => client.execute("DELETE FROM securities WHERE security_id = $1", &[&security_id])
-                .map(|_| ())
-                .context("failed to delete security"),
-            WriteOperation::DeleteTrade { trade_id } => client.execute("DELETE FROM trades WHERE trade_id = $1", &[&trade_id])
-                .map(|_| ())
-                .context("failed to delete trades"),
-            WriteOperation::DeleteOrder { order_id } => client.execute("DELETE FROM orders WHERE order_id = $1", &[&order_id])
-                .map(|_| ())
-                .context("failed to delete orders"),
-            WriteOperation::DeleteMarketData { market_data_id } => client.execute("DELETE FROM market_data WHERE market_data_id = $1", &[&market_data_id])
-                .map(|_| ())
-                .context("failed to delete market_data")
-        }
+                        &volume,
+                        &market_data_id
+            ]).map(|_| ())
+            .context("failed to update market_data"),
+        WriteOperation::DeleteCustomer { customer_id } => client
+            .execute("DELETE FROM customers WHERE customer_id = $1", &[&customer_id])
+            .map(|_| ())
+            .context("failed to delete customer"),
+        WriteOperation::DeleteAccount { account_id } => client.execute("DELETE FROM accounts WHERE account_id = $1", &[&account_id])
+            .map(|_| ())
+            .context("failed to delete accounts"),
+        WriteOperation::DeleteSecurity { security_id } => client.execute("DELETE FROM securities WHERE security_id = $1", &[&security_id])
+            .map(|_| ())
+            .context("failed to delete security"),
+        WriteOperation::DeleteTrade { trade_id } => client.execute("DELETE FROM trades WHERE trade_id = $1", &[&trade_id])
+            .map(|_| ())
+            .context("failed to delete trades"),
+        WriteOperation::DeleteOrder { order_id } => client.execute("DELETE FROM orders WHERE order_id = $1", &[&order_id])
+            .map(|_| ())
+            .context("failed to delete orders"),
+        WriteOperation::DeleteMarketData { market_data_id } => client.execute("DELETE FROM market_data WHERE market_data_id = $1", &[&market_data_id])
+            .map(|_| ())
+            .context("failed to delete market_data")
     }
 }
 
 impl ReadReplica for PostgresClient {
-    fn customer_portfolio(&mut self, customer_id: i32) -> Result<()> {
-        self.client
-            .query(
-                "SELECT * FROM customer_portfolio WHERE customer_id = $1",
-                &[&customer_id],
-            )
-            .map(|_| ())
-            .with_context(|| format!("failed to query customer profile {customer_id}"))
+    fn customer_portfolio(&mut self, customer_id: i32) -> Result<u64> {
+        self.read(
+            &format!(
+                "SELECT * FROM {} WHERE customer_id = $1",
+                self.view("customer_portfolio")
+            ),
+            &[&customer_id],
+        )
+        .with_context(|| format!("failed to query customer profile {customer_id}"))
     }
 
-    fn top_performers(&mut self) -> Result<()> {
-        self.client
-            .query("SELECT * FROM top_performers", &[])
-            .map(|_| ())
-            .with_context(|| "failed to query top_performers".to_string())
+    fn top_performers(&mut self) -> Result<u64> {
+        self.read(
+            &format!("SELECT * FROM {}", self.view("top_performers")),
+            &[],
+        )
+        .with_context(|| "failed to query top_performers".to_string())
     }
 
-    fn market_overview(&mut self, sector: &str) -> Result<()> {
-        self.client
-            .query(
-                "SELECT * FROM market_overview WHERE sector = $1",
-                &[&sector],
-            )
-            .map(|_| ())
-            .with_context(|| "failed to query market_overview".to_string())
+    fn market_overview(&mut self, sector: &str) -> Result<u64> {
+        self.read(
+            &format!(
+                "SELECT * FROM {} WHERE sector = $1",
+                self.view("market_overview")
+            ),
+            &[&sector],
+        )
+        .with_context(|| "failed to query market_overview".to_string())
+    }
+
+    fn recent_large_trades(&mut self, account_id: i32) -> Result<u64> {
+        self.read(
+            &format!(
+                "SELECT * FROM {} WHERE account_id = $1",
+                self.view("recent_large_trades")
+            ),
+            &[&account_id],
+        )
+        .with_context(|| "failed to query recent_large_trades".to_string())
+    }
+
+    fn customer_order_book(&mut self, customer_id: i32) -> Result<u64> {
+        self.read(
+            &format!(
+                "SELECT * FROM {} WHERE customer_id = $1",
+                self.view("customer_order_book")
+            ),
+            &[&customer_id],
+        )
+        .with_context(|| format!("failed to query customer_order_book {customer_id}"))
     }
 
-    fn recent_large_trades(&mut self, account_id: i32) -> Result<()> {
+    fn sector_performance(&mut self, sector: String) -> Result<u64> {
+        self.read(
+            &format!(
+                "SELECT * FROM {} WHERE sector = $1",
+                self.view("sector_performance")
+            ),
+            &[&sector],
+        )
+        .with_context(|| "failed to query sector_performance".to_string())
+    }
+
+    fn account_activity_summary(&mut self, account_id: i32) -> Result<u64> {
+        self.read(
+            &format!(
+                "SELECT * FROM {} WHERE account_id = $1",
+                self.view("account_activity_summary")
+            ),
+            &[&account_id],
+        )
+        .with_context(|| format!("failed to query account_activity_summary {account_id}"))
+    }
+
+    fn daily_market_movements(&mut self, security_id: i32) -> Result<u64> {
+        self.read(
+            &format!(
+                "SELECT * FROM {} WHERE security_id = $1",
+                self.view("daily_market_movements")
+            ),
+            &[&security_id],
+        )
+        .with_context(|| format!("failed to query daily_market_movements {security_id}"))
+    }
+
+    fn high_value_customers(&mut self) -> Result<u64> {
+        self.read(
+            &format!("SELECT * FROM {}", self.view("high_value_customers")),
+            &[],
+        )
+        .with_context(|| "failed to query high_value_customers".to_string())
+    }
+
+    fn pending_orders_summary(&mut self, ticker: &str) -> Result<u64> {
+        self.read(
+            &format!(
+                "SELECT * FROM {} WHERE ticker = $1",
+                self.view("pending_orders_summary")
+            ),
+            &[&ticker],
+        )
+        .with_context(|| "failed to query pending_orders_summary".to_string())
+    }
+
+    fn trade_volume_by_hour(&mut self) -> Result<u64> {
+        self.read(
+            &format!("SELECT * FROM {}", self.view("trade_volume_by_hour")),
+            &[],
+        )
+        .with_context(|| "failed to query trade_volume_by_hour".to_string())
+    }
+
+    fn top_securities_by_sector(&mut self, sector: String) -> Result<u64> {
+        self.read(
+            &format!(
+                "SELECT * FROM {} WHERE sector = $1",
+                self.view("top_securities_by_sector")
+            ),
+            &[&sector],
+        )
+        .with_context(|| "failed to query top_securities_by_sector".to_string())
+    }
+
+    fn recent_trades_by_account(&mut self, account_id: i32) -> Result<u64> {
+        self.read(
+            &format!(
+                "SELECT * FROM {} WHERE account_id = $1",
+                self.view("recent_trades_by_account")
+            ),
+            &[&account_id],
+        )
+        .with_context(|| format!("failed to query recent_trades_by_account {account_id}"))
+    }
+
+    fn order_fulfillment_rates(&mut self, customer_id: i32) -> Result<u64> {
+        self.read(
+            &format!(
+                "SELECT * FROM {} WHERE customer_id = $1",
+                self.view("order_fulfillment_rates")
+            ),
+            &[&customer_id],
+        )
+        .with_context(|| format!("failed to query order_fulfillment_rates {customer_id}"))
+    }
+
+    fn sector_order_activity(&mut self, sector: String) -> Result<u64> {
+        self.read(
+            &format!(
+                "SELECT * FROM {} WHERE sector = $1",
+                self.view("sector_order_activity")
+            ),
+            &[&sector],
+        )
+        .with_context(|| "failed to query sector_order_activity".to_string())
+    }
+
+    fn cascading_order_cancellation_alert(&mut self) -> Result<u64> {
+        self.read(
+            &format!(
+                "SELECT * FROM {}",
+                self.view("cascading_order_cancellation_alert")
+            ),
+            &[],
+        )
+        .with_context(|| "failed to query cascading_order_cancellation_alert".to_string())
+    }
+
+    fn check_row_freshness(&mut self, security_id: i32, expected_price: f64) -> Result<bool> {
+        // Prices are stored with four decimal places.
         self.client
-            .query(
-                "SELECT * FROM recent_large_trades WHERE account_id = $1",
-                &[&account_id],
+            .query_one(
+                "SELECT EXISTS (SELECT 1 FROM market_data \
+                 WHERE security_id = $1 AND abs(price::float8 - $2) < 0.0001) AS fresh",
+                &[&security_id, &expected_price],
             )
-            .map(|_| ())
-            .with_context(|| "failed to query recent_large_trades".to_string())
+            .map(|row| !row.get::<_, bool>("fresh"))
+            .with_context(|| format!("failed to check freshness of market data {security_id}"))
     }
 
-    fn customer_order_book(&mut self, customer_id: i32) -> Result<()> {
+    fn fetch_customer(&mut self, customer_id: i32) -> Result<Option<String>> {
         self.client
-            .query(
-                "SELECT * FROM customer_order_book WHERE customer_id = $1",
+            .query_opt(
+                "SELECT name FROM customers WHERE customer_id = $1",
                 &[&customer_id],
             )
-            .map(|_| ())
-            .with_context(|| format!("failed to query customer_order_book {customer_id}"))
+            .map(|row| row.map(|row| row.get("name")))
+            .with_context(|| format!("failed to fetch customer {customer_id}"))
     }
 
-    fn sector_performance(&mut self, sector: String) -> Result<()> {
-        self.client
-            .query(
-                "SELECT * FROM sector_performance WHERE sector = $1",
-                &[&sector],
-            )
-            .map(|_| ())
-            .with_context(|| "failed to query sector_performance".to_string())
+    fn reconnect(&mut self) -> Result<()> {
+        self.client = Self::connect(&self.url, self.query_timeout, &self.session_settings)?;
+        Ok(())
+    }
+
+    /// `statement_timeout` cancels the query with `query_canceled`.
+    fn is_timeout(&self, error: &anyhow::Error) -> bool {
+        error
+            .downcast_ref::<postgres::Error>()
+            .and_then(postgres::Error::code)
+            == Some(&SqlState::QUERY_CANCELED)
     }
 
-    fn account_activity_summary(&mut self, account_id: i32) -> Result<()> {
+    fn ping(&mut self) -> Result<()> {
         self.client
-            .query(
-                "SELECT * FROM account_activity_summary WHERE account_id = $1",
-                &[&account_id],
-            )
-            .map(|_| ())
-            .with_context(|| format!("failed to query account_activity_summary {account_id}"))
+            .batch_execute("SELECT 1")
+            .context("failed to ping the read replica")
     }
 
-    fn daily_market_movements(&mut self, security_id: i32) -> Result<()> {
+    fn max_observed_token(&mut self) -> Result<Option<i64>> {
         self.client
-            .query(
-                "SELECT * FROM daily_market_movements WHERE security_id = $1",
-                &[&security_id],
+            .query_one(
+                "SELECT MAX(token) AS token FROM replication_lag_tokens",
+                &[],
             )
-            .map(|_| ())
-            .with_context(|| format!("failed to query daily_market_movements {security_id}"))
+            .context("failed to query replication lag token")
+            .map(|row| row.get::<_, Option<i64>>("token"))
     }
+}
 
-    fn high_value_customers(&mut self) -> Result<()> {
-        self.client
-            .query("SELECT * FROM high_value_customers", &[])
-            .map(|_| ())
-            .with_context(|| "failed to query high_value_customers".to_string())
+/// The ignored tests write to the database at `RR_BENCH_POSTGRES_URL`, which must
+/// have been created with pg_ddl.sql and hold at least one security. Run them
+/// with `cargo test -p rr-bench-postgres -- --ignored`.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rr_bench_base::rand::SeedableRng;
+
+    fn primary_database() -> PostgresPooledClient {
+        let url = std::env::var("RR_BENCH_POSTGRES_URL").expect("RR_BENCH_POSTGRES_URL is not set");
+        let config = url.parse::<Config>().unwrap();
+        let pool = Pool::builder()
+            .max_size(1)
+            .build(PostgresConnectionManager::new(config, NoTls))
+            .unwrap();
+        PostgresPooledClient {
+            pool: WriterPool::NoTls(pool),
+            metrics: Metrics::default(),
+            ids: RandomIds::default(),
+            crdb: false,
+        }
     }
 
-    fn pending_orders_summary(&mut self, ticker: &str) -> Result<()> {
-        self.client
-            .query(
-                "SELECT * FROM pending_orders_summary WHERE ticker = $1",
-                &[&ticker],
-            )
-            .map(|_| ())
-            .with_context(|| "failed to query pending_orders_summary".to_string())
+    /// Returns the id of the most recently inserted row of `table`.
+    fn last_id(primary: &PostgresPooledClient, table: &str, id: &str) -> i32 {
+        primary
+            .conn()
+            .unwrap()
+            .query_one(&format!("SELECT MAX({id}) FROM {table}"), &[])
+            .unwrap()
+            .get(0)
     }
 
-    fn trade_volume_by_hour(&mut self) -> Result<()> {
-        self.client
-            .query("SELECT * FROM trade_volume_by_hour", &[])
-            .map(|_| ())
-            .with_context(|| "failed to query trade_volume_by_hour".to_string())
+    #[test]
+    fn endpoints_compare_with_default_port_and_database() {
+        let endpoint_of = |url: &str| {
+            let config = url.parse::<Config>().unwrap();
+            let (hosts, ports, dbname) = endpoint(&config);
+            (hosts.to_vec(), ports, dbname.map(str::to_string))
+        };
+        let same = [
+            (
+                "postgres://bench@db:5432/bench",
+                "postgres://bench@db/bench",
+            ),
+            ("postgres://bench@db/bench", "postgres://bench@db"),
+            (
+                "postgres://writer@db/bench",
+                "postgres://reader:secret@db:5432/bench?sslmode=disable",
+            ),
+        ];
+        for (a, b) in same {
+            assert_eq!(endpoint_of(a), endpoint_of(b), "{a} and {b}");
+        }
+        let different = [
+            (
+                "postgres://bench@primary/bench",
+                "postgres://bench@replica/bench",
+            ),
+            (
+                "postgres://bench@db:5432/bench",
+                "postgres://bench@db:5433/bench",
+            ),
+            ("postgres://bench@db/bench", "postgres://bench@db/other"),
+        ];
+        for (a, b) in different {
+            assert_ne!(endpoint_of(a), endpoint_of(b), "{a} and {b}");
+        }
     }
 
-    fn top_securities_by_sector(&mut self, sector: String) -> Result<()> {
-        self.client
-            .query(
-                "SELECT * FROM top_securities_by_sector WHERE sector = $1",
-                &[&sector],
+    #[test]
+    #[ignore = "requires a Postgres database at RR_BENCH_POSTGRES_URL"]
+    fn update_market_data_changes_the_row() {
+        let mut primary = primary_database();
+        let mut rng = StdRng::seed_from_u64(0);
+        let security_id = primary.get_random_security_id(&mut rng).unwrap();
+        primary
+            .execute_command(WriteOperation::InsertMarketData {
+                security_id,
+                price: 100.0,
+                volume: 1000,
+            })
+            .unwrap();
+        let market_data_id = last_id(&primary, "market_data", "market_data_id");
+
+        primary
+            .execute_command(WriteOperation::UpdateMarketData {
+                market_data_id,
+                price: 101.5,
+                volume: 2500,
+            })
+            .unwrap();
+        let row = primary
+            .conn()
+            .unwrap()
+            .query_one(
+                "SELECT price::FLOAT8, volume FROM market_data WHERE market_data_id = $1",
+                &[&market_data_id],
             )
-            .map(|_| ())
-            .with_context(|| "failed to query top_securities_by_sector".to_string())
+            .unwrap();
+        primary
+            .execute_command(WriteOperation::DeleteMarketData { market_data_id })
+            .unwrap();
+
+        assert_eq!((row.get::<_, f64>(0), row.get::<_, i32>(1)), (101.5, 2500));
     }
 
-    fn recent_trades_by_account(&mut self, account_id: i32) -> Result<()> {
-        self.client
-            .query(
-                "SELECT * FROM recent_trades_by_account WHERE account_id = $1",
-                &[&account_id],
-            )
-            .map(|_| ())
-            .with_context(|| format!("failed to query recent_trades_by_account {account_id}"))
+    #[test]
+    #[ignore = "requires a Postgres database at RR_BENCH_POSTGRES_URL"]
+    fn random_ids_match_the_single_id_queries() {
+        let mut primary = primary_database();
+        let ids = primary
+            .get_random_ids(IdKind::Security, &mut StdRng::seed_from_u64(0), 50)
+            .unwrap();
+        let mut rng = StdRng::seed_from_u64(0);
+        let expected = (0..50)
+            .map(|_| primary.get_random_security_id(&mut rng).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(ids, expected);
     }
 
-    fn order_fulfillment_rates(&mut self, customer_id: i32) -> Result<()> {
-        self.client
-            .query(
-                "SELECT * FROM order_fulfillment_rates WHERE customer_id = $1",
-                &[&customer_id],
-            )
-            .map(|_| ())
-            .with_context(|| format!("failed to query order_fulfillment_rates {customer_id}"))
+    #[test]
+    #[ignore = "requires a Postgres database at RR_BENCH_POSTGRES_URL"]
+    fn serialization_failures_and_deadlocks_are_conflicts() {
+        let primary = primary_database();
+        let raise = |code: &str| {
+            let error = primary
+                .conn()
+                .unwrap()
+                .batch_execute(&format!(
+                    "DO $$ BEGIN RAISE EXCEPTION 'conflict' USING ERRCODE = '{code}'; END $$"
+                ))
+                .context("failed to execute command")
+                .unwrap_err();
+            primary.is_conflict(&error)
+        };
+        assert!(raise("40001"));
+        assert!(raise("40P01"));
+        assert!(!raise("23505"));
     }
 
-    fn sector_order_activity(&mut self, sector: String) -> Result<()> {
-        self.client
-            .query(
-                "SELECT * FROM sector_order_activity WHERE sector = $1",
-                &[&sector],
+    #[test]
+    #[ignore = "requires a Postgres database at RR_BENCH_POSTGRES_URL"]
+    fn market_order_has_null_limit_price() {
+        let mut primary = primary_database();
+        let mut rng = StdRng::seed_from_u64(0);
+        let account_id = primary.get_random_account_id(&mut rng).unwrap();
+        let security_id = primary.get_random_security_id(&mut rng).unwrap();
+        primary
+            .execute_command(WriteOperation::InsertOrder {
+                account_id,
+                security_id,
+                order_type: "buy".to_string(),
+                quantity: 10,
+                limit_price: None,
+                status: "pending".to_string(),
+                parent_order_id: None,
+            })
+            .unwrap();
+        let order_id = last_id(&primary, "orders", "order_id");
+        // `PgNumeric::new(None)` binds NaN rather than NULL.
+        let limit_price = primary
+            .conn()
+            .unwrap()
+            .query_one(
+                "SELECT limit_price::TEXT FROM orders WHERE order_id = $1",
+                &[&order_id],
             )
-            .map(|_| ())
-            .with_context(|| "failed to query sector_order_activity".to_string())
+            .unwrap()
+            .get::<_, Option<String>>(0);
+        primary
+            .execute_command(WriteOperation::DeleteOrder { order_id })
+            .unwrap();
+
+        assert_eq!(limit_price, None);
     }
 
-    fn cascading_order_cancellation_alert(&mut self) -> Result<()> {
-        self.client
-            .query("SELECT * FROM cascading_order_cancellation_alert", &[])
-            .map(|_| ())
-            .with_context(|| "failed to query cascading_order_cancellation_alert".to_string())
+    #[test]
+    #[ignore = "requires a Postgres database at RR_BENCH_POSTGRES_URL"]
+    fn read_consumes_rows_as_asked() {
+        let url = std::env::var("RR_BENCH_POSTGRES_URL").expect("RR_BENCH_POSTGRES_URL is not set");
+        for (consume, expected) in [
+            (Consume::AllRows, 1000),
+            (Consume::FirstRow, 1),
+            (Consume::Count, 1000),
+        ] {
+            let mut reader = PostgresClient::from_url(&url, "", false, None, &[], consume).unwrap();
+            let count = reader
+                .read("SELECT generate_series(1, $1)", &[&1000])
+                .unwrap();
+            assert_eq!(count, expected, "{consume:?}");
+        }
     }
 }
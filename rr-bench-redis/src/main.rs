@@ -0,0 +1,841 @@
+use anyhow::{bail, Context, Result};
+use r2d2::{Pool, PooledConnection};
+use redis::{Client, Connection, Pipeline};
+use rr_bench_base::clap::{value_parser, Arg, ArgMatches};
+use rr_bench_base::log::info;
+use rr_bench_base::operations::WriteOperation;
+use rr_bench_base::rand::rngs::StdRng;
+use rr_bench_base::{
+    benchmark, connect_with_retries_from, reader_url, writer_url, Benchmark, ConnectionString,
+    Consume, Credentials, Metrics, PrimaryDatabase, ReadReplica,
+};
+use rusqlite::types::ValueRef;
+use rusqlite::OpenFlags;
+use serde_json::{Map, Number, Value};
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+
+/// The URL schemes accepted for --writer-url and --reader-url.
+const SCHEMES: &[&str] = &["redis"];
+
+/// The tables loaded by --load-db, and their id columns.
+const TABLES: &[(&str, &str)] = &[
+    ("customers", "customer_id"),
+    ("accounts", "account_id"),
+    ("securities", "security_id"),
+    ("trades", "trade_id"),
+    ("orders", "order_id"),
+    ("market_data", "market_data_id"),
+];
+
+/// The views cached by --load-db, and the column each read operation
+/// looks their rows up by. Views read in full are cached under one key.
+const VIEWS: &[(&str, Option<&str>)] = &[
+    ("customer_portfolio", Some("customer_id")),
+    ("top_performers", None),
+    ("market_overview", Some("sector")),
+    ("recent_large_trades", Some("account_id")),
+    ("customer_order_book", Some("customer_id")),
+    ("sector_performance", Some("sector")),
+    ("account_activity_summary", Some("account_id")),
+    ("daily_market_movements", Some("security_id")),
+    ("high_value_customers", None),
+    ("pending_orders_summary", Some("ticker")),
+    ("trade_volume_by_hour", None),
+    ("top_securities_by_sector", Some("sector")),
+    ("recent_trades_by_account", Some("account_id")),
+    ("order_fulfillment_rates", Some("customer_id")),
+    ("sector_order_activity", Some("sector")),
+    ("cascading_order_cancellation_alert", None),
+];
+
+/// The number of keys --load-db writes per pipeline.
+const LOAD_BATCH_SIZE: usize = 1000;
+
+/// The key holding the latest replication lag token.
+const LAG_TOKEN_KEY: &str = "replication_lag_token";
+
+fn main() {
+    benchmark(
+        || {
+            [
+                Arg::new("writer")
+                    .long("writer-url")
+                    .help("The URL to the primary Redis server. Defaults to $RR_BENCH_WRITER_URL"),
+                Arg::new("reader")
+                    .long("reader-url")
+                    .help("The URL to the replica Redis server. Defaults to $RR_BENCH_READER_URL"),
+                Arg::new("credentials")
+                    .long("credentials")
+                    .value_name("PATH")
+                    .value_parser(value_parser!(PathBuf))
+                    .help("A file with user and password lines that replace the credentials in --writer-url and --reader-url"),
+                Arg::new("load_db")
+                    .long("load-db")
+                    .value_name("PATH")
+                    .value_parser(value_parser!(PathBuf))
+                    .help("Load the rows and view results of a SQLite database written by rr-data-gen --keep-db into the primary before running the benchmark. The Redis database must be empty"),
+            ]
+        },
+        RedisBenchmark::new,
+    )
+}
+
+struct RedisBenchmark {
+    reader: Client,
+    pool: Pool<Client>,
+    view_prefix: String,
+}
+
+impl RedisBenchmark {
+    fn new(args: ArgMatches) -> Result<Self> {
+        if *args.get_one::<Consume>("consume").unwrap() != Consume::AllRows {
+            bail!("only --consume all-rows is supported by Redis");
+        }
+        if args.contains_id("query_timeout") {
+            bail!("--query-timeout is not supported by Redis");
+        }
+        if args.contains_id("session_setting") {
+            bail!("--session-setting is not supported by Redis");
+        }
+        let credentials = args
+            .get_one::<PathBuf>("credentials")
+            .map(|path| Credentials::load(path))
+            .transpose()?;
+
+        let (writer, writer_source) = writer_url(&args)?;
+        let writer = parse_url(&writer, writer_source, credentials.as_ref())?;
+
+        let (reader, reader_source) = reader_url(&args)?;
+        let reader = parse_url(&reader, reader_source, credentials.as_ref())?;
+
+        let writer = Client::open(writer).context("invalid writer-url")?;
+        // The pool connects when it is built, so building it is retried like
+        // every other connection.
+        let pool = connect_with_retries_from(&args, "primary database", || {
+            Pool::builder()
+                .build(writer.clone())
+                .context("failed to create connection pool")
+        })?;
+        let reader = Client::open(reader).context("invalid reader-url")?;
+        let view_prefix = args
+            .get_one::<String>("view_prefix")
+            .cloned()
+            .unwrap_or_default();
+
+        if let Some(path) = args.get_one::<PathBuf>("load_db") {
+            let mut conn = pool
+                .get()
+                .context("failed to acquire connection from pool")?;
+            load(&mut conn, path, &view_prefix)
+                .with_context(|| format!("failed to load {}", path.display()))?;
+        }
+
+        Ok(Self {
+            reader,
+            pool,
+            view_prefix,
+        })
+    }
+}
+
+/// Validates the URL passed to `flag` and replaces its
+/// user and password with those in `credentials`.
+fn parse_url(url: &str, flag: &str, credentials: Option<&Credentials>) -> Result<String> {
+    let mut url =
+        ConnectionString::parse(url, SCHEMES).with_context(|| format!("invalid {flag}"))?;
+    if url.pool_size().is_some() {
+        bail!("invalid {flag}: pool_size is not supported by Redis");
+    }
+    if let Some(credentials) = credentials {
+        url.set_credentials(credentials);
+    }
+    Ok(url.url().to_string())
+}
+
+impl Benchmark<'_> for RedisBenchmark {
+    type Writer = RedisPooledClient;
+    type Reader = RedisClient;
+
+    fn primary_database(&self, _metrics: &Metrics) -> Result<Self::Writer> {
+        Ok(RedisPooledClient {
+            pool: self.pool.clone(),
+        })
+    }
+
+    fn read_replica(&self) -> Result<Self::Reader> {
+        let conn = self
+            .reader
+            .get_connection()
+            .context("failed to connect to the replica")?;
+        Ok(RedisClient {
+            conn,
+            view_prefix: self.view_prefix.clone(),
+        })
+    }
+
+    /// Holds every connection at once, so that each is a different one.
+    fn prime_pool(&self) -> Result<u32> {
+        let mut conns = Vec::with_capacity(self.pool.max_size() as usize);
+        for _ in 0..self.pool.max_size() {
+            let mut conn = self
+                .pool
+                .get()
+                .context("failed to acquire connection from pool")?;
+            redis::cmd("PING")
+                .query::<String>(&mut *conn)
+                .context("failed to ping the primary database")?;
+            conns.push(conn);
+        }
+        Ok(self.pool.max_size())
+    }
+}
+
+/// The hash holding the row of `table` with id `id`.
+fn row_key(table: &str, id: impl Display) -> String {
+    format!("{table}:{id}")
+}
+
+/// The set of every id in `table`, which random ids are drawn from.
+fn ids_key(table: &str) -> String {
+    format!("{table}:ids")
+}
+
+/// The counter new ids in `table` are allocated from.
+fn next_id_key(table: &str) -> String {
+    format!("{table}:next_id")
+}
+
+/// The hash from the id of every market data row of a security to its price,
+/// which stands in for an index on `market_data.security_id`.
+fn prices_key(security_id: impl Display) -> String {
+    format!("market_data:security:{security_id}")
+}
+
+/// Loads the rows and view results of the SQLite database at `path`, written
+/// by `rr-data-gen --keep-db`, into the empty Redis database behind `conn`.
+/// The view results are stored under keys starting with `view_prefix`.
+fn load(conn: &mut Connection, path: &Path, view_prefix: &str) -> Result<()> {
+    let keys: usize = redis::cmd("DBSIZE")
+        .query(conn)
+        .context("failed to count keys")?;
+    if keys > 0 {
+        bail!("the Redis database is not empty, it holds {keys} keys");
+    }
+
+    let db = rusqlite::Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .context("failed to open SQLite database")?;
+    for (table, id) in TABLES {
+        let rows = load_table(conn, &db, table, id)
+            .with_context(|| format!("failed to load table {table}"))?;
+        info!("Loaded {rows} rows into {table}");
+    }
+    for (view, column) in VIEWS {
+        let keys = cache_view(conn, &db, view_prefix, view, *column)
+            .with_context(|| format!("failed to cache view {view}"))?;
+        info!("Cached {view} under {keys} keys");
+    }
+    Ok(())
+}
+
+/// Writes each row of `table` to a hash, adds its id to the id set, and
+/// starts the id counter after the largest id. Returns the number of rows.
+fn load_table(
+    conn: &mut Connection,
+    db: &rusqlite::Connection,
+    table: &str,
+    id: &str,
+) -> Result<usize> {
+    let mut stmt = db.prepare(&format!("SELECT * FROM {table}"))?;
+    let columns: Vec<String> = stmt.column_names().into_iter().map(String::from).collect();
+    let mut rows = stmt.query([])?;
+
+    let mut count = 0;
+    let mut max_id = 0;
+    let mut pipe = redis::pipe();
+    while let Some(row) = rows.next()? {
+        let row_id: i64 = row.get(id)?;
+        max_id = max_id.max(row_id);
+
+        let mut fields = vec![];
+        for (i, column) in columns.iter().enumerate() {
+            if column != id {
+                if let Some(value) = field_value(row.get_ref(i)?) {
+                    fields.push((column.as_str(), value));
+                }
+            }
+        }
+        pipe.add_command(hset(&row_key(table, row_id), &fields))
+            .ignore()
+            .cmd("SADD")
+            .arg(ids_key(table))
+            .arg(row_id)
+            .ignore();
+        if table == "market_data" {
+            let security_id: i64 = row.get("security_id")?;
+            let price: f64 = row.get("price")?;
+            pipe.cmd("HSET")
+                .arg(prices_key(security_id))
+                .arg(row_id)
+                .arg(price)
+                .ignore();
+        }
+
+        count += 1;
+        if count % LOAD_BATCH_SIZE == 0 {
+            pipe.query::<()>(conn)?;
+            pipe.clear();
+        }
+    }
+    pipe.cmd("SET").arg(next_id_key(table)).arg(max_id).ignore();
+    pipe.query::<()>(conn)?;
+
+    Ok(count)
+}
+
+/// Stores the rows of `view` as JSON arrays, under one key for each distinct
+/// value of `column`, or under a single key. Returns the number of keys.
+fn cache_view(
+    conn: &mut Connection,
+    db: &rusqlite::Connection,
+    view_prefix: &str,
+    view: &str,
+    column: Option<&str>,
+) -> Result<usize> {
+    let mut stmt = db.prepare(&format!("SELECT * FROM {view}"))?;
+    let columns: Vec<String> = stmt.column_names().into_iter().map(String::from).collect();
+    let mut rows = stmt.query([])?;
+
+    let mut results: BTreeMap<String, Vec<Value>> = BTreeMap::new();
+    if column.is_none() {
+        // Views read in full are cached even when they are empty.
+        results.insert(format!("{view_prefix}{view}"), vec![]);
+    }
+    while let Some(row) = rows.next()? {
+        let mut object = Map::new();
+        for (i, name) in columns.iter().enumerate() {
+            object.insert(name.clone(), json_value(row.get_ref(i)?));
+        }
+        let key = match column {
+            Some(column) => {
+                let value = field_value(row.get_ref(column)?).unwrap_or_default();
+                format!("{view_prefix}{view}:{value}")
+            }
+            None => format!("{view_prefix}{view}"),
+        };
+        results.entry(key).or_default().push(Value::Object(object));
+    }
+
+    let keys = results.len();
+    let mut pipe = redis::pipe();
+    for (i, (key, rows)) in results.into_iter().enumerate() {
+        pipe.cmd("SET")
+            .arg(key)
+            .arg(Value::Array(rows).to_string())
+            .ignore();
+        if (i + 1) % LOAD_BATCH_SIZE == 0 {
+            pipe.query::<()>(conn)?;
+            pipe.clear();
+        }
+    }
+    pipe.query::<()>(conn)?;
+
+    Ok(keys)
+}
+
+/// Formats a SQLite value as a hash field or key. Nulls are left out.
+fn field_value(value: ValueRef) -> Option<String> {
+    match value {
+        ValueRef::Null | ValueRef::Blob(_) => None,
+        ValueRef::Integer(i) => Some(i.to_string()),
+        ValueRef::Real(f) => Some(f.to_string()),
+        ValueRef::Text(text) => Some(String::from_utf8_lossy(text).into_owned()),
+    }
+}
+
+fn json_value(value: ValueRef) -> Value {
+    match value {
+        ValueRef::Null | ValueRef::Blob(_) => Value::Null,
+        ValueRef::Integer(i) => Value::from(i),
+        ValueRef::Real(f) => Number::from_f64(f).map_or(Value::Null, Value::Number),
+        ValueRef::Text(text) => Value::String(String::from_utf8_lossy(text).into_owned()),
+    }
+}
+
+/// An `HSET` of `fields` on `key`.
+fn hset(key: &str, fields: &[(&str, String)]) -> redis::Cmd {
+    let mut cmd = redis::cmd("HSET");
+    cmd.arg(key);
+    for (field, value) in fields {
+        cmd.arg(*field).arg(value);
+    }
+    cmd
+}
+
+struct RedisPooledClient {
+    pool: Pool<Client>,
+}
+
+impl RedisPooledClient {
+    fn conn(&self) -> Result<PooledConnection<Client>> {
+        self.pool
+            .get()
+            .context("failed to acquire connection from pool")
+    }
+
+    /// Returns a random id from the id set of `table`.
+    fn random_id(&self, table: &str) -> Result<i32> {
+        redis::cmd("SRANDMEMBER")
+            .arg(ids_key(table))
+            .query::<Option<i32>>(&mut *self.conn()?)?
+            .with_context(|| format!("no rows found in {table}"))
+    }
+
+    /// Returns `field` of a random security.
+    fn random_security_field(&self, field: &str) -> Result<String> {
+        let security_id = self.random_id("securities")?;
+        redis::cmd("HGET")
+            .arg(row_key("securities", security_id))
+            .arg(field)
+            .query::<Option<String>>(&mut *self.conn()?)?
+            .with_context(|| format!("security {security_id} has no {field}"))
+    }
+
+    /// Inserts a row with `fields` into `table` under a newly allocated id,
+    /// returning the id and the transaction, to be extended and executed.
+    fn insert(&self, table: &str, fields: &[(&str, String)]) -> Result<(i64, Pipeline)> {
+        let id: i64 = redis::cmd("INCR")
+            .arg(next_id_key(table))
+            .query(&mut *self.conn()?)?;
+        let mut pipe = redis::pipe();
+        pipe.atomic()
+            .add_command(hset(&row_key(table, id), fields))
+            .ignore()
+            .cmd("SADD")
+            .arg(ids_key(table))
+            .arg(id)
+            .ignore();
+        Ok((id, pipe))
+    }
+
+    /// Updates `fields` of a row. The row exists, because its id was drawn
+    /// from the id set by the primary database simulator, the only writer.
+    fn update(&self, table: &str, id: i32, fields: &[(&str, String)]) -> Result<()> {
+        hset(&row_key(table, id), fields)
+            .query::<()>(&mut *self.conn()?)
+            .map_err(Into::into)
+    }
+
+    /// Deletes a row, returning the transaction, to be extended and executed.
+    /// Rows that reference it are left in place.
+    fn delete(&self, table: &str, id: i32) -> Pipeline {
+        let mut pipe = redis::pipe();
+        pipe.atomic()
+            .cmd("DEL")
+            .arg(row_key(table, id))
+            .ignore()
+            .cmd("SREM")
+            .arg(ids_key(table))
+            .arg(id)
+            .ignore();
+        pipe
+    }
+
+    /// Returns the security of a market data row.
+    fn market_data_security_id(&self, market_data_id: i32) -> Result<Option<i32>> {
+        redis::cmd("HGET")
+            .arg(row_key("market_data", market_data_id))
+            .arg("security_id")
+            .query(&mut *self.conn()?)
+            .map_err(Into::into)
+    }
+
+    fn execute(&self, pipe: &Pipeline) -> Result<()> {
+        pipe.query::<()>(&mut *self.conn()?).map_err(Into::into)
+    }
+}
+
+impl PrimaryDatabase for RedisPooledClient {
+    fn get_random_customer_id(&mut self, _rng: &mut StdRng) -> Result<i32> {
+        self.random_id("customers")
+            .context("failed to retrieve customer_id")
+    }
+
+    fn get_random_account_id(&mut self, _rng: &mut StdRng) -> Result<i32> {
+        self.random_id("accounts")
+            .context("failed to retrieve account_id")
+    }
+
+    fn get_random_security_id(&mut self, _rng: &mut StdRng) -> Result<i32> {
+        self.random_id("securities")
+            .context("failed to retrieve security_id")
+    }
+
+    fn get_random_trade_id(&mut self, _rng: &mut StdRng) -> Result<i32> {
+        self.random_id("trades")
+            .context("failed to retrieve trade_id")
+    }
+
+    fn get_random_order_id(&mut self, _rng: &mut StdRng) -> Result<i32> {
+        self.random_id("orders")
+            .context("failed to retrieve order_id")
+    }
+
+    fn get_random_market_data_id(&mut self, _rng: &mut StdRng) -> Result<i32> {
+        self.random_id("market_data")
+            .context("failed to retrieve market_data_id")
+    }
+
+    fn get_random_ticker(&mut self, _rng: &mut StdRng) -> Result<String> {
+        self.random_security_field("ticker")
+            .context("failed to retrieve ticker")
+    }
+
+    fn get_random_sector(&mut self, _rng: &mut StdRng) -> Result<String> {
+        self.random_security_field("sector")
+            .context("failed to retrieve sector")
+    }
+
+    fn execute_command(&self, op: WriteOperation) -> Result<()> {
+        match op {
+            WriteOperation::InsertCustomer { name, address } => {
+                let (_, pipe) =
+                    self.insert("customers", &[("name", name), ("address", address)])?;
+                self.execute(&pipe).context("failed to insert customer")
+            }
+            WriteOperation::InsertAccount {
+                customer_id,
+                account_type,
+                balance,
+                parent_account_id,
+            } => {
+                let mut fields = vec![
+                    ("customer_id", customer_id.to_string()),
+                    ("account_type", account_type),
+                    ("balance", balance.to_string()),
+                ];
+                if let Some(parent_account_id) = parent_account_id {
+                    fields.push(("parent_account_id", parent_account_id.to_string()));
+                }
+                let (_, pipe) = self.insert("accounts", &fields)?;
+                self.execute(&pipe).context("failed to insert account")
+            }
+            WriteOperation::InsertSecurity {
+                ticker,
+                name,
+                sector,
+            } => {
+                let (_, pipe) = self.insert(
+                    "securities",
+                    &[("ticker", ticker), ("name", name), ("sector", sector)],
+                )?;
+                self.execute(&pipe).context("failed to insert security")
+            }
+            WriteOperation::InsertTrade {
+                account_id,
+                security_id,
+                trade_type,
+                quantity,
+                price,
+                parent_trade_id,
+            } => {
+                let mut fields = vec![
+                    ("account_id", account_id.to_string()),
+                    ("security_id", security_id.to_string()),
+                    ("trade_type", trade_type),
+                    ("quantity", quantity.to_string()),
+                    ("price", price.to_string()),
+                ];
+                if let Some(parent_trade_id) = parent_trade_id {
+                    fields.push(("parent_trade_id", parent_trade_id.to_string()));
+                }
+                let (_, pipe) = self.insert("trades", &fields)?;
+                self.execute(&pipe).context("failed to insert trade")
+            }
+            WriteOperation::InsertOrder {
+                account_id,
+                security_id,
+                order_type,
+                quantity,
+                limit_price,
+                status,
+                parent_order_id,
+            } => {
+                let mut fields = vec![
+                    ("account_id", account_id.to_string()),
+                    ("security_id", security_id.to_string()),
+                    ("order_type", order_type),
+                    ("quantity", quantity.to_string()),
+                    ("status", status),
+                ];
+                if let Some(limit_price) = limit_price {
+                    fields.push(("limit_price", limit_price.to_string()));
+                }
+                if let Some(parent_order_id) = parent_order_id {
+                    fields.push(("parent_order_id", parent_order_id.to_string()));
+                }
+                let (_, pipe) = self.insert("orders", &fields)?;
+                self.execute(&pipe).context("failed to insert order")
+            }
+            WriteOperation::InsertMarketData {
+                security_id,
+                price,
+                volume,
+            } => {
+                let (id, mut pipe) = self.insert(
+                    "market_data",
+                    &[
+                        ("security_id", security_id.to_string()),
+                        ("price", price.to_string()),
+                        ("volume", volume.to_string()),
+                    ],
+                )?;
+                pipe.cmd("HSET")
+                    .arg(prices_key(security_id))
+                    .arg(id)
+                    .arg(price)
+                    .ignore();
+                self.execute(&pipe).context("failed to insert market data")
+            }
+            WriteOperation::UpdateCustomer {
+                customer_id,
+                address,
+            } => self
+                .update("customers", customer_id, &[("address", address)])
+                .context("failed to update customer"),
+            WriteOperation::UpdateAccount {
+                account_id,
+                balance,
+            } => self
+                .update("accounts", account_id, &[("balance", balance.to_string())])
+                .context("failed to update account"),
+            WriteOperation::UpdateTrade { trade_id, price } => self
+                .update("trades", trade_id, &[("price", price.to_string())])
+                .context("failed to update trade"),
+            WriteOperation::UpdateOrder {
+                order_id,
+                status,
+                limit_price,
+            } => match limit_price {
+                Some(limit_price) => self.update(
+                    "orders",
+                    order_id,
+                    &[("status", status), ("limit_price", limit_price.to_string())],
+                ),
+                // A market order has no limit price, so the field is removed.
+                None => {
+                    let key = row_key("orders", order_id);
+                    let mut pipe = redis::pipe();
+                    pipe.atomic()
+                        .add_command(hset(&key, &[("status", status)]))
+                        .ignore()
+                        .cmd("HDEL")
+                        .arg(&key)
+                        .arg("limit_price")
+                        .ignore();
+                    self.execute(&pipe)
+                }
+            }
+            .context("failed to update order"),
+            WriteOperation::UpdateMarketData {
+                market_data_id,
+                price,
+                volume,
+            } => {
+                let security_id = self
+                    .market_data_security_id(market_data_id)
+                    .context("failed to update market data")?;
+                let mut pipe = redis::pipe();
+                pipe.atomic()
+                    .add_command(hset(
+                        &row_key("market_data", market_data_id),
+                        &[("price", price.to_string()), ("volume", volume.to_string())],
+                    ))
+                    .ignore();
+                if let Some(security_id) = security_id {
+                    pipe.cmd("HSET")
+                        .arg(prices_key(security_id))
+                        .arg(market_data_id)
+                        .arg(price)
+                        .ignore();
+                }
+                self.execute(&pipe).context("failed to update market data")
+            }
+            WriteOperation::DeleteCustomer { customer_id } => self
+                .execute(&self.delete("customers", customer_id))
+                .context("failed to delete customer"),
+            WriteOperation::DeleteAccount { account_id } => self
+                .execute(&self.delete("accounts", account_id))
+                .context("failed to delete account"),
+            WriteOperation::DeleteSecurity { security_id } => self
+                .execute(&self.delete("securities", security_id))
+                .context("failed to delete security"),
+            WriteOperation::DeleteTrade { trade_id } => self
+                .execute(&self.delete("trades", trade_id))
+                .context("failed to delete trade"),
+            WriteOperation::DeleteOrder { order_id } => self
+                .execute(&self.delete("orders", order_id))
+                .context("failed to delete order"),
+            WriteOperation::DeleteMarketData { market_data_id } => {
+                let security_id = self
+                    .market_data_security_id(market_data_id)
+                    .context("failed to delete market data")?;
+                let mut pipe = self.delete("market_data", market_data_id);
+                if let Some(security_id) = security_id {
+                    pipe.cmd("HDEL")
+                        .arg(prices_key(security_id))
+                        .arg(market_data_id)
+                        .ignore();
+                }
+                self.execute(&pipe).context("failed to delete market data")
+            }
+        }
+    }
+
+    fn insert_lag_token(&self, token: i64) -> Result<()> {
+        redis::cmd("SET")
+            .arg(LAG_TOKEN_KEY)
+            .arg(token)
+            .query::<()>(&mut *self.conn()?)
+            .context("failed to insert replication lag token")
+    }
+
+    fn insert_customer(&self, name: &str, address: &str) -> Result<i32> {
+        let (id, pipe) = self.insert(
+            "customers",
+            &[("name", name.to_string()), ("address", address.to_string())],
+        )?;
+        self.execute(&pipe).context("failed to insert customer")?;
+        i32::try_from(id).context("customer id out of range")
+    }
+}
+
+struct RedisClient {
+    conn: Connection,
+    view_prefix: String,
+}
+
+impl RedisClient {
+    /// Reads the cached result of `view` for `value` of the column it is looked up by.
+    fn get(&mut self, view: &str, value: impl Display) -> Result<u64> {
+        self.read(&format!("{view}:{value}"))
+    }
+
+    /// Reads a cached view result and returns the number of rows in it. A
+    /// missing key, e.g. for a customer inserted after the cache was loaded,
+    /// is a cache miss of no rows and not an error.
+    fn read(&mut self, key: &str) -> Result<u64> {
+        let result = redis::cmd("GET")
+            .arg(format!("{}{key}", self.view_prefix))
+            .query::<Option<String>>(&mut self.conn)
+            .with_context(|| format!("failed to read {key}"))?;
+        match result {
+            Some(rows) => serde_json::from_str::<Vec<Value>>(&rows)
+                .map(|rows| rows.len() as u64)
+                .with_context(|| format!("invalid cached rows in {key}")),
+            None => Ok(0),
+        }
+    }
+}
+
+impl ReadReplica for RedisClient {
+    fn customer_portfolio(&mut self, customer_id: i32) -> Result<u64> {
+        self.get("customer_portfolio", customer_id)
+    }
+
+    fn top_performers(&mut self) -> Result<u64> {
+        self.read("top_performers")
+    }
+
+    fn market_overview(&mut self, sector: &str) -> Result<u64> {
+        self.get("market_overview", sector)
+    }
+
+    fn recent_large_trades(&mut self, account_id: i32) -> Result<u64> {
+        self.get("recent_large_trades", account_id)
+    }
+
+    fn customer_order_book(&mut self, customer_id: i32) -> Result<u64> {
+        self.get("customer_order_book", customer_id)
+    }
+
+    fn sector_performance(&mut self, sector: String) -> Result<u64> {
+        self.get("sector_performance", sector)
+    }
+
+    fn account_activity_summary(&mut self, account_id: i32) -> Result<u64> {
+        self.get("account_activity_summary", account_id)
+    }
+
+    fn daily_market_movements(&mut self, security_id: i32) -> Result<u64> {
+        self.get("daily_market_movements", security_id)
+    }
+
+    fn high_value_customers(&mut self) -> Result<u64> {
+        self.read("high_value_customers")
+    }
+
+    fn pending_orders_summary(&mut self, ticker: &str) -> Result<u64> {
+        self.get("pending_orders_summary", ticker)
+    }
+
+    fn trade_volume_by_hour(&mut self) -> Result<u64> {
+        self.read("trade_volume_by_hour")
+    }
+
+    fn top_securities_by_sector(&mut self, sector: String) -> Result<u64> {
+        self.get("top_securities_by_sector", sector)
+    }
+
+    fn recent_trades_by_account(&mut self, account_id: i32) -> Result<u64> {
+        self.get("recent_trades_by_account", account_id)
+    }
+
+    fn order_fulfillment_rates(&mut self, customer_id: i32) -> Result<u64> {
+        self.get("order_fulfillment_rates", customer_id)
+    }
+
+    fn sector_order_activity(&mut self, sector: String) -> Result<u64> {
+        self.get("sector_order_activity", sector)
+    }
+
+    fn cascading_order_cancellation_alert(&mut self) -> Result<u64> {
+        self.read("cascading_order_cancellation_alert")
+    }
+
+    fn check_row_freshness(&mut self, security_id: i32, expected_price: f64) -> Result<bool> {
+        // Prices are compared with the same tolerance as the SQL backends.
+        redis::cmd("HVALS")
+            .arg(prices_key(security_id))
+            .query::<Vec<f64>>(&mut self.conn)
+            .map(|prices| {
+                prices
+                    .iter()
+                    .all(|price| (price - expected_price).abs() >= 0.0001)
+            })
+            .with_context(|| format!("failed to check freshness of market data {security_id}"))
+    }
+
+    fn fetch_customer(&mut self, customer_id: i32) -> Result<Option<String>> {
+        redis::cmd("HGET")
+            .arg(row_key("customers", customer_id))
+            .arg("name")
+            .query(&mut self.conn)
+            .with_context(|| format!("failed to fetch customer {customer_id}"))
+    }
+
+    fn ping(&mut self) -> Result<()> {
+        redis::cmd("PING")
+            .query::<String>(&mut self.conn)
+            .map(|_| ())
+            .context("failed to ping the read replica")
+    }
+
+    fn max_observed_token(&mut self) -> Result<Option<i64>> {
+        redis::cmd("GET")
+            .arg(LAG_TOKEN_KEY)
+            .query(&mut self.conn)
+            .context("failed to query replication lag token")
+    }
+}
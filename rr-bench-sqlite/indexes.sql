@@ -0,0 +1,10 @@
+-- Indexes on the columns the views join and filter on, created on the primary
+-- database by --apply-schema.
+
+CREATE INDEX IF NOT EXISTS accounts_customer_id ON accounts (customer_id);
+CREATE INDEX IF NOT EXISTS trades_account_id ON trades (account_id);
+CREATE INDEX IF NOT EXISTS trades_security_id ON trades (security_id);
+CREATE INDEX IF NOT EXISTS orders_account_id ON orders (account_id);
+CREATE INDEX IF NOT EXISTS orders_security_id ON orders (security_id);
+CREATE INDEX IF NOT EXISTS market_data_security_id ON market_data (security_id);
+CREATE INDEX IF NOT EXISTS securities_sector ON securities (sector);
@@ -1,17 +1,33 @@
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use rr_bench_base::benchmark;
-use rr_bench_base::clap::{Arg, ArgMatches};
-use rr_bench_base::operations::WriteOperation;
-use rr_bench_base::{Benchmark, PrimaryDatabase, ReadReplica};
-use rusqlite::{params, Connection};
+use rr_bench_base::clap::{Arg, ArgAction, ArgMatches};
+use rr_bench_base::operations::{IdKind, WriteOperation};
+use rr_bench_base::rand::rngs::StdRng;
+use rr_bench_base::{Benchmark, Consume, Metrics, PrimaryDatabase, RandomIdCache, ReadReplica};
+use rusqlite::types::FromSql;
+use rusqlite::{params, Connection, ErrorCode, OptionalExtension};
+use std::time::Duration;
+
+/// The views created by --apply-schema.
+const VIEWS: &str = include_str!("../views.sql");
+/// The indexes created on the primary database by --apply-schema.
+const INDEXES: &str = include_str!("../indexes.sql");
 
 fn main() {
     benchmark(
         || {
-            [Arg::new("dbpath")
-                .long("db-path")
-                .required(true)
-                .help("The path to the SQLite database file")]
+            [
+                Arg::new("dbpath")
+                    .long("db-path")
+                    .required(true)
+                    .help("The path to the SQLite database file"),
+                Arg::new("sqlite_pragma")
+                    .long("sqlite-pragma")
+                    .help("A PRAGMA applied to every connection right after it opens, in place of the default journal_mode=WAL if it sets journal_mode (e.g., synchronous=NORMAL, cache_size=-64000). May be given more than once")
+                    .value_name("KEY=VALUE")
+                    .action(ArgAction::Append)
+                    .value_parser(parse_pragma),
+            ]
         },
         SQLiteBenchmark::new,
     )
@@ -19,19 +35,66 @@ fn main() {
 
 struct SQLiteBenchmark {
     dbpath: String,
+    view_prefix: String,
+    query_timeout: Option<Duration>,
+    /// The `--sqlite-pragma` settings, followed by the `--session-setting`
+    /// ones, which SQLite applies as pragmas too.
+    pragmas: Vec<(String, String)>,
+    apply_schema: bool,
 }
 
 struct SQLiteConnection {
     conn: Connection,
+    ids: RandomIds,
+    view_prefix: String,
+}
+
+/// The cached id ranges used to pick random rows from each table.
+#[derive(Default)]
+struct RandomIds {
+    customers: RandomIdCache,
+    accounts: RandomIdCache,
+    securities: RandomIdCache,
+    trades: RandomIdCache,
+    orders: RandomIdCache,
+    market_data: RandomIdCache,
+}
+
+impl RandomIds {
+    fn get(&mut self, kind: IdKind) -> &mut RandomIdCache {
+        match kind {
+            IdKind::Customer => &mut self.customers,
+            IdKind::Account => &mut self.accounts,
+            IdKind::Security => &mut self.securities,
+            IdKind::Trade => &mut self.trades,
+            IdKind::Order => &mut self.orders,
+            IdKind::MarketData => &mut self.market_data,
+        }
+    }
 }
 
 impl SQLiteBenchmark {
     fn new(matches: ArgMatches) -> Result<Self> {
+        if *matches.get_one::<Consume>("consume").unwrap() != Consume::AllRows {
+            bail!("only --consume all-rows is supported by SQLite");
+        }
         Ok(Self {
             dbpath: matches
                 .get_one::<String>("dbpath")
                 .context("missing required parameter db-path")?
                 .to_string(),
+            view_prefix: matches
+                .get_one::<String>("view_prefix")
+                .cloned()
+                .unwrap_or_default(),
+            query_timeout: matches.get_one::<Duration>("query_timeout").copied(),
+            pragmas: ["sqlite_pragma", "session_setting"]
+                .into_iter()
+                .flat_map(|id| matches.get_many::<(String, String)>(id))
+                .flatten()
+                .cloned()
+                .collect(),
+            apply_schema: matches.get_flag("apply_schema"),
         })
     }
 }
@@ -40,104 +103,275 @@ impl Benchmark<'_> for SQLiteBenchmark {
     type Writer = SQLiteConnection;
     type Reader = SQLiteConnection;
 
-    fn primary_database(&self) -> Result<Self::Writer> {
-        SQLiteConnection::new(&self.dbpath)
+    fn primary_database(&self, _metrics: &Metrics) -> Result<Self::Writer> {
+        SQLiteConnection::new(&self.dbpath, &self.view_prefix, &self.pragmas)
     }
 
+    /// SQLite has no statement timeout, so `--query-timeout` bounds how long a
+    /// read waits for a lock held by the primary database simulator instead.
     fn read_replica(&self) -> Result<Self::Reader> {
-        SQLiteConnection::new(&self.dbpath)
+        let reader = SQLiteConnection::new(&self.dbpath, &self.view_prefix, &self.pragmas)?;
+        if let Some(timeout) = self.query_timeout {
+            reader
+                .conn
+                .busy_timeout(timeout)
+                .context("failed to set busy_timeout")?;
+        }
+        Ok(reader)
+    }
+
+    fn apply_schema(&self) -> Result<()> {
+        SQLiteConnection::new(&self.dbpath, &self.view_prefix, &self.pragmas)?
+            .conn
+            .execute_batch(VIEWS)
+            .context("failed to create views")
+    }
+
+    /// Creates the indexes the views rely on when run with `--apply-schema`.
+    fn setup(&self) -> Result<()> {
+        if !self.apply_schema {
+            return Ok(());
+        }
+        SQLiteConnection::new(&self.dbpath, &self.view_prefix, &self.pragmas)?
+            .conn
+            .execute_batch(INDEXES)
+            .context("failed to create indexes")
     }
 }
 
 impl SQLiteConnection {
-    fn new(db: &str) -> Result<Self> {
+    /// Opens `db` in WAL mode, unless `pragmas` set the journal mode, then
+    /// applies each of `pragmas` in order. Integer values are passed as
+    /// integers, anything else as a string.
+    fn new(db: &str, view_prefix: &str, pragmas: &[(String, String)]) -> Result<Self> {
         let conn = Connection::open(db).context("failed to open SQLite database")?;
-        conn.pragma_update(None, "journal_mode", "WAL")
-            .context("failed to enable WAL")?;
+        if !pragmas
+            .iter()
+            .any(|(key, _)| key.eq_ignore_ascii_case("journal_mode"))
+        {
+            conn.pragma_update(None, "journal_mode", "WAL")
+                .context("failed to enable WAL")?;
+        }
+        for (key, value) in pragmas {
+            let result = match value.parse::<i64>() {
+                Ok(value) => conn.pragma_update(None, key, value),
+                Err(_) => conn.pragma_update(None, key, value),
+            };
+            result.with_context(|| format!("failed to set PRAGMA {key}"))?;
+        }
 
-        Ok(Self { conn })
+        Ok(Self {
+            conn,
+            ids: RandomIds::default(),
+            view_prefix: view_prefix.to_string(),
+        })
     }
 }
 
-impl PrimaryDatabase for SQLiteConnection {
-    fn get_random_customer_id(&mut self) -> Result<i32> {
-        self.conn
-            .query_row(
-                "SELECT customer_id FROM customers ORDER BY random() LIMIT 1",
-                [],
-                |row| row.get("customer_id"),
-            )
-            .context("failed to retrieve customer_id")
+/// Parses a `--sqlite-pragma` of the form `key=value`. The key is used
+/// unquoted in the PRAGMA statement, so it may only contain letters, digits,
+/// and underscores, and must not start with a digit.
+fn parse_pragma(s: &str) -> Result<(String, String), String> {
+    let (key, value) = s
+        .split_once('=')
+        .ok_or_else(|| format!("invalid pragma '{s}', expected KEY=VALUE"))?;
+    let (key, value) = (key.trim(), value.trim());
+    let valid_key = key
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid_key {
+        return Err(format!(
+            "invalid pragma name '{key}', expected letters, digits, or '_'"
+        ));
     }
+    if value.is_empty() {
+        return Err(format!("missing value for pragma '{key}'"));
+    }
+    Ok((key.to_string(), value.to_string()))
+}
 
-    fn get_random_account_id(&mut self) -> Result<i32> {
-        self.conn
+/// Samples an id from the cached id range of `table`, loading the range when
+/// it is out of date.
+fn sample_id(
+    conn: &Connection,
+    cache: &mut RandomIdCache,
+    rng: &mut StdRng,
+    table: &str,
+    id: &str,
+) -> Result<i32> {
+    cache.sample(rng, || {
+        conn.query_row(
+            &format!("SELECT MIN({id}), MAX({id}) FROM {table}"),
+            [],
+            |row| Ok(row.get::<_, Option<i32>>(0)?.zip(row.get(1)?)),
+        )
+        .with_context(|| format!("failed to retrieve the {id} range"))
+    })
+}
+
+/// Returns `column` from a random row of `table`, chosen by sampling an id from
+/// the cached id range and selecting the first row at or after it. When rows at
+/// the end of the range have been deleted, the last row before it is used instead.
+fn random_row<T: FromSql>(
+    conn: &Connection,
+    cache: &mut RandomIdCache,
+    rng: &mut StdRng,
+    table: &str,
+    id: &str,
+    column: &str,
+) -> Result<T> {
+    let sampled = sample_id(conn, cache, rng, table, id)?;
+
+    let after = conn
+        .query_row(
+            &format!("SELECT {column} FROM {table} WHERE {id} >= ?1 ORDER BY {id} LIMIT 1"),
+            [sampled],
+            |row| row.get(0),
+        )
+        .optional()?;
+
+    match after {
+        Some(value) => Ok(value),
+        None => conn
             .query_row(
-                "SELECT account_id FROM accounts ORDER BY random() LIMIT 1",
-                [],
-                |row| row.get("account_id"),
+                &format!("SELECT {column} FROM {table} WHERE {id} < ?1 ORDER BY {id} DESC LIMIT 1"),
+                [sampled],
+                |row| row.get(0),
             )
-            .context("failed to retrieve account_id")
+            .map_err(Into::into),
     }
+}
 
-    fn get_random_security_id(&mut self) -> Result<i32> {
-        self.conn
-            .query_row(
-                "SELECT security_id FROM securities ORDER BY random() LIMIT 1",
-                [],
-                |row| row.get("security_id"),
-            )
-            .context("failed to retrieve security_id")
+/// Returns `n` random ids of `kind`, sampled like `random_row` but resolved to
+/// existing rows in a single query over a JSON array of the sampled ids.
+fn random_ids(
+    conn: &Connection,
+    cache: &mut RandomIdCache,
+    rng: &mut StdRng,
+    kind: IdKind,
+    n: usize,
+) -> Result<Vec<i32>> {
+    let (table, id) = (kind.table(), kind.column());
+    let sampled = (0..n)
+        .map(|_| sample_id(conn, cache, rng, table, id).map(|id| id.to_string()))
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut statement = conn.prepare(&format!(
+        "SELECT COALESCE(
+            (SELECT {id} FROM {table} WHERE {id} >= sampled.value ORDER BY {id} LIMIT 1),
+            (SELECT {id} FROM {table} WHERE {id} < sampled.value ORDER BY {id} DESC LIMIT 1))
+        FROM json_each(?1) AS sampled
+        ORDER BY sampled.key"
+    ))?;
+    let rows = statement.query_map([format!("[{}]", sampled.join(","))], |row| {
+        row.get::<_, Option<i32>>(0)
+    })?;
+    rows.map(|id| id?.with_context(|| format!("{table} is empty")))
+        .collect()
+}
+
+impl PrimaryDatabase for SQLiteConnection {
+    fn get_random_customer_id(&mut self, rng: &mut StdRng) -> Result<i32> {
+        random_row(
+            &self.conn,
+            &mut self.ids.customers,
+            rng,
+            "customers",
+            "customer_id",
+            "customer_id",
+        )
+        .context("failed to retrieve customer_id")
     }
 
-    fn get_random_trade_id(&mut self) -> Result<i32> {
-        self.conn
-            .query_row(
-                "SELECT trade_id FROM trades ORDER BY random() LIMIT 1",
-                [],
-                |row| row.get("trade_id"),
-            )
-            .context("failed to retrieve trade_id")
+    fn get_random_account_id(&mut self, rng: &mut StdRng) -> Result<i32> {
+        random_row(
+            &self.conn,
+            &mut self.ids.accounts,
+            rng,
+            "accounts",
+            "account_id",
+            "account_id",
+        )
+        .context("failed to retrieve account_id")
     }
 
-    fn get_random_order_id(&mut self) -> Result<i32> {
-        self.conn
-            .query_row(
-                "SELECT order_id FROM orders ORDER BY random() LIMIT 1",
-                [],
-                |row| row.get("order_id"),
-            )
-            .context("failed to retrieve order_id")
+    fn get_random_security_id(&mut self, rng: &mut StdRng) -> Result<i32> {
+        random_row(
+            &self.conn,
+            &mut self.ids.securities,
+            rng,
+            "securities",
+            "security_id",
+            "security_id",
+        )
+        .context("failed to retrieve security_id")
     }
 
-    fn get_random_market_data_id(&mut self) -> Result<i32> {
-        self.conn
-            .query_row(
-                "SELECT market_data_id FROM market_data ORDER BY random() LIMIT 1",
-                [],
-                |row| row.get("market_data_id"),
-            )
-            .context("failed to retrieve trade_id")
+    fn get_random_trade_id(&mut self, rng: &mut StdRng) -> Result<i32> {
+        random_row(
+            &self.conn,
+            &mut self.ids.trades,
+            rng,
+            "trades",
+            "trade_id",
+            "trade_id",
+        )
+        .context("failed to retrieve trade_id")
     }
 
-    fn get_random_ticker(&mut self) -> Result<String> {
-        self.conn
-            .query_row(
-                "SELECT ticker FROM tickers ORDER BY random() LIMIT 1",
-                [],
-                |row| row.get("ticker"),
-            )
-            .context("failed to retrieve ticker")
+    fn get_random_order_id(&mut self, rng: &mut StdRng) -> Result<i32> {
+        random_row(
+            &self.conn,
+            &mut self.ids.orders,
+            rng,
+            "orders",
+            "order_id",
+            "order_id",
+        )
+        .context("failed to retrieve order_id")
     }
 
-    fn get_random_sector(&mut self) -> Result<String> {
-        self.conn
-            .query_row(
-                "SELECT sector FROM securities ORDER BY random() LIMIT 1",
-                [],
-                |row| row.get("sector"),
-            )
-            .context("failed to retrieve sector")
+    fn get_random_market_data_id(&mut self, rng: &mut StdRng) -> Result<i32> {
+        random_row(
+            &self.conn,
+            &mut self.ids.market_data,
+            rng,
+            "market_data",
+            "market_data_id",
+            "market_data_id",
+        )
+        .context("failed to retrieve market_data_id")
+    }
+
+    fn get_random_ticker(&mut self, rng: &mut StdRng) -> Result<String> {
+        random_row(
+            &self.conn,
+            &mut self.ids.securities,
+            rng,
+            "securities",
+            "security_id",
+            "ticker",
+        )
+        .context("failed to retrieve ticker")
+    }
+
+    fn get_random_sector(&mut self, rng: &mut StdRng) -> Result<String> {
+        random_row(
+            &self.conn,
+            &mut self.ids.securities,
+            rng,
+            "securities",
+            "security_id",
+            "sector",
+        )
+        .context("failed to retrieve sector")
+    }
+
+    fn get_random_ids(&mut self, kind: IdKind, rng: &mut StdRng, n: usize) -> Result<Vec<i32>> {
+        random_ids(&self.conn, self.ids.get(kind), rng, kind, n)
+            .with_context(|| format!("failed to query {} ids", kind.table()))
     }
 
     fn execute_command(&self, op: WriteOperation) -> Result<()> {
@@ -181,7 +415,7 @@ impl PrimaryDatabase for SQLiteConnection {
                     None => self.conn.execute("INSERT INTO orders (account_id, security_id, order_type, quantity, limit_price, status) VALUES (?1, ?2, ?3, ?4, ?5, ?6)", params![account_id, security_id, order_type, quantity, limit_price, status])
                         .map(|_| ())
                         .context("failed to insert order"),
-                    Some(parent_order_id) => self.conn.execute("INSERT INTO orders (account_id, security_id, order_type, quantity, limit_price, status, parent_order_id) VALUES (?1, ?2, ?3, ?4, ?5, ?6)", params![account_id, security_id, order_type, quantity, limit_price, status, parent_order_id])
+                    Some(parent_order_id) => self.conn.execute("INSERT INTO orders (account_id, security_id, order_type, quantity, limit_price, status, parent_order_id) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)", params![account_id, security_id, order_type, quantity, limit_price, status, parent_order_id])
                         .map(|_| ())
                         .context("failed to insert order"),
                 }
@@ -192,7 +426,7 @@ impl PrimaryDatabase for SQLiteConnection {
             WriteOperation::UpdateCustomer { customer_id, address } => self.conn.execute("UPDATE customers SET address = ?1 WHERE customer_id = ?2", params![address, customer_id])
                 .map(|_| ())
                 .context("failed to update customer"),
-            WriteOperation::UpdateAccount { account_id, balance } => self.conn.execute("UPDATE accounts SET balance = ?1 WHERE customer_id = ?2",
+            WriteOperation::UpdateAccount { account_id, balance } => self.conn.execute("UPDATE accounts SET balance = ?1 WHERE account_id = ?2",
                                                                                        params![balance, account_id])
                 .map(|_| ())
                 .context("failed to update account"),
@@ -228,164 +462,512 @@ impl PrimaryDatabase for SQLiteConnection {
                 .context("failed to delete market_data")
         }
     }
+
+    fn execute_batch(&self, ops: Vec<WriteOperation>) -> Result<()> {
+        let tx = self
+            .conn
+            .unchecked_transaction()
+            .context("failed to begin transaction")?;
+        for op in ops {
+            self.execute_command(op)?;
+        }
+        tx.commit().context("failed to commit transaction")
+    }
+
+    fn insert_lag_token(&self, token: i64) -> Result<()> {
+        self.conn
+            .execute(
+                "INSERT INTO replication_lag_tokens (token) VALUES (?1)",
+                params![token],
+            )
+            .map(|_| ())
+            .context("failed to insert replication lag token")
+    }
+
+    fn insert_customer(&self, name: &str, address: &str) -> Result<i32> {
+        self.conn
+            .query_row(
+                "INSERT INTO customers (name, address) VALUES (?1, ?2) RETURNING customer_id",
+                params![name, address],
+                |row| row.get(0),
+            )
+            .context("failed to insert customer")
+    }
+
+    fn database_size(&mut self) -> Result<u64> {
+        self.conn
+            .query_row(
+                "SELECT page_count * page_size FROM pragma_page_count(), pragma_page_size()",
+                [],
+                |row| row.get::<_, i64>(0),
+            )
+            .map(|size| size as u64)
+            .context("failed to query database size")
+    }
+}
+
+/// Steps through every row of a read query, which runs it to completion,
+/// and returns the number of rows.
+fn count_rows(mut rows: rusqlite::Rows<'_>) -> rusqlite::Result<u64> {
+    let mut count = 0;
+    while rows.next()?.is_some() {
+        count += 1;
+    }
+    Ok(count)
 }
 
 impl ReadReplica for SQLiteConnection {
-    fn customer_portfolio(&mut self, customer_id: i32) -> Result<()> {
-        let mut stmt = self
-            .conn
-            .prepare("SELECT * FROM customer_portfolio WHERE customer_id = ?1")
-            .unwrap();
+    fn customer_portfolio(&mut self, customer_id: i32) -> Result<u64> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT * FROM {}customer_portfolio WHERE customer_id = ?1",
+            self.view_prefix
+        ))?;
 
         stmt.query(params![customer_id])
-            .map(|_| ())
+            .and_then(count_rows)
             .with_context(|| format!("failed to query customer profile {customer_id}"))
     }
 
-    fn top_performers(&mut self) -> Result<()> {
-        let mut stmt = self.conn.prepare("SELECT * FROM top_performers").unwrap();
+    fn top_performers(&mut self) -> Result<u64> {
+        let mut stmt = self
+            .conn
+            .prepare(&format!("SELECT * FROM {}top_performers", self.view_prefix))?;
         stmt.query(params![])
-            .map(|_| ())
+            .and_then(count_rows)
             .with_context(|| "failed to query top_performers".to_string())
     }
 
-    fn market_overview(&mut self, sector: &str) -> Result<()> {
-        let mut stmt = self
-            .conn
-            .prepare("SELECT * FROM market_overview WHERE sector = ?1")
-            .unwrap();
+    fn market_overview(&mut self, sector: &str) -> Result<u64> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT * FROM {}market_overview WHERE sector = ?1",
+            self.view_prefix
+        ))?;
         stmt.query(params![sector])
-            .map(|_| ())
+            .and_then(count_rows)
             .with_context(|| "failed to query market_overview".to_string())
     }
 
-    fn recent_large_trades(&mut self, account_id: i32) -> Result<()> {
-        let mut stmt = self
-            .conn
-            .prepare("SELECT * FROM recent_large_trades WHERE account_id = ?1")
-            .unwrap();
+    fn recent_large_trades(&mut self, account_id: i32) -> Result<u64> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT * FROM {}recent_large_trades WHERE account_id = ?1",
+            self.view_prefix
+        ))?;
         stmt.query(params![account_id])
-            .map(|_| ())
+            .and_then(count_rows)
             .with_context(|| "failed to query recent_large_trades".to_string())
     }
 
-    fn customer_order_book(&mut self, customer_id: i32) -> Result<()> {
-        let mut stmt = self
-            .conn
-            .prepare("SELECT * FROM customer_order_book WHERE customer_id = ?1")
-            .unwrap();
+    fn customer_order_book(&mut self, customer_id: i32) -> Result<u64> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT * FROM {}customer_order_book WHERE customer_id = ?1",
+            self.view_prefix
+        ))?;
         stmt.query(params![customer_id])
-            .map(|_| ())
+            .and_then(count_rows)
             .with_context(|| format!("failed to query customer_order_book {customer_id}"))
     }
 
-    fn sector_performance(&mut self, sector: String) -> Result<()> {
-        let mut stmt = self
-            .conn
-            .prepare("SELECT * FROM sector_performance WHERE sector = ?1")
-            .unwrap();
+    fn sector_performance(&mut self, sector: String) -> Result<u64> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT * FROM {}sector_performance WHERE sector = ?1",
+            self.view_prefix
+        ))?;
         stmt.query(params![sector])
-            .map(|_| ())
+            .and_then(count_rows)
             .with_context(|| "failed to query sector_performance".to_string())
     }
 
-    fn account_activity_summary(&mut self, account_id: i32) -> Result<()> {
-        let mut stmt = self
-            .conn
-            .prepare("SELECT * FROM account_activity_summary WHERE account_id = ?1")
-            .unwrap();
+    fn account_activity_summary(&mut self, account_id: i32) -> Result<u64> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT * FROM {}account_activity_summary WHERE account_id = ?1",
+            self.view_prefix
+        ))?;
         stmt.query(params![account_id])
-            .map(|_| ())
+            .and_then(count_rows)
             .with_context(|| format!("failed to query account_activity_summary {account_id}"))
     }
 
-    fn daily_market_movements(&mut self, security_id: i32) -> Result<()> {
-        let mut stmt = self
-            .conn
-            .prepare("SELECT * FROM daily_market_movements WHERE security_id = ?1")
-            .unwrap();
+    fn daily_market_movements(&mut self, security_id: i32) -> Result<u64> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT * FROM {}daily_market_movements WHERE security_id = ?1",
+            self.view_prefix
+        ))?;
         stmt.query(params![security_id])
-            .map(|_| ())
+            .and_then(count_rows)
             .with_context(|| format!("failed to query daily_market_movements {security_id}"))
     }
 
-    fn high_value_customers(&mut self) -> Result<()> {
-        let mut stmt = self
-            .conn
-            .prepare("SELECT * FROM high_value_customers")
-            .unwrap();
+    fn high_value_customers(&mut self) -> Result<u64> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT * FROM {}high_value_customers",
+            self.view_prefix
+        ))?;
         stmt.query(params![])
-            .map(|_| ())
+            .and_then(count_rows)
             .with_context(|| "failed to query high_value_customers".to_string())
     }
 
-    fn pending_orders_summary(&mut self, ticker: &str) -> Result<()> {
-        let mut stmt = self
-            .conn
-            .prepare("SELECT * FROM pending_orders_summary WHERE ticker = ?1")
-            .unwrap();
+    fn pending_orders_summary(&mut self, ticker: &str) -> Result<u64> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT * FROM {}pending_orders_summary WHERE ticker = ?1",
+            self.view_prefix
+        ))?;
         stmt.query(params![ticker])
-            .map(|_| ())
+            .and_then(count_rows)
             .with_context(|| "failed to query pending_orders_summary".to_string())
     }
 
-    fn trade_volume_by_hour(&mut self) -> Result<()> {
-        let mut stmt = self
-            .conn
-            .prepare("SELECT * FROM trade_volume_by_hour")
-            .unwrap();
+    fn trade_volume_by_hour(&mut self) -> Result<u64> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT * FROM {}trade_volume_by_hour",
+            self.view_prefix
+        ))?;
         stmt.query(params![])
-            .map(|_| ())
+            .and_then(count_rows)
             .with_context(|| "failed to query trade_volume_by_hour".to_string())
     }
 
-    fn top_securities_by_sector(&mut self, sector: String) -> Result<()> {
-        let mut stmt = self
-            .conn
-            .prepare("SELECT * FROM top_securities_by_sector WHERE sector = ?1")
-            .unwrap();
+    fn top_securities_by_sector(&mut self, sector: String) -> Result<u64> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT * FROM {}top_securities_by_sector WHERE sector = ?1",
+            self.view_prefix
+        ))?;
         stmt.query(params![sector])
-            .map(|_| ())
+            .and_then(count_rows)
             .with_context(|| "failed to query top_securities_by_sector".to_string())
     }
 
-    fn recent_trades_by_account(&mut self, account_id: i32) -> Result<()> {
-        let mut stmt = self
-            .conn
-            .prepare("SELECT * FROM recent_trades_by_account WHERE account_id = ?1")
-            .unwrap();
+    fn recent_trades_by_account(&mut self, account_id: i32) -> Result<u64> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT * FROM {}recent_trades_by_account WHERE account_id = ?1",
+            self.view_prefix
+        ))?;
         stmt.query(params![account_id])
-            .map(|_| ())
+            .and_then(count_rows)
             .with_context(|| format!("failed to query recent_trades_by_account {account_id}"))
     }
 
-    fn order_fulfillment_rates(&mut self, customer_id: i32) -> Result<()> {
-        let mut stmt = self
-            .conn
-            .prepare("SELECT * FROM order_fulfillment_rates WHERE customer_id = ?1")
-            .unwrap();
+    fn order_fulfillment_rates(&mut self, customer_id: i32) -> Result<u64> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT * FROM {}order_fulfillment_rates WHERE customer_id = ?1",
+            self.view_prefix
+        ))?;
         stmt.query(params![customer_id])
-            .map(|_| ())
+            .and_then(count_rows)
             .with_context(|| format!("failed to query order_fulfillment_rates {customer_id}"))
     }
 
-    fn sector_order_activity(&mut self, sector: String) -> Result<()> {
-        let mut stmt = self
-            .conn
-            .prepare("SELECT * FROM sector_order_activity WHERE sector = ?1")
-            .unwrap();
+    fn sector_order_activity(&mut self, sector: String) -> Result<u64> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT * FROM {}sector_order_activity WHERE sector = ?1",
+            self.view_prefix
+        ))?;
         stmt.query(params![sector])
-            .map(|_| ())
+            .and_then(count_rows)
             .with_context(|| "failed to query sector_order_activity".to_string())
     }
 
-    fn cascading_order_cancellation_alert(&mut self) -> Result<()> {
-        let mut stmt = self
-            .conn
-            .prepare("SELECT * FROM cascading_order_cancellation_alert")
-            .unwrap();
+    fn cascading_order_cancellation_alert(&mut self) -> Result<u64> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT * FROM {}cascading_order_cancellation_alert",
+            self.view_prefix
+        ))?;
         stmt.query(params![])
-            .map(|_| ())
+            .and_then(count_rows)
             .with_context(|| "failed to query cascading_order_cancellation_alert".to_string())
     }
+
+    fn check_row_freshness(&mut self, security_id: i32, expected_price: f64) -> Result<bool> {
+        // Prices are stored with four decimal places.
+        self.conn
+            .query_row(
+                "SELECT EXISTS (SELECT 1 FROM market_data \
+                 WHERE security_id = ?1 AND abs(price - ?2) < 0.0001)",
+                params![security_id, expected_price],
+                |row| row.get::<_, bool>(0),
+            )
+            .map(|fresh| !fresh)
+            .with_context(|| format!("failed to check freshness of market data {security_id}"))
+    }
+
+    fn fetch_customer(&mut self, customer_id: i32) -> Result<Option<String>> {
+        self.conn
+            .query_row(
+                "SELECT name FROM customers WHERE customer_id = ?1",
+                params![customer_id],
+                |row| row.get(0),
+            )
+            .optional()
+            .with_context(|| format!("failed to fetch customer {customer_id}"))
+    }
+
+    fn ping(&mut self) -> Result<()> {
+        self.conn
+            .query_row("SELECT 1", [], |_| Ok(()))
+            .context("failed to ping the read replica")
+    }
+
+    fn max_observed_token(&mut self) -> Result<Option<i64>> {
+        self.conn
+            .query_row("SELECT MAX(token) FROM replication_lag_tokens", [], |row| {
+                row.get(0)
+            })
+            .context("failed to query replication lag token")
+    }
+
+    fn is_timeout(&self, error: &anyhow::Error) -> bool {
+        matches!(
+            error.downcast_ref::<rusqlite::Error>(),
+            Some(rusqlite::Error::SqliteFailure(e, _)) if e.code == ErrorCode::DatabaseBusy
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rr_bench_base::rand::SeedableRng;
+
+    const DDL: &str = include_str!("../ddl.sql");
+
+    /// Returns a connection to an in-memory database with the benchmark schema.
+    fn connection() -> SQLiteConnection {
+        let conn = SQLiteConnection::new(":memory:", "", &[]).unwrap();
+        conn.conn.execute_batch(DDL).unwrap();
+        conn
+    }
+
+    fn column<T: FromSql>(conn: &SQLiteConnection, query: &str) -> Vec<T> {
+        let mut statement = conn.conn.prepare(query).unwrap();
+        let rows = statement.query_map([], |row| row.get(0)).unwrap();
+        rows.collect::<rusqlite::Result<_>>().unwrap()
+    }
+
+    fn pragma<T: FromSql>(conn: &SQLiteConnection, name: &str) -> T {
+        conn.conn
+            .pragma_query_value(None, name, |row| row.get(0))
+            .unwrap()
+    }
+
+    #[test]
+    fn pragmas_are_applied_in_place_of_wal() {
+        let path = std::env::temp_dir().join(format!("rr-bench-{}-pragmas.db", std::process::id()));
+        let db = path.to_str().unwrap();
+
+        let conn = SQLiteConnection::new(db, "", &[]).unwrap();
+        assert_eq!(pragma::<String>(&conn, "journal_mode"), "wal");
+        drop(conn);
+
+        let pragmas = [
+            parse_pragma("journal_mode=DELETE").unwrap(),
+            parse_pragma("synchronous = 0").unwrap(),
+            parse_pragma("cache_size=-4000").unwrap(),
+        ];
+        let conn = SQLiteConnection::new(db, "", &pragmas).unwrap();
+        assert_eq!(pragma::<String>(&conn, "journal_mode"), "delete");
+        assert_eq!(pragma::<i64>(&conn, "synchronous"), 0);
+        assert_eq!(pragma::<i64>(&conn, "cache_size"), -4000);
+        drop(conn);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn setup_creates_the_indexes_only_with_apply_schema() {
+        let path = std::env::temp_dir().join(format!("rr-bench-{}-indexes.db", std::process::id()));
+        let db = path.to_str().unwrap();
+        SQLiteConnection::new(db, "", &[])
+            .unwrap()
+            .conn
+            .execute_batch(DDL)
+            .unwrap();
+        let indexes = || -> Vec<String> {
+            column(
+                &SQLiteConnection::new(db, "", &[]).unwrap(),
+                "SELECT name FROM sqlite_master WHERE type = 'index' AND name = 'trades_account_id'",
+            )
+        };
+
+        let mut benchmark = SQLiteBenchmark {
+            dbpath: db.to_string(),
+            view_prefix: String::new(),
+            query_timeout: None,
+            pragmas: Vec::new(),
+            apply_schema: false,
+        };
+        benchmark.setup().unwrap();
+        assert!(indexes().is_empty());
+
+        benchmark.apply_schema = true;
+        benchmark.setup().unwrap();
+        // The indexes are only created if missing, so setup can run again.
+        benchmark.setup().unwrap();
+        assert_eq!(indexes(), ["trades_account_id"]);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn malformed_pragmas_are_errors() {
+        assert!(parse_pragma("journal_mode").is_err());
+        assert!(parse_pragma("cache_size=").is_err());
+        assert!(parse_pragma("main.cache_size=100").is_err());
+        assert_eq!(
+            parse_pragma(" mmap_size = 268435456 ").unwrap(),
+            ("mmap_size".to_string(), "268435456".to_string())
+        );
+    }
+
+    #[test]
+    fn update_account_changes_only_that_account() {
+        let conn = connection();
+        conn.execute_command(WriteOperation::InsertCustomer {
+            name: "Ada".to_string(),
+            address: "1 Main St".to_string(),
+        })
+        .unwrap();
+        // Both accounts belong to customer 1, so filtering
+        // on customer_id would update both of them.
+        for balance in [100.0, 200.0] {
+            conn.execute_command(WriteOperation::InsertAccount {
+                customer_id: 1,
+                account_type: "Savings".to_string(),
+                balance,
+                parent_account_id: None,
+            })
+            .unwrap();
+        }
+
+        conn.execute_command(WriteOperation::UpdateAccount {
+            account_id: 1,
+            balance: 150.0,
+        })
+        .unwrap();
+
+        let balances: Vec<f64> = column(&conn, "SELECT balance FROM accounts ORDER BY account_id");
+        assert_eq!(balances, [150.0, 200.0]);
+    }
+
+    #[test]
+    fn get_random_ticker_reads_securities() {
+        let mut conn = connection();
+        conn.execute_command(WriteOperation::InsertSecurity {
+            ticker: "ACME".to_string(),
+            name: "Acme Corp".to_string(),
+            sector: "Industrials".to_string(),
+        })
+        .unwrap();
+
+        let mut rng = StdRng::seed_from_u64(0);
+        assert_eq!(conn.get_random_ticker(&mut rng).unwrap(), "ACME");
+    }
+
+    #[test]
+    fn random_ids_skip_deleted_rows() {
+        let mut conn = connection();
+        for ticker in ["A", "B", "C", "D"] {
+            conn.execute_command(WriteOperation::InsertSecurity {
+                ticker: ticker.to_string(),
+                name: ticker.to_string(),
+                sector: "Industrials".to_string(),
+            })
+            .unwrap();
+        }
+        let ids = conn
+            .get_random_ids(IdKind::Security, &mut StdRng::seed_from_u64(0), 50)
+            .unwrap();
+        let mut rng = StdRng::seed_from_u64(0);
+        let expected = (0..50)
+            .map(|_| conn.get_random_security_id(&mut rng).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(ids, expected);
+
+        conn.execute_command(WriteOperation::DeleteSecurity { security_id: 2 })
+            .unwrap();
+        conn.execute_command(WriteOperation::DeleteSecurity { security_id: 4 })
+            .unwrap();
+        let ids = conn
+            .get_random_ids(IdKind::Security, &mut StdRng::seed_from_u64(0), 50)
+            .unwrap();
+        assert_eq!(ids.len(), 50);
+        assert!(ids.iter().all(|id| [1, 3].contains(id)), "{ids:?}");
+        assert!(ids.contains(&3));
+    }
+
+    #[test]
+    fn count_rows_steps_through_every_row() {
+        let conn = connection();
+        for ticker in ["A", "B", "C"] {
+            conn.execute_command(WriteOperation::InsertSecurity {
+                ticker: ticker.to_string(),
+                name: ticker.to_string(),
+                sector: "Industrials".to_string(),
+            })
+            .unwrap();
+        }
+
+        let mut stmt = conn.conn.prepare("SELECT * FROM securities").unwrap();
+        assert_eq!(stmt.query([]).and_then(count_rows).unwrap(), 3);
+        let mut stmt = conn
+            .conn
+            .prepare("SELECT * FROM securities WHERE ticker = ?1")
+            .unwrap();
+        assert_eq!(stmt.query(["D"]).and_then(count_rows).unwrap(), 0);
+    }
+
+    #[test]
+    fn market_data_is_inserted_and_updated() {
+        let conn = connection();
+        conn.execute_command(WriteOperation::InsertMarketData {
+            security_id: 1,
+            price: 100.0,
+            volume: 1000,
+        })
+        .unwrap();
+        conn.execute_command(WriteOperation::UpdateMarketData {
+            market_data_id: 1,
+            price: 101.5,
+            volume: 2500,
+        })
+        .unwrap();
+
+        let row: (f64, i32) = conn
+            .conn
+            .query_row(
+                "SELECT price, volume FROM market_data WHERE market_data_id = 1",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(row, (101.5, 2500));
+    }
+
+    #[test]
+    fn market_order_has_null_limit_price() {
+        let conn = connection();
+        for limit_price in [None, Some(12.5)] {
+            conn.execute_command(WriteOperation::InsertOrder {
+                account_id: 1,
+                security_id: 1,
+                order_type: "buy".to_string(),
+                quantity: 10,
+                limit_price,
+                status: "pending".to_string(),
+                parent_order_id: None,
+            })
+            .unwrap();
+        }
+
+        let limit_prices: Vec<Option<f64>> =
+            column(&conn, "SELECT limit_price FROM orders ORDER BY order_id");
+        assert_eq!(limit_prices, [None, Some(12.5)]);
+
+        conn.execute_command(WriteOperation::UpdateOrder {
+            order_id: 2,
+            status: "completed".to_string(),
+            limit_price: None,
+        })
+        .unwrap();
+        let limit_prices: Vec<Option<f64>> =
+            column(&conn, "SELECT limit_price FROM orders ORDER BY order_id");
+        assert_eq!(limit_prices, [None, None]);
+    }
 }
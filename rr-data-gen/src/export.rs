@@ -0,0 +1,269 @@
+use anyhow::{bail, Context, Result};
+use arrow::array::{
+    ArrayRef, Decimal128Builder, Int32Builder, StringBuilder, TimestampSecondBuilder,
+};
+use arrow::datatypes::{DataType, Field, Schema, SchemaRef, TimeUnit};
+use arrow::record_batch::RecordBatch;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use rusqlite::types::ValueRef;
+use rusqlite::Connection;
+use std::fs::File;
+use std::path::Path;
+use std::sync::Arc;
+
+/// The number of rows in each record batch, and so in each row group.
+const BATCH_SIZE: usize = 64 * 1024;
+
+/// The Parquet type of a column, derived from its declared SQLite type.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ColumnType {
+    Integer,
+    Text,
+    Decimal {
+        precision: u8,
+        scale: i8,
+    },
+    /// A `DATETIME`, which SQLite stores as `YYYY-MM-DD HH:MM:SS` text in UTC.
+    Timestamp,
+}
+
+impl ColumnType {
+    fn parse(declared: &str) -> Result<Self> {
+        let declared = declared.to_ascii_uppercase();
+        Ok(match declared.as_str() {
+            "INTEGER" => ColumnType::Integer,
+            "TEXT" => ColumnType::Text,
+            "DATETIME" => ColumnType::Timestamp,
+            _ => {
+                let Some((precision, scale)) = declared
+                    .strip_prefix("DECIMAL(")
+                    .and_then(|rest| rest.strip_suffix(')'))
+                    .and_then(|rest| rest.split_once(','))
+                else {
+                    bail!("unsupported column type {declared}");
+                };
+                ColumnType::Decimal {
+                    precision: precision.trim().parse()?,
+                    scale: scale.trim().parse()?,
+                }
+            }
+        })
+    }
+
+    fn data_type(self) -> DataType {
+        match self {
+            ColumnType::Integer => DataType::Int32,
+            ColumnType::Text => DataType::Utf8,
+            ColumnType::Decimal { precision, scale } => DataType::Decimal128(precision, scale),
+            ColumnType::Timestamp => DataType::Timestamp(TimeUnit::Second, None),
+        }
+    }
+
+    /// The expression that selects `column`. Timestamps are converted to
+    /// seconds since the epoch by SQLite.
+    fn select(self, column: &str) -> String {
+        match self {
+            ColumnType::Timestamp => format!("unixepoch({column})"),
+            _ => column.to_string(),
+        }
+    }
+}
+
+/// Accumulates the values of one column of a record batch.
+enum ColumnBuilder {
+    Integer(Int32Builder),
+    Text(StringBuilder),
+    Decimal(Decimal128Builder, i8),
+    Timestamp(TimestampSecondBuilder),
+}
+
+impl ColumnBuilder {
+    fn new(column_type: ColumnType) -> Result<Self> {
+        Ok(match column_type {
+            ColumnType::Integer => ColumnBuilder::Integer(Int32Builder::with_capacity(BATCH_SIZE)),
+            ColumnType::Text => ColumnBuilder::Text(StringBuilder::new()),
+            ColumnType::Decimal { precision, scale } => ColumnBuilder::Decimal(
+                Decimal128Builder::with_capacity(BATCH_SIZE)
+                    .with_precision_and_scale(precision, scale)?,
+                scale,
+            ),
+            ColumnType::Timestamp => {
+                ColumnBuilder::Timestamp(TimestampSecondBuilder::with_capacity(BATCH_SIZE))
+            }
+        })
+    }
+
+    fn append(&mut self, value: ValueRef) -> Result<()> {
+        match (self, value) {
+            (ColumnBuilder::Integer(builder), ValueRef::Null) => builder.append_null(),
+            (ColumnBuilder::Text(builder), ValueRef::Null) => builder.append_null(),
+            (ColumnBuilder::Decimal(builder, _), ValueRef::Null) => builder.append_null(),
+            (ColumnBuilder::Timestamp(builder), ValueRef::Null) => builder.append_null(),
+            (ColumnBuilder::Integer(builder), ValueRef::Integer(value)) => {
+                builder.append_value(i32::try_from(value)?)
+            }
+            (ColumnBuilder::Text(builder), ValueRef::Text(value)) => {
+                builder.append_value(std::str::from_utf8(value)?)
+            }
+            // DECIMAL columns have numeric affinity, so whole numbers are
+            // stored as integers.
+            (ColumnBuilder::Decimal(builder, scale), ValueRef::Integer(value)) => {
+                builder.append_value(i128::from(value) * 10i128.pow(*scale as u32))
+            }
+            (ColumnBuilder::Decimal(builder, scale), ValueRef::Real(value)) => {
+                builder.append_value((value * 10f64.powi(*scale as i32)).round() as i128)
+            }
+            (ColumnBuilder::Timestamp(builder), ValueRef::Integer(value)) => {
+                builder.append_value(value)
+            }
+            (_, value) => bail!("unexpected {:?} value", value.data_type()),
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> ArrayRef {
+        match self {
+            ColumnBuilder::Integer(builder) => Arc::new(builder.finish()),
+            ColumnBuilder::Text(builder) => Arc::new(builder.finish()),
+            ColumnBuilder::Decimal(builder, _) => Arc::new(builder.finish()),
+            ColumnBuilder::Timestamp(builder) => Arc::new(builder.finish()),
+        }
+    }
+}
+
+/// Writes every row of `table` to a Parquet file at `path`, compressed with
+/// Snappy. The schema follows the declared types of the table's columns, so
+/// that `DECIMAL(p, s)` columns become decimals of the same precision and
+/// scale, and `DATETIME` columns become timestamps.
+pub fn export_to_parquet(conn: &Connection, table: &str, path: &Path) -> Result<()> {
+    let mut columns = vec![];
+    let mut fields = vec![];
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({table})"))?;
+    let mut rows = stmt.query([])?;
+    while let Some(row) = rows.next()? {
+        let name: String = row.get("name")?;
+        let column_type = ColumnType::parse(&row.get::<_, String>("type")?)
+            .with_context(|| format!("failed to map column {table}.{name}"))?;
+        // SQLite does not mark INTEGER PRIMARY KEY columns NOT NULL.
+        let nullable = !row.get::<_, bool>("notnull")? && row.get::<_, i32>("pk")? == 0;
+        fields.push(Field::new(&name, column_type.data_type(), nullable));
+        columns.push((name, column_type));
+    }
+    let schema = Arc::new(Schema::new(fields));
+
+    let file =
+        File::create(path).with_context(|| format!("failed to create {}", path.display()))?;
+    let properties = WriterProperties::builder()
+        .set_compression(Compression::SNAPPY)
+        .build();
+    let mut writer = ArrowWriter::try_new(file, schema.clone(), Some(properties))?;
+
+    let select = columns
+        .iter()
+        .map(|(name, column_type)| column_type.select(name))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let mut stmt = conn.prepare(&format!("SELECT {select} FROM {table}"))?;
+    let mut rows = stmt.query([])?;
+    let mut builders = columns
+        .iter()
+        .map(|(_, column_type)| ColumnBuilder::new(*column_type))
+        .collect::<Result<Vec<_>>>()?;
+    let mut batch_rows = 0;
+    while let Some(row) = rows.next()? {
+        for (i, builder) in builders.iter_mut().enumerate() {
+            builder
+                .append(row.get_ref(i)?)
+                .with_context(|| format!("invalid {table}.{}", columns[i].0))?;
+        }
+        batch_rows += 1;
+        if batch_rows == BATCH_SIZE {
+            write_batch(&mut writer, &schema, &mut builders)?;
+            batch_rows = 0;
+        }
+    }
+    if batch_rows > 0 {
+        write_batch(&mut writer, &schema, &mut builders)?;
+    }
+    writer.close()?;
+    Ok(())
+}
+
+/// Writes the values accumulated by `builders` as a record batch, and
+/// clears them for the next one.
+fn write_batch(
+    writer: &mut ArrowWriter<File>,
+    schema: &SchemaRef,
+    builders: &mut [ColumnBuilder],
+) -> Result<()> {
+    let arrays = builders.iter_mut().map(ColumnBuilder::finish).collect();
+    writer.write(&RecordBatch::try_new(schema.clone(), arrays)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::{Array, Decimal128Array, Int32Array, StringArray, TimestampSecondArray};
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    #[test]
+    fn parquet_types_follow_the_declared_column_types() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE trades (
+                trade_id INTEGER PRIMARY KEY AUTOINCREMENT,
+                trade_type TEXT NOT NULL,
+                price DECIMAL(18, 4) NOT NULL,
+                limit_price DECIMAL(18, 4),
+                trade_date DATETIME DEFAULT CURRENT_TIMESTAMP
+            );
+            INSERT INTO trades (trade_type, price, limit_price, trade_date)
+            VALUES ('buy', 123.4567, NULL, '2024-01-02 03:04:05'), ('sell', 250, 99.5, NULL);",
+        )
+        .unwrap();
+        let path = std::env::temp_dir().join(format!("rr-data-gen-{}.parquet", std::process::id()));
+        export_to_parquet(&conn, "trades", &path).unwrap();
+
+        let reader = ParquetRecordBatchReaderBuilder::try_new(File::open(&path).unwrap())
+            .unwrap()
+            .build()
+            .unwrap();
+        let batches = reader.collect::<Result<Vec<_>, _>>().unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let batch = &batches[0];
+
+        let schema = batch.schema();
+        let types: Vec<_> = schema
+            .fields()
+            .iter()
+            .map(|field| (field.data_type().clone(), field.is_nullable()))
+            .collect();
+        assert_eq!(
+            types,
+            [
+                (DataType::Int32, false),
+                (DataType::Utf8, false),
+                (DataType::Decimal128(18, 4), false),
+                (DataType::Decimal128(18, 4), true),
+                (DataType::Timestamp(TimeUnit::Second, None), true),
+            ]
+        );
+
+        let column = |i: usize| batch.column(i).as_any();
+        let ids = column(0).downcast_ref::<Int32Array>().unwrap();
+        assert_eq!(ids.values(), &[1, 2]);
+        let types = column(1).downcast_ref::<StringArray>().unwrap();
+        assert_eq!((types.value(0), types.value(1)), ("buy", "sell"));
+        let prices = column(2).downcast_ref::<Decimal128Array>().unwrap();
+        assert_eq!(prices.values(), &[1_234_567, 2_500_000]);
+        let limit_prices = column(3).downcast_ref::<Decimal128Array>().unwrap();
+        assert!(limit_prices.is_null(0));
+        assert_eq!(limit_prices.value(1), 995_000);
+        let dates = column(4).downcast_ref::<TimestampSecondArray>().unwrap();
+        assert_eq!(dates.value(0), 1_704_164_645);
+        assert!(dates.is_null(1));
+    }
+}
@@ -1,30 +1,147 @@
-use anyhow::{Context, Result};
-use clap::Parser;
-use indicatif::{ProgressBar, ProgressStyle};
+use anyhow::{bail, ensure, Context, Result};
+use clap::{ArgGroup, Parser, ValueEnum};
+use indicatif::{HumanBytes, ProgressBar, ProgressStyle};
 use rand::prelude::StdRng;
 use rand::{thread_rng, Rng, SeedableRng};
 use rr_bench_core::DataGenerator;
 use rusqlite::functions::FunctionFlags;
-use rusqlite::{params, Connection, Transaction};
+use rusqlite::{params, Connection, OpenFlags};
 use serde::Serialize;
 use std::env;
 use std::fs::File;
 use std::num::NonZeroU8;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::thread;
 use uuid::Uuid;
 
+mod export;
+mod postgres;
+
+use export::export_to_parquet;
+use postgres::PostgresSink;
+
 const GIGABYTE: u64 = 1024 * 1024 * 1024;
 
+/// The approximate size of a generated row, measured in SQLite, used to
+/// convert the target size into a row count for --target-url.
+const BYTES_PER_ROW: u64 = 52;
+
+/// The schema of the SQLite benchmark, used for databases kept with --keep-db.
+const SQLITE_DDL: &str = include_str!("../../rr-bench-sqlite/ddl.sql");
+const SQLITE_VIEWS: &str = include_str!("../../rr-bench-sqlite/views.sql");
+
+/// Counts the securities whose ticker an earlier security already has, which
+/// is the same query in SQLite and Postgres.
+const DUPLICATE_TICKERS: &str = "SELECT COUNT(*) - COUNT(DISTINCT ticker) FROM securities";
+
+/// The generated tables, in foreign key order, with their columns.
+const TABLES: &[(&str, &str)] = &[
+    ("customers", "customer_id, name, address, created_at"),
+    (
+        "accounts",
+        "account_id, customer_id, account_type, balance, created_at",
+    ),
+    (
+        "securities",
+        "security_id, ticker, name, sector, created_at",
+    ),
+    (
+        "trades",
+        "trade_id, account_id, security_id, trade_type, quantity, price, trade_date",
+    ),
+    (
+        "orders",
+        "order_id, account_id, security_id, order_type, quantity, limit_price, status, order_date",
+    ),
+    (
+        "market_data",
+        "market_data_id, security_id, price, volume, market_date",
+    ),
+];
+
+/// The columns indexed by --with-indexes: the foreign keys the views join
+/// on, and the columns the read operations filter by.
+const INDEXES: &[(&str, &str)] = &[
+    ("accounts", "customer_id"),
+    ("securities", "sector"),
+    ("trades", "account_id"),
+    ("trades", "security_id"),
+    ("orders", "account_id"),
+    ("orders", "security_id"),
+    ("market_data", "security_id"),
+];
+
+/// The statements that create every index in `INDEXES`.
+fn create_indexes() -> String {
+    INDEXES
+        .iter()
+        .map(|(table, column)| {
+            format!("CREATE INDEX {table}_{column}_idx ON {table} ({column});\n")
+        })
+        .collect()
+}
+
 #[derive(Parser, Debug)]
+#[clap(group(ArgGroup::new("database").args(["target_url", "keep_db"])))]
 struct Cli {
+    /// The number of gigabytes (GiB) of data to generate. See --size for fractional or smaller amounts
     #[clap(long, default_value_t = NonZeroU8::new(1).unwrap())]
     scale: NonZeroU8,
 
+    /// The amount of data to generate (e.g., 500mb, 1.5gb, 2gib). Decimal
+    /// units are powers of 1000, binary units (kib, mib, gib, tib) powers of
+    /// 1024
+    #[clap(long, value_name = "SIZE", value_parser = rr_bench_base::parse_size, conflicts_with = "scale")]
+    size: Option<u64>,
+
     #[clap(long)]
     seed: Option<u64>,
 
+    /// Draw the sectors of securities from a Zipf distribution with this
+    /// exponent, so that a few sectors hold most of the securities, e.g.
+    /// 1.2. 0 spreads them evenly over the same sectors. By default sectors
+    /// are picked uniformly
+    #[clap(long, value_name = "EXPONENT", value_parser = parse_sector_skew)]
+    sector_skew: Option<f64>,
+
     #[clap(long, default_value = "data/")]
     target: PathBuf,
+
+    /// Load the data into the Postgres database at this URL instead of
+    /// writing CSV files. The benchmark tables are created first, and must
+    /// not already exist.
+    #[clap(long, value_name = "URL")]
+    target_url: Option<String>,
+
+    /// Write the data to a new SQLite database at this path instead of
+    /// writing CSV files. The database has the schema of rr-bench-sqlite,
+    /// so it can be passed to its --db-path as is
+    #[clap(long, value_name = "PATH", conflicts_with = "target_url")]
+    keep_db: Option<PathBuf>,
+
+    /// Index the foreign key and filter columns once the data is loaded
+    /// with --keep-db or --target-url, so the views are not limited to
+    /// full scans
+    #[clap(
+        long,
+        value_name = "BOOL",
+        default_value_t = true,
+        action = clap::ArgAction::Set,
+        requires = "database"
+    )]
+    with_indexes: bool,
+
+    /// The format of the files the data is exported to. The tables are
+    /// exported in parallel, one thread per table
+    #[clap(long, value_enum, default_value_t = ExportFormat::Csv, conflicts_with = "database")]
+    format: ExportFormat,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum ExportFormat {
+    Csv,
+    /// Snappy compressed Parquet, typed after the SQLite columns
+    Parquet,
 }
 
 #[derive(Serialize)]
@@ -85,48 +202,195 @@ struct MarketData {
     market_date: String,
 }
 
-fn get_db_size(conn: &mut Connection) -> u64 {
-    let page_count: u64 = conn
-        .query_row("PRAGMA page_count;", [], |row| row.get(0))
-        .expect("failed to query page_count");
-    let page_size: u64 = conn
-        .query_row("PRAGMA page_size;", [], |row| row.get(0))
-        .expect("failed to query page_size");
+/// `Sink` is the destination of the rows produced by `Generator`.
+trait Sink {
+    /// The progress bar template, which describes the unit of `size`.
+    fn progress_template(&self) -> &'static str;
+
+    /// The amount of data written so far, compared against the target passed
+    /// to `Generator::populate_database`.
+    fn size(&mut self) -> Result<u64>;
+
+    /// Returns up to `num` ids drawn at random from `column` of `table`.
+    fn random_ids(&mut self, table: &str, column: &str, num: usize) -> Result<Vec<i32>>;
+
+    fn insert_customers(&mut self, customers: Vec<rr_bench_core::Customer>) -> Result<()>;
 
-    page_count * page_size
+    fn insert_accounts(&mut self, accounts: Vec<(i32, rr_bench_core::Account)>) -> Result<()>;
+
+    fn insert_securities(&mut self, securities: Vec<rr_bench_core::Security>) -> Result<()>;
+
+    /// The number of securities whose ticker an earlier security already has.
+    fn duplicate_tickers(&mut self) -> Result<u64>;
+
+    fn insert_trades(&mut self, trades: Vec<(i32, i32, rr_bench_core::Trade)>) -> Result<()>;
+
+    fn insert_orders(&mut self, orders: Vec<(i32, i32, rr_bench_core::Order)>) -> Result<()>;
+
+    fn insert_market_data(
+        &mut self,
+        market_data: Vec<(i32, rr_bench_core::MarketData)>,
+    ) -> Result<()>;
 }
 
-fn get_random_ids(tx: &Transaction, table: &str, column: &str, num: usize) -> Vec<i32> {
-    let query = format!(
-        "SELECT {} FROM {} ORDER BY my_random() LIMIT {}",
-        column, table, num
-    );
-    let mut stmt = tx.prepare(&query).expect("failed to prepare statement");
-    let mut rows = stmt
-        .query([]) //, |row| row.get(0))
-        .unwrap_or_else(|_| panic!("failed to retrieve random {num} {column} from {table}"));
-
-    let mut ids = vec![];
-    while let Some(row) = rows.next().unwrap() {
-        ids.push(row.get(0).unwrap())
+/// `SqliteSink` writes to a temporary SQLite database, which is exported to
+/// CSV files once it reaches the target size.
+struct SqliteSink {
+    conn: Connection,
+    /// The path of the temporary database, which the export threads open
+    /// connections of their own to.
+    path: PathBuf,
+}
+
+impl SqliteSink {
+    fn create(seed: u64) -> Result<Self> {
+        let mut temp_path: PathBuf = env::temp_dir();
+        let unique_filename = format!("rr_data_gen_{}.db", Uuid::new_v4());
+        temp_path.push(unique_filename);
+        let conn = Connection::open(&temp_path).expect("failed to open database");
+
+        conn.execute_batch(
+            "
+            CREATE TABLE customers (
+                customer_id INTEGER PRIMARY KEY AUTOINCREMENT,
+                name TEXT NOT NULL,
+                address TEXT,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+            );
+            CREATE TABLE accounts (
+                account_id INTEGER PRIMARY KEY AUTOINCREMENT,
+                customer_id INTEGER,
+                account_type TEXT NOT NULL,
+                balance DECIMAL(18, 2) NOT NULL,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                FOREIGN KEY (customer_id) REFERENCES customers(customer_id) ON DELETE CASCADE
+            );
+            CREATE TABLE securities (
+                security_id INTEGER PRIMARY KEY AUTOINCREMENT,
+                ticker TEXT NOT NULL,
+                name TEXT,
+                sector TEXT,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+            );
+            CREATE TABLE trades (
+                trade_id INTEGER PRIMARY KEY AUTOINCREMENT,
+                account_id INTEGER,
+                security_id INTEGER,
+                trade_type TEXT NOT NULL CHECK (trade_type IN ('buy', 'sell')),
+                quantity INTEGER NOT NULL,
+                price DECIMAL(18, 4) NOT NULL,
+                trade_date DATETIME DEFAULT CURRENT_TIMESTAMP,
+                FOREIGN KEY (account_id) REFERENCES accounts(account_id) ON DELETE CASCADE,
+                FOREIGN KEY (security_id) REFERENCES securities(security_id) ON DELETE CASCADE
+            );
+            CREATE TABLE orders (
+                order_id INTEGER PRIMARY KEY AUTOINCREMENT,
+                account_id INTEGER,
+                security_id INTEGER,
+                order_type TEXT NOT NULL CHECK (order_type IN ('buy', 'sell')),
+                quantity INTEGER NOT NULL,
+                limit_price DECIMAL(18, 4),
+                status TEXT NOT NULL CHECK (status IN ('pending', 'completed', 'canceled')),
+                order_date DATETIME DEFAULT CURRENT_TIMESTAMP,
+                FOREIGN KEY (account_id) REFERENCES accounts(account_id) ON DELETE CASCADE,
+                FOREIGN KEY (security_id) REFERENCES securities(security_id) ON DELETE CASCADE
+            );
+            CREATE TABLE market_data (
+                market_data_id INTEGER PRIMARY KEY AUTOINCREMENT,
+                security_id INTEGER,
+                price DECIMAL(18, 4) NOT NULL,
+                volume INTEGER NOT NULL,
+                market_date DATETIME DEFAULT CURRENT_TIMESTAMP,
+                FOREIGN KEY (security_id) REFERENCES securities(security_id) ON DELETE CASCADE
+            );
+            ",
+        )?;
+
+        // Define our own random function
+        // to keep data generation deterministic
+        let mut sqlite_rng = StdRng::seed_from_u64(seed);
+
+        conn.create_scalar_function("my_random", 0, FunctionFlags::SQLITE_UTF8, move |_| {
+            Ok(sqlite_rng.gen::<i32>())
+        })?;
+
+        Ok(Self {
+            conn,
+            path: temp_path,
+        })
     }
 
-    ids
+    /// Copies the generated rows into a new database at `path`, created
+    /// with the schema of the SQLite benchmark, which adds columns, tables,
+    /// and views that the generator does not populate. `path` must not
+    /// already exist.
+    fn keep(&mut self, path: &Path, with_indexes: bool) -> Result<()> {
+        Connection::open(path)
+            .and_then(|conn| conn.execute_batch(&format!("{SQLITE_DDL}{SQLITE_VIEWS}")))
+            .with_context(|| format!("failed to create {}", path.display()))?;
+
+        self.conn
+            .execute(
+                "ATTACH DATABASE ?1 AS kept",
+                params![path.to_string_lossy()],
+            )
+            .with_context(|| format!("failed to attach {}", path.display()))?;
+        let tx = self.conn.transaction()?;
+        for (table, columns) in TABLES {
+            tx.execute(
+                &format!("INSERT INTO kept.{table} ({columns}) SELECT {columns} FROM main.{table}"),
+                [],
+            )
+            .with_context(|| format!("failed to copy {table}"))?;
+        }
+        tx.commit()?;
+        self.conn.execute("DETACH DATABASE kept", [])?;
+
+        if with_indexes {
+            // Created after the rows are copied, which is faster than
+            // maintaining them along the way.
+            Connection::open(path)
+                .and_then(|conn| conn.execute_batch(&create_indexes()))
+                .context("failed to create indexes")?;
+        }
+        Ok(())
+    }
 }
 
-struct Generator {
-    gen: DataGenerator,
-}
+impl Sink for SqliteSink {
+    fn progress_template(&self) -> &'static str {
+        "{wide_bar} {bytes}/{total_bytes} ({eta})"
+    }
 
-impl Generator {
-    fn populate_customers(
-        &mut self,
-        conn: &mut Connection,
-        batch_size: usize,
-    ) -> rusqlite::Result<()> {
-        let tx = conn.transaction()?;
-        for _ in 0..batch_size {
-            let customer = self.gen.generate_customer();
+    fn size(&mut self) -> Result<u64> {
+        let page_count: u64 = self
+            .conn
+            .query_row("PRAGMA page_count;", [], |row| row.get(0))
+            .context("failed to query page_count")?;
+        let page_size: u64 = self
+            .conn
+            .query_row("PRAGMA page_size;", [], |row| row.get(0))
+            .context("failed to query page_size")?;
+
+        Ok(page_count * page_size)
+    }
+
+    fn random_ids(&mut self, table: &str, column: &str, num: usize) -> Result<Vec<i32>> {
+        let query = format!(
+            "SELECT {} FROM {} ORDER BY my_random() LIMIT {}",
+            column, table, num
+        );
+        let mut stmt = self.conn.prepare(&query)?;
+        let ids = stmt
+            .query_map([], |row| row.get(0))?
+            .collect::<rusqlite::Result<_>>()
+            .with_context(|| format!("failed to retrieve random {num} {column} from {table}"))?;
+        Ok(ids)
+    }
+
+    fn insert_customers(&mut self, customers: Vec<rr_bench_core::Customer>) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        for customer in customers {
             tx.execute(
                 "INSERT INTO customers (name, address) VALUES (?, ?);",
                 params![customer.name, customer.address],
@@ -136,15 +400,9 @@ impl Generator {
         Ok(())
     }
 
-    fn populate_accounts(
-        &mut self,
-        conn: &mut Connection,
-        batch_size: usize,
-    ) -> rusqlite::Result<()> {
-        let tx = conn.transaction()?;
-        let customer_ids = get_random_ids(&tx, "customers", "customer_id", batch_size);
-        for customer_id in customer_ids {
-            let account = self.gen.generate_account();
+    fn insert_accounts(&mut self, accounts: Vec<(i32, rr_bench_core::Account)>) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        for (customer_id, account) in accounts {
             tx.execute(
                 "INSERT INTO accounts (customer_id, account_type, balance) VALUES (?, ?, ?);",
                 params![customer_id, account.account_type, account.balance],
@@ -154,14 +412,9 @@ impl Generator {
         Ok(())
     }
 
-    fn populate_securities(
-        &mut self,
-        conn: &mut Connection,
-        batch_size: usize,
-    ) -> rusqlite::Result<()> {
-        let tx = conn.transaction()?;
-        for _ in 0..batch_size {
-            let security = self.gen.generate_security();
+    fn insert_securities(&mut self, securities: Vec<rr_bench_core::Security>) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        for security in securities {
             tx.execute(
                 "INSERT INTO securities (ticker, name, sector) VALUES (?, ?, ?);",
                 params![security.ticker, security.name, security.sector],
@@ -171,19 +424,15 @@ impl Generator {
         Ok(())
     }
 
-    fn populate_trades(
-        &mut self,
-        conn: &mut Connection,
-        batch_size: usize,
-    ) -> rusqlite::Result<()> {
-        let tx = conn.transaction()?;
-        let account_ids = get_random_ids(&tx, "accounts", "account_id", batch_size);
-        let security_ids = get_random_ids(&tx, "securities", "security_id", batch_size);
-
-        let ids = account_ids.iter().zip(security_ids);
+    fn duplicate_tickers(&mut self) -> Result<u64> {
+        self.conn
+            .query_row(DUPLICATE_TICKERS, [], |row| row.get(0))
+            .context("failed to count duplicate tickers")
+    }
 
-        for (account_id, security_id) in ids {
-            let trade = self.gen.generate_trade();
+    fn insert_trades(&mut self, trades: Vec<(i32, i32, rr_bench_core::Trade)>) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        for (account_id, security_id, trade) in trades {
             tx.execute(
             "INSERT INTO trades (account_id, security_id, trade_type, quantity, price) VALUES (?, ?, ?, ?, ?);",
             params![account_id, security_id, trade.trade_type, trade.quantity, trade.price],
@@ -193,19 +442,9 @@ impl Generator {
         Ok(())
     }
 
-    fn populate_orders(
-        &mut self,
-        conn: &mut Connection,
-        batch_size: usize,
-    ) -> rusqlite::Result<()> {
-        let tx = conn.transaction()?;
-        let account_ids = get_random_ids(&tx, "accounts", "account_id", batch_size);
-        let security_ids = get_random_ids(&tx, "securities", "security_id", batch_size);
-
-        let ids = account_ids.iter().zip(security_ids);
-
-        for (account_id, security_id) in ids {
-            let order = self.gen.generate_order();
+    fn insert_orders(&mut self, orders: Vec<(i32, i32, rr_bench_core::Order)>) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        for (account_id, security_id, order) in orders {
             tx.execute(
             "INSERT INTO orders (account_id, security_id, order_type, quantity, limit_price, status) VALUES (?, ?, ?, ?, ?, ?);",
             params![account_id, security_id, order.order_type, order.quantity, order.limit_price, order.status])?;
@@ -214,16 +453,12 @@ impl Generator {
         Ok(())
     }
 
-    fn populate_market_data(
+    fn insert_market_data(
         &mut self,
-        conn: &mut Connection,
-        batch_size: usize,
-    ) -> rusqlite::Result<()> {
-        let tx = conn.transaction()?;
-        let security_ids = get_random_ids(&tx, "securities", "security_id", batch_size);
-
-        for security_id in security_ids {
-            let market_data = self.gen.generate_market_data();
+        market_data: Vec<(i32, rr_bench_core::MarketData)>,
+    ) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        for (security_id, market_data) in market_data {
             tx.execute(
                 "INSERT INTO market_data (security_id, price, volume) VALUES (?, ?, ?);",
                 params![security_id, market_data.price, market_data.volume],
@@ -232,45 +467,112 @@ impl Generator {
         tx.commit()?;
         Ok(())
     }
+}
+
+struct Generator {
+    gen: DataGenerator,
+}
+
+impl Generator {
+    fn populate_customers(&mut self, sink: &mut impl Sink, batch_size: usize) -> Result<()> {
+        let customers = (0..batch_size)
+            .map(|_| self.gen.generate_customer())
+            .collect();
+        sink.insert_customers(customers)
+    }
+
+    fn populate_accounts(&mut self, sink: &mut impl Sink, batch_size: usize) -> Result<()> {
+        let customer_ids = sink.random_ids("customers", "customer_id", batch_size)?;
+        let accounts = customer_ids
+            .into_iter()
+            .map(|customer_id| (customer_id, self.gen.generate_account()))
+            .collect();
+        sink.insert_accounts(accounts)
+    }
+
+    fn populate_securities(&mut self, sink: &mut impl Sink, batch_size: usize) -> Result<()> {
+        let securities = (0..batch_size)
+            .map(|_| self.gen.generate_security())
+            .collect();
+        sink.insert_securities(securities)
+    }
+
+    fn populate_trades(&mut self, sink: &mut impl Sink, batch_size: usize) -> Result<()> {
+        let account_ids = sink.random_ids("accounts", "account_id", batch_size)?;
+        let security_ids = sink.random_ids("securities", "security_id", batch_size)?;
+
+        let trades = account_ids
+            .into_iter()
+            .zip(security_ids)
+            .map(|(account_id, security_id)| (account_id, security_id, self.gen.generate_trade()))
+            .collect();
+        sink.insert_trades(trades)
+    }
+
+    fn populate_orders(&mut self, sink: &mut impl Sink, batch_size: usize) -> Result<()> {
+        let account_ids = sink.random_ids("accounts", "account_id", batch_size)?;
+        let security_ids = sink.random_ids("securities", "security_id", batch_size)?;
+
+        let orders = account_ids
+            .into_iter()
+            .zip(security_ids)
+            .map(|(account_id, security_id)| (account_id, security_id, self.gen.generate_order()))
+            .collect();
+        sink.insert_orders(orders)
+    }
+
+    fn populate_market_data(&mut self, sink: &mut impl Sink, batch_size: usize) -> Result<()> {
+        let security_ids = sink.random_ids("securities", "security_id", batch_size)?;
+        let market_data = security_ids
+            .into_iter()
+            .map(|security_id| (security_id, self.gen.generate_market_data()))
+            .collect();
+        sink.insert_market_data(market_data)
+    }
 
-    fn populate_database(&mut self, conn: &mut Connection, target_size_gb: u8) -> Result<()> {
-        let target_size_bytes = target_size_gb as u64 * GIGABYTE;
+    /// Inserts batches of rows into `sink` until its size reaches `target`.
+    fn populate_database(&mut self, sink: &mut impl Sink, target: u64) -> Result<()> {
         let batch_size = 1000;
 
-        let progress_bar = ProgressBar::new(target_size_bytes);
+        let progress_bar = ProgressBar::new(target);
         progress_bar.set_style(
             ProgressStyle::default_bar()
-                .template("{wide_bar} {bytes}/{total_bytes} ({eta})")
+                .template(sink.progress_template())
                 .unwrap()
                 .progress_chars("#>-"),
         );
 
-        let mut last_size = 0;
         loop {
-            let current_size = get_db_size(conn);
-            let diff = current_size - last_size;
-            progress_bar.inc(diff);
-            last_size = current_size;
+            let current_size = sink.size()?;
+            progress_bar.set_position(current_size);
 
-            if current_size >= target_size_bytes {
+            if current_size >= target {
                 break;
             }
 
-            self.populate_customers(conn, batch_size)
+            self.populate_customers(sink, batch_size)
                 .context("failed to populate customers")?;
-            self.populate_accounts(conn, batch_size * 2)
+            self.populate_accounts(sink, batch_size * 2)
                 .context("failed to populate accounts")?;
-            self.populate_securities(conn, batch_size * 3)
+            self.populate_securities(sink, batch_size * 3)
                 .context("failed to populate securities")?;
-            self.populate_trades(conn, batch_size * 10)
+            self.populate_trades(sink, batch_size * 10)
                 .context("failed to populate trades")?;
-            self.populate_orders(conn, batch_size * 8)
+            self.populate_orders(sink, batch_size * 8)
                 .context("failed to populate orders")?;
-            self.populate_market_data(conn, batch_size * 10)
+            self.populate_market_data(sink, batch_size * 10)
                 .context("failed to populate market_data")?;
         }
 
         progress_bar.finish();
+
+        // Views like pending_orders_summary, which is queried by ticker,
+        // assume that no two securities share one.
+        let duplicates = sink.duplicate_tickers()?;
+        ensure!(
+            duplicates == 0,
+            "generated {duplicates} securities with duplicate tickers"
+        );
         Ok(())
     }
 }
@@ -361,13 +663,51 @@ fn map_market_data(row: &rusqlite::Row) -> Result<MarketData> {
     })
 }
 
+fn parse_sector_skew(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(skew) if skew.is_finite() && skew >= 0.0 => Ok(skew),
+        _ => Err(format!(
+            "invalid sector skew '{s}', expected a non-negative number"
+        )),
+    }
+}
+
 fn main() -> Result<()> {
     let cli: Cli = Cli::parse();
 
+    // Checked up front rather than after spending the time to generate the data.
+    if let Some(path) = &cli.keep_db {
+        if path.exists() {
+            bail!("--keep-db {} already exists", path.display());
+        }
+    }
+
     let seed = cli.seed.unwrap_or_else(|| thread_rng().gen());
-    println!("Generating {} gb of data with seed {}", cli.scale, seed);
+    let target_size = cli
+        .size
+        .unwrap_or_else(|| cli.scale.get() as u64 * GIGABYTE);
+    println!(
+        "Generating {} of data with seed {}",
+        HumanBytes(target_size),
+        seed
+    );
 
-    if !cli.target.exists() {
+    let mut gen = DataGenerator::new(seed);
+    if let Some(skew) = cli.sector_skew {
+        gen = gen.with_sector_skew(skew);
+    }
+    let mut generator = Generator { gen };
+
+    if let Some(url) = &cli.target_url {
+        let mut sink = PostgresSink::create(url, seed)?;
+        generator.populate_database(&mut sink, target_size.div_ceil(BYTES_PER_ROW))?;
+        let rows = sink.size()?;
+        sink.finish(cli.with_indexes)?;
+        println!("Loaded {rows} rows.");
+        return Ok(());
+    }
+
+    if cli.keep_db.is_none() && !cli.target.exists() {
         println!("Directory does not exist. Creating: {:?}", cli.target);
         if let Err(e) = std::fs::create_dir_all(&cli.target) {
             eprintln!("Failed to create directory: {:?}", e);
@@ -375,110 +715,94 @@ fn main() -> Result<()> {
         }
     }
 
-    let mut temp_path: PathBuf = env::temp_dir();
-    let unique_filename = format!("rr_data_gen_{}.db", Uuid::new_v4());
-    temp_path.push(unique_filename);
-    let mut conn = Connection::open(temp_path).expect("failed to open database");
-
-    conn.execute_batch(
-        "
-        CREATE TABLE customers (
-            customer_id INTEGER PRIMARY KEY AUTOINCREMENT,
-            name TEXT NOT NULL,
-            address TEXT,
-            created_at DATETIME DEFAULT CURRENT_TIMESTAMP
-        );
-        CREATE TABLE accounts (
-            account_id INTEGER PRIMARY KEY AUTOINCREMENT,
-            customer_id INTEGER,
-            account_type TEXT NOT NULL,
-            balance DECIMAL(18, 2) NOT NULL,
-            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
-            FOREIGN KEY (customer_id) REFERENCES customers(customer_id) ON DELETE CASCADE
-        );
-        CREATE TABLE securities (
-            security_id INTEGER PRIMARY KEY AUTOINCREMENT,
-            ticker TEXT NOT NULL,
-            name TEXT,
-            sector TEXT,
-            created_at DATETIME DEFAULT CURRENT_TIMESTAMP
-        );
-        CREATE TABLE trades (
-            trade_id INTEGER PRIMARY KEY AUTOINCREMENT,
-            account_id INTEGER,
-            security_id INTEGER,
-            trade_type TEXT NOT NULL CHECK (trade_type IN ('buy', 'sell')),
-            quantity INTEGER NOT NULL,
-            price DECIMAL(18, 4) NOT NULL,
-            trade_date DATETIME DEFAULT CURRENT_TIMESTAMP,
-            FOREIGN KEY (account_id) REFERENCES accounts(account_id) ON DELETE CASCADE,
-            FOREIGN KEY (security_id) REFERENCES securities(security_id) ON DELETE CASCADE
-        );
-        CREATE TABLE orders (
-            order_id INTEGER PRIMARY KEY AUTOINCREMENT,
-            account_id INTEGER,
-            security_id INTEGER,
-            order_type TEXT NOT NULL CHECK (order_type IN ('buy', 'sell')),
-            quantity INTEGER NOT NULL,
-            limit_price DECIMAL(18, 4),
-            status TEXT NOT NULL CHECK (status IN ('pending', 'completed', 'canceled')),
-            order_date DATETIME DEFAULT CURRENT_TIMESTAMP,
-            FOREIGN KEY (account_id) REFERENCES accounts(account_id) ON DELETE CASCADE,
-            FOREIGN KEY (security_id) REFERENCES securities(security_id) ON DELETE CASCADE
-        );
-        CREATE TABLE market_data (
-            market_data_id INTEGER PRIMARY KEY AUTOINCREMENT,
-            security_id INTEGER,
-            price DECIMAL(18, 4) NOT NULL,
-            volume INTEGER NOT NULL,
-            market_date DATETIME DEFAULT CURRENT_TIMESTAMP,
-            FOREIGN KEY (security_id) REFERENCES securities(security_id) ON DELETE CASCADE
-        );
-        ",
-    )?;
+    let mut sink = SqliteSink::create(seed)?;
+    generator.populate_database(&mut sink, target_size)?;
 
-    // Define our own random function
-    // to keep data generation deterministic
-    let mut sqlite_rng = StdRng::seed_from_u64(seed);
+    if let Some(path) = &cli.keep_db {
+        sink.keep(path, cli.with_indexes)?;
+        println!("SQLite database written to {}.", path.display());
+        return Ok(());
+    }
 
-    conn.create_scalar_function("my_random", 0, FunctionFlags::SQLITE_UTF8, move |_| {
-        Ok(sqlite_rng.gen::<i32>())
+    // Each table is exported by a thread of its own, with its own read-only
+    // connection to the generated database.
+    let path = &sink.path;
+    let open = move || {
+        Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+            .context("failed to open the generated database")
+    };
+    thread::scope(|s| {
+        let exports: Vec<thread::ScopedJoinHandle<Result<()>>> = match cli.format {
+            ExportFormat::Csv => vec![
+                s.spawn(move || {
+                    export_to_csv::<Customer>(
+                        &mut open()?,
+                        "SELECT * FROM customers",
+                        "customers.csv",
+                        map_customer,
+                    )
+                }),
+                s.spawn(move || {
+                    export_to_csv::<Account>(
+                        &mut open()?,
+                        "SELECT * FROM accounts",
+                        "accounts.csv",
+                        map_account,
+                    )
+                }),
+                s.spawn(move || {
+                    export_to_csv::<Security>(
+                        &mut open()?,
+                        "SELECT * FROM securities",
+                        "securities.csv",
+                        map_security,
+                    )
+                }),
+                s.spawn(move || {
+                    export_to_csv::<Trade>(
+                        &mut open()?,
+                        "SELECT * FROM trades",
+                        "trades.csv",
+                        map_trade,
+                    )
+                }),
+                s.spawn(move || {
+                    export_to_csv::<Order>(
+                        &mut open()?,
+                        "SELECT * FROM orders",
+                        "orders.csv",
+                        map_order,
+                    )
+                }),
+                s.spawn(move || {
+                    export_to_csv::<MarketData>(
+                        &mut open()?,
+                        "SELECT * FROM market_data",
+                        "market_data.csv",
+                        map_market_data,
+                    )
+                }),
+            ],
+            ExportFormat::Parquet => TABLES
+                .iter()
+                .map(|(table, _)| {
+                    s.spawn(move || {
+                        let file = format!("{table}.parquet");
+                        export_to_parquet(&open()?, table, Path::new(&file))
+                            .with_context(|| format!("failed to export {table}"))
+                    })
+                })
+                .collect(),
+        };
+        exports
+            .into_iter()
+            .try_for_each(|export| export.join().unwrap())
     })?;
 
-    let mut generator = Generator {
-        gen: DataGenerator::new(seed),
-    };
-    generator.populate_database(&mut conn, cli.scale.get())?;
-
-    // Export to CSV
-    export_to_csv::<Customer>(
-        &mut conn,
-        "SELECT * FROM customers",
-        "customers.csv",
-        map_customer,
-    )?;
-    export_to_csv::<Account>(
-        &mut conn,
-        "SELECT * FROM accounts",
-        "accounts.csv",
-        map_account,
-    )?;
-    export_to_csv::<Security>(
-        &mut conn,
-        "SELECT * FROM securities",
-        "securities.csv",
-        map_security,
-    )?;
-    export_to_csv::<Trade>(&mut conn, "SELECT * FROM trades", "trades.csv", map_trade)?;
-    export_to_csv::<Order>(&mut conn, "SELECT * FROM orders", "orders.csv", map_order)?;
-    export_to_csv::<MarketData>(
-        &mut conn,
-        "SELECT * FROM market_data",
-        "market_data.csv",
-        map_market_data,
-    )?;
-
-    println!("CSV files generated.");
+    match cli.format {
+        ExportFormat::Csv => println!("CSV files generated."),
+        ExportFormat::Parquet => println!("Parquet files generated."),
+    }
 
     Ok(())
 }
@@ -0,0 +1,229 @@
+use crate::{create_indexes, Sink, DUPLICATE_TICKERS};
+use anyhow::{bail, Context, Result};
+use openssl::ssl::{SslConnector, SslMethod, SslVerifyMode};
+use postgres::Client;
+use postgres_openssl::MakeTlsConnector;
+use rand::prelude::StdRng;
+use rand::seq::index;
+use rand::SeedableRng;
+use rr_bench_base::ConnectionString;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io::Write;
+
+const SCHEMES: &[&str] = &["postgres", "postgresql"];
+
+/// The table definitions used by the Postgres benchmark.
+const DDL: &str = include_str!("../../rr-bench-postgres/pg_ddl.sql");
+
+/// The tables written by the generator, with their id columns.
+const TABLES: &[(&str, &str)] = &[
+    ("customers", "customer_id"),
+    ("accounts", "account_id"),
+    ("securities", "security_id"),
+    ("trades", "trade_id"),
+    ("orders", "order_id"),
+    ("market_data", "market_data_id"),
+];
+
+/// `PostgresSink` streams generated rows into a live Postgres database with
+/// `COPY`, after creating the schema.
+///
+/// Ids are assigned by the sink rather than by the `SERIAL` columns, so that
+/// random ids can be drawn locally instead of by scanning the tables. The
+/// sequences are advanced past them by `finish`.
+pub struct PostgresSink {
+    client: Client,
+    rng: StdRng,
+    /// The number of rows written to each table, which is also the largest id.
+    rows: HashMap<&'static str, i32>,
+}
+
+impl PostgresSink {
+    /// Connects to `url` and creates the benchmark tables, which must not
+    /// already exist.
+    pub fn create(url: &str, seed: u64) -> Result<Self> {
+        let url = ConnectionString::parse(url, SCHEMES).context("invalid --target-url")?;
+        if url.pool_size().is_some() {
+            bail!("invalid --target-url: pool_size is not supported");
+        }
+
+        let mut builder =
+            SslConnector::builder(SslMethod::tls()).context("Error creating ssl builder")?;
+        builder.set_verify(SslVerifyMode::NONE);
+        let tls = MakeTlsConnector::new(builder.build());
+        let mut client =
+            Client::connect(url.url(), tls).context("failed to open postgres client")?;
+
+        client
+            .batch_execute(DDL)
+            .context("failed to create tables, they must not already exist")?;
+
+        Ok(Self {
+            client,
+            rng: StdRng::seed_from_u64(seed),
+            rows: TABLES.iter().map(|(table, _)| (*table, 0)).collect(),
+        })
+    }
+
+    /// Sets the id sequence of every table past the ids written by the sink,
+    /// so that the benchmark can insert further rows, and creates the
+    /// indexes if `with_indexes` is set.
+    pub fn finish(mut self, with_indexes: bool) -> Result<()> {
+        for (table, column) in TABLES {
+            let rows = self.rows[table];
+            if rows == 0 {
+                continue;
+            }
+            self.client
+                .execute(
+                    "SELECT setval(pg_get_serial_sequence($1, $2), $3)",
+                    &[table, column, &i64::from(rows)],
+                )
+                .with_context(|| format!("failed to set the id sequence of {table}"))?;
+        }
+
+        if with_indexes {
+            self.client
+                .batch_execute(&create_indexes())
+                .context("failed to create indexes")?;
+        }
+        Ok(())
+    }
+
+    /// Copies `rows` into `columns` of `table`, prefixing each row with the
+    /// next id.
+    fn copy<T: Serialize>(
+        &mut self,
+        table: &'static str,
+        columns: &str,
+        rows: Vec<T>,
+    ) -> Result<()> {
+        let first_id = self.rows[table] + 1;
+        let statement = format!("COPY {table} ({columns}) FROM STDIN WITH (FORMAT csv)");
+        let writer = self.client.copy_in(&statement)?;
+        let mut writer = csv::WriterBuilder::new()
+            .has_headers(false)
+            .from_writer(writer);
+        let count = rows.len() as i32;
+        for (id, row) in (first_id..).zip(rows) {
+            writer.serialize((id, row))?;
+        }
+        let mut writer = writer.into_inner().map_err(|e| e.into_error())?;
+        writer.flush()?;
+        writer.finish()?;
+        *self.rows.get_mut(table).unwrap() += count;
+        Ok(())
+    }
+}
+
+impl Sink for PostgresSink {
+    fn progress_template(&self) -> &'static str {
+        "{wide_bar} {human_pos}/{human_len} rows ({eta})"
+    }
+
+    fn size(&mut self) -> Result<u64> {
+        Ok(self.rows.values().map(|rows| *rows as u64).sum())
+    }
+
+    fn random_ids(&mut self, table: &str, _column: &str, num: usize) -> Result<Vec<i32>> {
+        // Like `ORDER BY random() LIMIT num`, return at most one id per row.
+        let rows = self.rows[table] as usize;
+        Ok(index::sample(&mut self.rng, rows, num.min(rows))
+            .into_iter()
+            .map(|i| i as i32 + 1)
+            .collect())
+    }
+
+    fn insert_customers(&mut self, customers: Vec<rr_bench_core::Customer>) -> Result<()> {
+        let rows = customers
+            .into_iter()
+            .map(|customer| (customer.name, customer.address))
+            .collect();
+        self.copy("customers", "customer_id, name, address", rows)
+    }
+
+    fn insert_accounts(&mut self, accounts: Vec<(i32, rr_bench_core::Account)>) -> Result<()> {
+        let rows = accounts
+            .into_iter()
+            .map(|(customer_id, account)| (customer_id, account.account_type, account.balance))
+            .collect();
+        self.copy(
+            "accounts",
+            "account_id, customer_id, account_type, balance",
+            rows,
+        )
+    }
+
+    fn insert_securities(&mut self, securities: Vec<rr_bench_core::Security>) -> Result<()> {
+        let rows = securities
+            .into_iter()
+            .map(|security| (security.ticker, security.name, security.sector))
+            .collect();
+        self.copy("securities", "security_id, ticker, name, sector", rows)
+    }
+
+    fn duplicate_tickers(&mut self) -> Result<u64> {
+        let row = self
+            .client
+            .query_one(DUPLICATE_TICKERS, &[])
+            .context("failed to count duplicate tickers")?;
+        Ok(row.get::<_, i64>(0) as u64)
+    }
+
+    fn insert_trades(&mut self, trades: Vec<(i32, i32, rr_bench_core::Trade)>) -> Result<()> {
+        let rows = trades
+            .into_iter()
+            .map(|(account_id, security_id, trade)| {
+                (
+                    account_id,
+                    security_id,
+                    trade.trade_type,
+                    trade.quantity,
+                    trade.price,
+                )
+            })
+            .collect();
+        self.copy(
+            "trades",
+            "trade_id, account_id, security_id, trade_type, quantity, price",
+            rows,
+        )
+    }
+
+    fn insert_orders(&mut self, orders: Vec<(i32, i32, rr_bench_core::Order)>) -> Result<()> {
+        let rows = orders
+            .into_iter()
+            .map(|(account_id, security_id, order)| {
+                (
+                    account_id,
+                    security_id,
+                    order.order_type,
+                    order.quantity,
+                    order.limit_price,
+                    order.status,
+                )
+            })
+            .collect();
+        self.copy(
+            "orders",
+            "order_id, account_id, security_id, order_type, quantity, limit_price, status",
+            rows,
+        )
+    }
+
+    fn insert_market_data(
+        &mut self,
+        market_data: Vec<(i32, rr_bench_core::MarketData)>,
+    ) -> Result<()> {
+        let rows = market_data
+            .into_iter()
+            .map(|(security_id, market_data)| (security_id, market_data.price, market_data.volume))
+            .collect();
+        self.copy(
+            "market_data",
+            "market_data_id, security_id, price, volume",
+            rows,
+        )
+    }
+}